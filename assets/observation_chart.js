@@ -610,7 +610,12 @@ function loadObservation(id) {
 
       const startTime = new Date(data.start_time).toUTCString();
       const intTime = Math.round(data.integration_time_secs);
-      const coordLabel = data.coordinate_system === "sun" ? chartT("sunAzel", "Sun az/el") : data.coordinate_system;
+      const coordLabel =
+        data.coordinate_system === "sun"
+          ? chartT("sunAzel", "Sun az/el")
+          : data.coordinate_system === "moon"
+            ? chartT("moonAzel", "Moon az/el")
+            : data.coordinate_system;
       const coordStr = `(${data.target_x.toFixed(1)}°, ${data.target_y.toFixed(1)}°)`;
       const offsetParts = [];
       const azOff = data.az_offset_deg;
@@ -632,12 +637,18 @@ function loadObservation(id) {
       const titlePost = `${hasAzEl ? "  |  " : ""}Avg. power: ${powerLevel.toFixed(2)}`;
       const titleLine2 = startTime;
 
-      const vlsrCorrection = data.vlsr_correction_mps;
+      // Prefer the server's correction, which also covers observations
+      // saved before it was stored, and the telescope's configured line.
+      const vlsrCorrection =
+        data.vlsr_kms !== null && data.vlsr_kms !== undefined
+          ? data.vlsr_kms * 1000
+          : data.vlsr_correction_mps;
+      const restFreq = data.rest_freq_hz || F_REST;
       let showVlsr = vlsrCorrection !== null && vlsrCorrection !== undefined;
       let showLog = false;
 
       function freqToVlsr(freqHz) {
-        return (C * (F_REST - freqHz) / F_REST + vlsrCorrection) / 1000;
+        return (C * (restFreq - freqHz) / restFreq + vlsrCorrection) / 1000;
       }
 
       function freqToDisplay(freqHz) {
@@ -855,7 +866,7 @@ function loadObservation(id) {
           const xDisplay = x.invert(mouseX);
           const yVal = y.invert(mouseY);
           const freqHz = showVlsr
-            ? F_REST - (xDisplay * 1000 - vlsrCorrection) * F_REST / C
+            ? restFreq - (xDisplay * 1000 - vlsrCorrection) * restFreq / C
             : xDisplay * 1e6;
           if (analysisState.clickMode === "baseline") {
             if (analysisState.pendingRangeStart === null) {
@@ -21,6 +21,9 @@ account-user-id = SALSA user ID
 account-type = Account type
 account-type-admin = Admin
 account-type-user = User
+account-display-name-label = Display name
+account-display-name-help = The name shown for you on SALSA. Leave it empty to use the one from your login provider.
+account-display-name-save = Save
 account-timezone-label = Timezone
 account-timezone-help = Dates and times across SALSA — including the bookings calendar — are shown in this timezone.
 account-saved = Saved ✓ — applies as you navigate.
@@ -30,6 +33,24 @@ account-feedback-intro = Enjoying SALSA? We'd love to hear about it — what you
 account-feedback-outro = Stories like that help us prioritise the time we put into the system.
 account-delete = Delete account
 account-delete-confirm = Are you sure? This will anonymise your account and cancel any upcoming bookings. Past bookings and observations will be retained. If you want to remove your observations, please delete them on the Observations page first.
+account-logout-all = Log out everywhere
+account-logout-all-help = Ends your sessions in every browser and device, including this one. Use it if you think someone else has access to your account.
+account-token-heading = API token
+account-token-help = Scripts can use the JSON API with a personal token instead of logging in, by sending it as "Authorization: Bearer <token>". It acts as you, so keep it secret.
+account-token-create = Create token
+account-token-new = Your new token. Copy it now; it will not be shown again:
+account-token-created = You have a token, created
+account-token-replace = Replace token
+account-token-replace-confirm = Replace your token? Scripts using the old one will stop working.
+account-token-revoke = Revoke token
+account-token-revoke-confirm = Revoke your token? Scripts using it will stop working.
+account-identities-heading = Login methods
+account-identities-help = Link the other services you log in with, so that they all lead to this account and its bookings and observations.
+account-identities-current = You can log in with:
+account-identities-none = only a username and password.
+account-identities-link = Link
+account-identities-linked-now = Login method linked.
+account-identities-taken = That login already belongs to another account, so it was not linked. Contact us if you want the accounts merged.
 
 ## Welcome page
 
@@ -128,6 +149,9 @@ booking-error-slot-ended = Cannot book a slot that has already ended.
 booking-error-description-too-long = Description is too long (max { $max } characters).
 booking-error-maintenance = { $telescope } is currently under maintenance.
 booking-error-limit = You have reached the maximum of { $max } upcoming bookings.
+booking-error-too-far-ahead = Slots can be booked at most { $days } days ahead.
+booking-error-too-long = You can book at most { $max } hours in a row on one telescope.
+booking-error-weekly-quota = You can book at most { $max } hours on { $telescope } in any 7 days.
 booking-error-already-booked = Slot at { $time } on { $date } is already booked.
 
 ## Date format patterns (chrono strftime). Translated so word order can
@@ -147,8 +171,10 @@ observe-guest-tail = or sooner if a registered user books this telescope. Data i
 observe-target = Target
 observe-coord-galactic = Galactic
 observe-coord-equatorial = Equatorial (J2000)
+observe-coord-name = Named source
 observe-coord-horizontal = Horizontal
 observe-coord-sun = Sun
+observe-coord-moon = Moon
 observe-coord-gnss = GNSS
 observe-coord-stow = Stow
 observe-coord-service = Service
@@ -156,18 +182,28 @@ observe-lbl-long = Long. [deg]
 observe-lbl-lat = Lat. [deg]
 observe-lbl-ra = R.A. [deg]
 observe-lbl-dec = Dec. [deg]
+observe-lbl-name = Name
 observe-lbl-az = Az. [deg]
 observe-lbl-el = El. [deg]
 observe-satellite = Satellite
 observe-loading = Loading...
 observe-track = Track
 observe-stop = Stop
+observe-repoint = Repoint
+observe-repoint-title = Move to the new offsets without ending the integration
+observe-park = Park
+observe-park-title = End any running measurement and move the telescope to its stow position
 observe-adv-tracking = Advanced tracking settings
 observe-az-offset = Az. offset [deg]
 observe-el-offset = El. offset [deg]
 observe-min-elevation = Minimum elevation:
 observe-recommended = recommended
+observe-up-until = Up until { $time }
+observe-rises-at = Rises at { $time }
+observe-never-sets = Never sets below { $limit }°
+observe-never-rises = Never rises above { $limit }°
 observe-begin = Begin
+observe-label-placeholder = Label (optional)
 observe-end = End
 observe-adv-receiver = Advanced receiver settings
 observe-integration-time = Integration time
@@ -177,6 +213,11 @@ observe-seconds = seconds
 observe-mode = Mode
 observe-freq-switched = Freq. switched
 observe-raw = Raw
+observe-position-switched = Position switched
+observe-switch-title = Alternates between the target and the offset reference position; the stored spectrum is on minus off.
+observe-switch-az-offset = Off az offset (°)
+observe-switch-el-offset = Off el offset (°)
+observe-switch-cycle = Cycle (s)
 observe-center-freq = Center freq. [MHz]
 observe-ref-freq = Ref. freq. [MHz]
 observe-freq-range = Frequency range
@@ -185,6 +226,13 @@ observe-spectral-channels = Spectral channels
 observe-rfi-filter = RFI filter (sliding MAD-σ)
 observe-enabled = Enabled
 observe-disabled = Disabled
+observe-cal-diode = Calibration signal
+observe-cal-on = On
+observe-cal-off = Off
+observe-cal-diode-help = Adds the noise diode's known temperature to the spectrum. Compare Raw spectra taken with it on and off to work out the system temperature.
+observe-drift-scan = Drift scan
+observe-drift-cadence = A spectrum every
+observe-drift-scan-title = Keeps a horizontal target fixed while the sky drifts through the beam, and saves a spectrum every few seconds.
 observe-live-spectrum = Live spectrum
 observe-live-webcam = Live Webcam
 observe-webcam-alt = Live webcam feed of the SALSA telescope
@@ -210,24 +258,48 @@ observe-js-max-session = (maximum session length reached)
 
 observe-error-select-satellite = Please select a satellite.
 observe-error-invalid-coords = Please enter valid coordinates.
+observe-error-ra-range = R.A. must be between { $min } and { $max }°.
+observe-error-dec-range = Declination must be between { $min } and { $max }°.
+observe-error-long-range = Galactic longitude must be between { $min } and { $max }°.
+observe-error-lat-range = Galactic latitude must be between { $min } and { $max }°.
+observe-error-el-range = Elevation must be between { $min } and { $max }°.
+observe-error-unknown-source = No source named "{ $name }" in the catalog. Try for example { $examples }, or enter the coordinates instead.
 observe-error-elevation-range = Target is out of elevation range ({ $min }–{ $max }°).
+observe-error-keyhole = Target is above { $max }°, too close to zenith for the telescope to follow. Try again once it has descended.
+observe-error-telescope-not-connected = The telescope is not connected. Try again in a minute.
+observe-error-telescope-io = Communication with the telescope failed. Try again in a minute.
+observe-error-telescope-busy = The telescope is busy tracking a target. Stop it first.
+observe-error-controlled-by-other = Another user with a booking is controlling this telescope right now.
+observe-error-receiver-failed = The receiver failed: { $detail }
 observe-error-not-tracking = Telescope is not tracking. Please wait until it has reached the target.
+observe-error-jog-step = Jog steps can be at most { $max }° in each axis.
 observe-error-receiver-unreachable = Receiver is not reachable. Check the receiver address and network connection.
 observe-error-center-freq = Center frequency must be between { $min } and { $max } MHz.
 observe-error-ref-freq = Reference frequency must be between { $min } and { $max } MHz.
 observe-error-gain = Gain must be between { $min } and { $max } dB.
+observe-error-position-switch = The reference offset must be non-zero and at most { $max_offset }° in each axis, and the cycle between { $min_cycle } and { $max_cycle } s.
+observe-error-drift-scan = A drift scan needs a horizontal target, can't be position switched, and needs a spectrum every { $min } to { $max } s.
+observe-error-mode-unsupported = This telescope's receiver cannot observe in the selected mode.
+observe-error-cal-unsupported = This telescope's receiver has no calibration signal.
 
 ## Telescope status fragment
 
 state-telescope = Telescope
 state-idle = Idle
 state-slewing = Slewing
+state-slewing-eta = on target in about
+state-pointing-error = from target
 state-tracking = Tracking
+state-stowed = Stowed
+state-parked = Parked
 state-offline = Offline
 state-offline-error = Cannot connect to telescope controller.
+state-offline-reconnecting = Reconnecting in
+state-receiver-offline = Cannot reach the receiver. The telescope can still be pointed, but no spectra can be measured.
 state-low-elevation-1 = Low elevation: the telescope is pointed only
 state-low-elevation-2 = above the horizon — noise from the ground and surrounding buildings may degrade the spectrum.
 state-error-elevation-range = target is out of elevation range ({ $min }–{ $max }°)
+state-error-keyhole = target is above { $max }°, too close to zenith to track; waiting for it to descend
 state-error-io = io error in communication with telescope
 state-error-not-connected = telescope is not connected
 state-error-receiver = receiver failed: { $msg }
@@ -268,6 +340,7 @@ obs-my = My observations
 obs-none = No observations yet.
 obs-list-hint = observations · click to view spectrum · ✕ to delete selected
 obs-delete-confirm = Delete this observation?
+obs-rename = Rename
 obs-spectrum = Spectrum
 obs-save-png = Save PNG
 obs-save-csv = Save CSV
@@ -370,10 +443,11 @@ chart-seed-singular = seed
 chart-seed-plural = seeds
 chart-picking = (picking…)
 chart-sun-azel = Sun az/el
+chart-moon-azel = Moon az/el
 
 ## Login page
 
-login-same-method-hint = Always use the same login method to keep access to your bookings and observations.
+login-same-method-hint = Always use the same login method to keep access to your bookings and observations. To use another one as well, link it on your Account page first.
 login-local-summary = Local login (exceptional cases only)
 login-local-help = Local accounts are reserved for demonstrations and special cases. Contact SALSA support to request access.
 login-invalid-credentials = Invalid username or password.
@@ -381,3 +455,50 @@ login-rate-limited = Too many failed attempts. Please try again later.
 login-username = Username
 login-password = Password
 login-submit = Log in
+
+## Admin user list
+
+admin-users-heading = Users
+admin-users-intro-1 = Every registered account; guests and deleted accounts are not listed. Admins listed in the
+admin-users-intro-2 = section of
+admin-users-intro-3 = stay admins whatever is set here. A disabled user is logged out on their next request and can't log back in, nor use their API token, until enabled again.
+admin-users-back = Back to admin tools
+admin-users-error-self = You can't change your own account here.
+admin-users-error = An error occurred:
+admin-users-none = No registered users yet.
+admin-users-col-name = Name
+admin-users-col-provider = Provider
+admin-users-col-created = Created
+admin-users-col-bookings = Bookings
+admin-users-col-admin = Admin
+admin-users-col-account = Account
+admin-users-shown-as = shown as
+admin-users-yes = Yes
+admin-users-no = No
+admin-users-from-config = (config)
+admin-users-revoke = Revoke
+admin-users-make-admin = Make admin
+admin-users-active = Active
+admin-users-disabled = Disabled
+admin-users-enable = Enable
+admin-users-disable = Disable
+admin-users-confirm-disable-1 = Disable
+admin-users-confirm-disable-2 = ? They will be logged out.
+
+## Admin audit log
+
+admin-audit-heading = Audit log
+admin-audit-intro = Who pointed telescopes, started integrations, booked or cancelled, and what admins changed. Entries can't be edited or removed. Times are UTC; the user is the account name at the time of the action.
+admin-audit-back = Back to admin tools
+admin-audit-user = User
+admin-audit-any-user = Any
+admin-audit-from = From
+admin-audit-to = To
+admin-audit-update = Update
+admin-audit-none = Nothing recorded in this range.
+admin-audit-truncated-1 = Showing the
+admin-audit-truncated-2 = most recent entries; narrow the range to see older ones.
+admin-audit-col-time = Time
+admin-audit-col-user = User
+admin-audit-col-action = Action
+admin-audit-col-detail = Detail
@@ -21,6 +21,9 @@ account-user-id = SALSA-användar-ID
 account-type = Kontotyp
 account-type-admin = Administratör
 account-type-user = Användare
+account-display-name-label = Visningsnamn
+account-display-name-help = Namnet som visas för dig på SALSA. Lämna tomt för att använda namnet från din inloggningstjänst.
+account-display-name-save = Spara
 account-timezone-label = Tidszon
 account-timezone-help = Datum och tider i hela SALSA — inklusive bokningskalendern — visas i den här tidszonen.
 account-saved = Sparat ✓ — gäller när du navigerar vidare.
@@ -30,6 +33,24 @@ account-feedback-intro = Trivs du med SALSA? Vi vill gärna höra om det — vad
 account-feedback-outro = Sådana berättelser hjälper oss att prioritera tiden vi lägger på systemet.
 account-delete = Radera konto
 account-delete-confirm = Är du säker? Detta anonymiserar ditt konto och avbokar kommande bokningar. Tidigare bokningar och observationer behålls. Om du vill ta bort dina observationer, radera dem först på sidan Observationer.
+account-logout-all = Logga ut överallt
+account-logout-all-help = Avslutar dina sessioner i alla webbläsare och enheter, även denna. Använd det om du tror att någon annan har tillgång till ditt konto.
+account-token-heading = API-nyckel
+account-token-help = Skript kan använda JSON-API:t med en personlig nyckel i stället för att logga in, genom att skicka den som "Authorization: Bearer <nyckel>". Den agerar som du, så håll den hemlig.
+account-token-create = Skapa nyckel
+account-token-new = Din nya nyckel. Kopiera den nu; den visas inte igen:
+account-token-created = Du har en nyckel, skapad
+account-token-replace = Byt nyckel
+account-token-replace-confirm = Byta nyckel? Skript som använder den gamla slutar fungera.
+account-token-revoke = Återkalla nyckel
+account-token-revoke-confirm = Återkalla nyckeln? Skript som använder den slutar fungera.
+account-identities-heading = Inloggningsmetoder
+account-identities-help = Koppla de andra tjänster du loggar in med, så att alla leder till det här kontot och dess bokningar och observationer.
+account-identities-current = Du kan logga in med:
+account-identities-none = bara användarnamn och lösenord.
+account-identities-link = Koppla
+account-identities-linked-now = Inloggningsmetoden är kopplad.
+account-identities-taken = Den inloggningen hör redan till ett annat konto och kopplades inte. Kontakta oss om du vill slå ihop kontona.
 
 ## Welcome page
 
@@ -128,6 +149,9 @@ booking-error-slot-ended = Det går inte att boka en tid som redan har passerat.
 booking-error-description-too-long = Beskrivningen är för lång (max { $max } tecken).
 booking-error-maintenance = { $telescope } är på underhåll just nu.
 booking-error-limit = Du har nått gränsen på { $max } kommande bokningar.
+booking-error-too-far-ahead = Tider kan bokas högst { $days } dagar i förväg.
+booking-error-too-long = Du kan boka högst { $max } timmar i följd på ett teleskop.
+booking-error-weekly-quota = Du kan boka högst { $max } timmar på { $telescope } under sju dagar.
 booking-error-already-booked = Tiden { $time } den { $date } är redan bokad.
 
 ## Date format patterns (chrono strftime). Translated so word order can
@@ -147,8 +171,10 @@ observe-guest-tail = eller tidigare om en registrerad användare bokar det här
 observe-target = Mål
 observe-coord-galactic = Galaktiska
 observe-coord-equatorial = Ekvatoriella (J2000)
+observe-coord-name = Namngiven källa
 observe-coord-horizontal = Horisontella
 observe-coord-sun = Solen
+observe-coord-moon = Månen
 observe-coord-gnss = GNSS
 observe-coord-stow = Parkera
 observe-coord-service = Service
@@ -156,18 +182,28 @@ observe-lbl-long = Long. [grader]
 observe-lbl-lat = Lat. [grader]
 observe-lbl-ra = RA [grader]
 observe-lbl-dec = Dekl. [grader]
+observe-lbl-name = Namn
 observe-lbl-az = Az. [grader]
 observe-lbl-el = El. [grader]
 observe-satellite = Satellit
 observe-loading = Laddar...
 observe-track = Följ
 observe-stop = Stoppa
+observe-repoint = Flytta
+observe-repoint-title = Flytta till de nya offseten utan att avsluta integrationen
+observe-park = Parkera
+observe-park-title = Avsluta pågående mätning och flytta teleskopet till parkeringsläget
 observe-adv-tracking = Avancerade följningsinställningar
 observe-az-offset = Az.-offset [grader]
 observe-el-offset = El.-offset [grader]
 observe-min-elevation = Lägsta elevation:
 observe-recommended = rekommenderat
+observe-up-until = Uppe till { $time }
+observe-rises-at = Går upp { $time }
+observe-never-sets = Går aldrig ner under { $limit }°
+observe-never-rises = Når aldrig över { $limit }°
 observe-begin = Starta
+observe-label-placeholder = Etikett (valfri)
 observe-end = Avsluta
 observe-adv-receiver = Avancerade mottagarinställningar
 observe-integration-time = Integrationstid
@@ -177,6 +213,11 @@ observe-seconds = sekunder
 observe-mode = Läge
 observe-freq-switched = Frekvensväxlad
 observe-raw = Rå
+observe-position-switched = Positionsväxlad
+observe-switch-title = Växlar mellan målet och den förskjutna referenspositionen; det sparade spektrumet är på minus av.
+observe-switch-az-offset = Av-förskjutning az (°)
+observe-switch-el-offset = Av-förskjutning el (°)
+observe-switch-cycle = Cykel (s)
 observe-center-freq = Centerfrekvens [MHz]
 observe-ref-freq = Referensfrekvens [MHz]
 observe-freq-range = Frekvensområde
@@ -185,6 +226,13 @@ observe-spectral-channels = Spektralkanaler
 observe-rfi-filter = RFI-filter (glidande MAD-σ)
 observe-enabled = Aktiverat
 observe-disabled = Avaktiverat
+observe-cal-diode = Kalibreringssignal
+observe-cal-on = På
+observe-cal-off = Av
+observe-cal-diode-help = Lägger till brusdiodens kända temperatur i spektrumet. Jämför Raw-spektra tagna med den på och av för att räkna ut systemtemperaturen.
+observe-drift-scan = Driftskanning
+observe-drift-cadence = Ett spektrum var
+observe-drift-scan-title = Håller ett horisontellt mål fast medan himlen driver genom loben, och sparar ett spektrum med några sekunders mellanrum.
 observe-live-spectrum = Spektrum i realtid
 observe-live-webcam = Webbkamera i realtid
 observe-webcam-alt = Webbkamerabild i realtid av SALSA-teleskopet
@@ -210,24 +258,48 @@ observe-js-max-session = (maximal sessionslängd uppnådd)
 
 observe-error-select-satellite = Välj en satellit.
 observe-error-invalid-coords = Ange giltiga koordinater.
+observe-error-ra-range = RA måste vara mellan { $min } och { $max }°.
+observe-error-dec-range = Deklinationen måste vara mellan { $min } och { $max }°.
+observe-error-long-range = Galaktisk longitud måste vara mellan { $min } och { $max }°.
+observe-error-lat-range = Galaktisk latitud måste vara mellan { $min } och { $max }°.
+observe-error-el-range = Elevationen måste vara mellan { $min } och { $max }°.
+observe-error-unknown-source = Ingen källa med namnet "{ $name }" finns i katalogen. Prova till exempel { $examples }, eller ange koordinaterna i stället.
 observe-error-elevation-range = Målet är utanför elevationsområdet ({ $min }–{ $max }°).
+observe-error-keyhole = Målet står högre än { $max }°, för nära zenit för att teleskopet ska kunna följa det. Försök igen när det har sjunkit.
+observe-error-telescope-not-connected = Teleskopet är inte anslutet. Försök igen om en minut.
+observe-error-telescope-io = Kommunikationen med teleskopet misslyckades. Försök igen om en minut.
+observe-error-telescope-busy = Teleskopet följer ett mål. Stoppa det först.
+observe-error-controlled-by-other = En annan användare med en bokning styr teleskopet just nu.
+observe-error-receiver-failed = Mottagaren misslyckades: { $detail }
 observe-error-not-tracking = Teleskopet följer inte målet. Vänta tills det har nått målet.
+observe-error-jog-step = Stegen kan vara högst { $max }° i varje axel.
 observe-error-receiver-unreachable = Mottagaren kan inte nås. Kontrollera mottagarens adress och nätverksanslutning.
 observe-error-center-freq = Centerfrekvensen måste vara mellan { $min } och { $max } MHz.
 observe-error-ref-freq = Referensfrekvensen måste vara mellan { $min } och { $max } MHz.
 observe-error-gain = Förstärkningen måste vara mellan { $min } och { $max } dB.
+observe-error-position-switch = Referensförskjutningen måste vara skild från noll och högst { $max_offset }° i varje axel, och cykeln mellan { $min_cycle } och { $max_cycle } s.
+observe-error-drift-scan = En driftskanning kräver ett horisontellt mål, kan inte positionsväxlas och behöver ett spektrum var { $min } till { $max } s.
+observe-error-mode-unsupported = Det här teleskopets mottagare kan inte observera i det valda läget.
+observe-error-cal-unsupported = Det här teleskopets mottagare har ingen kalibreringssignal.
 
 ## Telescope status fragment
 
 state-telescope = Teleskop
 state-idle = Vilande
 state-slewing = Rör sig
+state-slewing-eta = framme om cirka
+state-pointing-error = från målet
 state-tracking = Följer
+state-stowed = Stuvad
+state-parked = Parkerad
 state-offline = Offline
 state-offline-error = Kan inte ansluta till teleskopets styrenhet.
+state-offline-reconnecting = Ansluter igen om
+state-receiver-offline = Kan inte nå mottagaren. Teleskopet kan fortfarande riktas, men inga spektra kan mätas.
 state-low-elevation-1 = Låg elevation: teleskopet pekar bara
 state-low-elevation-2 = över horisonten — brus från marken och omgivande byggnader kan försämra spektrumet.
 state-error-elevation-range = målet är utanför elevationsområdet ({ $min }–{ $max }°)
+state-error-keyhole = målet står högre än { $max }°, för nära zenit för att följas; väntar på att det sjunker
 state-error-io = IO-fel i kommunikationen med teleskopet
 state-error-not-connected = teleskopet är inte anslutet
 state-error-receiver = mottagaren misslyckades: { $msg }
@@ -268,6 +340,7 @@ obs-my = Mina observationer
 obs-none = Inga observationer ännu.
 obs-list-hint = observationer · klicka för att visa spektrum · ✕ för att radera vald
 obs-delete-confirm = Radera den här observationen?
+obs-rename = Byt namn
 obs-spectrum = Spektrum
 obs-save-png = Spara PNG
 obs-save-csv = Spara CSV
@@ -370,10 +443,11 @@ chart-seed-singular = startgissning
 chart-seed-plural = startgissningar
 chart-picking = (väljer…)
 chart-sun-azel = Solen az/el
+chart-moon-azel = Månen az/el
 
 ## Login page
 
-login-same-method-hint = Använd alltid samma inloggningsmetod för att behålla åtkomsten till dina bokningar och observationer.
+login-same-method-hint = Använd alltid samma inloggningsmetod för att behålla åtkomsten till dina bokningar och observationer. Vill du använda fler, koppla dem först på din Kontosida.
 login-local-summary = Lokal inloggning (endast undantagsfall)
 login-local-help = Lokala konton är reserverade för demonstrationer och specialfall. Kontakta SALSA-supporten för att begära åtkomst.
 login-invalid-credentials = Fel användarnamn eller lösenord.
@@ -381,3 +455,50 @@ login-rate-limited = För många misslyckade försök. Försök igen senare.
 login-username = Användarnamn
 login-password = Lösenord
 login-submit = Logga in
+
+## Admin user list
+
+admin-users-heading = Användare
+admin-users-intro-1 = Alla registrerade konton; gäster och raderade konton visas inte. Administratörer som listas i avsnittet
+admin-users-intro-2 = i
+admin-users-intro-3 = förblir administratörer oavsett vad som anges här. En inaktiverad användare loggas ut vid nästa förfrågan och kan inte logga in igen, eller använda sin API-token, förrän kontot aktiveras igen.
+admin-users-back = Tillbaka till administrationsverktygen
+admin-users-error-self = Du kan inte ändra ditt eget konto här.
+admin-users-error = Ett fel uppstod:
+admin-users-none = Inga registrerade användare än.
+admin-users-col-name = Namn
+admin-users-col-provider = Leverantör
+admin-users-col-created = Skapad
+admin-users-col-bookings = Bokningar
+admin-users-col-admin = Admin
+admin-users-col-account = Konto
+admin-users-shown-as = visas som
+admin-users-yes = Ja
+admin-users-no = Nej
+admin-users-from-config = (konfiguration)
+admin-users-revoke = Återkalla
+admin-users-make-admin = Gör till admin
+admin-users-active = Aktiv
+admin-users-disabled = Inaktiverad
+admin-users-enable = Aktivera
+admin-users-disable = Inaktivera
+admin-users-confirm-disable-1 = Inaktivera
+admin-users-confirm-disable-2 = ? Användaren loggas ut.
+
+## Admin audit log
+
+admin-audit-heading = Granskningslogg
+admin-audit-intro = Vem som riktade teleskop, startade integrationer, bokade eller avbokade, och vad administratörer ändrade. Poster kan inte ändras eller tas bort. Tiderna anges i UTC; användaren är kontots namn vid tidpunkten för händelsen.
+admin-audit-back = Tillbaka till administrationsverktygen
+admin-audit-user = Användare
+admin-audit-any-user = Alla
+admin-audit-from = Från
+admin-audit-to = Till
+admin-audit-update = Uppdatera
+admin-audit-none = Inget registrerat i detta intervall.
+admin-audit-truncated-1 = Visar de
+admin-audit-truncated-2 = senaste posterna; begränsa intervallet för att se äldre.
+admin-audit-col-time = Tid
+admin-audit-col-user = Användare
+admin-audit-col-action = Händelse
+admin-audit-col-detail = Detaljer
@@ -0,0 +1,4 @@
+ALTER TABLE session ADD COLUMN "expires_at" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE session ADD COLUMN "last_used_at" INTEGER NOT NULL DEFAULT 0;
+-- Existing sessions keep the 30 days they were issued with.
+UPDATE session SET expires_at = created_at + 30 * 24 * 60 * 60, last_used_at = created_at;
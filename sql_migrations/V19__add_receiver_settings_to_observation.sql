@@ -0,0 +1,2 @@
+ALTER TABLE observation ADD COLUMN bandwidth_hz REAL;
+ALTER TABLE observation ADD COLUMN spectral_channels INTEGER;
@@ -0,0 +1,4 @@
+ALTER TABLE observation ADD COLUMN system_temperature_k REAL;
+ALTER TABLE observation ADD COLUMN receiver_gain_db REAL;
+ALTER TABLE observation ADD COLUMN site_longitude_deg REAL;
+ALTER TABLE observation ADD COLUMN site_latitude_deg REAL;
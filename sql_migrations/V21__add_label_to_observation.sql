@@ -0,0 +1 @@
+ALTER TABLE observation ADD COLUMN label TEXT;
@@ -0,0 +1,3 @@
+ALTER TABLE observation ADD COLUMN switch_az_offset_deg REAL;
+ALTER TABLE observation ADD COLUMN switch_el_offset_deg REAL;
+ALTER TABLE observation ADD COLUMN switch_cycle_secs REAL;
@@ -0,0 +1,7 @@
+CREATE TABLE user_token (
+    user_id INTEGER PRIMARY KEY,
+    token_hash TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    last_used_at INTEGER,
+    FOREIGN KEY (user_id) REFERENCES user(id)
+);
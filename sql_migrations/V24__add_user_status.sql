@@ -0,0 +1,7 @@
+-- When the account was created; NULL for accounts older than this column.
+ALTER TABLE user ADD COLUMN created_at INTEGER;
+-- Admins granted from the admin page, on top of those listed in
+-- config.toml.
+ALTER TABLE user ADD COLUMN is_admin INTEGER NOT NULL DEFAULT 0;
+-- Disabled accounts are treated as logged out on every request.
+ALTER TABLE user ADD COLUMN disabled INTEGER NOT NULL DEFAULT 0;
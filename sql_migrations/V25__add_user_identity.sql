@@ -0,0 +1,16 @@
+-- External identities an account can log in with. An account is created
+-- with one; more can be linked from the account page.
+CREATE TABLE user_identity (
+    provider TEXT NOT NULL,
+    external_id TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    PRIMARY KEY (provider, external_id),
+    FOREIGN KEY (user_id) REFERENCES user(id)
+);
+INSERT OR IGNORE INTO user_identity (provider, external_id, user_id)
+    SELECT provider, external_id, id FROM user
+    WHERE provider NOT IN ('', 'local', 'guest')
+        AND external_id IS NOT NULL AND external_id != '';
+
+-- Set when the login links another provider to this account instead.
+ALTER TABLE pending_oauth2 ADD COLUMN link_user_id INTEGER;
@@ -0,0 +1 @@
+ALTER TABLE user ADD COLUMN display_name TEXT;
@@ -0,0 +1,4 @@
+-- Sessions are now looked up by the SHA-256 of their token. Tokens stored
+-- in the clear can't be matched that way, so everyone logs in again.
+DELETE FROM session;
+ALTER TABLE session RENAME COLUMN "token" TO "token_hash";
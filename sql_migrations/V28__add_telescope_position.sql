@@ -0,0 +1,9 @@
+CREATE TABLE telescope_position (
+    telescope_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    azimuth_deg REAL NOT NULL,
+    elevation_deg REAL NOT NULL,
+    status TEXT NOT NULL
+);
+
+CREATE INDEX telescope_position_by_time ON telescope_position (telescope_id, timestamp);
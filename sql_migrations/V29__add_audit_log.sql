@@ -0,0 +1,22 @@
+-- No foreign key to user: entries must outlive the accounts they name,
+-- so the name is copied in as it was at the time.
+CREATE TABLE audit_log (
+    id INTEGER PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    user_name TEXT NOT NULL,
+    action TEXT NOT NULL,
+    detail TEXT NOT NULL
+);
+
+CREATE INDEX audit_log_by_time ON audit_log (timestamp);
+
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
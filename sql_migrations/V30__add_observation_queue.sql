@@ -0,0 +1,20 @@
+-- Targets a user has lined up to be observed unattended during their
+-- bookings, run in id order by `queue_runner`.
+CREATE TABLE observation_queue (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    telescope_id TEXT NOT NULL,
+    -- TelescopeTarget as JSON.
+    target TEXT NOT NULL,
+    az_offset_deg REAL NOT NULL,
+    el_offset_deg REAL NOT NULL,
+    integration_time_secs REAL NOT NULL,
+    label TEXT NOT NULL,
+    -- 'queued', 'running', 'done', 'failed' or 'cancelled'.
+    status TEXT NOT NULL,
+    error TEXT,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES user(id)
+);
+
+CREATE INDEX observation_queue_by_owner ON observation_queue (telescope_id, user_id, status);
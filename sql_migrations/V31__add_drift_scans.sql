@@ -0,0 +1,14 @@
+-- Set for drift scans: the pointing was held fixed (the horizontal target)
+-- and a spectrum recorded every this many seconds, in drift_scan_spectrum.
+ALTER TABLE observation ADD COLUMN drift_cadence_secs REAL;
+
+CREATE TABLE drift_scan_spectrum (
+    observation_id INTEGER NOT NULL,
+    -- From the observation's start_time to the start of this slice.
+    start_offset_secs REAL NOT NULL,
+    integration_time_secs REAL NOT NULL,
+    amplitudes_json TEXT NOT NULL,
+    FOREIGN KEY (observation_id) REFERENCES observation(id) ON DELETE CASCADE
+);
+
+CREATE INDEX drift_scan_spectrum_by_observation ON drift_scan_spectrum (observation_id);
@@ -0,0 +1,3 @@
+-- Names are joined in from user when entries are read, so an account
+-- deleted (and anonymised) later no longer shows by name in the log.
+ALTER TABLE audit_log DROP COLUMN user_name;
@@ -1,29 +1,48 @@
-use axum::extract::{MatchedPath, State};
+use axum::extract::State;
 use axum::http::{HeaderMap, Request, Uri};
 use axum::middleware::{self, Next};
 use axum::response::{IntoResponse, Redirect, Response};
 use axum::{Router, routing::get};
-use rusqlite::Connection;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use thiserror::Error;
 use tokio::sync::Mutex;
+use tower_http::compression::CompressionLayer;
+use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::services::ServeDir;
+use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
-use tracing::{debug, debug_span, warn};
+use tracing::{debug, info, warn};
 
 use serde::Deserialize;
 
+use crate::catalog::{CatalogConfig, SourceCatalog};
+use crate::clock_check::{ClockConfig, start_clock_check};
 use crate::correlator::CorrelatorHandle;
-use crate::database::create_sqlite_database_on_disk;
+use crate::database::{ConnectionPool, DEFAULT_POOL_SIZE, create_connection_pool_on_disk};
 use crate::guest_rate_limiter::GuestStartLimiterHandle;
+use crate::logging::http_request_span;
 use crate::login_rate_limiter::LoginRateLimiterHandle;
+use crate::metrics::latency_middleware;
 use crate::middleware::cookies::cookies_middleware;
+use crate::middleware::csrf::csrf_middleware;
 use crate::middleware::language::language_middleware;
+use crate::middleware::rate_limit::RateLimiterHandle;
 use crate::middleware::session::session_middleware;
-use crate::models::session::{purge_expired_pending_oauth2, purge_expired_sessions};
-use crate::models::telescope::{TelescopeCollectionHandle, create_telescope_collection};
+use crate::models::booking::Booking;
+use crate::models::guest::GuestSession;
+use crate::models::session::{
+    DEFAULT_SESSION_LIFETIME_DAYS, purge_expired_pending_oauth2, purge_expired_sessions,
+};
+use crate::models::telescope::{
+    Telescope, TelescopeCollectionHandle, TelescopeConfigError, create_telescope_collection,
+};
+use crate::models::user::User;
 use crate::routes;
-use crate::secrets::Secrets;
+use crate::routes::observe::{stop_and_save_observation, take_observation_label};
+use crate::secrets::{Secrets, SecretsError};
 use crate::tle_cache::{TleCacheHandle, start_tle_refresh};
 use crate::weather_cache::{WeatherCacheHandle, start_weather_refresh};
 
@@ -31,16 +50,151 @@ use crate::weather_cache::{WeatherCacheHandle, start_weather_refresh};
 pub struct BookingConfig {
     #[serde(default = "default_max_upcoming_bookings")]
     pub max_upcoming_bookings: u32,
+    /// Longest unbroken run of back-to-back slots one user may hold on a
+    /// telescope.
+    #[serde(default = "default_max_booking_hours")]
+    pub max_booking_hours: u32,
+    /// How far ahead a slot may be booked.
+    #[serde(default = "default_max_advance_days")]
+    pub max_advance_days: u32,
+    /// Most hours one user may have booked on a telescope within any
+    /// rolling week.
+    #[serde(default = "default_max_weekly_hours")]
+    pub max_weekly_hours: u32,
 }
 
 fn default_max_upcoming_bookings() -> u32 {
     6
 }
 
+fn default_max_booking_hours() -> u32 {
+    4
+}
+
+fn default_max_advance_days() -> u32 {
+    30
+}
+
+fn default_max_weekly_hours() -> u32 {
+    10
+}
+
 impl Default for BookingConfig {
     fn default() -> Self {
         Self {
             max_upcoming_bookings: default_max_upcoming_bookings(),
+            max_booking_hours: default_max_booking_hours(),
+            max_advance_days: default_max_advance_days(),
+            max_weekly_hours: default_max_weekly_hours(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionConfig {
+    /// How long a login stays valid.
+    #[serde(default = "default_session_lifetime_days")]
+    pub lifetime_days: u32,
+    /// Mark the session cookie Secure. Only for deployments reached over
+    /// plain HTTP, where browsers drop Secure cookies; see
+    /// `middleware::session::session_cookie`.
+    #[serde(default = "default_secure_cookies")]
+    pub secure_cookies: bool,
+}
+
+fn default_session_lifetime_days() -> u32 {
+    DEFAULT_SESSION_LIFETIME_DAYS
+}
+
+fn default_secure_cookies() -> bool {
+    true
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            lifetime_days: default_session_lifetime_days(),
+            secure_cookies: default_secure_cookies(),
+        }
+    }
+}
+
+/// `COOKIE_SECURE` from the environment, which overrides
+/// `[sessions] secure_cookies` so a dev setup can turn it off without a
+/// config file.
+fn secure_cookies_from_env() -> Option<bool> {
+    let value = std::env::var("COOKIE_SECURE").ok()?;
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => {
+            warn!("Ignoring COOKIE_SECURE={value}: expected true or false");
+            None
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn lifetime(&self) -> chrono::Duration {
+        chrono::Duration::days(self.lifetime_days.into())
+    }
+}
+
+/// Limits on the unauthenticated OAuth2 redirect and callback routes, per
+/// client IP.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default = "default_oauth_requests_per_minute")]
+    pub oauth_requests_per_minute: u32,
+    #[serde(default = "default_oauth_burst")]
+    pub oauth_burst: u32,
+}
+
+fn default_oauth_requests_per_minute() -> u32 {
+    10
+}
+
+fn default_oauth_burst() -> u32 {
+    10
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            oauth_requests_per_minute: default_oauth_requests_per_minute(),
+            oauth_burst: default_oauth_burst(),
+        }
+    }
+}
+
+/// Limits on every request, so a slow or oversized one can't tie up the
+/// server.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpConfig {
+    /// Largest request body accepted; larger ones get 413 Payload Too
+    /// Large. Forms and API requests are a few kilobytes at most.
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Longest a request may take, reading its body included; slower ones
+    /// get 503 Service Unavailable. Streams (the spectrum SSE and the
+    /// WebSockets) only need their response started within this.
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+}
+
+fn default_max_body_bytes() -> usize {
+    64 * 1024
+}
+
+fn default_request_timeout_secs() -> u64 {
+    60
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: default_max_body_bytes(),
+            request_timeout_secs: default_request_timeout_secs(),
         }
     }
 }
@@ -56,43 +210,124 @@ struct SalsaConfig {
     #[serde(default)]
     bookings: BookingConfig,
     #[serde(default)]
+    sessions: SessionConfig,
+    #[serde(default)]
+    auth: AuthConfig,
+    #[serde(default)]
     admin: AdminConfig,
+    #[serde(default)]
+    clock: ClockConfig,
+    #[serde(default)]
+    catalog: CatalogConfig,
+    #[serde(default)]
+    http: HttpConfig,
 }
 
 // Anything that goes in here must be a handle or pointer that can be cloned.
 // The underlying state itself should be shared.
 #[derive(Clone)]
 pub struct AppState {
-    pub database_connection: Arc<Mutex<Connection>>,
+    pub database_connection: ConnectionPool,
     pub telescopes: TelescopeCollectionHandle,
     pub secrets: Arc<Secrets>,
     pub booking_config: Arc<BookingConfig>,
+    pub session_config: Arc<SessionConfig>,
     pub admin_config: Arc<AdminConfig>,
     pub tle_cache: TleCacheHandle,
     pub weather_cache: WeatherCacheHandle,
     pub login_rate_limiter: LoginRateLimiterHandle,
     pub guest_start_limiter: GuestStartLimiterHandle,
+    pub oauth_rate_limiter: RateLimiterHandle,
+    /// Throttles `/observe/{telescope_id}/jog` per client IP.
+    pub jog_rate_limiter: RateLimiterHandle,
+    pub source_catalog: Arc<SourceCatalog>,
     /// At most one correlator session running at a time.
     pub active_correlator: Arc<Mutex<Option<CorrelatorHandle>>>,
+    /// Label given in the observe form for the integration running on each
+    /// telescope, keyed by telescope id. Taken when the spectrum is saved.
+    pub observation_labels: Arc<Mutex<HashMap<String, String>>>,
+    /// User with an active booking who last pointed each telescope or
+    /// started an integration on it, keyed by telescope id. See
+    /// `routes::observe::claim_control`.
+    pub telescope_controllers: Arc<Mutex<HashMap<String, User>>>,
+    /// Static assets and content pages, from `--assets-dir`.
+    pub assets_dir: Arc<PathBuf>,
+}
+
+/// Why `create_app` gave up. These are mistakes in the deployment rather
+/// than bugs, so `main` reports them in a line instead of panicking.
+#[derive(Debug, Error)]
+pub enum StartupError {
+    #[error("Could not read {}: {source}", path.display())]
+    ReadConfig {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid {}: {source}", path.display())]
+    ParseConfig {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("{}: {source}", path.display())]
+    Telescopes {
+        path: PathBuf,
+        source: TelescopeConfigError,
+    },
+    #[error("{}: {source}", path.display())]
+    Secrets { path: PathBuf, source: SecretsError },
 }
 
-pub async fn create_app(config_dir: &Path, database_dir: &Path) -> (Router, AppState) {
-    let database_connection = Arc::new(Mutex::new(
-        create_sqlite_database_on_disk(database_dir.join("database.sqlite3"))
-            .expect("failed to create sqlite database"),
-    ));
+/// Start the app with `config.toml` and `.secrets.toml` read from
+/// `config_dir`, the database in `database_dir` and static files served
+/// from `assets_dir`. Nothing is looked up in the working directory.
+pub async fn create_app(
+    config_dir: &Path,
+    database_dir: &Path,
+    assets_dir: &Path,
+) -> Result<(Router, AppState), StartupError> {
+    let database_connection =
+        create_connection_pool_on_disk(database_dir.join("database.sqlite3"), DEFAULT_POOL_SIZE)
+            .expect("failed to create sqlite database");
     purge_expired_sessions(database_connection.clone())
         .await
         .expect("failed to purge expired sessions on startup");
     purge_expired_pending_oauth2(database_connection.clone())
         .await
         .expect("failed to purge expired pending oauth2 on startup");
+    start_login_purge(database_connection.clone());
     let config_path = config_dir.join("config.toml");
-    let config_str = std::fs::read_to_string(&config_path).unwrap_or_default();
-    let salsa_config: SalsaConfig =
-        toml::from_str(&config_str).expect("config.toml should be valid toml");
+    let config_str = match std::fs::read_to_string(&config_path) {
+        Ok(config_str) => config_str,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            warn!(
+                "No {}; using the defaults, without telescopes",
+                config_path.display()
+            );
+            String::new()
+        }
+        Err(source) => {
+            return Err(StartupError::ReadConfig {
+                path: config_path,
+                source,
+            });
+        }
+    };
+    let mut salsa_config: SalsaConfig =
+        toml::from_str(&config_str).map_err(|source| StartupError::ParseConfig {
+            path: config_path.clone(),
+            source,
+        })?;
+    if let Some(secure) = secure_cookies_from_env() {
+        salsa_config.sessions.secure_cookies = secure;
+    }
+    if !salsa_config.sessions.secure_cookies {
+        warn!("Session cookies are not marked Secure; only do this behind plain HTTP");
+    }
     let booking_config = Arc::new(salsa_config.bookings);
+    let session_config = Arc::new(salsa_config.sessions);
     let admin_config = Arc::new(salsa_config.admin);
+    start_clock_check(salsa_config.clock);
+    let source_catalog = Arc::new(SourceCatalog::load(&salsa_config.catalog));
 
     let tle_cache = TleCacheHandle::new();
     start_tle_refresh(tle_cache.clone());
@@ -100,21 +335,24 @@ pub async fn create_app(config_dir: &Path, database_dir: &Path) -> (Router, AppS
     start_weather_refresh(weather_cache.clone());
     let login_rate_limiter = LoginRateLimiterHandle::new();
     let guest_start_limiter = GuestStartLimiterHandle::new();
-    let telescopes = create_telescope_collection(
-        config_path
-            .to_str()
-            .expect("Config path should be convertible to string"),
-        tle_cache.clone(),
+    let oauth_rate_limiter = RateLimiterHandle::new(
+        salsa_config.auth.oauth_requests_per_minute,
+        salsa_config.auth.oauth_burst,
     );
+    let telescopes =
+        create_telescope_collection(&config_path, tle_cache.clone()).map_err(|source| {
+            StartupError::Telescopes {
+                path: config_path.clone(),
+                source,
+            }
+        })?;
     let secrets_path = config_dir.join(".secrets.toml");
-    let secrets = Arc::new(
-        Secrets::read(
-            secrets_path
-                .to_str()
-                .expect("Secret path should be convertible to string"),
-        )
-        .expect("Reading .secrets.toml should always succeed"),
-    );
+    let mut secrets = Secrets::read(&secrets_path).map_err(|source| StartupError::Secrets {
+        path: secrets_path.clone(),
+        source,
+    })?;
+    secrets.discover_oidc_endpoints().await;
+    let secrets = Arc::new(secrets);
     let webcam_snapshot_url = match secrets.webcam.as_ref() {
         Some(creds) => format!(
             // snapType=main gives the full-resolution frame; the crops served to
@@ -131,16 +369,25 @@ pub async fn create_app(config_dir: &Path, database_dir: &Path) -> (Router, AppS
         telescopes,
         secrets,
         booking_config,
+        session_config,
         admin_config,
         tle_cache,
         weather_cache,
         login_rate_limiter,
         guest_start_limiter,
+        oauth_rate_limiter,
+        jog_rate_limiter: RateLimiterHandle::new(
+            routes::observe::JOG_REQUESTS_PER_MINUTE,
+            routes::observe::JOG_BURST,
+        ),
+        source_catalog,
         active_correlator: Arc::new(Mutex::new(None)),
+        observation_labels: Arc::new(Mutex::new(HashMap::new())),
+        telescope_controllers: Arc::new(Mutex::new(HashMap::new())),
+        assets_dir: Arc::new(assets_dir.to_path_buf()),
     };
 
-    let assets_path = "assets";
-    debug!("serving asserts from {}", assets_path);
+    debug!("serving assets from {}", assets_dir.display());
     let app = Router::new()
         .route("/", get(routes::index::get_index))
         .nest(
@@ -150,11 +397,14 @@ pub async fn create_app(config_dir: &Path, database_dir: &Path) -> (Router, AppS
             )),
         )
         .nest("/admin", routes::admin::routes(state.clone()))
-        .nest("/about", routes::about::routes())
-        .nest("/experiments", routes::experiments::routes())
+        .nest("/api", routes::api::routes(state.clone()))
+        .nest("/about", routes::about::routes(state.clone()))
+        .nest("/experiments", routes::experiments::routes(state.clone()))
         .nest("/support", routes::support::routes(state.clone()))
-        .nest("/technical", routes::technical::routes())
+        .nest("/technical", routes::technical::routes(state.clone()))
         .nest("/visibility", routes::visibility::routes())
+        .nest("/health", routes::health::routes(state.clone()))
+        .nest("/metrics", routes::metrics::routes(state.clone()))
         .nest("/auth", routes::authentication::routes(state.clone()))
         .nest("/observe", routes::observe::routes(state.clone()))
         .nest(
@@ -182,49 +432,185 @@ pub async fn create_app(config_dir: &Path, database_dir: &Path) -> (Router, AppS
         )
         // Registered before the layers below so assets get the security
         // headers too (a fallback added after layering would bypass them).
-        .fallback_service(ServeDir::new(assets_path))
-        .layer(
-            TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
-                let matched_path = request
-                    .extensions()
-                    .get::<MatchedPath>()
-                    .map(MatchedPath::as_str);
-                let requested_path = request.uri().to_string();
-                debug_span!(
-                    "http_request",
-                    method = ?request.method(),
-                    matched_path,
-                    requested_path,
-                )
-            }),
+        .fallback_service(
+            Router::new()
+                .fallback_service(ServeDir::new(assets_dir))
+                .layer(middleware::from_fn(asset_cache_middleware)),
         )
-        // Layers run outermost-last: cookies → session → language, so the
-        // language resolution sees both the parsed cookies and the user.
+        // gzip or deflate, whichever the client accepts; brotli and zstd
+        // cost more CPU per response for little gain on JSON this size.
+        // The default predicate leaves alone tiny bodies, images and the
+        // SSE spectrum stream (which must reach the browser event by
+        // event), and WebSocket upgrades have no body to compress.
+        .layer(CompressionLayer::new().no_br().no_zstd())
+        .layer(TraceLayer::new_for_http().make_span_with(http_request_span))
+        // Layers run outermost-last: cookies → CSRF → session → language,
+        // so the language resolution sees both the parsed cookies and the
+        // user, and forged requests are turned away before anything else.
         .route_layer(middleware::from_fn(language_middleware))
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
             session_middleware,
         ))
+        .route_layer(middleware::from_fn(csrf_middleware))
         .route_layer(middleware::from_fn(cookies_middleware))
         .layer(middleware::from_fn(slow_request_middleware))
-        .layer(middleware::from_fn(security_headers_middleware));
+        .layer(middleware::from_fn(latency_middleware));
+    let app = with_request_limits(
+        app,
+        salsa_config.http.max_body_bytes,
+        std::time::Duration::from_secs(salsa_config.http.request_timeout_secs),
+    )
+    .layer(middleware::from_fn(security_headers_middleware))
+    // Outermost, so every layer and the trace span see the id.
+    .layer(PropagateRequestIdLayer::x_request_id())
+    .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
-    (app, state)
+    Ok((app, state))
+}
+
+/// How often abandoned OAuth2 logins and expired sessions are deleted
+/// while running. Both are also refused on use, so this only bounds table
+/// growth.
+const LOGIN_PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+fn start_login_purge(connection: ConnectionPool) {
+    crate::supervised_task::spawn_supervised("login_purge", move || {
+        let connection = connection.clone();
+        async move {
+            loop {
+                tokio::time::sleep(LOGIN_PURGE_INTERVAL).await;
+                if let Err(err) = purge_expired_pending_oauth2(connection.clone()).await {
+                    warn!("Failed to purge pending oauth2 logins: {err:?}");
+                }
+                if let Err(err) = purge_expired_sessions(connection.clone()).await {
+                    warn!("Failed to purge expired sessions: {err:?}");
+                }
+            }
+        }
+    });
 }
 
 pub async fn teardown_app(app: AppState) {
+    stop_observing(&app).await;
+    app.telescopes.shutdown_all().await;
+}
+
+/// Stop and save whatever is being observed. Done as soon as shutdown
+/// starts, as the spectrum streams only end when their integration does
+/// and the server waits for them. Safe to call again.
+pub async fn stop_observing(app: &AppState) {
     // Stop any running correlator first — otherwise the session row is left
     // without an end_time and visibility inserts keep firing against a
     // soon-to-be-dropped DB connection.
     let running = app.active_correlator.lock().await.take();
     if let Some(handle) = running {
-        crate::routes::interferometry::stop_correlator_session(&app, handle).await;
+        crate::routes::interferometry::stop_correlator_session(app, handle).await;
+    }
+    save_running_integrations(app).await;
+}
+
+/// Stop and save the integrations still running, so that a restart
+/// doesn't lose them.
+async fn save_running_integrations(app: &AppState) {
+    for name in app.telescopes.get_names().await {
+        if let Some(telescope) = app.telescopes.get(&name).await {
+            save_integration(app, &name, telescope.as_ref()).await;
+        }
+    }
+}
+
+/// Stop and save the integration running on `telescope`, if any, for
+/// whoever holds it. Used before a telescope is shut down.
+pub async fn save_integration(app: &AppState, name: &str, telescope: &dyn Telescope) {
+    if !telescope
+        .get_info()
+        .await
+        .is_ok_and(|info| info.measurement_in_progress)
+    {
+        return;
     }
-    for telescope in app.telescopes.get_all().await {
-        telescope.shutdown().await;
+    let bookings = Booking::fetch_active(app.database_connection.clone())
+        .await
+        .unwrap_or_else(|err| {
+            warn!("Failed to fetch active bookings: {err:?}");
+            Vec::new()
+        });
+    let Some(owner) = integration_owner(app, name, &bookings).await else {
+        warn!("Dropping the integration on {name}: no booking or guest session holds it");
+        return;
+    };
+    info!("Saving the integration on {name} before shutting it down");
+    let label = take_observation_label(app, name).await;
+    stop_and_save_observation(
+        telescope,
+        app.database_connection.clone(),
+        &owner,
+        &app.tle_cache,
+        label.as_deref(),
+    )
+    .await;
+}
+
+/// Who an integration on `telescope_name` belongs to: the user steering
+/// it, else the holder of the active booking or guest session, the same
+/// users `booking_monitor` and `guest_monitor` save for.
+async fn integration_owner(
+    app: &AppState,
+    telescope_name: &str,
+    active_bookings: &[Booking],
+) -> Option<User> {
+    if let Some(holder) = app.telescope_controllers.lock().await.get(telescope_name) {
+        return Some(holder.clone());
+    }
+    if let Some(booking) = active_bookings
+        .iter()
+        .find(|b| b.telescope_name == telescope_name)
+    {
+        return Some(User {
+            id: booking.user_id,
+            name: booking.user_name.clone(),
+            provider: booking.user_provider.clone(),
+            is_admin: false,
+            timezone: None,
+            language: None,
+            display_name: None,
+        });
+    }
+    match GuestSession::fetch_active_for_telescope(app.database_connection.clone(), telescope_name)
+        .await
+    {
+        Ok(guest) => guest.map(|guest| User {
+            id: guest.user_id,
+            name: format!("guest-{}", guest.user_id),
+            provider: "guest".to_string(),
+            is_admin: false,
+            timezone: None,
+            language: None,
+            display_name: None,
+        }),
+        Err(err) => {
+            warn!("Failed to fetch the guest session on {telescope_name}: {err:?}");
+            None
+        }
     }
 }
 
+/// Cut off requests that take longer than `timeout` and refuse bodies
+/// over `max_body_bytes`.
+fn with_request_limits(
+    router: Router,
+    max_body_bytes: usize,
+    timeout: std::time::Duration,
+) -> Router {
+    router
+        .layer(TimeoutLayer::with_status_code(
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            timeout,
+        ))
+        .layer(RequestBodyLimitLayer::new(max_body_bytes))
+}
+
 /// Standard security response headers on every response. The CSP allows
 /// inline scripts/styles (templates use inline <script> blocks and
 /// on*-attributes) but blocks all external origins, so injected content
@@ -262,6 +648,83 @@ async fn security_headers_middleware(req: Request<axum::body::Body>, next: Next)
     response
 }
 
+/// A year, about the longest `max-age` browsers honour.
+const IMMUTABLE_ASSET_MAX_AGE_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// Assets whose names change whenever their content does: the versioned
+/// vendor libraries and the hash-named font files. Browsers can keep
+/// these for good.
+fn is_fingerprinted_asset(path: &str) -> bool {
+    path.starts_with("/vendor/") || (path.starts_with("/fonts/") && path.ends_with(".woff2"))
+}
+
+/// Weak validator for an asset, from its `Last-Modified` date: ServeDir
+/// already answers `If-Modified-Since` from the same date, so the two
+/// validators always agree.
+fn asset_etag(last_modified: &axum::http::HeaderValue) -> Option<axum::http::HeaderValue> {
+    let modified = chrono::DateTime::parse_from_rfc2822(last_modified.to_str().ok()?).ok()?;
+    axum::http::HeaderValue::from_str(&format!("W/\"{:x}\"", modified.timestamp())).ok()
+}
+
+/// Whether an `If-None-Match` header names `etag`, comparing weakly as
+/// RFC 9110 asks for that header.
+fn etag_matches(if_none_match: &axum::http::HeaderValue, etag: &axum::http::HeaderValue) -> bool {
+    let strip = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let (Ok(candidates), Ok(etag)) = (if_none_match.to_str(), etag.to_str()) else {
+        return false;
+    };
+    candidates
+        .split(',')
+        .any(|candidate| candidate.trim() == "*" || strip(candidate) == strip(etag))
+}
+
+/// Caching headers for static assets. ServeDir sends `Last-Modified` and
+/// answers `If-Modified-Since`; this adds an `ETag` and answers
+/// `If-None-Match` with a 304 as well. Fingerprinted assets are marked
+/// immutable; everything else may be cached but must be revalidated on
+/// use, so an edited stylesheet or script is picked up on the next load.
+async fn asset_cache_middleware(req: Request<axum::body::Body>, next: Next) -> Response {
+    use axum::http::{HeaderValue, StatusCode, header};
+
+    let fingerprinted = is_fingerprinted_asset(req.uri().path());
+    let if_none_match = req.headers().get(header::IF_NONE_MATCH).cloned();
+    let mut response = next.run(req).await;
+    if !matches!(response.status(), StatusCode::OK | StatusCode::NOT_MODIFIED) {
+        return response;
+    }
+    let cache_control = if fingerprinted {
+        HeaderValue::from_str(&format!(
+            "public, max-age={IMMUTABLE_ASSET_MAX_AGE_SECS}, immutable"
+        ))
+        .expect("Cache-Control value is valid")
+    } else {
+        HeaderValue::from_static("no-cache")
+    };
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, cache_control);
+    let Some(etag) = response
+        .headers()
+        .get(header::LAST_MODIFIED)
+        .and_then(asset_etag)
+    else {
+        return response;
+    };
+    if response.status() == StatusCode::OK
+        && if_none_match.is_some_and(|candidates| etag_matches(&candidates, &etag))
+    {
+        let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+        for name in [header::CACHE_CONTROL, header::LAST_MODIFIED] {
+            if let Some(value) = response.headers().get(&name) {
+                not_modified.headers_mut().insert(name, value.clone());
+            }
+        }
+        response = not_modified;
+    }
+    response.headers_mut().insert(header::ETAG, etag);
+    response
+}
+
 /// Logs at WARN whenever a request takes longer than this. Helps surface
 /// the freezes users have reported: the suspicion is that long blocking
 /// FFI work in `measure()` starves the runtime, and the symptom would be
@@ -308,3 +771,47 @@ async fn redirect_to_https(
     };
     Redirect::permanent(&https_url).into_response()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::routing::post;
+
+    #[tokio::test]
+    async fn oversized_and_hung_requests_are_cut_off() {
+        let router = Router::new()
+            .route("/echo", post(|body: String| async move { body }))
+            .route(
+                "/hang",
+                get(|| async {
+                    tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+                }),
+            );
+        let router = with_request_limits(router, 16, std::time::Duration::from_millis(100));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let client = reqwest::Client::new();
+
+        let res = client
+            .post(format!("http://{addr}/echo"))
+            .body("short")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        let res = client
+            .post(format!("http://{addr}/echo"))
+            .body("x".repeat(17))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+        let res = client
+            .get(format!("http://{addr}/hang"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
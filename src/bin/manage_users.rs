@@ -1,11 +1,14 @@
-use std::{path::PathBuf, sync::Arc};
+use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
-use tokio::sync::Mutex;
+use tracing::level_filters::LevelFilter;
 use tracing::{error, info};
 
-use salsa::logging::setup_logging;
-use salsa::{database::create_sqlite_database_on_disk, models::user::User};
+use salsa::logging::{LogFormat, setup_logging};
+use salsa::{
+    database::{ConnectionPool, create_sqlite_database_on_disk},
+    models::user::User,
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -32,7 +35,7 @@ enum Commands {
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
-    setup_logging(false);
+    setup_logging(false, LevelFilter::ERROR, LogFormat::default());
     match args.command {
         Commands::AddLocal {
             username,
@@ -41,7 +44,7 @@ async fn main() {
         } => {
             let connection =
                 create_sqlite_database_on_disk(database_dir.join("database.sqlite3")).unwrap();
-            let connection = Arc::new(Mutex::new(connection));
+            let connection = ConnectionPool::single(connection);
             match User::create_local(connection, username, password, "".to_string()).await {
                 Ok(user) => {
                     info!("Added user {:?} to database", user);
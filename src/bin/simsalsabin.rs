@@ -2,39 +2,149 @@ use clap::Parser;
 use std::io::prelude::*;
 use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::process;
+use std::time::Instant;
 use tracing::info;
 
-fn handle(request: &[u8]) -> [u8; 12] {
-    if request
-        == [
-            0x57, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x6F,
-        ]
-    {
-        println!("Got direction request");
-        // ACK
-        [
-            0x58, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20,
-        ]
-    } else if request
-        == [
-            0x57, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0F,
-        ]
-    {
-        println!("Got stop request");
-        // ACK
-        [
-            0x57, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20,
-        ]
-    } else {
-        println!("Unknown request. Data: {:02X?}", request);
+// ROT2PROG protocol, as spoken by the SALSA rotor controllers.
+//
+// Commands are 13 bytes:
+//   [0]      0x57 start byte
+//   [1..=5]  azimuth as five ASCII digits of (degrees + 360) * 100
+//   [6..=10] elevation, same encoding
+//   [11]     command: 0x0F stop, 0x6F get direction, 0x5F set direction
+//   [12]     0x20 end byte
+// The angle fields are ignored (sent as zeros) for stop and get direction.
+//
+// Direction responses are 12 bytes:
+//   [0]      0x58 start byte
+//   [1..=5]  azimuth as five raw digit values (not ASCII) of
+//            (degrees + 360) * 100
+//   [6..=10] elevation, same encoding
+//   [11]     0x20 end byte
+const COMMAND_START: u8 = 0x57;
+const RESPONSE_START: u8 = 0x58;
+const END: u8 = 0x20;
+const CMD_STOP: u8 = 0x0F;
+const CMD_GET_DIRECTION: u8 = 0x6F;
+const CMD_SET_DIRECTION: u8 = 0x5F;
+
+// Defaults for the command line options: parked near zenith, slewing at
+// the same rate as the fake telescope.
+const DEFAULT_START_AZIMUTH_DEG: f64 = 0.0;
+const DEFAULT_START_ELEVATION_DEG: f64 = 90.0;
+const DEFAULT_SLEW_SPEED_DEG_PER_SEC: f64 = 18.0;
+
+struct Rotor {
+    azimuth_deg: f64,
+    elevation_deg: f64,
+    target: Option<(f64, f64)>,
+    slew_speed_deg_per_sec: f64,
+    last_update: Instant,
+}
+
+impl Rotor {
+    fn new(azimuth_deg: f64, elevation_deg: f64, slew_speed_deg_per_sec: f64) -> Rotor {
+        Rotor {
+            azimuth_deg,
+            elevation_deg,
+            target: None,
+            slew_speed_deg_per_sec,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Move toward the target at the slew speed for the wall-clock time
+    /// passed since the last update.
+    fn update(&mut self, now: Instant) {
+        let max_step = self.slew_speed_deg_per_sec * (now - self.last_update).as_secs_f64();
+        self.last_update = now;
+        if let Some((azimuth_deg, elevation_deg)) = self.target {
+            self.azimuth_deg += (azimuth_deg - self.azimuth_deg).clamp(-max_step, max_step);
+            self.elevation_deg += (elevation_deg - self.elevation_deg).clamp(-max_step, max_step);
+            if self.azimuth_deg == azimuth_deg && self.elevation_deg == elevation_deg {
+                self.target = None;
+            }
+        }
+    }
+
+    fn direction_response(&self) -> [u8; 12] {
+        let mut response = [0; 12];
+        response[0] = RESPONSE_START;
+        response[1..=5].copy_from_slice(&encode_angle(self.azimuth_deg));
+        response[6..=10].copy_from_slice(&encode_angle(self.elevation_deg));
+        response[11] = END;
+        response
+    }
+}
+
+/// Five ASCII digits of (degrees + 360) * 100, or None if any byte is not
+/// a digit.
+fn decode_angle(digits: &[u8]) -> Option<f64> {
+    let mut value = 0u32;
+    for &digit in digits {
+        if !digit.is_ascii_digit() {
+            return None;
+        }
+        value = value * 10 + u32::from(digit - b'0');
+    }
+    Some(value as f64 / 100.0 - 360.0)
+}
+
+fn encode_angle(degrees: f64) -> [u8; 5] {
+    let mut value = ((degrees + 360.0) * 100.0).round() as u32;
+    let mut digits = [0; 5];
+    for digit in digits.iter_mut().rev() {
+        *digit = (value % 10) as u8;
+        value /= 10;
+    }
+    digits
+}
+
+fn handle(rotor: &mut Rotor, request: &[u8]) -> [u8; 12] {
+    rotor.update(Instant::now());
+    if request.len() != 13 || request[0] != COMMAND_START || request[12] != END {
+        println!("Malformed request. Data: {:02X?}", request);
         // FIXME: Is this a proper error
-        [
+        return [
             0x57, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ]
+        ];
+    }
+    match request[11] {
+        CMD_GET_DIRECTION => {
+            println!("Got direction request");
+            rotor.direction_response()
+        }
+        CMD_STOP => {
+            println!("Got stop request");
+            rotor.target = None;
+            rotor.direction_response()
+        }
+        CMD_SET_DIRECTION => {
+            match (
+                decode_angle(&request[1..=5]),
+                decode_angle(&request[6..=10]),
+            ) {
+                (Some(azimuth_deg), Some(elevation_deg)) => {
+                    println!(
+                        "Got set direction request: az {azimuth_deg:.2}, el {elevation_deg:.2}"
+                    );
+                    rotor.target = Some((azimuth_deg, elevation_deg));
+                }
+                _ => println!("Set direction with non-digit angle. Data: {:02X?}", request),
+            }
+            rotor.direction_response()
+        }
+        _ => {
+            println!("Unknown request. Data: {:02X?}", request);
+            // FIXME: Is this a proper error
+            [
+                0x57, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ]
+        }
     }
 }
 
-fn controller_connection(mut stream: TcpStream) {
+fn controller_connection(mut stream: TcpStream, rotor: &mut Rotor) {
     loop {
         let mut command_buffer = [0; 13];
         match stream.read(&mut command_buffer) {
@@ -44,7 +154,7 @@ fn controller_connection(mut stream: TcpStream) {
             }
             Ok(13) => {
                 eprintln!("Client sent: {:02X?}", command_buffer);
-                let response = handle(&command_buffer[0..12]);
+                let response = handle(rotor, &command_buffer);
                 // FIXME: Error handling
                 stream.write_all(&response).unwrap();
             }
@@ -67,6 +177,14 @@ fn controller_connection(mut stream: TcpStream) {
 struct Args {
     #[arg(short, long)]
     port: Option<u16>,
+    /// Azimuth the simulated rotor starts at, in degrees.
+    #[arg(long, default_value_t = DEFAULT_START_AZIMUTH_DEG, allow_negative_numbers = true)]
+    start_azimuth: f64,
+    /// Elevation the simulated rotor starts at, in degrees.
+    #[arg(long, default_value_t = DEFAULT_START_ELEVATION_DEG, allow_negative_numbers = true)]
+    start_elevation: f64,
+    #[arg(long, default_value_t = DEFAULT_SLEW_SPEED_DEG_PER_SEC)]
+    slew_speed_deg_per_sec: f64,
 }
 
 fn main() {
@@ -91,12 +209,81 @@ fn main() {
         println!("port:{}", listener.local_addr().unwrap().port());
     }
 
+    let mut rotor = Rotor::new(
+        args.start_azimuth,
+        args.start_elevation,
+        args.slew_speed_deg_per_sec,
+    );
     for stream in listener.incoming() {
         match stream {
-            Ok(stream) => controller_connection(stream),
+            Ok(stream) => controller_connection(stream, &mut rotor),
             Err(err) => {
                 println!("Failed to accept connection ({})", err);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn command(azimuth_deg: f64, elevation_deg: f64, command: u8) -> [u8; 13] {
+        let mut request = [0; 13];
+        request[0] = COMMAND_START;
+        for (field, angle) in [(1, azimuth_deg), (6, elevation_deg)] {
+            for (i, digit) in encode_angle(angle).iter().enumerate() {
+                request[field + i] = digit + b'0';
+            }
+        }
+        request[11] = command;
+        request[12] = END;
+        request
+    }
+
+    fn response_angles(response: &[u8; 12]) -> (f64, f64) {
+        let ascii = |digits: &[u8]| digits.iter().map(|d| d + b'0').collect::<Vec<_>>();
+        (
+            decode_angle(&ascii(&response[1..=5])).unwrap(),
+            decode_angle(&ascii(&response[6..=10])).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_angle_round_trip() {
+        for angle in [-90.0, 0.0, 5.54, 180.0, 359.99] {
+            let ascii: Vec<u8> = encode_angle(angle).iter().map(|d| d + b'0').collect();
+            assert!((decode_angle(&ascii).unwrap() - angle).abs() < 1e-9);
+        }
+        assert_eq!(decode_angle(&[0x00, 0x00, 0x00, 0x00, 0x00]), None);
+    }
+
+    #[test]
+    fn test_set_direction_slews_toward_target() {
+        let mut rotor = Rotor::new(0.0, 90.0, 18.0);
+        handle(&mut rotor, &command(90.0, 30.0, CMD_SET_DIRECTION));
+        rotor.last_update -= Duration::from_secs(1);
+        let (azimuth_deg, elevation_deg) =
+            response_angles(&handle(&mut rotor, &command(0.0, 0.0, CMD_GET_DIRECTION)));
+        assert!((azimuth_deg - 18.0).abs() < 0.1);
+        assert!((elevation_deg - 72.0).abs() < 0.1);
+
+        rotor.last_update -= Duration::from_secs(10);
+        let response = handle(&mut rotor, &command(0.0, 0.0, CMD_GET_DIRECTION));
+        assert_eq!(response_angles(&response), (90.0, 30.0));
+        assert_eq!(rotor.target, None);
+    }
+
+    #[test]
+    fn test_stop_halts_slew() {
+        let mut rotor = Rotor::new(0.0, 90.0, 18.0);
+        handle(&mut rotor, &command(90.0, 30.0, CMD_SET_DIRECTION));
+        handle(&mut rotor, &command(0.0, 0.0, CMD_STOP));
+        rotor.last_update -= Duration::from_secs(1);
+        let response = handle(&mut rotor, &command(0.0, 0.0, CMD_GET_DIRECTION));
+        let (azimuth_deg, elevation_deg) = response_angles(&response);
+        assert!(azimuth_deg < 0.1);
+        assert!((elevation_deg - 90.0).abs() < 0.1);
+    }
+}
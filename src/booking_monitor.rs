@@ -8,7 +8,7 @@ use crate::app::AppState;
 use crate::models::booking::Booking;
 use crate::models::user::User;
 use crate::routes::interferometry::stop_correlator_session;
-use crate::routes::observe::stop_and_save_observation;
+use crate::routes::observe::{stop_and_save_observation, take_observation_label};
 
 pub fn start(state: AppState) {
     crate::supervised_task::spawn_supervised("booking_monitor", move || {
@@ -42,6 +42,7 @@ pub fn start(state: AppState) {
                         is_admin: false,
                         timezone: None,
                         language: None,
+                        display_name: None,
                     });
                     let previous_user = active_users.get(telescope_name).cloned();
 
@@ -72,11 +73,13 @@ pub fn start(state: AppState) {
                         None => continue,
                     };
 
+                    let label = take_observation_label(&state, telescope_name).await;
                     stop_and_save_observation(
                         telescope.as_ref(),
                         state.database_connection.clone(),
                         &prev_user,
                         &state.tle_cache,
+                        label.as_deref(),
                     )
                     .await;
 
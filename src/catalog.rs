@@ -0,0 +1,258 @@
+//! Named-source catalog, so observers can type "M31" or "Cas A" instead
+//! of coordinates. A small catalog is compiled in; `[catalog] path` in
+//! config.toml replaces it with a file of the same format. Sources with a
+//! proper motion are moved from J2000 to the time they are looked up.
+
+use crate::coords::{J2000, apply_proper_motion, julian_years_since_j2000};
+use crate::models::telescope_types::TelescopeTarget;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tracing::{error, info};
+
+const BUNDLED_CATALOG: &str = include_str!("../source_catalog.toml");
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CatalogConfig {
+    /// Catalog file to load instead of the bundled one.
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CatalogFile {
+    sources: Vec<CatalogSource>,
+}
+
+#[derive(Deserialize)]
+struct CatalogSource {
+    name: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    /// J2000 right ascension in degrees.
+    ra: f64,
+    /// J2000 declination in degrees.
+    dec: f64,
+    /// Proper motion in R.A. times cos(Dec.), in mas/yr.
+    #[serde(default)]
+    pm_ra: f64,
+    /// Proper motion in declination, in mas/yr.
+    #[serde(default)]
+    pm_dec: f64,
+}
+
+/// A source's J2000 position in radians and proper motion in mas/yr.
+#[derive(Debug, Clone, Copy)]
+struct CatalogPosition {
+    ra: f64,
+    dec: f64,
+    pm_ra: f64,
+    pm_dec: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct SourceCatalog {
+    /// Keyed by `normalize`d name and aliases.
+    by_name: HashMap<String, CatalogPosition>,
+    /// Primary names, in file order, for suggestions.
+    names: Vec<String>,
+}
+
+/// Case, spaces, hyphens and underscores don't distinguish sources.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl SourceCatalog {
+    pub fn parse(toml_str: &str) -> Result<SourceCatalog, String> {
+        let file: CatalogFile = toml::from_str(toml_str).map_err(|err| err.to_string())?;
+        let mut catalog = SourceCatalog::default();
+        for source in file.sources {
+            let position = CatalogPosition {
+                ra: source.ra.to_radians(),
+                dec: source.dec.to_radians(),
+                pm_ra: source.pm_ra,
+                pm_dec: source.pm_dec,
+            };
+            for name in std::iter::once(&source.name).chain(&source.aliases) {
+                catalog.by_name.insert(normalize(name), position);
+            }
+            catalog.names.push(source.name);
+        }
+        Ok(catalog)
+    }
+
+    /// Load the configured catalog, falling back to the bundled one if it
+    /// can't be read so a typo in the path doesn't take the site down.
+    pub fn load(config: &CatalogConfig) -> SourceCatalog {
+        if let Some(path) = &config.path {
+            match std::fs::read_to_string(path)
+                .map_err(|err| err.to_string())
+                .and_then(|content| SourceCatalog::parse(&content))
+            {
+                Ok(catalog) => {
+                    info!("Loaded {} named sources from {path}", catalog.names.len());
+                    return catalog;
+                }
+                Err(err) => error!("Failed to load source catalog {path}: {err}; using bundled"),
+            }
+        }
+        SourceCatalog::parse(BUNDLED_CATALOG).expect("Bundled source catalog should parse")
+    }
+
+    /// The source named `name` where it is at `when`, still referred to
+    /// the J2000 equinox; pointing precesses it from there.
+    pub fn lookup(&self, name: &str, when: DateTime<Utc>) -> Option<TelescopeTarget> {
+        self.by_name.get(&normalize(name)).map(|position| {
+            let (right_ascension, declination) = if position.pm_ra == 0.0 && position.pm_dec == 0.0
+            {
+                (position.ra, position.dec)
+            } else {
+                apply_proper_motion(
+                    position.ra,
+                    position.dec,
+                    position.pm_ra,
+                    position.pm_dec,
+                    julian_years_since_j2000(when),
+                )
+            };
+            TelescopeTarget::Equatorial {
+                right_ascension,
+                declination,
+                epoch: J2000,
+            }
+        })
+    }
+
+    /// The primary name of the source `target` points at, if it was looked
+    /// up here. Proper motion moves a source far less than an arcsecond
+    /// between looking it up and asking, so that is close enough.
+    pub fn name_of(&self, target: &TelescopeTarget, when: DateTime<Utc>) -> Option<&str> {
+        let TelescopeTarget::Equatorial {
+            right_ascension,
+            declination,
+            epoch,
+        } = *target
+        else {
+            return None;
+        };
+        if epoch != J2000 {
+            return None;
+        }
+        let arcsecond = (1.0f64 / 3600.0).to_radians();
+        self.names.iter().map(String::as_str).find(|name| {
+            matches!(
+                self.lookup(name, when),
+                Some(TelescopeTarget::Equatorial { right_ascension: ra, declination: dec, .. })
+                    if (ra - right_ascension).abs() < arcsecond
+                        && (dec - declination).abs() < arcsecond
+            )
+        })
+    }
+
+    /// A few primary names, for "did you mean" style hints.
+    pub fn example_names(&self) -> String {
+        self.names
+            .iter()
+            .take(4)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn bundled_catalog_parses() {
+        let catalog = SourceCatalog::load(&CatalogConfig::default());
+        assert!(catalog.lookup("Cas A", Utc::now()).is_some());
+    }
+
+    #[test]
+    fn lookup_ignores_case_spacing_and_aliases() {
+        let catalog = SourceCatalog::parse(
+            r#"
+            [[sources]]
+            name = "Cas A"
+            aliases = ["Cassiopeia A"]
+            ra = 350.8584
+            dec = 58.8117
+            "#,
+        )
+        .unwrap();
+        let expected = Some(TelescopeTarget::Equatorial {
+            right_ascension: 350.8584f64.to_radians(),
+            declination: 58.8117f64.to_radians(),
+            epoch: J2000,
+        });
+        let now = Utc::now();
+        assert_eq!(catalog.lookup("Cas A", now), expected);
+        assert_eq!(catalog.lookup("casa", now), expected);
+        assert_eq!(catalog.lookup(" CAS-A ", now), expected);
+        assert_eq!(catalog.lookup("cassiopeia a", now), expected);
+        assert_eq!(catalog.lookup("Cyg A", now), None);
+    }
+
+    #[test]
+    fn name_of_finds_looked_up_sources() {
+        let catalog = SourceCatalog::load(&CatalogConfig::default());
+        let now = Utc::now();
+        let target = catalog.lookup("cassiopeia a", now).unwrap();
+        assert_eq!(catalog.name_of(&target, now), Some("Cas A"));
+        let elsewhere = TelescopeTarget::Equatorial {
+            right_ascension: 1.0,
+            declination: 0.2,
+            epoch: J2000,
+        };
+        assert_eq!(catalog.name_of(&elsewhere, now), None);
+        assert_eq!(catalog.name_of(&TelescopeTarget::Sun, now), None);
+    }
+
+    #[test]
+    fn proper_motion_moves_sources_from_j2000() {
+        let catalog = SourceCatalog::parse(
+            r#"
+            [[sources]]
+            name = "Barnard's Star"
+            ra = 269.4521
+            dec = 4.6934
+            pm_ra = -802.8
+            pm_dec = 10362.5
+            "#,
+        )
+        .unwrap();
+        let position = |when| match catalog.lookup("Barnard's Star", when) {
+            Some(TelescopeTarget::Equatorial {
+                right_ascension,
+                declination,
+                epoch,
+            }) => {
+                assert_eq!(epoch, J2000);
+                (right_ascension, declination)
+            }
+            other => panic!("Unexpected target {other:?}"),
+        };
+        let arcsec = (1.0 / 3600.0f64).to_radians();
+
+        let (ra, dec) = position(Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 0).unwrap());
+        assert!((ra - 269.4521f64.to_radians()).abs() < 1e-3 * arcsec);
+        assert!((dec - 4.6934f64.to_radians()).abs() < 1e-3 * arcsec);
+
+        // About 10.4"/yr north, so over 25 years it has moved some 4'.
+        let when = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let (ra, dec) = position(when);
+        let years = julian_years_since_j2000(when);
+        assert!((dec - 4.6934f64.to_radians() - 10.3625 * years * arcsec).abs() < 0.5 * arcsec);
+        // Moving it back by the same time gives the catalog position.
+        let (ra0, dec0) = apply_proper_motion(ra, dec, -802.8, 10362.5, -years);
+        assert!((ra0 - 269.4521f64.to_radians()).abs() < 0.01 * arcsec);
+        assert!((dec0 - 4.6934f64.to_radians()).abs() < 0.01 * arcsec);
+    }
+}
@@ -0,0 +1,166 @@
+//! Server clock sanity check.
+//!
+//! Bookings, sessions and the booking monitor all compare against
+//! `Utc::now()`. On a field machine without working NTP the clock can
+//! drift by minutes, and the only symptom is bookings starting or ending
+//! at the wrong time. At startup we log the system time so it is easy to
+//! spot in the journal, and if `[clock] ntp_server` is configured we
+//! periodically ask it for the time and warn when we are too far off.
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Deserialize;
+use tokio::net::UdpSocket;
+use tokio::time::{Duration, interval, timeout};
+use tracing::{info, warn};
+
+const NTP_TIMEOUT: Duration = Duration::from_secs(5);
+/// Seconds between the NTP epoch (1900-01-01) and the Unix epoch.
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClockConfig {
+    /// `host:port` or `host` of an NTP server to compare against. The
+    /// check is disabled when unset.
+    #[serde(default)]
+    pub ntp_server: Option<String>,
+    #[serde(default = "default_max_offset_secs")]
+    pub max_offset_secs: f64,
+    #[serde(default = "default_check_interval_secs")]
+    pub check_interval_secs: u64,
+}
+
+fn default_max_offset_secs() -> f64 {
+    2.0
+}
+
+fn default_check_interval_secs() -> u64 {
+    60 * 60
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            ntp_server: None,
+            max_offset_secs: default_max_offset_secs(),
+            check_interval_secs: default_check_interval_secs(),
+        }
+    }
+}
+
+/// Build a 48-byte SNTP client request (version 4, mode 3).
+fn sntp_request() -> [u8; 48] {
+    let mut packet = [0u8; 48];
+    packet[0] = (4 << 3) | 3;
+    packet
+}
+
+/// Read the server's transmit timestamp (bytes 40..48) from an SNTP reply.
+fn parse_transmit_timestamp(reply: &[u8]) -> Option<DateTime<Utc>> {
+    if reply.len() < 48 {
+        return None;
+    }
+    let seconds = u32::from_be_bytes(reply[40..44].try_into().ok()?) as i64;
+    let fraction = u32::from_be_bytes(reply[44..48].try_into().ok()?) as u64;
+    if seconds == 0 {
+        // Kiss-o'-death or unsynchronised server.
+        return None;
+    }
+    let nanos = ((fraction * 1_000_000_000) >> 32) as u32;
+    Utc.timestamp_opt(seconds - NTP_UNIX_OFFSET, nanos).single()
+}
+
+/// Query `server` and return how far the local clock is ahead of it, in
+/// seconds. Half the round trip is credited to the reply's transit time.
+async fn query_offset(server: &str) -> Result<f64, String> {
+    let address = if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{server}:123")
+    };
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .map_err(|e| format!("bind failed: {e}"))?;
+    socket
+        .connect(&address)
+        .await
+        .map_err(|e| format!("failed to resolve {address}: {e}"))?;
+    let sent_at = Utc::now();
+    socket
+        .send(&sntp_request())
+        .await
+        .map_err(|e| format!("send failed: {e}"))?;
+    let mut reply = [0u8; 48];
+    let len = timeout(NTP_TIMEOUT, socket.recv(&mut reply))
+        .await
+        .map_err(|_| format!("no reply within {NTP_TIMEOUT:?}"))?
+        .map_err(|e| format!("receive failed: {e}"))?;
+    let received_at = Utc::now();
+    let server_time =
+        parse_transmit_timestamp(&reply[..len]).ok_or("malformed or unsynchronised reply")?;
+    let half_round_trip = (received_at - sent_at) / 2;
+    Ok(((sent_at + half_round_trip) - server_time).num_milliseconds() as f64 / 1000.0)
+}
+
+pub fn start_clock_check(config: ClockConfig) {
+    info!(
+        "System clock at startup: {} UTC",
+        Utc::now().format("%Y-%m-%d %H:%M:%S")
+    );
+    let Some(server) = config.ntp_server.clone() else {
+        info!("Clock check: no ntp_server configured, not comparing against NTP");
+        return;
+    };
+    crate::supervised_task::spawn_supervised("clock_check", move || {
+        let server = server.clone();
+        let max_offset_secs = config.max_offset_secs;
+        let check_interval = Duration::from_secs(config.check_interval_secs.max(60));
+        async move {
+            // First tick fires immediately so skew shows up in the startup log.
+            let mut ticker = interval(check_interval);
+            loop {
+                ticker.tick().await;
+                match query_offset(&server).await {
+                    Ok(offset) if offset.abs() > max_offset_secs => warn!(
+                        "System clock is {offset:+.1} s off from {server} \
+                         (limit {max_offset_secs} s); bookings and sessions \
+                         will start and end at the wrong time"
+                    ),
+                    Ok(offset) => info!("Clock check: {offset:+.3} s relative to {server}"),
+                    Err(e) => warn!("Clock check: failed to query {server}: {e}"),
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_transmit_timestamp() {
+        let mut reply = [0u8; 48];
+        // 2026-01-01T00:00:00.5Z
+        let seconds = (1_767_225_600 + NTP_UNIX_OFFSET) as u32;
+        reply[40..44].copy_from_slice(&seconds.to_be_bytes());
+        reply[44..48].copy_from_slice(&(1u32 << 31).to_be_bytes());
+        let parsed = parse_transmit_timestamp(&reply).unwrap();
+        assert_eq!(
+            parsed,
+            Utc.timestamp_opt(1_767_225_600, 500_000_000).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_transmit_timestamp_rejects_bad_replies() {
+        assert_eq!(parse_transmit_timestamp(&[0u8; 20]), None);
+        assert_eq!(parse_transmit_timestamp(&[0u8; 48]), None);
+    }
+
+    #[test]
+    fn test_sntp_request_is_client_mode() {
+        let request = sntp_request();
+        assert_eq!(request[0] & 0x07, 3);
+        assert_eq!((request[0] >> 3) & 0x07, 4);
+    }
+}
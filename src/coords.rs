@@ -1,4 +1,4 @@
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use sgp4::Elements;
 use std::f64::consts::PI;
@@ -11,17 +11,22 @@ const EC: f64 = 0.40909260052;
 const ARA: f64 = 1.5 * PI;
 const ADE: f64 = PI / 6.0;
 const R_EARTH: f64 = 6378.135; // Earth radius in km
+const EARTH_ROTATION_RATE: f64 = 7.2921159e-5; // sidereal, in rad/s
 const FULL_CIRCLE: f64 = 2.0 * PI;
 
+/// The standard epoch of catalog coordinates, as a Julian year.
+pub const J2000: f64 = 2000.0;
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
 pub struct Location {
     pub longitude: f64,
     pub latitude: f64,
 }
 
-/// Onsala Space Observatory, where all SALSA telescopes are co-located
-/// (they are too close together to differ in az/el for any target).
-/// The same values are used as defaults in config.toml.example.
+/// Onsala Space Observatory, home of the SALSA telescopes. Each telescope
+/// has its own `location` in the config; this is the fallback when that is
+/// unavailable, e.g. for observations from a telescope that has since been
+/// removed. The same values are used in config.toml.example.
 pub const ONSALA_LOCATION: Location = Location {
     longitude: 11.9188 * PI / 180.0,
     latitude: 57.3934 * PI / 180.0,
@@ -34,6 +39,9 @@ pub const ONSALA_LOCATION: Location = Location {
 /// quoted in templates/visibility.html — update those too when changing.
 pub const PRACTICAL_ELEVATION_LIMIT_DEG: f64 = 15.0;
 
+/// Rest frequency of the 21 cm neutral hydrogen line.
+pub const HI_REST_FREQ_HZ: f64 = 1_420_405_751.768;
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
 pub struct Direction {
     pub azimuth: f64,
@@ -110,6 +118,82 @@ fn julian_day(when: DateTime<Utc>) -> f64 {
     2451545.0 + (diff.num_milliseconds() as f64 / (24.0 * 60.0 * 60.0 * 1000.0))
 }
 
+/// Time since the J2000.0 epoch in Julian years (365.25 days), the unit
+/// catalog proper motions are quoted in.
+pub fn julian_years_since_j2000(when: DateTime<Utc>) -> f64 {
+    (julian_day(when) - 2451545.0) / 365.25
+}
+
+/// Propagate a catalog position by its proper motion.
+/// # Arguments
+/// * `ra`, `dec` - Position at the catalog epoch, in radians
+/// * `pm_ra_cosdec` - Proper motion in R.A. multiplied by cos(dec), in mas/yr
+///   (the convention used by Hipparcos/Gaia and SIMBAD)
+/// * `pm_dec` - Proper motion in declination, in mas/yr
+/// * `years` - Julian years to propagate; negative goes back in time
+/// # Returns
+/// * `(ra, dec)` at the new epoch, in radians, R.A. in [0, 2π)
+///
+/// The motion is applied as a straight line on the unit sphere rather than
+/// by adding offsets to R.A./Dec., so positions near the celestial poles
+/// stay well-behaved. Radial velocity and parallax are ignored; for the
+/// few decades we care about that is far below the beam size.
+pub fn apply_proper_motion(
+    ra: f64,
+    dec: f64,
+    pm_ra_cosdec: f64,
+    pm_dec: f64,
+    years: f64,
+) -> (f64, f64) {
+    let mas_to_rad = (1.0 / 3_600_000.0_f64).to_radians();
+    let mu_a = pm_ra_cosdec * mas_to_rad * years;
+    let mu_d = pm_dec * mas_to_rad * years;
+    // Unit vector towards the source plus displacement along the local
+    // east (increasing R.A.) and north (increasing Dec.) directions.
+    let x = dec.cos() * ra.cos() - mu_a * ra.sin() - mu_d * dec.sin() * ra.cos();
+    let y = dec.cos() * ra.sin() + mu_a * ra.cos() - mu_d * dec.sin() * ra.sin();
+    let z = dec.sin() + mu_d * dec.cos();
+    let new_ra = y.atan2(x).rem_euclid(FULL_CIRCLE);
+    let new_dec = (z / (x * x + y * y + z * z).sqrt()).asin();
+    (new_ra, new_dec)
+}
+
+/// Julian epoch of `when`, e.g. 2026.5 halfway through 2026.
+pub fn julian_epoch(when: DateTime<Utc>) -> f64 {
+    J2000 + julian_years_since_j2000(when)
+}
+
+/// Precess equatorial coordinates between two epochs using the IAU 1976
+/// angles (Meeus, Astronomical Algorithms, ch. 21).
+/// # Arguments
+/// * `ra`, `dec` - Position at `from_epoch`, in radians
+/// * `from_epoch`, `to_epoch` - Julian epochs, e.g. `J2000`
+/// # Returns
+/// * `(ra, dec)` at `to_epoch`, in radians, R.A. in [0, 2π)
+///
+/// Over a few decades this moves sources by tens of arcminutes, most of it
+/// in R.A. and the most near the celestial poles.
+pub fn precess(ra: f64, dec: f64, from_epoch: f64, to_epoch: f64) -> (f64, f64) {
+    // Julian centuries from J2000 to the start epoch, and of the interval.
+    let t0 = (from_epoch - J2000) / 100.0;
+    let t = (to_epoch - from_epoch) / 100.0;
+    let arcsec = |x: f64| (x / 3600.0).to_radians();
+    let base = 2306.2181 + 1.39656 * t0 - 0.000139 * t0 * t0;
+    let zeta = arcsec(base * t + (0.30188 - 0.000344 * t0) * t * t + 0.017998 * t * t * t);
+    let z = arcsec(base * t + (1.09468 + 0.000066 * t0) * t * t + 0.018203 * t * t * t);
+    let theta = arcsec(
+        (2004.3109 - 0.85330 * t0 - 0.000217 * t0 * t0) * t
+            - (0.42665 + 0.000217 * t0) * t * t
+            - 0.041833 * t * t * t,
+    );
+    let a = dec.cos() * (ra + zeta).sin();
+    let b = theta.cos() * dec.cos() * (ra + zeta).cos() - theta.sin() * dec.sin();
+    let c = theta.sin() * dec.cos() * (ra + zeta).cos() + theta.cos() * dec.sin();
+    let new_ra = (a.atan2(b) + z).rem_euclid(FULL_CIRCLE);
+    let new_dec = c.asin();
+    (new_ra, new_dec)
+}
+
 fn gmst(when: DateTime<Utc>) -> f64 {
     // Algoritm from https://aa.usno.navy.mil/faq/GAST
     let jd = julian_day(when);
@@ -123,11 +207,71 @@ fn gmst(when: DateTime<Utc>) -> f64 {
     gmst * PI / 12.0
 }
 
-/// Convert equatorial coordinates to horizontal coordinates
+/// Time for an az/el mount, which drives both axes at once, to get from
+/// `current` to `target`.
+/// # Arguments
+/// * `azimuth_rate`, `elevation_rate` - Axis speeds in rad/s
+/// # Returns
+/// * Seconds until the slower axis arrives, or None if an axis that still has
+///   to move is not moving
+pub fn seconds_to_direction(
+    current: Direction,
+    target: Direction,
+    azimuth_rate: f64,
+    elevation_rate: f64,
+) -> Option<f64> {
+    let axis_seconds = |distance: f64, rate: f64| {
+        // Within the rotor's 0.1° resolution counts as arrived.
+        if distance < 0.1_f64.to_radians() {
+            Some(0.0)
+        } else if rate.abs() < 1e-9 {
+            None
+        } else {
+            Some(distance / rate.abs())
+        }
+    };
+    let az = axis_seconds((target.azimuth - current.azimuth).abs(), azimuth_rate)?;
+    let el = axis_seconds((target.elevation - current.elevation).abs(), elevation_rate)?;
+    Some(az.max(el))
+}
+
+/// Great-circle angle between two horizontal directions, in radians.
+/// Uses the haversine formula, which stays accurate for the small
+/// separations a tracking dish is off by.
+pub fn angular_separation(a: Direction, b: Direction) -> f64 {
+    let half_elevation = (b.elevation - a.elevation) / 2.0;
+    let half_azimuth = (b.azimuth - a.azimuth) / 2.0;
+    let haversine = half_elevation.sin().powi(2)
+        + a.elevation.cos() * b.elevation.cos() * half_azimuth.sin().powi(2);
+    2.0 * haversine.sqrt().min(1.0).asin()
+}
+
+/// Atmospheric refraction at `elevation`, from Bennett's formula (1982).
+/// Refraction lifts sources above their geometric position, so this is
+/// added to a computed elevation to get where the dish has to point.
+/// Bennett is defined for apparent elevation; using it with the geometric
+/// one is off by well under an arcminute, far below the beam width.
+/// # Arguments
+/// * `elevation` - Elevation in radians
+/// # Returns
+/// * Refraction in radians, zero for directions well below the horizon
+pub fn refraction_correction(elevation: f64) -> f64 {
+    let h = elevation.to_degrees();
+    // The formula diverges at h = -4.4°; nothing is observed that low.
+    if h < -1.0 {
+        return 0.0;
+    }
+    let arcmin = 1.0 / (h + 7.31 / (h + 4.4)).to_radians().tan();
+    (arcmin / 60.0).to_radians().max(0.0)
+}
+
+/// Convert J2000 equatorial coordinates to horizontal coordinates. The
+/// position is precessed to the epoch of `when` before the hour angle is
+/// computed.
 /// # Arguments
 /// * `location` - Location struct with latitude and longitude
-/// * `ra` - Right ascension in radians
-/// * `dec` - Declination in radians
+/// * `ra` - Right ascension (J2000) in radians
+/// * `dec` - Declination (J2000) in radians
 /// # Returns
 /// * `Direction` struct with azimuth and elevation
 pub fn horizontal_from_equatorial(
@@ -135,6 +279,18 @@ pub fn horizontal_from_equatorial(
     when: DateTime<Utc>,
     ra: f64,
     dec: f64,
+) -> Direction {
+    let (ra, dec) = precess(ra, dec, J2000, julian_epoch(when));
+    horizontal_from_equatorial_of_date(location, when, ra, dec)
+}
+
+/// As `horizontal_from_equatorial`, for coordinates already referred to
+/// the equator and equinox of `when`, such as the computed Sun and Moon.
+fn horizontal_from_equatorial_of_date(
+    location: Location,
+    when: DateTime<Utc>,
+    ra: f64,
+    dec: f64,
 ) -> Direction {
     // Assume input in radians
 
@@ -156,7 +312,62 @@ pub fn horizontal_from_equatorial(
     }
 }
 
-fn equatorial_from_galactic(l: f64, b: f64) -> (f64, f64) {
+/// Rise, set and transit of a fixed sky position around `date` (UTC).
+/// # Arguments
+/// * `ra`, `dec` - Position (J2000) in radians
+/// * `min_elevation` - Elevation in radians that counts as risen, normally
+///   the telescope's lower limit rather than the geometric horizon
+/// # Returns
+/// * `(rise, set, transit)` for the transit that falls on `date`; the rise
+///   may be on the day before and the set on the day after. Rise and set
+///   are None when the position never goes below `min_elevation`
+///   (circumpolar) or never reaches it.
+pub fn rise_set_transit(
+    location: Location,
+    ra: f64,
+    dec: f64,
+    min_elevation: f64,
+    date: NaiveDate,
+) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>, DateTime<Utc>) {
+    let day_start = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap());
+    let (ra, dec) = precess(ra, dec, J2000, julian_epoch(day_start));
+    let sidereal_secs = |angle: f64| {
+        chrono::Duration::milliseconds((angle / EARTH_ROTATION_RATE * 1000.0).round() as i64)
+    };
+    // Local hour angle at the start of the day; transit is when it wraps to 0.
+    let lha = (gmst(day_start) + location.longitude - ra).rem_euclid(FULL_CIRCLE);
+    let transit = day_start + sidereal_secs((FULL_CIRCLE - lha).rem_euclid(FULL_CIRCLE));
+
+    let lat = location.latitude;
+    let cos_h0 = (min_elevation.sin() - lat.sin() * dec.sin()) / (lat.cos() * dec.cos());
+    if !(-1.0..=1.0).contains(&cos_h0) {
+        return (None, None, transit);
+    }
+    let half_arc = sidereal_secs(cos_h0.acos());
+    (Some(transit - half_arc), Some(transit + half_arc), transit)
+}
+
+/// Convert horizontal coordinates to equatorial coordinates, the inverse of
+/// `horizontal_from_equatorial`.
+/// # Returns
+/// * `(ra, dec)` (J2000) in radians
+pub fn equatorial_from_horizontal(
+    location: Location,
+    when: DateTime<Utc>,
+    direction: Direction,
+) -> (f64, f64) {
+    let lat = location.latitude;
+    let (az, el) = (direction.azimuth, direction.elevation);
+    let dec = (el.sin() * lat.sin() + el.cos() * lat.cos() * az.cos()).asin();
+    let lha = (-az.sin() * el.cos()).atan2(el.sin() * lat.cos() - el.cos() * lat.sin() * az.cos());
+    let ra = (gmst(when) + location.longitude - lha).rem_euclid(FULL_CIRCLE);
+    precess(ra, dec, julian_epoch(when), J2000)
+}
+
+/// Convert galactic coordinates to J2000 equatorial coordinates.
+/// # Returns
+/// * `(ra, dec)` in radians, R.A. in [0, 2π)
+pub fn equatorial_from_galactic(l: f64, b: f64) -> (f64, f64) {
     // Assume input in radians
 
     // Calculation from https://physics.stackexchange.com/questions/88663/converting-between-galactic-and-ecliptic-coordinates
@@ -171,7 +382,102 @@ fn equatorial_from_galactic(l: f64, b: f64) -> (f64, f64) {
     let ra = (b.cos() * (l_ncp - l).sin())
         .atan2(dec_ngp.cos() * b.sin() - dec_ngp.sin() * b.cos() * (l_ncp - l).cos())
         + ra_ngp;
-    (ra, dec)
+    (ra.rem_euclid(FULL_CIRCLE), dec)
+}
+
+/// Convert J2000 equatorial coordinates to galactic coordinates, the
+/// inverse of `equatorial_from_galactic`.
+/// # Returns
+/// * `(l, b)` in radians, longitude in [0, 2π)
+pub fn galactic_from_equatorial(ra: f64, dec: f64) -> (f64, f64) {
+    // Same galactic pole as in `equatorial_from_galactic`.
+    let ra_ngp: f64 = 192.85948_f64.to_radians();
+    let dec_ngp: f64 = 27.12825_f64.to_radians();
+    let l_ncp: f64 = 122.93192_f64.to_radians();
+    let b = (dec.sin() * dec_ngp.sin() + dec.cos() * dec_ngp.cos() * (ra - ra_ngp).cos()).asin();
+    let l = l_ncp
+        - (dec.cos() * (ra - ra_ngp).sin())
+            .atan2(dec.sin() * dec_ngp.cos() - dec.cos() * dec_ngp.sin() * (ra - ra_ngp).cos());
+    (l.rem_euclid(FULL_CIRCLE), b)
+}
+
+/// A coordinate of a target, for saying which one was entered out of range.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Coordinate {
+    RightAscension,
+    Declination,
+    GalacticLongitude,
+    GalacticLatitude,
+    Elevation,
+}
+
+/// A coordinate entered outside `min_deg..=max_deg`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CoordinateOutOfRange {
+    pub coordinate: Coordinate,
+    pub min_deg: f64,
+    pub max_deg: f64,
+}
+
+impl std::fmt::Display for CoordinateOutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self.coordinate {
+            Coordinate::RightAscension => "R.A.",
+            Coordinate::Declination => "Declination",
+            Coordinate::GalacticLongitude => "Galactic longitude",
+            Coordinate::GalacticLatitude => "Galactic latitude",
+            Coordinate::Elevation => "Elevation",
+        };
+        write!(
+            f,
+            "{name} must be between {:.0} and {:.0}°.",
+            self.min_deg, self.max_deg
+        )
+    }
+}
+
+fn checked_deg(
+    coordinate: Coordinate,
+    value_deg: f64,
+    min_deg: f64,
+    max_deg: f64,
+) -> Result<f64, CoordinateOutOfRange> {
+    if (min_deg..=max_deg).contains(&value_deg) {
+        Ok(value_deg.to_radians())
+    } else {
+        Err(CoordinateOutOfRange {
+            coordinate,
+            min_deg,
+            max_deg,
+        })
+    }
+}
+
+/// Check equatorial coordinates entered in degrees.
+/// # Returns
+/// * `(ra, dec)` in radians, R.A. in [0, 2π)
+pub fn normalize_equatorial(ra_deg: f64, dec_deg: f64) -> Result<(f64, f64), CoordinateOutOfRange> {
+    let ra = checked_deg(Coordinate::RightAscension, ra_deg, 0.0, 360.0)?;
+    let dec = checked_deg(Coordinate::Declination, dec_deg, -90.0, 90.0)?;
+    Ok((ra.rem_euclid(FULL_CIRCLE), dec))
+}
+
+/// Check galactic coordinates entered in degrees.
+/// # Returns
+/// * `(l, b)` in radians, longitude in [0, 2π)
+pub fn normalize_galactic(l_deg: f64, b_deg: f64) -> Result<(f64, f64), CoordinateOutOfRange> {
+    let l = checked_deg(Coordinate::GalacticLongitude, l_deg, 0.0, 360.0)?;
+    let b = checked_deg(Coordinate::GalacticLatitude, b_deg, -90.0, 90.0)?;
+    Ok((l.rem_euclid(FULL_CIRCLE), b))
+}
+
+/// Check horizontal coordinates entered in degrees. Any azimuth is
+/// accepted and taken modulo 360°.
+pub fn normalize_horizontal(az_deg: f64, el_deg: f64) -> Result<Direction, CoordinateOutOfRange> {
+    Ok(Direction {
+        azimuth: az_deg.to_radians().rem_euclid(FULL_CIRCLE),
+        elevation: checked_deg(Coordinate::Elevation, el_deg, 0.0, 90.0)?,
+    })
 }
 
 pub fn horizontal_from_galactic(
@@ -230,14 +536,114 @@ fn equatorial_from_sun(when: DateTime<Utc>) -> (f64, f64) {
 
 pub fn horizontal_from_sun(location: Location, when: DateTime<Utc>) -> Direction {
     let (ra, dec) = equatorial_from_sun(when);
-    horizontal_from_equatorial(location, when, ra, dec)
+    horizontal_from_equatorial_of_date(location, when, ra, dec)
 }
 
-pub fn vlsrcorr_from_galactic(l: f64, b: f64, when: DateTime<Utc>) -> f64 {
-    // From http://web.mit.edu/8.13/www/srt_software/vlsr.pdf
+// Periodic terms of the truncated ELP-2000/82 lunar theory (Meeus,
+// Astronomical Algorithms, ch. 47). Multiples of D, M, M', F followed by the
+// coefficient for longitude (1e-6 deg) and distance (m).
+const MOON_LONGITUDE_DISTANCE_TERMS: [(f64, f64, f64, f64, f64, f64); 22] = [
+    (0.0, 0.0, 1.0, 0.0, 6288774.0, -20905355.0),
+    (2.0, 0.0, -1.0, 0.0, 1274027.0, -3699111.0),
+    (2.0, 0.0, 0.0, 0.0, 658314.0, -2955968.0),
+    (0.0, 0.0, 2.0, 0.0, 213618.0, -569925.0),
+    (0.0, 1.0, 0.0, 0.0, -185116.0, 48888.0),
+    (0.0, 0.0, 0.0, 2.0, -114332.0, -3149.0),
+    (2.0, 0.0, -2.0, 0.0, 58793.0, 246158.0),
+    (2.0, -1.0, -1.0, 0.0, 57066.0, -152138.0),
+    (2.0, 0.0, 1.0, 0.0, 53322.0, -170733.0),
+    (2.0, -1.0, 0.0, 0.0, 45758.0, -204586.0),
+    (0.0, 1.0, -1.0, 0.0, -40923.0, -129620.0),
+    (1.0, 0.0, 0.0, 0.0, -34720.0, 108743.0),
+    (0.0, 1.0, 1.0, 0.0, -30383.0, 104755.0),
+    (2.0, 0.0, 0.0, -2.0, 15327.0, 10321.0),
+    (0.0, 0.0, 1.0, 2.0, -12528.0, 0.0),
+    (0.0, 0.0, 1.0, -2.0, 10980.0, 79661.0),
+    (4.0, 0.0, -1.0, 0.0, 10675.0, -34782.0),
+    (0.0, 0.0, 3.0, 0.0, 10034.0, -23210.0),
+    (4.0, 0.0, -2.0, 0.0, 8548.0, -21636.0),
+    (2.0, 1.0, -1.0, 0.0, -7888.0, 24208.0),
+    (2.0, 1.0, 0.0, 0.0, -6766.0, 30824.0),
+    (1.0, 0.0, -1.0, 0.0, -5163.0, -8379.0),
+];
+
+// As above, for latitude (1e-6 deg).
+const MOON_LATITUDE_TERMS: [(f64, f64, f64, f64, f64); 10] = [
+    (0.0, 0.0, 0.0, 1.0, 5128122.0),
+    (0.0, 0.0, 1.0, 1.0, 280602.0),
+    (0.0, 0.0, 1.0, -1.0, 277693.0),
+    (2.0, 0.0, 0.0, -1.0, 173237.0),
+    (2.0, 0.0, -1.0, 1.0, 55413.0),
+    (2.0, 0.0, -1.0, -1.0, 46271.0),
+    (2.0, 0.0, 0.0, 1.0, 32573.0),
+    (0.0, 0.0, 2.0, 1.0, 17198.0),
+    (2.0, 0.0, 1.0, -1.0, 9266.0),
+    (0.0, 0.0, 2.0, -1.0, 8822.0),
+];
+
+/// Geocentric equatorial position of the Moon.
+/// # Returns
+/// * `(ra, dec, distance)` with angles in radians and distance in km
+fn equatorial_from_moon(when: DateTime<Utc>) -> (f64, f64, f64) {
+    // Largest terms of Meeus ch. 47; good to a few arcminutes, well below
+    // the beam size. UT is used in place of TT (about a minute apart).
+    let t = (julian_day(when) - 2451545.0) / 36525.0;
+    // Mean longitude, elongation, solar and lunar anomalies and argument of
+    // latitude, in degrees.
+    let l0 = 218.3164477 + 481267.88123421 * t;
+    let d = (297.8501921 + 445267.1114034 * t).to_radians();
+    let m = (357.5291092 + 35999.0502909 * t).to_radians();
+    let mp = (134.9633964 + 477198.8675055 * t).to_radians();
+    let f = (93.2720950 + 483202.0175233 * t).to_radians();
+
+    let mut sum_l = 0.0;
+    let mut sum_r = 0.0;
+    for (cd, cm, cmp, cf, l, r) in MOON_LONGITUDE_DISTANCE_TERMS {
+        let arg = cd * d + cm * m + cmp * mp + cf * f;
+        sum_l += l * arg.sin();
+        sum_r += r * arg.cos();
+    }
+    let mut sum_b = 0.0;
+    for (cd, cm, cmp, cf, b) in MOON_LATITUDE_TERMS {
+        sum_b += b * (cd * d + cm * m + cmp * mp + cf * f).sin();
+    }
+    let lambda = (l0 + sum_l * 1e-6).to_radians();
+    let beta = (sum_b * 1e-6).to_radians();
+    let distance = 385000.56 + sum_r / 1000.0;
+
+    let e = (23.439291 - 0.0130042 * t).to_radians();
+    let ra = (lambda.sin() * e.cos() - beta.tan() * e.sin())
+        .atan2(lambda.cos())
+        .rem_euclid(FULL_CIRCLE);
+    let dec = (beta.sin() * e.cos() + beta.cos() * e.sin() * lambda.sin()).asin();
+    (ra, dec, distance)
+}
+
+/// Horizontal position of the Moon as seen from `location`.
+///
+/// The Moon is close enough that the observer's offset from the Earth's
+/// centre shifts it by up to a degree, so the geocentric position is
+/// corrected for parallax before converting to az/el.
+pub fn horizontal_from_moon(location: Location, when: DateTime<Utc>) -> Direction {
+    let (ra, dec, distance) = equatorial_from_moon(when);
+    let lst = gmst(when) + location.longitude;
+    let lat = location.latitude;
+    let x = distance * dec.cos() * ra.cos() - R_EARTH * lat.cos() * lst.cos();
+    let y = distance * dec.cos() * ra.sin() - R_EARTH * lat.cos() * lst.sin();
+    let z = distance * dec.sin() - R_EARTH * lat.sin();
+    let topo_ra = y.atan2(x);
+    let topo_dec = (z / (x * x + y * y + z * z).sqrt()).asin();
+    horizontal_from_equatorial_of_date(location, when, topo_ra, topo_dec)
+}
 
+pub fn vlsrcorr_from_galactic(l: f64, b: f64, when: DateTime<Utc>) -> f64 {
     // Target direction in equatorial coordinates
     let (ra, dec) = equatorial_from_galactic(l, b);
+    vlsrcorr_from_equatorial(ra, dec, when)
+}
+
+fn vlsrcorr_from_equatorial(ra: f64, dec: f64, when: DateTime<Utc>) -> f64 {
+    // From http://web.mit.edu/8.13/www/srt_software/vlsr.pdf
 
     // Movement of Sun with respect to LSR: dot product of target & apex vectors, in km/s
     let vsun = 20.0
@@ -258,6 +664,23 @@ pub fn vlsrcorr_from_galactic(l: f64, b: f64, when: DateTime<Utc>) -> f64 {
     1e3 * (vsun + vorb)
 }
 
+/// Velocity correction to the Local Standard of Rest for a telescope at
+/// `location` pointing at `target` (horizontal coordinates) at `when`.
+///
+/// Combines the solar motion towards the apex, the Earth's orbital motion
+/// and the Earth's rotation, projected on the line of sight. Add the result
+/// to a velocity measured relative to the telescope to get the LSR velocity.
+/// # Returns
+/// * Correction in m/s
+pub fn vlsr_correction(location: Location, when: DateTime<Utc>, target: Direction) -> f64 {
+    let (ra, dec) = equatorial_from_horizontal(location, when, target);
+    // The observer moves due east; project onto the east component of the
+    // line of sight.
+    let vrot = EARTH_ROTATION_RATE * R_EARTH * 1e3 * location.latitude.cos();
+    let vrot_los = vrot * target.elevation.cos() * target.azimuth.sin();
+    vlsrcorr_from_equatorial(ra, dec, when) + vrot_los
+}
+
 #[cfg(test)]
 mod test {
     use chrono::Duration;
@@ -275,6 +698,79 @@ mod test {
         };
     }
 
+    #[test]
+    fn angular_separation_is_the_great_circle_angle() {
+        let at = |az: f64, el: f64| Direction {
+            azimuth: az.to_radians(),
+            elevation: el.to_radians(),
+        };
+        assert_similar!(
+            angular_separation(at(10.0, 20.0), at(10.0, 20.0)),
+            0.0,
+            1e-12
+        );
+        assert_similar!(
+            angular_separation(at(0.0, 10.0), at(360.0, 10.0)),
+            0.0,
+            1e-12
+        );
+        assert_similar!(
+            angular_separation(at(0.0, 0.0), at(90.0, 0.0)),
+            PI / 2.0,
+            1e-12
+        );
+        assert_similar!(
+            angular_separation(at(0.0, 0.0), at(0.0, 90.0)),
+            PI / 2.0,
+            1e-12
+        );
+        // Azimuth shrinks towards zenith.
+        assert_similar!(
+            angular_separation(at(0.0, 89.0), at(180.0, 89.0)),
+            2f64.to_radians(),
+            1e-9
+        );
+    }
+
+    #[test]
+    fn entered_coordinates_are_range_checked_and_normalized() {
+        let (ra, dec) = normalize_equatorial(360.0, -90.0).unwrap();
+        assert_similar!(ra, 0.0, 1e-12);
+        assert_similar!(dec, -PI / 2.0, 1e-12);
+        assert_eq!(
+            normalize_equatorial(-1.0, 0.0),
+            Err(CoordinateOutOfRange {
+                coordinate: Coordinate::RightAscension,
+                min_deg: 0.0,
+                max_deg: 360.0
+            })
+        );
+        assert_eq!(
+            normalize_equatorial(10.0, 200.0).unwrap_err().coordinate,
+            Coordinate::Declination
+        );
+        assert_eq!(
+            normalize_galactic(361.0, 0.0).unwrap_err().coordinate,
+            Coordinate::GalacticLongitude
+        );
+        assert_eq!(
+            normalize_galactic(120.0, -91.0).unwrap_err().coordinate,
+            Coordinate::GalacticLatitude
+        );
+        let direction = normalize_horizontal(-90.0, 45.0).unwrap();
+        assert_similar!(direction.azimuth, 1.5 * PI, 1e-12);
+        assert_similar!(
+            normalize_horizontal(720.0, 0.0).unwrap().azimuth,
+            0.0,
+            1e-12
+        );
+        assert_eq!(
+            normalize_horizontal(0.0, 95.0).unwrap_err().coordinate,
+            Coordinate::Elevation
+        );
+        assert!(normalize_equatorial(f64::NAN, 0.0).is_err());
+    }
+
     #[test]
     fn test_julian_day() {
         // Test that we get the correct julian day for a given date
@@ -311,6 +807,141 @@ mod test {
         assert_similar!(dir.elevation, expected_el, 1e-6);
     }
 
+    #[test]
+    fn test_sun_position_at_solstices() {
+        // set_target relies on this to refuse slewing to a Sun that has set.
+        let midwinter = Utc.with_ymd_and_hms(2023, 12, 21, 23, 0, 0).unwrap();
+        let midsummer_noon = Utc.with_ymd_and_hms(2023, 6, 21, 11, 15, 0).unwrap();
+        let night = horizontal_from_sun(ONSALA_LOCATION, midwinter);
+        let noon = horizontal_from_sun(ONSALA_LOCATION, midsummer_noon);
+        assert!(night.elevation < -40.0_f64.to_radians());
+        // Noon altitude at 57.4° N on the solstice is 90 - 57.4 + 23.4 = 56°.
+        assert_similar!(noon.elevation.to_degrees(), 56.0, 0.5);
+        assert_similar!(noon.azimuth.to_degrees(), 180.0, 3.0);
+    }
+
+    #[test]
+    fn test_equatorial_from_moon() {
+        // Meeus, Astronomical Algorithms, example 47.a: 1992 April 12, 0h TD.
+        let when = Utc.with_ymd_and_hms(1992, 4, 12, 0, 0, 0).unwrap();
+        let (ra, dec, distance) = equatorial_from_moon(when);
+        assert_similar!(ra.to_degrees(), 134.688470, 0.05);
+        assert_similar!(dec.to_degrees(), 13.768368, 0.05);
+        assert_similar!(distance, 368409.7, 300.0);
+    }
+
+    #[test]
+    fn test_horizontal_from_moon_parallax() {
+        // Seen from the ground the Moon sits lower than its geocentric
+        // position by up to its horizontal parallax, about 1 degree.
+        let when = Utc.with_ymd_and_hms(2026, 3, 20, 21, 15, 0).unwrap();
+        let (ra, dec, distance) = equatorial_from_moon(when);
+        let geocentric = horizontal_from_equatorial_of_date(ONSALA_LOCATION, when, ra, dec);
+        let topocentric = horizontal_from_moon(ONSALA_LOCATION, when);
+        let parallax = (R_EARTH / distance).asin() * geocentric.elevation.cos();
+        assert_similar!(
+            geocentric.elevation - topocentric.elevation,
+            parallax,
+            0.05_f64.to_radians()
+        );
+    }
+
+    #[test]
+    fn test_seconds_to_direction() {
+        let current = Direction {
+            azimuth: 1.0,
+            elevation: 0.5,
+        };
+        let target = Direction {
+            azimuth: 0.4,
+            elevation: 0.7,
+        };
+        // Azimuth needs 0.6 rad at 0.1 rad/s, elevation 0.2 rad at 0.05 rad/s.
+        assert_similar!(
+            seconds_to_direction(current, target, 0.1, -0.05).unwrap(),
+            6.0,
+            1e-9
+        );
+        assert_eq!(seconds_to_direction(current, target, 0.1, 0.0), None);
+        assert_eq!(seconds_to_direction(current, current, 0.0, 0.0), Some(0.0));
+    }
+
+    #[test]
+    fn test_precess_theta_persei() {
+        // Meeus, Astronomical Algorithms, example 21.b: θ Persei (with its
+        // proper motion already applied) from J2000 to 2028 Nov 13.19.
+        let ra = 41.054063_f64.to_radians();
+        let dec = 49.227750_f64.to_radians();
+        let to_epoch = J2000 + (2462088.69 - 2451545.0) / 365.25;
+        let (ra2, dec2) = precess(ra, dec, J2000, to_epoch);
+        assert_similar!(ra2.to_degrees(), 41.547214, 1e-5);
+        assert_similar!(dec2.to_degrees(), 49.348483, 1e-5);
+        // And back again.
+        let (ra3, dec3) = precess(ra2, dec2, to_epoch, J2000);
+        assert_similar!(ra3, ra, 1e-9);
+        assert_similar!(dec3, dec, 1e-9);
+    }
+
+    #[test]
+    fn test_rise_set_transit() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 20).unwrap();
+        let limit = 5.0_f64.to_radians();
+        let el_at = |ra: f64, dec: f64, when| {
+            horizontal_from_equatorial(ONSALA_LOCATION, when, ra, dec)
+                .elevation
+                .to_degrees()
+        };
+
+        // Cas A never drops below ~26° from Onsala, and culminates just
+        // north of the zenith: precessed to 2026 its declination is 58.96°.
+        let (ra, dec) = (350.8584_f64.to_radians(), 58.8117_f64.to_radians());
+        let (rise, set, transit) = rise_set_transit(ONSALA_LOCATION, ra, dec, limit, date);
+        assert_eq!((rise, set), (None, None));
+        assert_eq!(transit.date_naive(), date);
+        assert_similar!(el_at(ra, dec, transit), 90.0 - (58.96 - 57.39), 0.02);
+        let ten_minutes = Duration::minutes(10);
+        assert!(el_at(ra, dec, transit) > el_at(ra, dec, transit - ten_minutes));
+        assert!(el_at(ra, dec, transit) > el_at(ra, dec, transit + ten_minutes));
+
+        // Tau A rises and sets; at both it sits on the elevation limit.
+        let (ra, dec) = (83.6331_f64.to_radians(), 22.0145_f64.to_radians());
+        let (rise, set, transit) = rise_set_transit(ONSALA_LOCATION, ra, dec, limit, date);
+        let (rise, set) = (rise.unwrap(), set.unwrap());
+        assert!(rise < transit && transit < set);
+        assert_similar!(el_at(ra, dec, rise), 5.0, 0.1);
+        assert_similar!(el_at(ra, dec, set), 5.0, 0.1);
+
+        // Sgr A* peaks below 5° this far north.
+        let (ra, dec) = (266.4168_f64.to_radians(), (-29.0078_f64).to_radians());
+        let (rise, set, _) = rise_set_transit(ONSALA_LOCATION, ra, dec, limit, date);
+        assert_eq!((rise, set), (None, None));
+    }
+
+    #[test]
+    fn test_galactic_equatorial_round_trip() {
+        // The Galactic centre sits at about (266.40°, -28.94°) in J2000.
+        let (ra, dec) = equatorial_from_galactic(0.0, 0.0);
+        assert_similar!(ra.to_degrees(), 266.40, 0.01);
+        assert_similar!(dec.to_degrees(), -28.94, 0.01);
+        for (l, b) in [(0.5_f64, 0.3_f64), (3.0, -0.2), (5.5, 1.2)] {
+            let (ra, dec) = equatorial_from_galactic(l, b);
+            let (l2, b2) = galactic_from_equatorial(ra, dec);
+            assert_similar!(l2, l, 1e-9);
+            assert_similar!(b2, b, 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_refraction_correction() {
+        let arcmin = |el_deg: f64| refraction_correction(el_deg.to_radians()).to_degrees() * 60.0;
+        // Standard values: about 34' at the horizon, 5.3' at 10°, 1' at 45°.
+        assert_similar!(arcmin(0.0), 34.5, 0.2);
+        assert_similar!(arcmin(10.0), 5.3, 0.1);
+        assert_similar!(arcmin(45.0), 1.0, 0.05);
+        assert!(arcmin(90.0).abs() < 0.01);
+        assert_eq!(refraction_correction((-10.0_f64).to_radians()), 0.0);
+    }
+
     #[test]
     fn test_vlsrcorr_from_galactic() {
         // Test that we get the correct VLSR-correction for
@@ -323,6 +954,92 @@ mod test {
         assert_similar!(vlsrcorr, expected_vlsrcorr, 1e-6);
     }
 
+    #[test]
+    fn test_equatorial_from_horizontal_round_trip() {
+        let when = Utc.with_ymd_and_hms(2026, 3, 20, 21, 15, 0).unwrap();
+        for (ra, dec) in [(0.5_f64, 0.3_f64), (3.0, -0.2), (5.5, 1.2)] {
+            let direction = horizontal_from_equatorial(ONSALA_LOCATION, when, ra, dec);
+            let (ra2, dec2) = equatorial_from_horizontal(ONSALA_LOCATION, when, direction);
+            assert_similar!(ra2, ra, 1e-9);
+            assert_similar!(dec2, dec, 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_vlsr_correction_includes_earth_rotation() {
+        let when = Utc.with_ymd_and_hms(2026, 3, 20, 21, 15, 0).unwrap();
+        let (l, b) = (120.0_f64.to_radians(), 0.0);
+        let direction = horizontal_from_galactic(ONSALA_LOCATION, when, l, b);
+        let without_rotation = vlsrcorr_from_galactic(l, b, when);
+        let expected_rotation = 465.1
+            * ONSALA_LOCATION.latitude.cos()
+            * direction.elevation.cos()
+            * direction.azimuth.sin();
+        assert_similar!(
+            vlsr_correction(ONSALA_LOCATION, when, direction),
+            without_rotation + expected_rotation,
+            0.5
+        );
+        // Looking due east, the observer approaches the source.
+        let east = Direction {
+            azimuth: PI / 2.0,
+            elevation: 0.0,
+        };
+        let west = Direction {
+            azimuth: 1.5 * PI,
+            elevation: 0.0,
+        };
+        let (ra_e, dec_e) = equatorial_from_horizontal(ONSALA_LOCATION, when, east);
+        let (ra_w, dec_w) = equatorial_from_horizontal(ONSALA_LOCATION, when, west);
+        assert!(
+            vlsr_correction(ONSALA_LOCATION, when, east)
+                - vlsrcorr_from_equatorial(ra_e, dec_e, when)
+                > 200.0
+        );
+        assert!(
+            vlsr_correction(ONSALA_LOCATION, when, west)
+                - vlsrcorr_from_equatorial(ra_w, dec_w, when)
+                < -200.0
+        );
+    }
+
+    #[test]
+    fn test_apply_proper_motion_barnards_star() {
+        // Barnard's star (SIMBAD, J2000): the largest proper motion of any
+        // star, about 10.4"/yr almost due north.
+        let ra = 269.452_076_f64.to_radians();
+        let dec = 4.693_391_f64.to_radians();
+        let (pm_ra, pm_dec) = (-801.551, 10362.394);
+        let (ra10, dec10) = apply_proper_motion(ra, dec, pm_ra, pm_dec, 10.0);
+        // 10 years: ~103.6" north and ~8.0" (on the sky) west.
+        assert_similar!((dec10 - dec).to_degrees() * 3600.0, 103.62, 0.01);
+        assert_similar!((ra10 - ra).to_degrees() * 3600.0 * dec.cos(), -8.02, 0.01);
+    }
+
+    #[test]
+    fn test_apply_proper_motion_round_trip() {
+        // Propagating to an epoch and back must recover the catalog position,
+        // including across R.A. = 0 and at high declination. Going back with
+        // the catalog's proper motion components is only exact to second
+        // order (the components are defined at the catalog epoch), hence the
+        // 1e-7 rad (~20 mas) tolerance — far below the 0.1° rotor resolution.
+        let when = Utc.with_ymd_and_hms(2026, 7, 1, 0, 0, 0).unwrap();
+        let years = julian_years_since_j2000(when);
+        assert_similar!(years, 26.5, 0.01);
+        for (ra_deg, dec_deg) in [(0.001_f64, 20.0_f64), (359.999, -45.0), (120.0, 80.0)] {
+            let (ra, dec) = (ra_deg.to_radians(), dec_deg.to_radians());
+            let (ra1, dec1) = apply_proper_motion(ra, dec, 500.0, -300.0, years);
+            let (ra2, dec2) = apply_proper_motion(ra1, dec1, 500.0, -300.0, -years);
+            let dra = (ra2 - ra + PI).rem_euclid(FULL_CIRCLE) - PI;
+            assert_similar!(dra * dec.cos(), 0.0, 1e-7);
+            assert_similar!(dec2, dec, 1e-7);
+        }
+        // Zero proper motion is the identity.
+        let (ra0, dec0) = apply_proper_motion(1.0, 0.5, 0.0, 0.0, years);
+        assert_similar!(ra0, 1.0, 1e-12);
+        assert_similar!(dec0, 0.5, 1e-12);
+    }
+
     #[test]
     fn test_horizontal_from_sat_eci() {
         //fn horizontal_from_sat_eci(xs: f64, ys: f64, zs: f64, lat: f64, lon: f64, el: f64, when: DateTime<Utc>) -> (f64, f64) {
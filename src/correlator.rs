@@ -13,15 +13,13 @@
 ///      - Compute delay spectrum via IFFT(V), find peak → delay_ns
 ///      - Store to DB
 use std::f64::consts::PI;
-use std::sync::Arc;
 
 use chrono::Utc;
-use rusqlite::Connection;
 use rustfft::{FftPlanner, num_complex::Complex};
-use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
+use crate::database::ConnectionPool;
 use crate::models::interferometry::InterferometryVisibility;
 use crate::models::telescope_types::{IQ_BLOCK_SIZE, IqBlock, ReceiverConfiguration};
 
@@ -41,7 +39,7 @@ impl CorrelatorHandle {
         rx_a: tokio::sync::mpsc::Receiver<IqBlock>,
         rx_b: tokio::sync::mpsc::Receiver<IqBlock>,
         config: ReceiverConfiguration,
-        db: Arc<Mutex<Connection>>,
+        db: ConnectionPool,
     ) -> Self {
         let token = CancellationToken::new();
         let task = tokio::spawn(correlator_task(
@@ -79,7 +77,7 @@ async fn correlator_task(
     mut rx_a: tokio::sync::mpsc::Receiver<IqBlock>,
     mut rx_b: tokio::sync::mpsc::Receiver<IqBlock>,
     config: ReceiverConfiguration,
-    db: Arc<Mutex<Connection>>,
+    db: ConnectionPool,
     token: CancellationToken,
 ) {
     let samples_per_second = config.bandwidth_hz as usize;
@@ -1,7 +1,11 @@
+use askama::Template;
+use axum::body::Body;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use tracing::error;
 
+use crate::models::telescope_types::TelescopeError;
+
 #[derive(Debug)]
 pub struct InternalError {
     pub message: String,
@@ -30,3 +34,165 @@ impl From<InternalError> for StatusCode {
         StatusCode::INTERNAL_SERVER_ERROR
     }
 }
+
+/// Error returned by route handlers. Converts from the model and telescope
+/// errors so handlers can use `?` and still answer with the right status.
+/// Errors with something to tell the user render the error callout and
+/// retarget it at `#errors`, where the observe page shows it.
+#[derive(Debug, PartialEq)]
+pub enum AppError {
+    NotFound,
+    Unauthorized,
+    BadRequest(String),
+    /// Someone else holds what the request wants to change.
+    Conflict(String),
+    Telescope(TelescopeError),
+    /// Logged; the client only sees a generic 500.
+    Internal(String),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Telescope(err) => match err {
+                TelescopeError::TargetOutOfElevationRange { .. }
+                | TelescopeError::TargetInKeyhole { .. } => StatusCode::BAD_REQUEST,
+                TelescopeError::TelescopeBusy => StatusCode::CONFLICT,
+                TelescopeError::TelescopeIOError(_)
+                | TelescopeError::TelescopeNotConnected
+                | TelescopeError::ReceiverFailed(_) => StatusCode::SERVICE_UNAVAILABLE,
+            },
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The error in words for API clients and the observation queue, which
+    /// have no page to show a callout on. Internal errors are logged and
+    /// only reported as such.
+    pub fn into_message(self) -> String {
+        match self {
+            AppError::NotFound => "No such telescope".to_string(),
+            AppError::Unauthorized => "Log in and book the telescope first".to_string(),
+            AppError::BadRequest(message) | AppError::Conflict(message) => message,
+            AppError::Telescope(err) => err.to_string(),
+            AppError::Internal(message) => {
+                error!("Error encountered while processing request: {message}");
+                "Internal error".to_string()
+            }
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        match self {
+            AppError::NotFound | AppError::Unauthorized => status.into_response(),
+            AppError::BadRequest(message) | AppError::Conflict(message) => {
+                error_callout(status, message)
+            }
+            AppError::Telescope(err) => error_callout(status, err.to_string()),
+            AppError::Internal(message) => {
+                error!("Error encountered while processing request: {message}");
+                status.into_response()
+            }
+        }
+    }
+}
+
+impl From<InternalError> for AppError {
+    fn from(value: InternalError) -> Self {
+        AppError::Internal(value.message)
+    }
+}
+
+impl From<TelescopeError> for AppError {
+    fn from(value: TelescopeError) -> Self {
+        AppError::Telescope(value)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(value: serde_json::Error) -> Self {
+        AppError::Internal(format!("Malformed stored JSON: {value}"))
+    }
+}
+
+/// For helpers that still answer with a bare status code.
+impl From<StatusCode> for AppError {
+    fn from(value: StatusCode) -> Self {
+        match value {
+            StatusCode::NOT_FOUND => AppError::NotFound,
+            StatusCode::UNAUTHORIZED => AppError::Unauthorized,
+            StatusCode::BAD_REQUEST => AppError::BadRequest(String::new()),
+            status => AppError::Internal(format!("Request failed with {status}")),
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "error_callout.html")]
+struct ErrorCallout {
+    message: String,
+}
+
+/// Render `message` in the error callout with `status`, retargeted at the
+/// page's `#errors` box.
+pub fn error_callout(status: StatusCode, message: String) -> Response {
+    let body = ErrorCallout { message }
+        .render()
+        .expect("Rendering error_callout.html should never fail");
+    Response::builder()
+        .status(status)
+        .header("HX-Retarget", "#errors")
+        .header("HX-Reswap", "innerHTML")
+        .body(Body::from(body))
+        .expect("Building a response should never fail")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn status_follows_the_kind_of_error() {
+        assert_eq!(
+            AppError::from(StatusCode::NOT_FOUND).status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            AppError::from(TelescopeError::TelescopeNotConnected).status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            AppError::from(TelescopeError::TargetOutOfElevationRange {
+                min_deg: 5.0,
+                max_deg: 90.0
+            })
+            .status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            AppError::Conflict("Held by someone else".to_string()).status(),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            AppError::from(InternalError::new("db".to_string())).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn user_facing_errors_are_shown_in_the_error_box() {
+        let response = AppError::BadRequest("Bad offset".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()["HX-Retarget"], "#errors");
+
+        let response = AppError::Internal("secret detail".to_string()).into_response();
+        assert!(response.headers().get("HX-Retarget").is_none());
+    }
+}
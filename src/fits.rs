@@ -1,3 +1,5 @@
+use crate::coords::Location;
+
 /// Minimal FITS writer for 1D spectra stored as 3D image HDUs (NAXIS1=N, NAXIS2=1, NAXIS3=1).
 /// BITPIX=-32 (IEEE 754 single-precision float, big-endian).
 /// Compliant with FITS standard (NOST 100-2.0) and WCS Paper III for spectral axes.
@@ -16,8 +18,12 @@ pub struct SpectrumMeta<'a> {
     /// standard allows no timezone offset — astropy rejects e.g. `+00:00`.
     pub start_time: &'a str,
     pub vlsr_correction_mps: Option<f64>,
+    pub rest_freq_hz: f64,
     pub azimuth_deg: Option<f64>,
     pub elevation_deg: Option<f64>,
+    pub system_temperature_k: Option<f64>,
+    pub receiver_gain_db: Option<f64>,
+    pub location: Location,
 }
 
 fn card_logical(key: &str, val: bool, comment: &str) -> [u8; 80] {
@@ -133,8 +139,7 @@ pub fn write_spectrum_fits(meta: &SpectrumMeta) -> Vec<u8> {
         card_float("CRVAL3", meta.target_y, "coordinate (deg)"),
         card_float("CDELT3", 1.0, ""),
         card_str("CUNIT3", "deg", ""),
-        // HI rest frequency
-        card_float("RESTFRQ", 1_420_405_751.77, "HI rest frequency (Hz)"),
+        card_float("RESTFRQ", meta.rest_freq_hz, "rest frequency (Hz)"),
     ];
 
     // Commanded pointing direction at observation start, offsets included
@@ -153,6 +158,23 @@ pub fn write_spectrum_fits(meta: &SpectrumMeta) -> Vec<u8> {
         ));
     }
 
+    header.push(card_float(
+        "SITELONG",
+        meta.location.longitude.to_degrees(),
+        "telescope longitude (deg, east positive)",
+    ));
+    header.push(card_float(
+        "SITELAT",
+        meta.location.latitude.to_degrees(),
+        "telescope latitude (deg)",
+    ));
+    if let Some(tsys) = meta.system_temperature_k {
+        header.push(card_float("TSYS", tsys, "system temperature (K)"));
+    }
+    if let Some(gain_db) = meta.receiver_gain_db {
+        header.push(card_float("RXGAIN", gain_db, "receiver gain (dB)"));
+    }
+
     // VLSR: modern WCS (VELOSYS + SPECSYS) and legacy (VELO-LSR) for compatibility
     if let Some(vlsr_mps) = meta.vlsr_correction_mps {
         header.push(card_float(
@@ -181,3 +203,103 @@ pub fn write_spectrum_fits(meta: &SpectrumMeta) -> Vec<u8> {
 
     bytes
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coords::ONSALA_LOCATION;
+
+    fn meta<'a>(frequencies: &'a [f64], amplitudes: &'a [f64]) -> SpectrumMeta<'a> {
+        SpectrumMeta {
+            frequencies,
+            amplitudes,
+            telescope_id: "brage",
+            coordinate_system: "galactic",
+            target_x: 140.0,
+            target_y: 0.0,
+            integration_time_secs: 60.0,
+            start_time: "2026-07-12T12:00:00",
+            vlsr_correction_mps: Some(-12345.0),
+            rest_freq_hz: 1_420_405_751.768,
+            azimuth_deg: Some(180.0),
+            elevation_deg: Some(45.0),
+            system_temperature_k: Some(300.0),
+            receiver_gain_db: Some(60.0),
+            location: ONSALA_LOCATION,
+        }
+    }
+
+    fn header_cards(bytes: &[u8]) -> Vec<String> {
+        bytes
+            .chunks(RECORD_SIZE)
+            .map(|card| String::from_utf8(card.to_vec()).unwrap())
+            .take_while(|card| !card.starts_with("END "))
+            .collect()
+    }
+
+    /// Offset of the data: the header, END card included, in whole blocks.
+    fn data_start(bytes: &[u8]) -> usize {
+        ((header_cards(bytes).len() + 1) * RECORD_SIZE).div_ceil(BLOCK_SIZE) * BLOCK_SIZE
+    }
+
+    fn card<'a>(cards: &'a [String], key: &str) -> &'a str {
+        cards
+            .iter()
+            .find(|card| card[..8].trim_end() == key)
+            .unwrap_or_else(|| panic!("missing {key}"))
+    }
+
+    #[test]
+    fn file_is_padded_to_whole_blocks() {
+        let frequencies: Vec<f64> = (0..1000).map(|i| 1.42e9 + i as f64 * 1e3).collect();
+        let amplitudes = vec![1.0; 1000];
+        let bytes = write_spectrum_fits(&meta(&frequencies, &amplitudes));
+        // Two header blocks, then 4000 data bytes rounded up to two blocks.
+        let data_start = data_start(&bytes);
+        assert_eq!(data_start, 2 * BLOCK_SIZE);
+        assert_eq!(bytes.len(), 4 * BLOCK_SIZE);
+
+        let cards = header_cards(&bytes);
+        let header_end = (cards.len() + 1) * RECORD_SIZE;
+        assert!(bytes[header_end..data_start].iter().all(|&b| b == b' '));
+        let data_end = data_start + 4 * amplitudes.len();
+        assert!(bytes[data_end..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn header_cards_follow_fixed_layout() {
+        let frequencies = [1.42e9, 1.42e9 + 2e3, 1.42e9 + 4e3];
+        let bytes = write_spectrum_fits(&meta(&frequencies, &[1.0, 2.0, 3.0]));
+        let cards = header_cards(&bytes);
+
+        let keys: Vec<&str> = cards.iter().map(|c| c[..8].trim_end()).collect();
+        assert_eq!(
+            keys[..6],
+            ["SIMPLE", "BITPIX", "NAXIS", "NAXIS1", "NAXIS2", "NAXIS3"]
+        );
+        for card in &cards {
+            assert_eq!(card.len(), RECORD_SIZE);
+            assert_eq!(&card[8..10], "= ", "{card}");
+        }
+        // Fixed-format logical and integer values end in column 30.
+        assert_eq!(&card(&cards, "SIMPLE")[10..30], format!("{:>20}", "T"));
+        assert_eq!(&card(&cards, "NAXIS1")[10..30], format!("{:>20}", 3));
+        assert!(card(&cards, "TELESCOP").starts_with("TELESCOP= 'brage   '"));
+        assert!(card(&cards, "CDELT1").starts_with("CDELT1  =       2.00000000E+03"));
+        assert!(card(&cards, "RESTFRQ").contains("1.42040575E+09"));
+        assert!(card(&cards, "CTYPE2").contains("'GLON    '"));
+        assert!(card(&cards, "TSYS").contains("3.00000000E+02"));
+        assert!(card(&cards, "SITELAT").contains("5.73934000E+01"));
+    }
+
+    #[test]
+    fn data_is_big_endian_float32() {
+        let bytes = write_spectrum_fits(&meta(&[1.0, 2.0], &[1.5, -2.0]));
+        let data_start = data_start(&bytes);
+        assert_eq!(&bytes[data_start..data_start + 4], &1.5f32.to_be_bytes());
+        assert_eq!(
+            &bytes[data_start + 4..data_start + 8],
+            &(-2.0f32).to_be_bytes()
+        );
+    }
+}
@@ -26,7 +26,7 @@ use crate::models::guest::{
     EndReason, GUEST_IDLE_RELEASE_SECS, GUEST_SESSION_HARD_CEILING_SECS, GuestSession,
 };
 use crate::models::user::User;
-use crate::routes::observe::stop_and_save_observation;
+use crate::routes::observe::{stop_and_save_observation, take_observation_label};
 
 /// Tick interval. Short enough that a real booking starting at hour boundary
 /// preempts the guest within a few seconds; long enough that the periodic
@@ -104,13 +104,16 @@ pub async fn end_session(state: &AppState, guest: &GuestSession, reason: EndReas
         is_admin: false,
         timezone: None,
         language: None,
+        display_name: None,
     };
     if let Some(telescope) = state.telescopes.get(&guest.telescope_id).await {
+        let label = take_observation_label(state, &guest.telescope_id).await;
         stop_and_save_observation(
             telescope.as_ref(),
             state.database_connection.clone(),
             &synthetic_user,
             &state.tle_cache,
+            label.as_deref(),
         )
         .await;
         if let Err(err) = telescope.stop().await {
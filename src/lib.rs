@@ -1,5 +1,7 @@
 pub mod app;
 pub mod booking_monitor;
+pub mod catalog;
+pub mod clock_check;
 pub mod coords;
 pub mod correlator;
 pub mod database;
@@ -11,8 +13,11 @@ pub mod guest_rate_limiter;
 pub mod i18n;
 pub mod logging;
 pub mod login_rate_limiter;
+pub mod metrics;
 pub mod middleware;
 pub mod models;
+pub mod position_history;
+pub mod queue_runner;
 pub mod routes;
 pub mod secrets;
 pub mod supervised_task;
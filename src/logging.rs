@@ -1,15 +1,102 @@
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use clap::ValueEnum;
+use tower_http::request_id::RequestId;
+use tracing::level_filters::LevelFilter;
+use tracing::{Span, debug_span};
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::fmt;
 use tracing_subscriber::prelude::*;
 
-pub fn setup_logging(journald_logging: bool) {
+/// How log lines are written when not logging to journald.
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Full,
+    Compact,
+    Pretty,
+    /// One JSON object per line, for shipping to a log aggregator.
+    Json,
+}
+
+/// Install the global subscriber. `level` applies when `RUST_LOG` is
+/// unset or invalid; otherwise `RUST_LOG` decides what is logged.
+pub fn setup_logging(journald_logging: bool, level: LevelFilter, format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::default().add_directive(level.into()));
+    let registry = tracing_subscriber::registry().with(filter);
     if journald_logging {
         let journald_layer = tracing_journald::layer().expect("failed to open journald log");
-        tracing_subscriber::registry()
-            .with(journald_layer)
-            .with(EnvFilter::from_default_env())
-            .init();
-    } else {
-        fmt::init();
+        registry.with(journald_layer).init();
+        return;
+    }
+    match format {
+        LogFormat::Full => registry.with(fmt::layer()).init(),
+        LogFormat::Compact => registry.with(fmt::layer().compact()).init(),
+        LogFormat::Pretty => registry.with(fmt::layer().pretty()).init(),
+        LogFormat::Json => registry.with(fmt::layer().json()).init(),
+    }
+}
+
+/// Span for one HTTP request, carrying the id `SetRequestIdLayer` gave it
+/// and, for routes about a telescope, the telescope's id. The telescope
+/// tasks use the same `telescope_id` field, so e.g.
+/// `RUST_LOG='salsa[{telescope_id=salsa1}]=debug'` follows one telescope.
+pub fn http_request_span<B>(request: &Request<B>) -> Span {
+    let matched_path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str);
+    let requested_path = request.uri().to_string();
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok());
+    let telescope_id =
+        matched_path.and_then(|matched| telescope_id_from_path(matched, request.uri().path()));
+    debug_span!(
+        "http_request",
+        method = ?request.method(),
+        matched_path,
+        requested_path,
+        request_id,
+        telescope_id,
+    )
+}
+
+/// The segment of `path` standing for `{telescope_id}` in the route
+/// pattern `matched_path`.
+fn telescope_id_from_path<'a>(matched_path: &str, path: &'a str) -> Option<&'a str> {
+    matched_path
+        .split('/')
+        .zip(path.split('/'))
+        .find(|(pattern, _)| *pattern == "{telescope_id}")
+        .map(|(_, segment)| segment)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn telescope_id_is_taken_from_the_matching_segment() {
+        assert_eq!(
+            telescope_id_from_path(
+                "/observe/{telescope_id}/set-target",
+                "/observe/salsa1/set-target"
+            ),
+            Some("salsa1")
+        );
+        assert_eq!(
+            telescope_id_from_path(
+                "/api/telescopes/{telescope_id}/info",
+                "/api/telescopes/fake1/info"
+            ),
+            Some("fake1")
+        );
+        assert_eq!(
+            telescope_id_from_path("/bookings/{booking_id}", "/bookings/4"),
+            None
+        );
     }
 }
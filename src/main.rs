@@ -1,12 +1,20 @@
 use axum_server::tls_rustls::RustlsConfig;
 use clap::Parser;
-use salsa::{app, app::teardown_app, booking_monitor, guest_monitor, logging};
+use salsa::app::{AppState, stop_observing, teardown_app};
+use salsa::logging::LogFormat;
+use salsa::{app, booking_monitor, guest_monitor, logging, position_history, queue_runner};
 use std::net::SocketAddr;
 use std::net::TcpListener;
 use std::path::PathBuf;
+use std::time::Duration;
 use tokio::signal;
+use tracing::level_filters::LevelFilter;
 use tracing::{error, info, warn};
 
+/// How long open connections get to finish once shutdown starts.
+/// Websockets never end by themselves, so this bounds the wait for them.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -25,14 +33,26 @@ struct Args {
     #[arg(long, default_value = ".")]
     config_dir: PathBuf,
 
+    /// Static files and content pages, normally the repository's `assets`.
+    #[arg(long, default_value = "assets")]
+    assets_dir: PathBuf,
+
     #[arg(long)]
     log_to_journald: bool,
+
+    /// Most verbose level logged when RUST_LOG is not set.
+    #[arg(long, default_value_t = LevelFilter::ERROR)]
+    log_level: LevelFilter,
+
+    /// Format of log lines; ignored when logging to journald.
+    #[arg(long, value_enum, default_value_t)]
+    log_format: LogFormat,
 }
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
-    logging::setup_logging(args.log_to_journald);
+    logging::setup_logging(args.log_to_journald, args.log_level, args.log_format);
 
     let addr = if let Some(port) = args.port {
         SocketAddr::from(([0, 0, 0, 0], port))
@@ -40,9 +60,20 @@ async fn main() {
         SocketAddr::from(([0, 0, 0, 0], 3000))
     };
 
-    let (app, state) = app::create_app(&args.config_dir, &args.database_dir).await;
+    let (app, state) =
+        match app::create_app(&args.config_dir, &args.database_dir, &args.assets_dir).await {
+            Ok(created) => created,
+            Err(err) => {
+                // Not logged as well: with the default console logging it
+                // would print twice, and systemd captures stderr anyway.
+                eprintln!("salsa: could not start: {err}");
+                std::process::exit(1);
+            }
+        };
     booking_monitor::start(state.clone());
     guest_monitor::start(state.clone());
+    position_history::start(state.clone());
+    queue_runner::start(state.clone());
 
     // Runtime heartbeat: if scheduling is healthy, this loop wakes every
     // ~500 ms. A skew well above that means tokio worker threads are
@@ -73,7 +104,7 @@ async fn main() {
     }
 
     let handle = axum_server::Handle::new();
-    tokio::spawn(handle_shutdown_signal(handle.clone()));
+    tokio::spawn(handle_shutdown_signal(handle.clone(), state.clone()));
 
     if let Some(key_file_path) = args.key_file_path {
         // This is needed because rustls tries to magically figure out which provider
@@ -121,7 +152,7 @@ async fn main() {
     teardown_app(state).await;
 }
 
-async fn handle_shutdown_signal(handle: axum_server::Handle) {
+async fn handle_shutdown_signal(handle: axum_server::Handle, state: AppState) {
     let interrupt = async {
         signal::unix::signal(signal::unix::SignalKind::interrupt())
             .expect("Should succeed installing interrupt signal handler.")
@@ -145,5 +176,8 @@ async fn handle_shutdown_signal(handle: axum_server::Handle) {
     }
 
     info!("Shutting down");
-    handle.graceful_shutdown(None);
+    // Before waiting on the connections, which include the spectrum
+    // streams of these integrations.
+    stop_observing(&state).await;
+    handle.graceful_shutdown(Some(SHUTDOWN_GRACE));
 }
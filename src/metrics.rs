@@ -0,0 +1,131 @@
+//! Process-wide counters for the Prometheus `/metrics` endpoint.
+//!
+//! Values that are cheap to read on demand (bookings, observations,
+//! telescope status) are gathered at scrape time by the route; this module
+//! only holds what has to be accumulated as it happens.
+
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// Upper bounds, in seconds, of the request latency histogram buckets.
+const LATENCY_BUCKETS_SECS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Default)]
+pub struct Metrics {
+    /// Failed connects to any rotor controller. Not labelled by address:
+    /// `/metrics` is public and the controllers' addresses are not.
+    controller_connection_failures: AtomicU64,
+    request_latency: Mutex<Histogram>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Non-cumulative; one entry per bucket in `LATENCY_BUCKETS_SECS`.
+    bucket_counts: [u64; LATENCY_BUCKETS_SECS.len()],
+    count: u64,
+    sum_secs: f64,
+}
+
+impl Metrics {
+    pub fn controller_connection_failed(&self) {
+        self.controller_connection_failures
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn observe_request(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let mut histogram = self.request_latency.lock().unwrap();
+        if let Some(bucket) = LATENCY_BUCKETS_SECS.iter().position(|&le| secs <= le) {
+            histogram.bucket_counts[bucket] += 1;
+        }
+        histogram.count += 1;
+        histogram.sum_secs += secs;
+    }
+
+    /// Append the accumulated metrics in Prometheus text format.
+    pub fn render(&self, out: &mut String) {
+        let _ = writeln!(
+            out,
+            "# HELP salsa_controller_connection_failures_total Failed connections to a rotor controller.\n\
+             # TYPE salsa_controller_connection_failures_total counter\n\
+             salsa_controller_connection_failures_total {}",
+            self.controller_connection_failures.load(Ordering::Relaxed)
+        );
+
+        let histogram = self.request_latency.lock().unwrap();
+        out.push_str(
+            "# HELP salsa_http_request_duration_seconds Time taken to answer HTTP requests.\n\
+             # TYPE salsa_http_request_duration_seconds histogram\n",
+        );
+        let mut cumulative = 0;
+        for (le, count) in LATENCY_BUCKETS_SECS.iter().zip(histogram.bucket_counts) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "salsa_http_request_duration_seconds_bucket{{le=\"{le}\"}} {cumulative}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "salsa_http_request_duration_seconds_bucket{{le=\"+Inf\"}} {}\n\
+             salsa_http_request_duration_seconds_sum {}\n\
+             salsa_http_request_duration_seconds_count {}",
+            histogram.count, histogram.sum_secs, histogram.count
+        );
+    }
+}
+
+/// Escape a label value as the text exposition format requires.
+pub fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Records how long each request took in the latency histogram.
+pub async fn latency_middleware(req: Request, next: Next) -> Response {
+    let start = std::time::Instant::now();
+    let response = next.run(req).await;
+    METRICS.observe_request(start.elapsed());
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let metrics = Metrics::default();
+        metrics.observe_request(Duration::from_millis(3));
+        metrics.observe_request(Duration::from_millis(200));
+        metrics.observe_request(Duration::from_secs(60));
+        let mut out = String::new();
+        metrics.render(&mut out);
+        assert!(out.contains("salsa_http_request_duration_seconds_bucket{le=\"0.005\"} 1\n"));
+        assert!(out.contains("salsa_http_request_duration_seconds_bucket{le=\"0.25\"} 2\n"));
+        assert!(out.contains("salsa_http_request_duration_seconds_bucket{le=\"10\"} 2\n"));
+        assert!(out.contains("salsa_http_request_duration_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(out.contains("salsa_http_request_duration_seconds_count 3"));
+    }
+
+    #[test]
+    fn controller_failures_are_counted_without_addresses() {
+        let metrics = Metrics::default();
+        metrics.controller_connection_failed();
+        metrics.controller_connection_failed();
+        let mut out = String::new();
+        metrics.render(&mut out);
+        assert!(out.contains("salsa_controller_connection_failures_total 2\n"));
+    }
+}
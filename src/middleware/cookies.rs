@@ -1,7 +1,8 @@
 use std::collections::HashMap;
+use std::fmt;
 
-use axum::http::HeaderMap;
 use axum::http::header::COOKIE;
+use axum::http::{HeaderMap, HeaderValue};
 use axum::{extract::Request, middleware::Next, response::Response};
 
 /// Cookies sent by the client, keyed by name. A name maps to multiple values
@@ -51,10 +52,143 @@ fn parse_cookies_into(cookie_header: &str, cookies: &mut HashMap<String, Vec<Str
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+/// A cookie to send in a `Set-Cookie` header. Everything we set goes
+/// through this so the attributes are spelled and ordered the same way
+/// everywhere; in particular, a deletion only takes effect if its name,
+/// path and prefix-related attributes match the cookie it replaces.
+#[derive(Clone, Debug)]
+pub struct Cookie {
+    name: &'static str,
+    value: String,
+    same_site: Option<SameSite>,
+    http_only: bool,
+    secure: bool,
+    path: Option<&'static str>,
+    max_age_secs: Option<i64>,
+}
+
+impl Cookie {
+    /// A cookie with no attributes: sent to the current path only, and
+    /// forgotten when the browser closes.
+    pub fn new(name: &'static str, value: impl Into<String>) -> Self {
+        Cookie {
+            name,
+            value: value.into(),
+            same_site: None,
+            http_only: false,
+            secure: false,
+            path: None,
+            max_age_secs: None,
+        }
+    }
+
+    /// A cookie that replaces `name` with an already expired one. Set the
+    /// same attributes as the cookie being removed.
+    pub fn removal(name: &'static str) -> Self {
+        Cookie::new(name, "deleted").max_age_secs(0)
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn path(mut self, path: &'static str) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Expire the cookie this many seconds from now; zero or less removes
+    /// it at once.
+    pub fn max_age_secs(mut self, seconds: i64) -> Self {
+        self.max_age_secs = Some(seconds.max(0));
+        self
+    }
+
+    pub fn header_value(&self) -> HeaderValue {
+        HeaderValue::from_str(&self.to_string())
+            .expect("Cookie names and values we set should always be valid in a header")
+    }
+}
+
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        match self.same_site {
+            Some(SameSite::Strict) => write!(f, "; SameSite=Strict")?,
+            Some(SameSite::Lax) => write!(f, "; SameSite=Lax")?,
+            Some(SameSite::None) => write!(f, "; SameSite=None")?,
+            None => {}
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        if let Some(path) = self.path {
+            write!(f, "; Path={path}")?;
+        }
+        if let Some(max_age) = self.max_age_secs {
+            write!(f, "; Max-Age={max_age}")?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn session_cookie_has_all_attributes_in_order() {
+        let cookie = Cookie::new("__Host-session", "abc+/=")
+            .same_site(SameSite::Lax)
+            .http_only(true)
+            .secure(true)
+            .path("/")
+            .max_age_secs(3600);
+        assert_eq!(
+            "__Host-session=abc+/=; SameSite=Lax; HttpOnly; Secure; Path=/; Max-Age=3600",
+            cookie.to_string()
+        );
+    }
+
+    #[test]
+    fn removal_cookie_expires_immediately() {
+        let cookie = Cookie::removal("__Host-session")
+            .same_site(SameSite::Lax)
+            .http_only(true)
+            .secure(true)
+            .path("/");
+        assert_eq!(
+            "__Host-session=deleted; SameSite=Lax; HttpOnly; Secure; Path=/; Max-Age=0",
+            cookie.to_string()
+        );
+        // Negative ages, e.g. from a session that just ran out, clamp too.
+        assert_eq!(
+            "a=b; Max-Age=0",
+            Cookie::new("a", "b").max_age_secs(-5).to_string()
+        );
+    }
+
     fn parse(header: &str) -> HashMap<String, Vec<String>> {
         let mut cookies = HashMap::new();
         parse_cookies_into(header, &mut cookies);
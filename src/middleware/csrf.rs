@@ -0,0 +1,218 @@
+//! Double-submit CSRF protection. Every visitor gets a random token in the
+//! `csrf` cookie; state-changing requests must echo it back, either in the
+//! `X-CSRF-Token` header (htmx and `fetch`, filled in by the layout's
+//! script) or in a `csrf_token` form field (plain forms, added by the same
+//! script on submit). A cross-site page can make the browser send the
+//! cookie but can't read it, so it can't supply the matching copy.
+
+use axum::{
+    Form,
+    body::{Body, to_bytes},
+    extract::{FromRequest, Request},
+    http::{
+        Method, StatusCode,
+        header::{AUTHORIZATION, CONTENT_TYPE, SET_COOKIE},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use rand::Rng;
+use serde::Deserialize;
+use tracing::warn;
+
+use super::cookies::{Cookie, Cookies, SameSite};
+use super::session::{INSECURE_SESSION_COOKIE_NAME, SESSION_COOKIE_NAME};
+
+/// Readable from JavaScript by design, and like the language cookie it
+/// must work on plain-HTTP dev servers, so neither HttpOnly nor Secure.
+pub const CSRF_COOKIE_NAME: &str = "csrf";
+pub const CSRF_HEADER_NAME: &str = "x-csrf-token";
+pub const CSRF_FORM_FIELD: &str = "csrf_token";
+
+/// About a year, like the language cookie. The token protects nothing by
+/// itself, so there is no point rotating it.
+const CSRF_COOKIE_MAX_AGE_SECS: i64 = 365 * 24 * 60 * 60;
+
+/// Random bytes in a token.
+const TOKEN_LEN: usize = 32;
+
+/// Largest form body read while looking for the token. Far above any
+/// form on the site.
+const MAX_FORM_BYTES: usize = 1024 * 1024;
+
+/// Routes that take unsafe requests from other sites by design. Providers
+/// using `response_mode=form_post` POST their OAuth2 callback.
+const EXEMPT_PATHS: &[&str] = &["/auth/authorized"];
+
+#[derive(Deserialize)]
+struct TokenForm {
+    csrf_token: Option<String>,
+}
+
+fn new_token() -> String {
+    let mut bytes = [0; TOKEN_LEN];
+    rand::rng().fill(&mut bytes);
+    BASE64_URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn csrf_cookie(token: String) -> Cookie {
+    Cookie::new(CSRF_COOKIE_NAME, token)
+        .same_site(SameSite::Lax)
+        .path("/")
+        .max_age_secs(CSRF_COOKIE_MAX_AGE_SECS)
+}
+
+/// Compare without returning early, so timing doesn't tell how much of a
+/// guess was right.
+fn tokens_match(expected: &str, submitted: &str) -> bool {
+    expected.len() == submitted.len()
+        && expected
+            .bytes()
+            .zip(submitted.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn needs_check(request: &Request, cookies: Option<&Cookies>) -> bool {
+    let unsafe_method = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+    // API clients authenticate with a bearer token rather than a cookie
+    // the browser attaches on its own, so they can't be forged this way.
+    // With a session cookie along, the session may be what authenticates
+    // the request, so it is checked like any other.
+    let bearer = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("Bearer "));
+    let session = cookies.is_some_and(|cookies| {
+        [SESSION_COOKIE_NAME, INSECURE_SESSION_COOKIE_NAME]
+            .iter()
+            .any(|name| !cookies.get_all(name).is_empty())
+    });
+    let api_client = bearer && !session;
+    unsafe_method && !api_client && !EXEMPT_PATHS.contains(&request.uri().path())
+}
+
+/// The token submitted with `request`: the header if present, otherwise
+/// the form field. Reading the form consumes the body, so the request is
+/// handed back rebuilt.
+async fn submitted_token(request: Request) -> Result<(Option<String>, Request), Response> {
+    if let Some(token) = request
+        .headers()
+        .get(CSRF_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+    {
+        return Ok((Some(token.to_string()), request));
+    }
+    let is_form = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+    if !is_form {
+        return Ok((None, request));
+    }
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, MAX_FORM_BYTES)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())?;
+    let form_request = Request::builder()
+        .method(Method::POST)
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(bytes.clone()))
+        .expect("Building a request should never fail");
+    let token = Form::<TokenForm>::from_request(form_request, &())
+        .await
+        .ok()
+        .and_then(|Form(form)| form.csrf_token);
+    Ok((token, Request::from_parts(parts, Body::from(bytes))))
+}
+
+/// Reject unsafe requests whose submitted token doesn't match the cookie,
+/// and give visitors without a token cookie one. Must run after the
+/// cookies middleware.
+pub async fn csrf_middleware(request: Request, next: Next) -> Response {
+    let cookies = request.extensions().get::<Cookies>();
+    let cookie_tokens = cookies
+        .map(|cookies| cookies.get_all(CSRF_COOKIE_NAME).to_vec())
+        .unwrap_or_default();
+    let issue = cookie_tokens.is_empty().then(new_token);
+
+    let mut response = if needs_check(&request, cookies) {
+        match submitted_token(request).await {
+            Ok((Some(submitted), request))
+                if cookie_tokens
+                    .iter()
+                    .any(|token| tokens_match(token, &submitted)) =>
+            {
+                next.run(request).await
+            }
+            Ok((_, request)) => {
+                warn!(
+                    method = %request.method(),
+                    path = request.uri().path(),
+                    "Rejecting request with missing or mismatched CSRF token"
+                );
+                (StatusCode::FORBIDDEN, "Missing or invalid CSRF token").into_response()
+            }
+            Err(response) => response,
+        }
+    } else {
+        next.run(request).await
+    };
+
+    if let Some(token) = issue {
+        response
+            .headers_mut()
+            .append(SET_COOKIE, csrf_cookie(token).header_value());
+    }
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_identical_tokens_match() {
+        let token = new_token();
+        assert!(tokens_match(&token, &token.clone()));
+        assert!(!tokens_match(&token, &new_token()));
+        assert!(!tokens_match(&token, &token[1..]));
+        assert!(!tokens_match(&token, ""));
+    }
+
+    #[test]
+    fn only_bearer_requests_without_a_session_skip_the_check() {
+        let post = |authorization: Option<&str>| {
+            let mut request = Request::builder().method(Method::POST).uri("/api/x");
+            if let Some(value) = authorization {
+                request = request.header(AUTHORIZATION, value);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        let session = Cookies(
+            [(
+                INSECURE_SESSION_COOKIE_NAME.to_string(),
+                vec!["s".to_string()],
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let other = Cookies(
+            [(CSRF_COOKIE_NAME.to_string(), vec!["t".to_string()])]
+                .into_iter()
+                .collect(),
+        );
+
+        assert!(!needs_check(&post(Some("Bearer abc")), None));
+        assert!(!needs_check(&post(Some("Bearer abc")), Some(&other)));
+        assert!(needs_check(&post(Some("Bearer abc")), Some(&session)));
+        assert!(needs_check(&post(Some("Basic abc")), None));
+        assert!(needs_check(&post(None), None));
+    }
+}
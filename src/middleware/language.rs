@@ -1,6 +1,6 @@
 use axum::{extract::Request, http::header::ACCEPT_LANGUAGE, middleware::Next, response::Response};
 
-use super::cookies::Cookies;
+use super::cookies::{Cookie, Cookies, SameSite};
 use crate::{i18n::Language, models::user::User};
 
 /// Cookie holding the language choice of visitors without a profile
@@ -13,10 +13,11 @@ pub const LANGUAGE_COOKIE_NAME: &str = "lang";
 const LANGUAGE_COOKIE_MAX_AGE_SECS: i64 = 365 * 24 * 60 * 60;
 
 pub fn language_cookie(language: Language) -> String {
-    format!(
-        "{LANGUAGE_COOKIE_NAME}={}; SameSite=Lax; Path=/; Max-Age={LANGUAGE_COOKIE_MAX_AGE_SECS}",
-        language.code()
-    )
+    Cookie::new(LANGUAGE_COOKIE_NAME, language.code())
+        .same_site(SameSite::Lax)
+        .path("/")
+        .max_age_secs(LANGUAGE_COOKIE_MAX_AGE_SECS)
+        .to_string()
 }
 
 /// Resolve the request's UI language — profile setting, then language
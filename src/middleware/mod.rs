@@ -1,4 +1,6 @@
 pub mod cookies;
+pub mod csrf;
 pub mod language;
 pub mod no_guests;
+pub mod rate_limit;
 pub mod session;
@@ -0,0 +1,130 @@
+//! Per-IP token-bucket rate limiting for unauthenticated routes that do
+//! real work on every hit, such as the OAuth2 redirect and callback (each
+//! writes a CSRF row, and the callback may create a user).
+//!
+//! In-memory like `login_rate_limiter`: a restart resets the buckets,
+//! which is fine for a best-effort abuse guard. Apply with
+//! `.route_layer(middleware::from_fn_with_state(limiter, rate_limit))`.
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::info;
+
+/// Full buckets are forgotten once this many IPs are tracked, so a scan
+/// from many addresses can't grow the map without bound.
+const PRUNE_ABOVE: usize = 1024;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Clone)]
+pub struct RateLimiterHandle {
+    inner: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+    /// Requests allowed in a burst.
+    capacity: f64,
+    refill_per_sec: f64,
+}
+
+impl RateLimiterHandle {
+    /// Allow bursts of `burst` requests, refilled at `per_minute`.
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(HashMap::new())),
+            capacity: f64::from(burst.max(1)),
+            refill_per_sec: f64::from(per_minute) / 60.0,
+        }
+    }
+
+    /// Take a token for `ip`. Returns false if its bucket is empty.
+    pub fn try_acquire(&self, ip: IpAddr) -> bool {
+        self.try_acquire_at(ip, Instant::now())
+    }
+
+    fn try_acquire_at(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut map = self.inner.lock().unwrap();
+        if map.len() > PRUNE_ABOVE {
+            map.retain(|_, bucket| self.refilled(bucket, now) < self.capacity);
+        }
+        let bucket = map.entry(ip).or_insert(Bucket {
+            tokens: self.capacity,
+            updated: now,
+        });
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity)
+    }
+}
+
+pub async fn rate_limit(
+    State(limiter): State<RateLimiterHandle>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !limiter.try_acquire(addr.ip()) {
+        info!(
+            ip = addr.ip().to_string(),
+            path = request.uri().path(),
+            "rate limiting request"
+        );
+        return StatusCode::TOO_MANY_REQUESTS.into_response();
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    fn ip(a: u8) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(127, 0, 0, a))
+    }
+
+    #[test]
+    fn burst_is_allowed_then_refused() {
+        let limiter = RateLimiterHandle::new(60, 3);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at(ip(1), now));
+        }
+        assert!(!limiter.try_acquire_at(ip(1), now));
+    }
+
+    #[test]
+    fn tokens_refill_over_time() {
+        let limiter = RateLimiterHandle::new(60, 1);
+        let now = Instant::now();
+        assert!(limiter.try_acquire_at(ip(1), now));
+        assert!(!limiter.try_acquire_at(ip(1), now + Duration::from_millis(500)));
+        assert!(limiter.try_acquire_at(ip(1), now + Duration::from_millis(1100)));
+    }
+
+    #[test]
+    fn different_ips_are_independent() {
+        let limiter = RateLimiterHandle::new(60, 1);
+        let now = Instant::now();
+        assert!(limiter.try_acquire_at(ip(1), now));
+        assert!(limiter.try_acquire_at(ip(2), now));
+    }
+}
@@ -1,15 +1,19 @@
-use super::cookies::Cookies;
+use super::cookies::{Cookie, Cookies, SameSite};
 use crate::{
-    app::AppState,
-    models::session::{SESSION_LIFETIME_SECS, Session},
+    app::{AppState, SessionConfig},
+    models::{session::Session, user::User, user_token},
 };
 use axum::{
     Extension,
     extract::{Request, State},
-    http::{HeaderValue, StatusCode, header::SET_COOKIE},
+    http::{
+        HeaderValue, StatusCode,
+        header::{AUTHORIZATION, SET_COOKIE},
+    },
     middleware::Next,
     response::Response,
 };
+use chrono::Utc;
 use tracing::{info, trace};
 
 /// The `__Host-` prefix makes browsers reject any cookie with this name that
@@ -19,23 +23,61 @@ use tracing::{info, trace};
 /// affected users.
 pub const SESSION_COOKIE_NAME: &str = "__Host-session";
 
-/// The session cookie sent on login. All attributes required by the
-/// `__Host-` prefix must be present or browsers silently drop the cookie.
-pub fn session_cookie(token: &str) -> String {
-    format!(
-        "{SESSION_COOKIE_NAME}={token}; SameSite=Lax; HttpOnly; Secure; Path=/; Max-Age={SESSION_LIFETIME_SECS}"
+/// The session cookie's name with `[sessions] secure_cookies` off. Browsers
+/// refuse `__Host-` cookies that aren't Secure, so it can't keep the prefix
+/// (nor its protection against shadowing).
+pub const INSECURE_SESSION_COOKIE_NAME: &str = "session";
+
+pub fn session_cookie_name(config: &SessionConfig) -> &'static str {
+    if config.secure_cookies {
+        SESSION_COOKIE_NAME
+    } else {
+        INSECURE_SESSION_COOKIE_NAME
+    }
+}
+
+/// Name and attributes shared by setting and clearing the session cookie.
+/// All attributes required by the `__Host-` prefix must be present or
+/// browsers silently drop the cookie; without Secure, browsers reached
+/// over plain HTTP drop it as well, hence the config switch.
+fn with_session_attributes(config: &SessionConfig, cookie: Cookie) -> Cookie {
+    cookie
+        .same_site(SameSite::Lax)
+        .http_only(true)
+        .secure(config.secure_cookies)
+        .path("/")
+}
+
+/// The session cookie sent on login, living as long as the session.
+pub fn session_cookie(config: &SessionConfig, session: &Session) -> String {
+    let max_age = (session.expires_at - Utc::now()).num_seconds();
+    with_session_attributes(
+        config,
+        Cookie::new(session_cookie_name(config), session.token.clone()),
     )
+    .max_age_secs(max_age)
+    .to_string()
 }
 
-/// Clears the session cookie. Must carry the same Secure/Path attributes as
+/// Clears the session cookie. Carries the same attributes as
 /// [`session_cookie`], both to satisfy the `__Host-` prefix rules and so the
 /// deletion targets the same cookie regardless of which URL triggered it.
-pub fn clear_session_cookie() -> String {
-    format!("{SESSION_COOKIE_NAME}=deleted; SameSite=Lax; HttpOnly; Secure; Path=/; Max-Age=0")
+pub fn clear_session_cookie(config: &SessionConfig) -> String {
+    with_session_attributes(config, Cookie::removal(session_cookie_name(config))).to_string()
+}
+
+fn bearer_token(request: &Request) -> Option<String> {
+    request
+        .headers()
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(|token| token.trim().to_string())
 }
 
-fn get_session_tokens(cookies: &Cookies) -> &[String] {
-    cookies.get_all(SESSION_COOKIE_NAME)
+fn get_session_tokens<'a>(cookies: &'a Cookies, config: &SessionConfig) -> &'a [String] {
+    cookies.get_all(session_cookie_name(config))
 }
 
 pub async fn session_middleware(
@@ -47,14 +89,13 @@ pub async fn session_middleware(
     trace!("Authenticating user session");
     // The client may hold several cookies with the session cookie's name;
     // accept whichever one matches an active session.
-    let session_tokens = get_session_tokens(&cookies);
+    let session_tokens = get_session_tokens(&cookies, &state.session_config);
     let mut should_reset_cookie = !session_tokens.is_empty();
     let mut session = None;
     for session_token in session_tokens {
-        if let Some(mut found) =
+        if let Some(found) =
             Session::fetch(state.database_connection.clone(), session_token).await?
         {
-            found.user.is_admin = state.admin_config.user_ids.contains(&found.user.id);
             session = Some(found);
             should_reset_cookie = false;
             break;
@@ -64,7 +105,30 @@ pub async fn session_middleware(
         info!("Session cookie matched no active session; clearing it");
     }
 
-    let user = session.as_ref().map(|session| session.user.clone());
+    let mut user = session.as_ref().map(|session| session.user.clone());
+    // Scripts authenticate with their personal API token instead.
+    if user.is_none()
+        && let Some(token) = bearer_token(&request)
+    {
+        user = user_token::fetch_user(state.database_connection.clone(), &token).await?;
+        if user.is_none() {
+            info!("Bearer token matched no user");
+        }
+    }
+    if let Some(found) = &mut user {
+        match User::fetch_status(state.database_connection.clone(), found.id).await? {
+            Some((is_admin, false)) => {
+                found.is_admin = is_admin || state.admin_config.user_ids.contains(&found.id);
+            }
+            // Disabled, or deleted meanwhile: treat the request as logged
+            // out.
+            _ => {
+                info!("User {} is disabled; ignoring their credentials", found.id);
+                user = None;
+                session = None;
+            }
+        }
+    }
     request.extensions_mut().insert(user);
     request.extensions_mut().insert(session);
 
@@ -74,10 +138,31 @@ pub async fn session_middleware(
     if should_reset_cookie && response.headers().get(SET_COOKIE).is_none() {
         response.headers_mut().insert(
             SET_COOKIE,
-            HeaderValue::from_str(&clear_session_cookie())
+            HeaderValue::from_str(&clear_session_cookie(&state.session_config))
                 .expect("Hardcoded header value should always work"),
         );
     }
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insecure_cookies_drop_secure_and_the_host_prefix() {
+        let config = SessionConfig {
+            secure_cookies: false,
+            ..SessionConfig::default()
+        };
+        assert_eq!(
+            "session=deleted; SameSite=Lax; HttpOnly; Path=/; Max-Age=0",
+            clear_session_cookie(&config)
+        );
+        assert_eq!(
+            "__Host-session=deleted; SameSite=Lax; HttpOnly; Secure; Path=/; Max-Age=0",
+            clear_session_cookie(&SessionConfig::default())
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Append-only record of privileged actions: who pointed a telescope,
+//! started an integration, booked or cancelled, and what admins changed.
+//! Triggers in the schema refuse updates and deletes, so entries can only
+//! be added. Entries name users by id only; the name shown is the
+//! account's current one, so deleting an account anonymises its entries.
+
+use chrono::{DateTime, Utc};
+use tracing::error;
+
+use crate::database::ConnectionPool;
+use crate::error::InternalError;
+use crate::models::user::User;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    SetTarget,
+    Jog,
+    Park,
+    StartIntegration,
+    CreateBooking,
+    CancelBooking,
+    Maintenance,
+    Calibrate,
+    Stow,
+    ReloadTelescopes,
+    Announcement,
+    CreateLocalUser,
+    DeleteLocalUser,
+    SetLocalPassword,
+    SetAdmin,
+    SetDisabled,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::SetTarget => "set_target",
+            AuditAction::Jog => "jog",
+            AuditAction::Park => "park",
+            AuditAction::StartIntegration => "start_integration",
+            AuditAction::CreateBooking => "create_booking",
+            AuditAction::CancelBooking => "cancel_booking",
+            AuditAction::Maintenance => "maintenance",
+            AuditAction::Calibrate => "calibrate",
+            AuditAction::Stow => "stow",
+            AuditAction::ReloadTelescopes => "reload_telescopes",
+            AuditAction::Announcement => "announcement",
+            AuditAction::CreateLocalUser => "create_local_user",
+            AuditAction::DeleteLocalUser => "delete_local_user",
+            AuditAction::SetLocalPassword => "set_local_password",
+            AuditAction::SetAdmin => "set_admin",
+            AuditAction::SetDisabled => "set_disabled",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub user_id: i64,
+    /// The user's current name.
+    pub user_name: String,
+    pub action: String,
+    pub detail: String,
+}
+
+/// Record that `user` took `action`. Best-effort like `touch_if_guest`: a
+/// failure is logged but doesn't undo or block the action itself.
+pub async fn record(connection: ConnectionPool, user: &User, action: AuditAction, detail: &str) {
+    let conn = connection.get().await;
+    if let Err(err) = conn.execute(
+        "INSERT INTO audit_log (timestamp, user_id, action, detail)
+         VALUES (?1, ?2, ?3, ?4)",
+        (Utc::now().timestamp(), user.id, action.as_str(), detail),
+    ) {
+        error!(
+            "Failed to record {} by user {} in the audit log: {err}",
+            action.as_str(),
+            user.id
+        );
+    }
+}
+
+/// Entries from `[from, to)`, newest first and at most `limit` of them,
+/// optionally only those by the user named `user_name`.
+pub async fn fetch(
+    connection: ConnectionPool,
+    user_name: Option<&str>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    limit: usize,
+) -> Result<Vec<AuditEntry>, InternalError> {
+    let conn = connection.get().await;
+    let mut stmt = conn
+        .prepare(
+            "SELECT a.timestamp, a.user_id, u.username, a.action, a.detail
+             FROM audit_log a INNER JOIN user u ON a.user_id = u.id
+             WHERE a.timestamp >= ?1 AND a.timestamp < ?2 AND (?3 IS NULL OR u.username = ?3)
+             ORDER BY a.timestamp DESC, a.id DESC
+             LIMIT ?4",
+        )
+        .map_err(|e| InternalError::new(format!("Failed to prepare statement: {e}")))?;
+    stmt.query_map(
+        (from.timestamp(), to.timestamp(), user_name, limit as i64),
+        |row| {
+            Ok(AuditEntry {
+                timestamp: DateTime::from_timestamp(row.get(0)?, 0).unwrap_or_default(),
+                user_id: row.get(1)?,
+                user_name: row.get(2)?,
+                action: row.get(3)?,
+                detail: row.get(4)?,
+            })
+        },
+    )
+    .map_err(|e| InternalError::new(format!("Failed to query_map: {e}")))?
+    .map(|r| r.map_err(|err| InternalError::new(format!("Failed to map row: {err}"))))
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::{create_test_connection, create_test_user};
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn entries_are_filtered_and_cannot_be_changed() {
+        let connection = create_test_connection();
+        let alice = create_test_user(&connection, "alice").await;
+        let bob = create_test_user(&connection, "bob").await;
+
+        record(connection.clone(), &alice, AuditAction::SetTarget, "first").await;
+        record(connection.clone(), &bob, AuditAction::Stow, "second").await;
+
+        let now = Utc::now();
+        let hour = Duration::hours(1);
+        let all = fetch(connection.clone(), None, now - hour, now + hour, 100)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 2);
+        let by_bob = fetch(connection.clone(), Some("bob"), now - hour, now + hour, 100)
+            .await
+            .unwrap();
+        assert_eq!(by_bob.len(), 1);
+        assert_eq!(by_bob[0].action, "stow");
+        assert_eq!(by_bob[0].detail, "second");
+        let earlier = fetch(connection.clone(), None, now - hour * 2, now - hour, 100)
+            .await
+            .unwrap();
+        assert!(earlier.is_empty());
+
+        bob.delete(connection.clone()).await.unwrap();
+        let all = fetch(connection.clone(), None, now - hour, now + hour, 100)
+            .await
+            .unwrap();
+        assert_eq!(
+            all.iter().map(|e| e.user_name.as_str()).collect::<Vec<_>>(),
+            vec!["Deleted account", "alice"]
+        );
+
+        let conn = connection.get().await;
+        assert!(conn.execute("DELETE FROM audit_log", ()).is_err());
+        assert!(
+            conn.execute("UPDATE audit_log SET detail = 'changed'", ())
+                .is_err()
+        );
+    }
+}
@@ -1,13 +1,19 @@
-use std::sync::Arc;
-
 use chrono::{DateTime, Utc};
-use rusqlite::Connection;
+use rusqlite::params_from_iter;
 use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
 
+use crate::database::ConnectionPool;
 use crate::error::InternalError;
 use crate::models::user::User;
 
+/// How early a booking may be used, so that a small difference between the
+/// server clock and the user's clock doesn't lock them out of the first
+/// seconds of their slot. Only granted while the telescope is otherwise free.
+pub const BOOKING_START_SKEW_TOLERANCE_SECS: i64 = 30;
+
+/// Length of the rolling window the weekly booking quota applies to.
+pub const QUOTA_WINDOW_DAYS: i64 = 7;
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct Booking {
     pub id: i64,
@@ -32,10 +38,10 @@ impl Booking {
 
     pub async fn delete(
         self,
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user: &User,
     ) -> Result<bool, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let rows_deleted = if user.is_admin {
             conn.execute("DELETE FROM booking WHERE id = (?1)", (&self.id,))
         } else {
@@ -58,7 +64,7 @@ impl Booking {
     /// already exists. The conflict check and insert run as a single SQL
     /// statement, so this is safe against concurrent create requests.
     pub async fn create(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user: User,
         telescope_id: String,
         start: DateTime<Utc>,
@@ -66,7 +72,7 @@ impl Booking {
         description: Option<String>,
         country: Option<String>,
     ) -> Result<bool, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let rows = conn.execute(
             "INSERT INTO booking (user_id, telescope_id, start_timestamp, end_timestamp, description, country)
                  SELECT (?1), (?2), (?3), (?4), (?5), (?6)
@@ -82,108 +88,161 @@ impl Booking {
         Ok(rows > 0)
     }
 
-    pub async fn fetch_all(
-        connection: Arc<Mutex<Connection>>,
-    ) -> Result<Vec<Booking>, InternalError> {
-        let conn = connection.lock().await;
-        let mut stmt = conn
-            .prepare(
-                "SELECT booking.id, start_timestamp, end_timestamp, telescope_id, user.id, username, provider, description, country
-                FROM booking, user WHERE booking.user_id = user.id
-                ORDER BY start_timestamp ASC",
+    /// Hours of `user`'s bookings on `telescope_id` that fall within the
+    /// `QUOTA_WINDOW_DAYS` starting at `since`. Bookings straddling either
+    /// edge count only for the part inside the window.
+    pub async fn booked_hours_for_user_since(
+        connection: ConnectionPool,
+        user: &User,
+        telescope_id: &str,
+        since: DateTime<Utc>,
+    ) -> Result<f64, InternalError> {
+        let until = since + chrono::Duration::days(QUOTA_WINDOW_DAYS);
+        let conn = connection.get().await;
+        let seconds: i64 = conn
+            .query_row(
+                "SELECT COALESCE(SUM(MIN(end_timestamp, (?4)) - MAX(start_timestamp, (?3))), 0)
+                 FROM booking
+                 WHERE user_id = (?1)
+                   AND telescope_id = (?2)
+                   AND start_timestamp < (?4)
+                   AND end_timestamp > (?3)",
+                (&user.id, telescope_id, since.timestamp(), until.timestamp()),
+                |row| row.get(0),
             )
-            .map_err(|err| InternalError::new(format!("Failed to prepare statement: {err}")))?;
-        stmt.query_map([], map_booking_row)
-            .map_err(|err| InternalError::new(format!("Failed to query_map: {err}")))?
-            .map(|r| r.map_err(|err| InternalError::new(format!("Failed to map row: {err}"))))
-            .collect()
+            .map_err(|err| InternalError::new(format!("Failed to sum booked hours: {err}")))?;
+        Ok(seconds as f64 / 3600.0)
+    }
+
+    /// The most hours of `user`'s bookings on `telescope_id` in any quota
+    /// window starting between `from` and `to`. The count only changes
+    /// slope where a window edge meets a booking edge, so the busiest
+    /// window is among those starting at such a meeting or at either end.
+    pub async fn max_booked_hours_for_user(
+        connection: ConnectionPool,
+        user: &User,
+        telescope_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<f64, InternalError> {
+        let window = chrono::Duration::days(QUOTA_WINDOW_DAYS);
+        let mut starts = vec![from, to];
+        for booking in Self::fetch_for_user(connection.clone(), user)
+            .await?
+            .iter()
+            .filter(|b| b.telescope_name == telescope_id)
+        {
+            for edge in [booking.start_time, booking.end_time] {
+                starts.extend([edge, edge - window]);
+            }
+        }
+        starts.retain(|since| from <= *since && *since <= to);
+        starts.sort();
+        starts.dedup();
+        let mut max_hours: f64 = 0.0;
+        for since in starts {
+            let hours =
+                Self::booked_hours_for_user_since(connection.clone(), user, telescope_id, since)
+                    .await?;
+            max_hours = max_hours.max(hours);
+        }
+        Ok(max_hours)
+    }
+
+    pub async fn fetch_all(connection: ConnectionPool) -> Result<Vec<Booking>, InternalError> {
+        fetch(connection, BookingFilter::All).await
     }
 
     pub async fn fetch_for_user(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user: &User,
     ) -> Result<Vec<Booking>, InternalError> {
         Self::fetch_for_user_id(connection, user.id).await
     }
 
     pub async fn fetch_for_user_id(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
     ) -> Result<Vec<Booking>, InternalError> {
-        let conn = connection.lock().await;
-        let mut stmt = conn
-            .prepare(
-                "SELECT booking.id, start_timestamp, end_timestamp, telescope_id, user.id, username, provider, description, country
-                FROM booking, user WHERE booking.user_id = user.id AND user.id = ?1
-                ORDER BY start_timestamp ASC",
-            )
-            .map_err(|err| InternalError::new(format!("Failed to prepare statement: {err}")))?;
-        stmt.query_map([user_id], map_booking_row)
-            .map_err(|err| InternalError::new(format!("Failed to query_map: {err}")))?
-            .map(|r| r.map_err(|err| InternalError::new(format!("Failed to map row: {err}"))))
-            .collect()
+        fetch(connection, BookingFilter::User(user_id)).await
     }
 
     pub async fn fetch_one(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         id: i64,
     ) -> Result<Option<Booking>, InternalError> {
-        let conn = connection.lock().await;
-        let mut stmt = conn
-            .prepare(
-                "SELECT booking.id, start_timestamp, end_timestamp, telescope_id, user.id, username, provider, description, country
-                FROM booking, user WHERE booking.user_id = user.id AND booking.id = ?1
-                ORDER BY start_timestamp ASC",
-            )
-            .map_err(|err| InternalError::new(format!("Failed to prepare statement: {err}")))?;
-        Ok(stmt
-            .query_map([id], map_booking_row)
-            .map_err(|err| InternalError::new(format!("Failed to query_map: {err}")))?
-            .map(|r| r.map_err(|err| InternalError::new(format!("Failed to map row: {err}"))))
-            .collect::<Result<Vec<_>, _>>()?
-            .pop())
+        Ok(fetch(connection, BookingFilter::Id(id)).await?.pop())
     }
 
     pub async fn fetch_in_range(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         from: DateTime<Utc>,
         to: DateTime<Utc>,
     ) -> Result<Vec<Booking>, InternalError> {
-        let conn = connection.lock().await;
-        let mut stmt = conn
-            .prepare(
-                "SELECT booking.id, start_timestamp, end_timestamp, telescope_id, user.id, username, provider, description, country
-                FROM booking, user WHERE booking.user_id = user.id
-                AND start_timestamp >= ?1 AND start_timestamp < ?2
-                ORDER BY start_timestamp ASC",
-            )
-            .map_err(|err| InternalError::new(format!("Failed to prepare statement: {err}")))?;
-        stmt.query_map([from.timestamp(), to.timestamp()], map_booking_row)
-            .map_err(|err| InternalError::new(format!("Failed to query_map: {err}")))?
-            .map(|r| r.map_err(|err| InternalError::new(format!("Failed to map row: {err}"))))
-            .collect()
+        fetch(
+            connection,
+            BookingFilter::StartingIn(from.timestamp(), to.timestamp()),
+        )
+        .await
     }
 
-    pub async fn fetch_active(
-        connection: Arc<Mutex<Connection>>,
-    ) -> Result<Vec<Booking>, InternalError> {
-        let conn = connection.lock().await;
-        let now = Utc::now().timestamp();
-        let mut stmt = conn
-            .prepare(
-                "SELECT booking.id, start_timestamp, end_timestamp, telescope_id, user.id, username, provider, description, country
-                FROM booking, user WHERE booking.user_id = user.id
-                AND start_timestamp <= ?1 AND end_timestamp > ?1
-                ORDER BY start_timestamp ASC",
-            )
-            .map_err(|err| InternalError::new(format!("Failed to prepare statement: {err}")))?;
-        stmt.query_map([now], map_booking_row)
-            .map_err(|err| InternalError::new(format!("Failed to query_map: {err}")))?
-            .map(|r| r.map_err(|err| InternalError::new(format!("Failed to map row: {err}"))))
-            .collect()
+    pub async fn fetch_active(connection: ConnectionPool) -> Result<Vec<Booking>, InternalError> {
+        fetch(connection, BookingFilter::ActiveAt(Utc::now().timestamp())).await
     }
 }
 
+/// Which bookings [`fetch`] selects. The values are bound as parameters,
+/// so every variant's SQL is fixed.
+enum BookingFilter {
+    All,
+    User(i64),
+    Id(i64),
+    /// Starting at or after the first timestamp and before the second.
+    StartingIn(i64, i64),
+    ActiveAt(i64),
+}
+
+impl BookingFilter {
+    fn condition(&self) -> &'static str {
+        match self {
+            BookingFilter::All => "",
+            BookingFilter::User(_) => "AND user.id = ?1",
+            BookingFilter::Id(_) => "AND booking.id = ?1",
+            BookingFilter::StartingIn(..) => "AND start_timestamp >= ?1 AND start_timestamp < ?2",
+            BookingFilter::ActiveAt(_) => "AND start_timestamp <= ?1 AND end_timestamp > ?1",
+        }
+    }
+
+    fn params(&self) -> Vec<i64> {
+        match *self {
+            BookingFilter::All => vec![],
+            BookingFilter::User(id) | BookingFilter::Id(id) | BookingFilter::ActiveAt(id) => {
+                vec![id]
+            }
+            BookingFilter::StartingIn(from, to) => vec![from, to],
+        }
+    }
+}
+
+async fn fetch(
+    connection: ConnectionPool,
+    filter: BookingFilter,
+) -> Result<Vec<Booking>, InternalError> {
+    let conn = connection.get().await;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT booking.id, start_timestamp, end_timestamp, telescope_id, user.id, username, provider, description, country
+            FROM booking, user WHERE booking.user_id = user.id {}
+            ORDER BY start_timestamp ASC",
+            filter.condition()
+        ))
+        .map_err(|err| InternalError::new(format!("Failed to prepare statement: {err}")))?;
+    stmt.query_map(params_from_iter(filter.params()), map_booking_row)
+        .map_err(|err| InternalError::new(format!("Failed to query_map: {err}")))?
+        .map(|r| r.map_err(|err| InternalError::new(format!("Failed to map row: {err}"))))
+        .collect()
+}
+
 fn map_booking_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Booking> {
     Ok(Booking {
         id: row.get(0)?,
@@ -199,7 +258,7 @@ fn map_booking_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Booking> {
 }
 
 pub async fn consecutive_booking_end(
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
     user: &User,
     telescope_id: &str,
 ) -> Result<Option<DateTime<Utc>>, InternalError> {
@@ -227,15 +286,66 @@ pub async fn consecutive_booking_end(
     Ok(Some(end_time))
 }
 
+/// Start and end of the unbroken run of `bookings` on `telescope_id` that a
+/// new slot from `start` to `end` would be part of, the slot included.
+/// Empty bookings are skipped; they cannot extend the run, and following
+/// one would never end.
+pub fn contiguous_run_with(
+    bookings: &[Booking],
+    telescope_id: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    let neighbours: Vec<&Booking> = bookings
+        .iter()
+        .filter(|b| b.telescope_name == telescope_id && b.start_time < b.end_time)
+        .collect();
+    let (mut run_start, mut run_end) = (start, end);
+    loop {
+        let before = neighbours.iter().find(|b| b.end_time == run_start);
+        let after = neighbours.iter().find(|b| b.start_time == run_end);
+        if before.is_none() && after.is_none() {
+            return (run_start, run_end);
+        }
+        if let Some(before) = before {
+            run_start = before.start_time;
+        }
+        if let Some(after) = after {
+            run_end = after.end_time;
+        }
+    }
+}
+
 pub async fn booking_is_active(
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
     user: &User,
     telescope_id: &str,
 ) -> Result<bool, InternalError> {
-    Ok(Booking::fetch_for_user(connection, user)
+    let now = Utc::now();
+    let bookings: Vec<Booking> = Booking::fetch_for_user(connection.clone(), user)
+        .await?
+        .into_iter()
+        .filter(|b| b.telescope_name == telescope_id)
+        .collect();
+    if bookings.iter().any(|b| b.active_at(&now)) {
+        return Ok(true);
+    }
+    let tolerance = chrono::Duration::seconds(BOOKING_START_SKEW_TOLERANCE_SECS);
+    if !bookings.iter().any(|b| b.active_at(&(now + tolerance))) {
+        return Ok(false);
+    }
+    // The booking starts within the skew tolerance. Let the user in early
+    // only if nobody else holds the telescope; otherwise the handover in
+    // `booking_monitor` must happen first.
+    let held_by_booking = Booking::fetch_active(connection.clone())
+        .await?
+        .iter()
+        .any(|b| b.telescope_name == telescope_id);
+    let held_by_guest = crate::models::guest::GuestSession::fetch_all_active(connection)
         .await?
         .iter()
-        .any(|b| b.active_at(&Utc::now()) && b.telescope_name == telescope_id))
+        .any(|g| g.telescope_id == telescope_id);
+    Ok(!held_by_booking && !held_by_guest)
 }
 
 /// Authorisation gate for the observe page and its sub-handlers: returns
@@ -244,7 +354,7 @@ pub async fn booking_is_active(
 /// `models::guest`) precisely so the booking calendar stays untouched —
 /// this helper is where the two paths meet.
 pub async fn is_authorized_for_telescope(
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
     user: &User,
     telescope_id: &str,
 ) -> Result<bool, InternalError> {
@@ -257,6 +367,7 @@ pub async fn is_authorized_for_telescope(
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::database::{create_test_connection, create_test_user};
 
     fn create_booking(start_time_ts: i64, end_time_ts: i64) -> Booking {
         Booking {
@@ -295,4 +406,217 @@ mod test {
         assert!(!booking1.overlaps(&booking2));
         assert!(!booking2.overlaps(&booking1));
     }
+
+    #[test]
+    fn contiguous_run_joins_neighbours_on_same_telescope() {
+        let hour = 3600;
+        let mut bookings = vec![
+            create_booking(0, hour),
+            create_booking(hour, 2 * hour),
+            create_booking(3 * hour, 4 * hour),
+            create_booking(4 * hour, 5 * hour),
+        ];
+        bookings[3].telescope_name = "other".to_string();
+        let at = |ts| DateTime::from_timestamp(ts, 0).unwrap();
+
+        // Filling the gap joins the run before it with the one after.
+        assert_eq!(
+            contiguous_run_with(&bookings, "", at(2 * hour), at(3 * hour)),
+            (at(0), at(4 * hour))
+        );
+        // Bookings on other telescopes don't count.
+        assert_eq!(
+            contiguous_run_with(&bookings, "", at(5 * hour), at(6 * hour)),
+            (at(5 * hour), at(6 * hour))
+        );
+    }
+
+    #[test]
+    fn contiguous_run_skips_empty_bookings() {
+        let hour = 3600;
+        let bookings = vec![
+            create_booking(hour, hour),
+            create_booking(2 * hour, 2 * hour),
+            create_booking(2 * hour, 3 * hour),
+        ];
+        let at = |ts| DateTime::from_timestamp(ts, 0).unwrap();
+
+        assert_eq!(
+            contiguous_run_with(&bookings, "", at(0), at(hour)),
+            (at(0), at(hour))
+        );
+        assert_eq!(
+            contiguous_run_with(&bookings, "", at(hour), at(2 * hour)),
+            (at(hour), at(3 * hour))
+        );
+    }
+
+    #[tokio::test]
+    async fn create_refuses_overlapping_slot_on_same_telescope() {
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "user").await;
+        let at = |hour: i64| DateTime::from_timestamp(hour * 3600, 0).unwrap();
+        let create = |telescope: &str, start, end| {
+            Booking::create(
+                connection.clone(),
+                user.clone(),
+                telescope.to_string(),
+                start,
+                end,
+                None,
+                None,
+            )
+        };
+
+        assert!(create("a", at(10), at(12)).await.unwrap());
+        assert!(!create("a", at(11), at(13)).await.unwrap());
+        assert!(!create("a", at(9), at(11)).await.unwrap());
+        assert!(create("a", at(12), at(13)).await.unwrap());
+        assert!(create("b", at(11), at(12)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn booked_hours_are_clipped_to_the_window() {
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "user").await;
+        let at = |hour: i64| DateTime::from_timestamp(hour * 3600, 0).unwrap();
+        for (telescope, start, end) in [("a", 0, 2), ("a", 10, 11), ("a", 24 * 7, 24 * 7 + 2)] {
+            Booking::create(
+                connection.clone(),
+                user.clone(),
+                telescope.to_string(),
+                at(start),
+                at(end),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        let hours =
+            |since| Booking::booked_hours_for_user_since(connection.clone(), &user, "a", since);
+        assert_eq!(hours(at(1)).await.unwrap(), 1.0 + 1.0 + 1.0);
+        assert_eq!(hours(at(0)).await.unwrap(), 2.0 + 1.0);
+        assert_eq!(
+            Booking::booked_hours_for_user_since(connection.clone(), &user, "b", at(0))
+                .await
+                .unwrap(),
+            0.0
+        );
+    }
+
+    #[tokio::test]
+    async fn busiest_quota_window_can_start_between_the_ends() {
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "user").await;
+        let at = |hour: i64| DateTime::from_timestamp(hour * 3600, 0).unwrap();
+        for (start, end) in [(5, 15), (170, 180)] {
+            Booking::create(
+                connection.clone(),
+                user.clone(),
+                "a".to_string(),
+                at(start),
+                at(end),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        // Windows starting at either end hold one booking each, but one
+        // starting at hour 12 holds the last 3 hours of the first and all
+        // of the second.
+        let hours =
+            |since| Booking::booked_hours_for_user_since(connection.clone(), &user, "a", since);
+        assert_eq!(hours(at(0)).await.unwrap(), 10.0);
+        assert_eq!(hours(at(100)).await.unwrap(), 10.0);
+        assert_eq!(
+            Booking::max_booked_hours_for_user(connection.clone(), &user, "a", at(0), at(100))
+                .await
+                .unwrap(),
+            13.0
+        );
+    }
+
+    #[tokio::test]
+    async fn each_fetch_selects_its_own_bookings() {
+        let connection = create_test_connection();
+        let ada = create_test_user(&connection, "ada").await;
+        let bob = create_test_user(&connection, "bob").await;
+        let now = Utc::now();
+        let hours = |h: i64| now + chrono::Duration::hours(h);
+        for (user, start, end) in [(&ada, -1, 1), (&ada, 5, 6), (&bob, 2, 3)] {
+            Booking::create(
+                connection.clone(),
+                user.clone(),
+                "a".to_string(),
+                hours(start),
+                hours(end),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+        let starts = |bookings: Vec<Booking>| {
+            bookings
+                .iter()
+                .map(|b| (b.user_name.clone(), b.start_time.timestamp()))
+                .collect::<Vec<_>>()
+        };
+        let ts = |h| hours(h).timestamp();
+
+        assert_eq!(
+            starts(Booking::fetch_all(connection.clone()).await.unwrap()),
+            [
+                ("ada".into(), ts(-1)),
+                ("bob".into(), ts(2)),
+                ("ada".into(), ts(5))
+            ]
+        );
+        assert_eq!(
+            starts(
+                Booking::fetch_for_user(connection.clone(), &ada)
+                    .await
+                    .unwrap()
+            ),
+            [("ada".into(), ts(-1)), ("ada".into(), ts(5))]
+        );
+        assert_eq!(
+            starts(
+                Booking::fetch_for_user_id(connection.clone(), bob.id)
+                    .await
+                    .unwrap()
+            ),
+            [("bob".into(), ts(2))]
+        );
+        assert_eq!(
+            starts(
+                Booking::fetch_in_range(connection.clone(), hours(0), hours(6))
+                    .await
+                    .unwrap()
+            ),
+            [("bob".into(), ts(2)), ("ada".into(), ts(5))]
+        );
+        assert_eq!(
+            starts(Booking::fetch_active(connection.clone()).await.unwrap()),
+            [("ada".into(), ts(-1))]
+        );
+
+        let bobs = Booking::fetch_for_user(connection.clone(), &bob)
+            .await
+            .unwrap();
+        let one = Booking::fetch_one(connection.clone(), bobs[0].id)
+            .await
+            .unwrap();
+        assert_eq!(one.as_ref(), bobs.first());
+        assert_eq!(
+            Booking::fetch_one(connection, bobs[0].id + 100)
+                .await
+                .unwrap(),
+            None
+        );
+    }
 }
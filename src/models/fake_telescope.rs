@@ -1,9 +1,17 @@
-use crate::coords::{Direction, Location};
-use crate::coords::{horizontal_from_equatorial, horizontal_from_galactic, horizontal_from_sun};
+use crate::coords::{
+    Direction, HI_REST_FREQ_HZ, J2000, Location, angular_separation, seconds_to_direction,
+};
+use crate::coords::{
+    equatorial_from_horizontal, galactic_from_equatorial, horizontal_from_equatorial,
+    horizontal_from_galactic, horizontal_from_moon, horizontal_from_sun, precess,
+    refraction_correction, vlsr_correction,
+};
 use crate::models::telescope::Telescope;
 use crate::models::telescope_types::{
-    CalibrationResult, IQ_BLOCK_SIZE, IqBlock, ObservedSpectra, ReceiverConfiguration,
-    ReceiverError, TelescopeError, TelescopeInfo, TelescopeStatus, TelescopeTarget,
+    CalibrationResult, DriftScan, IQ_BLOCK_SIZE, IqBlock, ObservationMode, ObservedSpectra,
+    PARKED_TOLERANCE_DEG, POSITION_UPDATE_BUFFER, PositionSwitch, PositionUpdate,
+    ReceiverCalibration, ReceiverConfiguration, ReceiverError, ScanSpectrum, StowPhase,
+    SubsystemStatus, TelescopeError, TelescopeInfo, TelescopeStatus, TelescopeTarget,
 };
 use crate::tle_cache::TleCacheHandle;
 use async_trait::async_trait;
@@ -13,10 +21,10 @@ use rand_distr::StandardNormal;
 use rustfft::num_complex::Complex;
 use std::f64::consts::PI;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, broadcast};
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error, info, trace};
+use tracing::{Instrument, debug, error, info, info_span, trace};
 
 const FAKE_TELESCOPE_PARKING_HORIZONTAL: Direction = Direction {
     azimuth: 0.0,
@@ -24,11 +32,49 @@ const FAKE_TELESCOPE_PARKING_HORIZONTAL: Direction = Direction {
 };
 
 pub const FAKE_TELESCOPE_SLEWING_SPEED: f64 = PI / 10.0;
+/// The most the motors can accelerate or brake (rad/s²).
+const FAKE_TELESCOPE_MAX_ACCELERATION: f64 = 0.3;
+/// Speed (rad/s) per radian of pointing error once close to the target,
+/// where the drives follow the error rather than brake at full force.
+const FAKE_TELESCOPE_SERVO_GAIN: f64 = 10.0;
+/// Slack in the azimuth gears, taken up by the motor before the dish
+/// moves each time the azimuth drive reverses.
+const FAKE_TELESCOPE_AZ_BACKLASH_RAD: f64 = 0.02 * PI / 180.0;
+/// Time step the servo is simulated with, independent of how often the
+/// update loop runs.
+const FAKE_TELESCOPE_SERVO_STEP: Duration = Duration::from_millis(10);
+/// Receiver settings the fake telescope starts with; integrations use
+/// whatever the observe form asks for.
 pub const FAKE_TELESCOPE_CHANNELS: usize = 400;
-pub const FAKE_TELESCOPE_CHANNEL_WIDTH: f64 = 2e6f64 / FAKE_TELESCOPE_CHANNELS as f64;
-pub const FAKE_TELESCOPE_FIRST_CHANNEL: f64 =
-    1.420e9f64 - FAKE_TELESCOPE_CHANNEL_WIDTH * FAKE_TELESCOPE_CHANNELS as f64 / 2f64;
+pub const FAKE_TELESCOPE_BANDWIDTH_HZ: f64 = 2e6;
+pub const FAKE_TELESCOPE_CENTER_FREQ_HZ: f64 = 1.420e9;
 pub const FAKE_TELESCOPE_NOISE: f64 = 2f64;
+/// Reported system temperature; the fake spectra are not scaled by it.
+const FAKE_TELESCOPE_TSYS_K: f64 = 300.0;
+/// Receiver bandpass level at the band centre, and how far it droops at
+/// the edges. Frequency switching is meant to remove exactly this shape.
+const FAKE_TELESCOPE_BANDPASS_LEVEL: f64 = 5.0;
+const FAKE_TELESCOPE_BANDPASS_DROOP: f64 = 1.5;
+/// A hydrogen line for the fake sky: peak above the bandpass, and width.
+/// The peak is that of the Galactic disk component in the plane.
+const FAKE_TELESCOPE_LINE_PEAK: f64 = 2.0;
+const FAKE_TELESCOPE_LINE_WIDTH_HZ: f64 = 50e3;
+/// Flat rotation curve for the fake Galaxy: the Sun's distance from the
+/// centre and the circular speed.
+const FAKE_GALAXY_R0_KPC: f64 = 8.5;
+const FAKE_GALAXY_V0_MPS: f64 = 220e3;
+/// Distance along the line of sight to the gas of the disk component.
+const FAKE_GALAXY_DISK_DISTANCE_KPC: f64 = 4.0;
+/// Gaussian scale heights, in galactic latitude, of the thin disk and of
+/// the nearby gas that is seen all over the sky at zero velocity.
+const FAKE_GALAXY_DISK_WIDTH_RAD: f64 = 5.0 * PI / 180.0;
+const FAKE_GALAXY_LOCAL_WIDTH_RAD: f64 = 30.0 * PI / 180.0;
+const FAKE_GALAXY_LOCAL_PEAK: f64 = 1.0;
+const SPEED_OF_LIGHT_MPS: f64 = 299_792_458.0;
+/// Noise temperature of the fake calibration diode. The bandpass stands
+/// for `FAKE_TELESCOPE_TSYS_K`, so T_sys = T_cal · P_off / (P_on − P_off)
+/// gives the reported value back.
+const FAKE_TELESCOPE_CAL_K: f64 = 60.0;
 pub const TELESCOPE_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
 
 struct Inner {
@@ -36,26 +82,47 @@ struct Inner {
     az_offset_rad: f64,
     el_offset_rad: f64,
     horizontal: Direction,
+    drive: Drive,
     location: Location,
     min_elevation_rad: f64,
     max_elevation_rad: f64,
+    keyhole_elevation_rad: Option<f64>,
+    apply_refraction: bool,
     webcam_crop: Option<[f64; 4]>,
     default_ref_freq_hz: f64,
     default_gain_db: f64,
+    rest_freq_hz: f64,
+    max_integration_secs: f64,
     most_recent_error: Option<TelescopeError>,
+    last_successful_update: Option<DateTime<Utc>>,
     receiver_configuration: ReceiverConfiguration,
+    /// When the current integration started; only meaningful while
+    /// `receiver_configuration.integrate` is set.
+    integration_started: Option<Instant>,
     current_spectra: Vec<ObservedSpectra>,
+    /// Reference-position spectra of a position-switched integration;
+    /// `current_spectra` then holds the on-source half.
+    off_spectra: Vec<ObservedSpectra>,
+    /// Time into the current position-switched integration, which decides
+    /// whether the next spectrum is on or off source.
+    switch_elapsed: Duration,
     name: String,
     stow_position: Option<Direction>,
     service_position: Option<Direction>,
+    stow: Option<StowPhase>,
     alive: bool,
     tle_cache: TleCacheHandle,
     iq_cancellation_token: Option<CancellationToken>,
     spectrum_cancellation_token: Option<CancellationToken>,
+    position_updates: Option<broadcast::Sender<PositionUpdate>>,
+    /// Wakes the update loop so it notices `alive` is false without
+    /// waiting out its sleep.
+    shutdown_token: CancellationToken,
 }
 
 pub struct FakeTelescope {
     inner: Arc<Mutex<Inner>>,
+    update_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -66,53 +133,126 @@ pub fn create(
     location: Location,
     min_elevation_rad: f64,
     max_elevation_rad: f64,
+    keyhole_elevation_rad: Option<f64>,
     webcam_crop: Option<[f64; 4]>,
     default_ref_freq_hz: f64,
     default_gain_db: f64,
+    rest_freq_hz: f64,
+    max_integration_secs: f64,
+    apply_refraction: bool,
     tle_cache: TleCacheHandle,
 ) -> FakeTelescope {
+    // Not a child of whatever request (re)loaded the telescope.
+    let span = info_span!(parent: None, "telescope", telescope_id = %name);
     let inner = Arc::new(Mutex::new(Inner {
         target: None,
         az_offset_rad: 0.0,
         el_offset_rad: 0.0,
         horizontal: FAKE_TELESCOPE_PARKING_HORIZONTAL,
+        drive: Drive::default(),
         location,
         min_elevation_rad,
         max_elevation_rad,
+        keyhole_elevation_rad,
+        apply_refraction,
         webcam_crop,
         default_ref_freq_hz,
         default_gain_db,
+        rest_freq_hz,
+        max_integration_secs,
         most_recent_error: None,
+        last_successful_update: None,
         receiver_configuration: ReceiverConfiguration {
             integrate: false,
+            center_freq_hz: FAKE_TELESCOPE_CENTER_FREQ_HZ,
             ref_freq_hz: default_ref_freq_hz,
+            bandwidth_hz: FAKE_TELESCOPE_BANDWIDTH_HZ,
             gain_db: default_gain_db,
+            spectral_channels: FAKE_TELESCOPE_CHANNELS,
             ..Default::default()
         },
+        integration_started: None,
         current_spectra: vec![],
+        off_spectra: vec![],
+        switch_elapsed: Duration::ZERO,
         name,
         stow_position,
         service_position,
+        stow: None,
         alive: true,
         tle_cache,
         iq_cancellation_token: None,
         spectrum_cancellation_token: None,
+        position_updates: Some(broadcast::channel(POSITION_UPDATE_BUFFER).0),
+        shutdown_token: CancellationToken::new(),
     }));
 
     let task_inner = inner.clone();
-    tokio::spawn(async move {
-        loop {
-            {
-                let mut inner = task_inner.lock().await;
-                if let Err(error) = inner.update(TELESCOPE_UPDATE_INTERVAL) {
-                    error!("Failed to update telescope: {}", error);
+    let update_task = tokio::spawn(
+        async move {
+            loop {
+                let shutdown_token = {
+                    let mut inner = task_inner.lock().await;
+                    if !inner.alive {
+                        break;
+                    }
+                    match inner.update(TELESCOPE_UPDATE_INTERVAL) {
+                        Ok(()) => inner.last_successful_update = Some(Utc::now()),
+                        Err(error) => error!("Failed to update telescope: {}", error),
+                    }
+                    if let Some(sender) = &inner.position_updates {
+                        let (status, commanded_horizontal) = inner.status();
+                        // Sending fails only when nobody is subscribed.
+                        let _ = sender.send(PositionUpdate {
+                            status,
+                            current_horizontal: Some(inner.horizontal),
+                            commanded_horizontal,
+                        });
+                    }
+                    inner.shutdown_token.clone()
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep(TELESCOPE_UPDATE_INTERVAL) => {}
+                    _ = shutdown_token.cancelled() => {}
                 }
             }
-            tokio::time::sleep(TELESCOPE_UPDATE_INTERVAL).await;
         }
-    });
+        .instrument(span),
+    );
 
-    FakeTelescope { inner }
+    FakeTelescope {
+        inner,
+        update_task: Mutex::new(Some(update_task)),
+    }
+}
+
+/// A fake telescope for tests, at longitude 0.2 and latitude 1.0 rad, free
+/// to point anywhere above the horizon and without refraction.
+#[cfg(test)]
+pub(crate) fn test_telescope(
+    stow_position: Option<Direction>,
+    keyhole_elevation_rad: Option<f64>,
+    max_integration_secs: f64,
+) -> FakeTelescope {
+    create(
+        "fake".to_string(),
+        stow_position,
+        None,
+        Location {
+            longitude: 0.2,
+            latitude: 1.0,
+        },
+        0.0,
+        PI,
+        keyhole_elevation_rad,
+        None,
+        1417.9e6,
+        60.0,
+        HI_REST_FREQ_HZ,
+        max_integration_secs,
+        false,
+        TleCacheHandle::new(),
+    )
 }
 
 #[async_trait]
@@ -124,47 +264,49 @@ impl Telescope for FakeTelescope {
         el_offset_rad: f64,
     ) -> Result<TelescopeTarget, TelescopeError> {
         let mut inner = self.inner.lock().await;
-
         inner.most_recent_error = None;
-        inner.receiver_configuration.integrate = false;
-        inner.current_spectra.clear();
+        inner.point_at(target, az_offset_rad, el_offset_rad)
+    }
 
-        let raw = calculate_target_horizontal(inner.location, Utc::now(), target, &inner.tle_cache)
-            .unwrap_or(Direction {
-                azimuth: 0.0,
-                elevation: -1.0,
-            });
-        let target_horizontal = apply_offset(raw, az_offset_rad, el_offset_rad);
-        if target_horizontal.elevation < inner.min_elevation_rad
-            || target_horizontal.elevation > inner.max_elevation_rad
-        {
-            info!(
-                "Refusing to set target for telescope {} to {}. Target is out of elevation range",
-                &inner.name, &target
-            );
-            Err(TelescopeError::TargetOutOfElevationRange {
-                min_deg: inner.min_elevation_rad.to_degrees(),
-                max_deg: inner.max_elevation_rad.to_degrees(),
-            })
-        } else {
-            info!(
-                "Setting target for telescope {} to {} (az={:.2}°, el={:.2}°)",
-                &inner.name,
-                &target,
-                target_horizontal.azimuth.to_degrees(),
-                target_horizontal.elevation.to_degrees()
-            );
-            inner.az_offset_rad = az_offset_rad;
-            inner.el_offset_rad = el_offset_rad;
-            inner.target = Some(target);
-            Ok(target)
-        }
+    async fn repoint(
+        &self,
+        target: TelescopeTarget,
+        az_offset_rad: f64,
+        el_offset_rad: f64,
+    ) -> Result<TelescopeTarget, TelescopeError> {
+        let mut inner = self.inner.lock().await;
+        inner.point_at(target, az_offset_rad, el_offset_rad)
     }
 
     async fn stop(&self) -> Result<(), TelescopeError> {
         let mut inner = self.inner.lock().await;
         info!("Stopping telescope {}", &inner.name);
         inner.target = None;
+        if inner.stow == Some(StowPhase::Driving) {
+            inner.stow = None;
+        }
+        Ok(())
+    }
+
+    async fn stow(&self) -> Result<(), TelescopeError> {
+        let mut inner = self.inner.lock().await;
+        inner.receiver_configuration.integrate = false;
+        inner.clear_spectra();
+        let Some(position) = inner.stow_position else {
+            inner.target = None;
+            return Ok(());
+        };
+        inner.most_recent_error = None;
+        inner.point_at(
+            TelescopeTarget::Horizontal {
+                azimuth: position.azimuth,
+                elevation: position.elevation,
+            },
+            0.0,
+            0.0,
+        )?;
+        info!("Stowing telescope {}", &inner.name);
+        inner.stow = Some(StowPhase::Driving);
         Ok(())
     }
 
@@ -204,9 +346,11 @@ impl Telescope for FakeTelescope {
             if inner.iq_cancellation_token.is_some() {
                 return Err(ReceiverError::IntegrationAlreadyRunning);
             }
+            receiver_configuration.validate()?;
             info!("Starting integration on {}", &inner.name);
-            inner.current_spectra.clear();
-            inner.receiver_configuration.integrate = true;
+            inner.clear_spectra();
+            inner.receiver_configuration = receiver_configuration;
+            inner.integration_started = Some(Instant::now());
             inner.spectrum_cancellation_token = Some(CancellationToken::new());
         } else if !receiver_configuration.integrate && inner.receiver_configuration.integrate {
             info!("Stopping integration on {}", &inner.name);
@@ -229,35 +373,26 @@ impl Telescope for FakeTelescope {
             return None;
         }
         info!("Stopping integration on {}", &inner.name);
+        let scan = inner.scan_spectra();
         inner.receiver_configuration.integrate = false;
         if let Some(token) = inner.spectrum_cancellation_token.take() {
             token.cancel();
         }
-        if inner.current_spectra.is_empty() {
-            return None;
-        }
-        let mut result = ObservedSpectra {
-            frequencies: vec![0f64; FAKE_TELESCOPE_CHANNELS],
-            spectra: vec![0f64; FAKE_TELESCOPE_CHANNELS],
-            observation_time: Duration::from_secs(0),
-        };
-        for integration in &inner.current_spectra {
-            result.spectra = result
-                .spectra
-                .into_iter()
-                .zip(integration.spectra.iter())
-                .map(|(a, b)| a + b)
-                .collect();
-            result.observation_time += integration.observation_time;
-        }
-        result.frequencies = inner.current_spectra[0].frequencies.clone();
-        let n = inner.current_spectra.len() as f64;
-        result.spectra = result.spectra.into_iter().map(|v| v / n).collect();
-        Some(result)
+        inner
+            .averaged_spectra()
+            .map(|spectra| ObservedSpectra { scan, ..spectra })
     }
 
     async fn clear_measurements(&self) {
-        self.inner.lock().await.current_spectra.clear();
+        self.inner.lock().await.clear_spectra();
+    }
+
+    async fn latest_spectrum(&self) -> Option<ObservedSpectra> {
+        let inner = self.inner.lock().await;
+        if !inner.receiver_configuration.integrate {
+            return None;
+        }
+        inner.averaged_spectra()
     }
 
     async fn interferometry_capable(&self) -> bool {
@@ -270,61 +405,39 @@ impl Telescope for FakeTelescope {
 
     async fn get_info(&self) -> Result<TelescopeInfo, TelescopeError> {
         let inner = self.inner.lock().await;
+        // Like the tracker of a real telescope, a shut-down one no longer
+        // knows where it is pointing.
+        if !inner.alive {
+            return Err(TelescopeError::TelescopeNotConnected);
+        }
 
-        let (status, commanded_horizontal) = if let Some(target) = inner.target {
-            let raw =
-                calculate_target_horizontal(inner.location, Utc::now(), target, &inner.tle_cache)
-                    .unwrap_or(Direction {
-                        azimuth: 0.0,
-                        elevation: -1.0,
-                    });
-            let target_horizontal = apply_offset(raw, inner.az_offset_rad, inner.el_offset_rad);
-            let horizontal_offset_squared = (target_horizontal.azimuth - inner.horizontal.azimuth)
-                .powi(2)
-                + (target_horizontal.elevation - inner.horizontal.elevation).powi(2);
-            let status = if horizontal_offset_squared > 0.2f64.to_radians().powi(2) {
-                TelescopeStatus::Slewing
-            } else {
-                TelescopeStatus::Tracking
-            };
-            (status, Some(target_horizontal))
-        } else {
-            (TelescopeStatus::Idle, None)
-        };
+        let (status, commanded_horizontal) = inner.status();
 
-        let latest_observation = if inner.current_spectra.is_empty() {
-            None
-        } else {
-            let mut latest_observation = ObservedSpectra {
-                frequencies: vec![0f64; FAKE_TELESCOPE_CHANNELS],
-                spectra: vec![0f64; FAKE_TELESCOPE_CHANNELS],
-                observation_time: Duration::from_secs(0),
-            };
-            for integration in &inner.current_spectra {
-                latest_observation.spectra = latest_observation
-                    .spectra
-                    .into_iter()
-                    .zip(integration.spectra.iter())
-                    .map(|(a, b)| a + b)
-                    .collect();
-                latest_observation.observation_time += integration.observation_time;
-            }
-            latest_observation.frequencies = inner.current_spectra[0].frequencies.clone();
-            latest_observation.spectra = latest_observation
-                .spectra
-                .into_iter()
-                .map(|value| value / inner.current_spectra.len() as f64)
-                .collect();
-            Some(latest_observation)
+        let latest_observation = inner.averaged_spectra();
+        let estimated_seconds_to_target = match (status, commanded_horizontal) {
+            (TelescopeStatus::Slewing, Some(commanded)) => seconds_to_direction(
+                inner.horizontal,
+                commanded,
+                FAKE_TELESCOPE_SLEWING_SPEED,
+                FAKE_TELESCOPE_SLEWING_SPEED,
+            ),
+            _ => None,
         };
         Ok(TelescopeInfo {
             id: inner.name.clone(),
             status,
             current_horizontal: Some(inner.horizontal),
             commanded_horizontal,
+            estimated_seconds_to_target,
+            pointing_error_degrees: commanded_horizontal
+                .map(|commanded| angular_separation(commanded, inner.horizontal).to_degrees()),
             current_target: inner.target,
             most_recent_error: inner.most_recent_error.clone(),
             measurement_in_progress: inner.receiver_configuration.integrate,
+            integration_elapsed: inner
+                .integration_started
+                .filter(|_| inner.receiver_configuration.integrate)
+                .map(|started| started.elapsed()),
             latest_observation,
             stow_position: inner.stow_position,
             service_position: inner.service_position,
@@ -333,18 +446,62 @@ impl Telescope for FakeTelescope {
             location: inner.location,
             min_elevation_rad: inner.min_elevation_rad,
             max_elevation_rad: inner.max_elevation_rad,
+            azimuth_range_rad: None,
             webcam_crop: inner.webcam_crop,
-            receiver_connected: None,
-            controller_connected: None,
+            mount_status: SubsystemStatus::healthy(),
+            receiver_status: SubsystemStatus::healthy(),
+            controller_reconnect_in_secs: None,
+            last_successful_update: inner.last_successful_update,
             wind_warning_ms: None,
+            max_integration_secs: inner.max_integration_secs,
             default_ref_freq_mhz: inner.default_ref_freq_hz / 1e6,
             default_gain_db: inner.default_gain_db,
+            rest_freq_mhz: inner.rest_freq_hz / 1e6,
+            receiver_calibration: ReceiverCalibration {
+                system_temperature_k: FAKE_TELESCOPE_TSYS_K,
+                gain_db: inner.receiver_configuration.gain_db,
+            },
+            position_switch: inner.position_switch(),
+            supports_position_switching: true,
+            drift_scan: inner.drift_scan(),
         })
     }
+    async fn error_history(&self) -> Vec<(DateTime<Utc>, TelescopeError)> {
+        // There is no controller link to fail; the only error the fake
+        // telescope produces is shown in `most_recent_error`.
+        Vec::new()
+    }
+
+    async fn subscribe_position(&self) -> Option<broadcast::Receiver<PositionUpdate>> {
+        self.inner
+            .lock()
+            .await
+            .position_updates
+            .as_ref()
+            .map(broadcast::Sender::subscribe)
+    }
+
     async fn shutdown(&self) {
-        let mut inner = self.inner.lock().await;
-        inner.alive = false;
-        debug!("Shutting down {}", inner.name);
+        {
+            let mut inner = self.inner.lock().await;
+            inner.alive = false;
+            inner.position_updates = None;
+            inner.shutdown_token.cancel();
+            inner.receiver_configuration.integrate = false;
+            for token in [
+                inner.spectrum_cancellation_token.take(),
+                inner.iq_cancellation_token.take(),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                token.cancel();
+            }
+            debug!("Shutting down {}", inner.name);
+        }
+        if let Some(task) = self.update_task.lock().await.take() {
+            let _ = task.await;
+        }
     }
 
     async fn start_iq_stream(
@@ -400,15 +557,201 @@ impl Telescope for FakeTelescope {
 }
 
 impl Inner {
-    fn update(&mut self, delta_time: Duration) -> Result<(), TelescopeError> {
-        assert!(self.alive);
+    /// Average of the spectra integrated so far, or `None` before the
+    /// first one arrives. When position switching, the averaged reference
+    /// spectrum is subtracted and nothing is returned until both positions
+    /// have been visited.
+    fn averaged_spectra(&self) -> Option<ObservedSpectra> {
+        let mut result = average(&self.current_spectra)?;
+        if self.receiver_configuration.mode == ObservationMode::PositionSwitched {
+            let off = average(&self.off_spectra)?;
+            result.spectra = result
+                .spectra
+                .iter()
+                .zip(&off.spectra)
+                .map(|(on, off)| on - off)
+                .collect();
+            result.observation_time += off.observation_time;
+        }
+        Some(result)
+    }
 
+    fn clear_spectra(&mut self) {
+        self.current_spectra.clear();
+        self.off_spectra.clear();
+        self.switch_elapsed = Duration::ZERO;
+    }
+
+    /// The switching of the running integration, if it is position switched.
+    fn position_switch(&self) -> Option<PositionSwitch> {
+        let configuration = &self.receiver_configuration;
+        if configuration.integrate && configuration.mode == ObservationMode::PositionSwitched {
+            configuration.position_switch
+        } else {
+            None
+        }
+    }
+
+    /// The drift scan of the running integration, if it is one.
+    fn drift_scan(&self) -> Option<DriftScan> {
+        let configuration = &self.receiver_configuration;
+        if configuration.integrate {
+            configuration.drift_scan
+        } else {
+            None
+        }
+    }
+
+    /// The drift scan's slices: the spectra integrated so far, averaged
+    /// over one cadence at a time. The last slice may be shorter.
+    fn scan_spectra(&self) -> Vec<ScanSpectrum> {
+        let Some(scan) = self.drift_scan() else {
+            return Vec::new();
+        };
+        let cadence = Duration::from_secs_f64(scan.cadence_secs);
+        let mut slices = Vec::new();
+        let mut start_offset = Duration::ZERO;
+        let mut slice_start = 0;
+        let mut slice_time = Duration::ZERO;
+        for (index, spectra) in self.current_spectra.iter().enumerate() {
+            slice_time += spectra.observation_time;
+            let last = index + 1 == self.current_spectra.len();
+            if slice_time < cadence && !last {
+                continue;
+            }
+            if let Some(average) = average(&self.current_spectra[slice_start..=index]) {
+                slices.push(ScanSpectrum {
+                    start_offset,
+                    spectra: average.spectra,
+                    observation_time: average.observation_time,
+                });
+            }
+            start_offset += slice_time;
+            slice_start = index + 1;
+            slice_time = Duration::ZERO;
+        }
+        slices
+    }
+
+    /// Point at `target` if it is within the elevation limits, leaving the
+    /// receiver alone.
+    fn point_at(
+        &mut self,
+        target: TelescopeTarget,
+        az_offset_rad: f64,
+        el_offset_rad: f64,
+    ) -> Result<TelescopeTarget, TelescopeError> {
+        let raw = calculate_target_horizontal(
+            self.location,
+            Utc::now(),
+            target,
+            &self.tle_cache,
+            self.apply_refraction,
+        )
+        .unwrap_or(Direction {
+            azimuth: 0.0,
+            elevation: -1.0,
+        });
+        let target_horizontal = apply_offset(raw, az_offset_rad, el_offset_rad);
+        if target_horizontal.elevation < self.min_elevation_rad
+            || target_horizontal.elevation > self.max_elevation_rad
+        {
+            info!(
+                "Refusing to set target for telescope {} to {}. Target is out of elevation range",
+                &self.name, &target
+            );
+            Err(TelescopeError::TargetOutOfElevationRange {
+                min_deg: self.min_elevation_rad.to_degrees(),
+                max_deg: self.max_elevation_rad.to_degrees(),
+            })
+        } else if let Some(err) = self.keyhole_error(target, target_horizontal) {
+            info!(
+                "Refusing to set target for telescope {} to {}. Target is in the keyhole",
+                &self.name, &target
+            );
+            Err(err)
+        } else {
+            info!(
+                "Setting target for telescope {} to {} (az={:.2}°, el={:.2}°)",
+                &self.name,
+                &target,
+                target_horizontal.azimuth.to_degrees(),
+                target_horizontal.elevation.to_degrees()
+            );
+            self.az_offset_rad = az_offset_rad;
+            self.el_offset_rad = el_offset_rad;
+            self.target = Some(target);
+            self.stow = None;
+            Ok(target)
+        }
+    }
+
+    /// `TargetInKeyhole` if `target` is a moving target currently above the
+    /// keyhole elevation. Fixed horizontal positions (stow, for one) need
+    /// no azimuth motion and are fine at any elevation.
+    fn keyhole_error(
+        &self,
+        target: TelescopeTarget,
+        target_horizontal: Direction,
+    ) -> Option<TelescopeError> {
+        let keyhole_rad = self.keyhole_elevation_rad?;
+        (!matches!(target, TelescopeTarget::Horizontal { .. })
+            && target_horizontal.elevation > keyhole_rad)
+            .then(|| TelescopeError::TargetInKeyhole {
+                max_deg: keyhole_rad.to_degrees(),
+            })
+    }
+
+    /// Status and commanded direction, judged from how far the dish is
+    /// from its target.
+    fn status(&self) -> (TelescopeStatus, Option<Direction>) {
+        if self.stow == Some(StowPhase::Stowed) {
+            return (TelescopeStatus::Stowed, None);
+        }
+        let Some(target) = self.target else {
+            let home = self
+                .stow_position
+                .unwrap_or(FAKE_TELESCOPE_PARKING_HORIZONTAL);
+            let status =
+                if angular_separation(self.horizontal, home).to_degrees() < PARKED_TOLERANCE_DEG {
+                    TelescopeStatus::Parked
+                } else {
+                    TelescopeStatus::Idle
+                };
+            return (status, None);
+        };
+        let raw = calculate_target_horizontal(
+            self.location,
+            Utc::now(),
+            target,
+            &self.tle_cache,
+            self.apply_refraction,
+        )
+        .unwrap_or(Direction {
+            azimuth: 0.0,
+            elevation: -1.0,
+        });
+        let target_horizontal = apply_offset(raw, self.az_offset_rad, self.el_offset_rad);
+        let status = if angular_separation(target_horizontal, self.horizontal) > 0.2f64.to_radians()
+        {
+            TelescopeStatus::Slewing
+        } else {
+            TelescopeStatus::Tracking
+        };
+        (status, Some(target_horizontal))
+    }
+
+    fn update(&mut self, delta_time: Duration) -> Result<(), TelescopeError> {
         if let Some(target) = self.target {
             let now = Utc::now();
             let current_horizontal = self.horizontal;
-            let Some(raw) =
-                calculate_target_horizontal(self.location, now, target, &self.tle_cache)
-            else {
+            let Some(raw) = calculate_target_horizontal(
+                self.location,
+                now,
+                target,
+                &self.tle_cache,
+                self.apply_refraction,
+            ) else {
                 // Satellite not yet in TLE cache — skip update
                 return Ok(());
             };
@@ -425,42 +768,293 @@ impl Inner {
                     min_deg: self.min_elevation_rad.to_degrees(),
                     max_deg: self.max_elevation_rad.to_degrees(),
                 });
+                self.drive.stop();
+            } else if let Some(err) = self.keyhole_error(target, target_horizontal) {
+                // Hold still until the target has passed zenith and come
+                // back down, then pick up tracking again.
+                if self.most_recent_error.is_none() {
+                    info!(
+                        "Pausing telescope since target {} is in the keyhole.",
+                        &target
+                    );
+                }
+                self.most_recent_error = Some(err);
+                self.drive.stop();
             } else {
-                let max_delta_angle = FAKE_TELESCOPE_SLEWING_SPEED * delta_time.as_secs_f64();
-                self.horizontal.azimuth += (target_horizontal.azimuth - current_horizontal.azimuth)
-                    .clamp(-max_delta_angle, max_delta_angle);
-                self.horizontal.elevation += (target_horizontal.elevation
-                    - current_horizontal.elevation)
-                    .clamp(-max_delta_angle, max_delta_angle);
+                if matches!(
+                    self.most_recent_error,
+                    Some(TelescopeError::TargetInKeyhole { .. })
+                ) {
+                    self.most_recent_error = None;
+                }
+                self.horizontal = self.drive.step(
+                    current_horizontal,
+                    target_horizontal,
+                    (self.min_elevation_rad, self.max_elevation_rad),
+                    delta_time,
+                );
             }
+        } else {
+            self.drive.stop();
+        }
+        if self.stow == Some(StowPhase::Driving) && self.status().0 == TelescopeStatus::Tracking {
+            info!("Telescope {} reached its stow position", &self.name);
+            self.target = None;
+            self.stow = Some(StowPhase::Stowed);
         }
 
         if self.receiver_configuration.integrate {
             trace!("Pushing spectum...");
-            self.current_spectra.push(create_fake_spectra(delta_time))
+            // The dish is not actually moved to the reference position:
+            // the fake sky has no structure besides the line, so it is
+            // enough to leave the line out of the off-source half.
+            let on_source = match self.position_switch() {
+                Some(switch) => {
+                    let half_cycle = switch.cycle_secs / 2.0;
+                    let phase = (self.switch_elapsed.as_secs_f64() / half_cycle) as u64;
+                    self.switch_elapsed += delta_time;
+                    phase.is_multiple_of(2)
+                }
+                None => true,
+            };
+            let line = on_source.then(|| {
+                let now = Utc::now();
+                let (ra, dec) = equatorial_from_horizontal(self.location, now, self.horizontal);
+                let (l, b) = galactic_from_equatorial(ra, dec);
+                FakeHiLine::towards(l, b, vlsr_correction(self.location, now, self.horizontal))
+            });
+            let spectra =
+                create_fake_spectra(delta_time, &self.receiver_configuration, line.as_ref());
+            if on_source {
+                self.current_spectra.push(spectra);
+            } else {
+                self.off_spectra.push(spectra);
+            }
         }
 
         Ok(())
     }
 }
 
-fn create_fake_spectra(integration_time: Duration) -> ObservedSpectra {
+/// State of the fake dish's drives between updates.
+#[derive(Debug, Default, Clone, Copy)]
+struct Drive {
+    az_velocity: f64,
+    el_velocity: f64,
+    /// Sign of the azimuth drive's last motion, to notice reversals.
+    az_direction: f64,
+    /// Backlash still to be taken up before the dish follows the motor.
+    az_slack: f64,
+}
+
+impl Drive {
+    /// Brake to a standstill, as when the telescope is stopped.
+    fn stop(&mut self) {
+        self.az_velocity = 0.0;
+        self.el_velocity = 0.0;
+    }
+
+    /// Drive the dish from `current` towards `target` for `delta_time`
+    /// and return where it ends up. The elevation drive stops at the
+    /// `elevation_limits` (rad) rather than run past them.
+    fn step(
+        &mut self,
+        current: Direction,
+        target: Direction,
+        elevation_limits: (f64, f64),
+        delta_time: Duration,
+    ) -> Direction {
+        let dt = FAKE_TELESCOPE_SERVO_STEP.as_secs_f64();
+        let steps = (delta_time.as_secs_f64() / dt).round() as u32;
+        let mut position = current;
+        for _ in 0..steps {
+            self.az_velocity =
+                servo_velocity(self.az_velocity, target.azimuth - position.azimuth, dt);
+            self.el_velocity =
+                servo_velocity(self.el_velocity, target.elevation - position.elevation, dt);
+
+            let mut az_move = self.az_velocity * dt;
+            let direction = az_move.signum();
+            if az_move != 0.0 && direction != self.az_direction {
+                self.az_direction = direction;
+                self.az_slack = FAKE_TELESCOPE_AZ_BACKLASH_RAD;
+            }
+            let taken_up = self.az_slack.min(az_move.abs());
+            self.az_slack -= taken_up;
+            az_move -= direction * taken_up;
+
+            position.azimuth += az_move;
+            // A dish already outside the limits (parked at zenith, say) may
+            // still move back inside them.
+            let (min_el, max_el) = elevation_limits;
+            let elevation = position.elevation + self.el_velocity * dt;
+            let clamped = elevation.clamp(
+                min_el.min(position.elevation),
+                max_el.max(position.elevation),
+            );
+            if clamped != elevation {
+                self.el_velocity = 0.0;
+            }
+            position.elevation = clamped;
+        }
+        position
+    }
+}
+
+/// One axis' velocity after `dt` of driving towards a target `error`
+/// away. The drive runs at full acceleration up to slewing speed and
+/// brakes as late as it can still stop in time (v² = 2ad), so it does not
+/// run past the target.
+fn servo_velocity(velocity: f64, error: f64, dt: f64) -> f64 {
+    // Judged from where this step will end, so braking starts in time.
+    let distance = (error.abs() - velocity.abs() * dt).max(0.0);
+    let wanted = error.signum()
+        * (2.0 * FAKE_TELESCOPE_MAX_ACCELERATION * distance)
+            .sqrt()
+            .min(FAKE_TELESCOPE_SERVO_GAIN * distance)
+            .min(FAKE_TELESCOPE_SLEWING_SPEED);
+    let max_change = FAKE_TELESCOPE_MAX_ACCELERATION * dt;
+    velocity + (wanted - velocity).clamp(-max_change, max_change)
+}
+
+/// Element-wise mean of `spectra`, with their observation times summed.
+fn average(spectra: &[ObservedSpectra]) -> Option<ObservedSpectra> {
+    let first = spectra.first()?;
+    let mut result = ObservedSpectra {
+        frequencies: first.frequencies.clone(),
+        spectra: vec![0f64; first.spectra.len()],
+        observation_time: Duration::from_secs(0),
+        scan: Vec::new(),
+    };
+    for integration in spectra {
+        result.spectra = result
+            .spectra
+            .into_iter()
+            .zip(integration.spectra.iter())
+            .map(|(a, b)| a + b)
+            .collect();
+        result.observation_time += integration.observation_time;
+    }
+    let n = spectra.len() as f64;
+    result.spectra = result.spectra.into_iter().map(|v| v / n).collect();
+    Some(result)
+}
+
+/// The hydrogen emission in one direction of the fake sky: a component
+/// at rest in the LSR from nearby gas, and one from the Galactic disk
+/// whose velocity follows a flat rotation curve. Both fade with latitude,
+/// the disk much faster. Frequencies are as seen by the telescope, so
+/// correcting them to the LSR gives the model's velocities back.
+#[derive(Debug, Clone, Copy)]
+struct FakeHiLine {
+    local_freq_hz: f64,
+    local_peak: f64,
+    disk_freq_hz: f64,
+    disk_peak: f64,
+}
+
+impl FakeHiLine {
+    /// The line towards galactic (`l`, `b`), in radians, for a telescope
+    /// whose LSR correction is `vlsr_correction_mps`.
+    fn towards(l: f64, b: f64, vlsr_correction_mps: f64) -> FakeHiLine {
+        let d = FAKE_GALAXY_DISK_DISTANCE_KPC;
+        let r0 = FAKE_GALAXY_R0_KPC;
+        let r = (r0 * r0 + d * d - 2.0 * r0 * d * l.cos()).sqrt();
+        let disk_vlsr_mps = FAKE_GALAXY_V0_MPS * l.sin() * (r0 / r - 1.0);
+        let sky_freq_hz = |vlsr_mps: f64| {
+            HI_REST_FREQ_HZ * (1.0 - (vlsr_mps - vlsr_correction_mps) / SPEED_OF_LIGHT_MPS)
+        };
+        FakeHiLine {
+            local_freq_hz: sky_freq_hz(0.0),
+            local_peak: FAKE_GALAXY_LOCAL_PEAK
+                * (-0.5 * (b / FAKE_GALAXY_LOCAL_WIDTH_RAD).powi(2)).exp(),
+            disk_freq_hz: sky_freq_hz(disk_vlsr_mps),
+            disk_peak: FAKE_TELESCOPE_LINE_PEAK
+                * (-0.5 * (b / FAKE_GALAXY_DISK_WIDTH_RAD).powi(2)).exp(),
+        }
+    }
+
+    fn power(&self, sky_freq_hz: f64) -> f64 {
+        let gaussian = |center_hz: f64| {
+            (-0.5 * ((sky_freq_hz - center_hz) / FAKE_TELESCOPE_LINE_WIDTH_HZ).powi(2)).exp()
+        };
+        self.local_peak * gaussian(self.local_freq_hz)
+            + self.disk_peak * gaussian(self.disk_freq_hz)
+    }
+}
+
+/// One channel of total power as seen with the LO placing `channel` at
+/// `sky_freq_hz`: the receiver's bandpass, which moves with the LO, plus
+/// the line, which stays put on the sky and is only seen on source. The
+/// cal diode adds to the system temperature, so it scales the bandpass.
+fn fake_total_power(
+    channel: usize,
+    channels: usize,
+    sky_freq_hz: f64,
+    line: Option<&FakeHiLine>,
+    cal_diode: bool,
+    rng: &mut impl Rng,
+) -> f64 {
+    let x = 2.0 * channel as f64 / (channels - 1) as f64 - 1.0;
+    let mut bandpass = FAKE_TELESCOPE_BANDPASS_LEVEL - FAKE_TELESCOPE_BANDPASS_DROOP * x * x;
+    if cal_diode {
+        bandpass *= 1.0 + FAKE_TELESCOPE_CAL_K / FAKE_TELESCOPE_TSYS_K;
+    }
+    let line = line.map_or(0.0, |line| line.power(sky_freq_hz));
+    bandpass + line + FAKE_TELESCOPE_NOISE * rng.sample::<f64, StandardNormal>(StandardNormal)
+}
+
+fn create_fake_spectra(
+    integration_time: Duration,
+    configuration: &ReceiverConfiguration,
+    line: Option<&FakeHiLine>,
+) -> ObservedSpectra {
     let mut rng = rand::rng();
+    let cal_diode = configuration.cal_diode;
 
-    let frequencies: Vec<f64> = (0..FAKE_TELESCOPE_CHANNELS)
-        .map(|channel| channel as f64 * FAKE_TELESCOPE_CHANNEL_WIDTH + FAKE_TELESCOPE_FIRST_CHANNEL)
-        .collect();
-    let spectra: Vec<f64> = vec![5f64; FAKE_TELESCOPE_CHANNELS]
-        .into_iter()
-        .map(|value| {
-            value + FAKE_TELESCOPE_NOISE * rng.sample::<f64, StandardNormal>(StandardNormal)
-        })
+    let channels = configuration.spectral_channels;
+    let channel_width = configuration.bandwidth_hz / channels as f64;
+    let first_channel = configuration.center_freq_hz - configuration.bandwidth_hz / 2.0;
+    let frequencies: Vec<f64> = (0..channels)
+        .map(|channel| channel as f64 * channel_width + first_channel)
         .collect();
+    let spectra: Vec<f64> = match configuration.mode {
+        // Half the time at the signal frequency, half with the LO moved to
+        // the reference frequency; the difference cancels the bandpass.
+        ObservationMode::FreqSwitched => {
+            let throw_hz = configuration.ref_freq_hz - configuration.center_freq_hz;
+            frequencies
+                .iter()
+                .enumerate()
+                .map(|(channel, &freq)| {
+                    fake_total_power(channel, channels, freq, line, cal_diode, &mut rng)
+                        - fake_total_power(
+                            channel,
+                            channels,
+                            freq + throw_hz,
+                            line,
+                            cal_diode,
+                            &mut rng,
+                        )
+                })
+                .collect()
+        }
+        ObservationMode::Raw
+        | ObservationMode::Interferometry
+        | ObservationMode::PositionSwitched => frequencies
+            .iter()
+            .enumerate()
+            .map(|(channel, &freq)| {
+                fake_total_power(channel, channels, freq, line, cal_diode, &mut rng)
+            })
+            .collect(),
+    };
 
     ObservedSpectra {
         frequencies,
         spectra,
         observation_time: integration_time,
+        scan: Vec::new(),
     }
 }
 
@@ -469,12 +1063,17 @@ fn calculate_target_horizontal(
     when: DateTime<Utc>,
     target: TelescopeTarget,
     tle_cache: &TleCacheHandle,
+    apply_refraction: bool,
 ) -> Option<Direction> {
-    match target {
+    let direction = match target {
         TelescopeTarget::Equatorial {
             right_ascension: ra,
             declination: dec,
-        } => Some(horizontal_from_equatorial(location, when, ra, dec)),
+            epoch,
+        } => {
+            let (ra, dec) = precess(ra, dec, epoch, J2000);
+            Some(horizontal_from_equatorial(location, when, ra, dec))
+        }
         TelescopeTarget::Galactic {
             longitude: l,
             latitude: b,
@@ -487,10 +1086,18 @@ fn calculate_target_horizontal(
             elevation: el,
         }),
         TelescopeTarget::Sun => Some(horizontal_from_sun(location, when)),
+        TelescopeTarget::Moon => Some(horizontal_from_moon(location, when)),
         TelescopeTarget::Satellite { norad_id } => {
             tle_cache.satellite_direction(norad_id, location, when)
         }
+    }?;
+    if !apply_refraction || matches!(target, TelescopeTarget::Horizontal { .. }) {
+        return Some(direction);
     }
+    Some(Direction {
+        azimuth: direction.azimuth,
+        elevation: direction.elevation + refraction_correction(direction.elevation),
+    })
 }
 
 fn apply_offset(dir: Direction, az_offset_rad: f64, el_offset_rad: f64) -> Direction {
@@ -500,3 +1107,331 @@ fn apply_offset(dir: Direction, az_offset_rad: f64, el_offset_rad: f64) -> Direc
         elevation: dir.elevation + el_offset_rad,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn shutdown_stops_the_update_task_and_integration() {
+        let telescope = test_telescope(None, None, 3600.0);
+        telescope
+            .set_receiver_configuration(ReceiverConfiguration {
+                integrate: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let integration = telescope.current_integration_token().await.unwrap();
+        // Shorter than one update interval: the loop must be woken, not
+        // left to finish its sleep.
+        tokio::time::timeout(Duration::from_millis(500), telescope.shutdown())
+            .await
+            .expect("shutdown should not wait for the update interval");
+        assert!(telescope.update_task.lock().await.is_none());
+        assert!(telescope.subscribe_position().await.is_none());
+        assert!(integration.is_cancelled());
+        assert!(telescope.stop_integration().await.is_none());
+        // Telling it to shut down again is harmless.
+        telescope.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn drift_scans_are_sliced_by_cadence() {
+        let telescope = test_telescope(None, None, 3600.0);
+        telescope
+            .set_receiver_configuration(ReceiverConfiguration {
+                integrate: true,
+                drift_scan: Some(DriftScan { cadence_secs: 2.0 }),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        {
+            let mut inner = telescope.inner.lock().await;
+            inner.current_spectra = (0..5)
+                .map(|n| ObservedSpectra {
+                    frequencies: vec![1420e6],
+                    spectra: vec![n as f64],
+                    observation_time: Duration::from_secs(1),
+                    scan: Vec::new(),
+                })
+                .collect();
+            let slices: Vec<_> = inner
+                .scan_spectra()
+                .into_iter()
+                .map(|slice| {
+                    (
+                        slice.start_offset.as_secs(),
+                        slice.spectra,
+                        slice.observation_time.as_secs(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                slices,
+                vec![(0, vec![0.5], 2), (2, vec![2.5], 2), (4, vec![4.0], 1)]
+            );
+        }
+        telescope.shutdown().await;
+    }
+
+    #[test]
+    fn cal_diode_gives_back_the_system_temperature() {
+        let mean_power = |cal_diode| {
+            let configuration = ReceiverConfiguration {
+                mode: ObservationMode::Raw,
+                spectral_channels: 8192,
+                cal_diode,
+                ..Default::default()
+            };
+            // Enough samples to bring the noise well below the cal step.
+            let samples: Vec<f64> = (0..10)
+                .flat_map(|_| {
+                    create_fake_spectra(Duration::from_secs(1), &configuration, None).spectra
+                })
+                .collect();
+            samples.iter().sum::<f64>() / samples.len() as f64
+        };
+        let off = mean_power(false);
+        let on = mean_power(true);
+        let tsys_k = FAKE_TELESCOPE_CAL_K * off / (on - off);
+        assert!(
+            (tsys_k - FAKE_TELESCOPE_TSYS_K).abs() < 30.0,
+            "T_sys from cal on/off was {tsys_k} K"
+        );
+    }
+
+    #[test]
+    fn frequency_switching_removes_the_bandpass() {
+        // Mean power in the middle tenth of the band minus that in the
+        // outer tenth, off source.
+        let droop = |mode| {
+            let configuration = ReceiverConfiguration {
+                mode,
+                spectral_channels: 8192,
+                ..Default::default()
+            };
+            let spectra: Vec<Vec<f64>> = (0..10)
+                .map(|_| create_fake_spectra(Duration::from_secs(1), &configuration, None).spectra)
+                .collect();
+            let mean = |channels: &dyn Fn(usize) -> bool| {
+                let samples: Vec<f64> = spectra
+                    .iter()
+                    .flat_map(|spectrum| {
+                        spectrum
+                            .iter()
+                            .enumerate()
+                            .filter(|(channel, _)| channels(*channel))
+                            .map(|(_, power)| *power)
+                    })
+                    .collect();
+                samples.iter().sum::<f64>() / samples.len() as f64
+            };
+            let middle = mean(&|channel| (3686..4506).contains(&channel));
+            let edges = mean(&|channel| !(410..7782).contains(&channel));
+            middle - edges
+        };
+        let raw = droop(ObservationMode::Raw);
+        assert!(raw > 1.0, "raw bandpass droop was {raw}");
+        let switched = droop(ObservationMode::FreqSwitched);
+        assert!(
+            switched.abs() < 0.2,
+            "switched bandpass droop was {switched}"
+        );
+    }
+
+    /// Positions of a dish driven from `start` to `target` for `seconds`,
+    /// one per update.
+    fn slew(
+        start: Direction,
+        target: Direction,
+        limits: (f64, f64),
+        seconds: u64,
+    ) -> Vec<Direction> {
+        let mut drive = Drive::default();
+        (0..seconds)
+            .scan(start, |position, _| {
+                *position = drive.step(*position, target, limits, TELESCOPE_UPDATE_INTERVAL);
+                Some(*position)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn long_slew_settles_without_overshoot() {
+        let start = Direction {
+            azimuth: 0.5,
+            elevation: 0.1,
+        };
+        let target = Direction {
+            azimuth: 0.5 + PI / 2.0,
+            elevation: 0.1 + PI / 2.0 - 0.2,
+        };
+        let limits = (0.0, PI / 2.0);
+        // 90° at slewing speed, plus ten seconds to speed up, brake and settle.
+        let slewing_secs = ((PI / 2.0) / FAKE_TELESCOPE_SLEWING_SPEED).ceil() as u64;
+        let positions = slew(start, target, limits, slewing_secs + 10);
+
+        // Still speeding up after the first update.
+        assert!(positions[0].azimuth - start.azimuth < FAKE_TELESCOPE_SLEWING_SPEED / 2.0);
+        let overshoot = 0.01f64.to_radians();
+        assert!(
+            positions
+                .iter()
+                .all(|p| p.azimuth < target.azimuth + overshoot)
+        );
+        assert!(
+            positions
+                .iter()
+                .all(|p| p.elevation < target.elevation + overshoot)
+        );
+        let settled = positions.last().unwrap();
+        assert!(angular_separation(*settled, target) < 0.01f64.to_radians());
+    }
+
+    #[test]
+    fn elevation_drive_stops_at_its_limits() {
+        let limits = (0.1, 1.2);
+        let start = Direction {
+            azimuth: 0.0,
+            elevation: 1.0,
+        };
+        let above = Direction {
+            azimuth: 0.0,
+            elevation: 1.5,
+        };
+        let positions = slew(start, above, limits, 20);
+        assert!(positions.iter().all(|p| p.elevation <= limits.1));
+        assert_eq!(positions.last().unwrap().elevation, limits.1);
+
+        // A dish parked above the upper limit can still come back down.
+        let parked = Direction {
+            azimuth: 0.0,
+            elevation: PI / 2.0,
+        };
+        let target = Direction {
+            azimuth: 0.0,
+            elevation: 0.5,
+        };
+        let positions = slew(parked, target, limits, 20);
+        assert!(angular_separation(*positions.last().unwrap(), target) < 0.01f64.to_radians());
+    }
+
+    #[test]
+    fn hi_line_follows_galactic_rotation() {
+        let vlsr_kms = |freq_hz: f64| {
+            -(freq_hz - HI_REST_FREQ_HZ) * SPEED_OF_LIGHT_MPS / 1e3 / HI_REST_FREQ_HZ
+        };
+        // Gas inside the solar circle approaches in the fourth quadrant and
+        // recedes in the first; outside it, the other way round.
+        let first = FakeHiLine::towards(30f64.to_radians(), 0.0, 0.0);
+        let second = FakeHiLine::towards(135f64.to_radians(), 0.0, 0.0);
+        let fourth = FakeHiLine::towards(330f64.to_radians(), 0.0, 0.0);
+        assert!(vlsr_kms(first.disk_freq_hz) > 30.0);
+        assert!(vlsr_kms(second.disk_freq_hz) < -10.0);
+        assert!(vlsr_kms(fourth.disk_freq_hz) < -30.0);
+        assert!(vlsr_kms(first.local_freq_hz).abs() < 1e-9);
+
+        // Off the plane the disk fades first.
+        let high = FakeHiLine::towards(30f64.to_radians(), 20f64.to_radians(), 0.0);
+        assert!(high.disk_peak < 0.01 * first.disk_peak);
+        assert!(high.local_peak > 0.5 * first.local_peak);
+
+        // The telescope's own motion shifts what it sees, not the LSR line.
+        let moving = FakeHiLine::towards(30f64.to_radians(), 0.0, 20e3);
+        let shift_kms = vlsr_kms(moving.disk_freq_hz) - vlsr_kms(first.disk_freq_hz);
+        assert!((shift_kms + 20.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn integration_elapsed_is_only_reported_while_integrating() {
+        let telescope = test_telescope(None, None, 3600.0);
+        let elapsed = async || telescope.get_info().await.unwrap().integration_elapsed;
+        assert_eq!(elapsed().await, None);
+        telescope
+            .set_receiver_configuration(ReceiverConfiguration {
+                integrate: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(elapsed().await.is_some());
+        telescope.stop_integration().await;
+        assert_eq!(elapsed().await, None);
+        telescope.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn moving_targets_in_the_keyhole_are_refused() {
+        let telescope = test_telescope(None, Some(85f64.to_radians()), 3600.0);
+        let location = telescope.inner.lock().await.location;
+        let (right_ascension, declination) = crate::coords::equatorial_from_horizontal(
+            location,
+            Utc::now(),
+            Direction {
+                azimuth: 0.0,
+                elevation: 88f64.to_radians(),
+            },
+        );
+        let near_zenith = TelescopeTarget::Equatorial {
+            right_ascension,
+            declination,
+            epoch: J2000,
+        };
+        assert_eq!(
+            telescope.set_target(near_zenith, 0.0, 0.0).await,
+            Err(TelescopeError::TargetInKeyhole { max_deg: 85.0 })
+        );
+        // Stowing at zenith doesn't need the azimuth axis to keep up.
+        let zenith = TelescopeTarget::Horizontal {
+            azimuth: 0.0,
+            elevation: PI / 2.0,
+        };
+        assert_eq!(telescope.set_target(zenith, 0.0, 0.0).await, Ok(zenith));
+        telescope.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn stow_stops_at_the_stow_position() {
+        let telescope = test_telescope(Some(FAKE_TELESCOPE_PARKING_HORIZONTAL), None, 3600.0);
+        telescope
+            .set_receiver_configuration(ReceiverConfiguration {
+                integrate: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        telescope.stow().await.unwrap();
+        assert!(!telescope.get_info().await.unwrap().measurement_in_progress);
+        telescope
+            .inner
+            .lock()
+            .await
+            .update(TELESCOPE_UPDATE_INTERVAL)
+            .unwrap();
+        let info = telescope.get_info().await.unwrap();
+        assert_eq!(info.status, TelescopeStatus::Stowed);
+        assert_eq!(info.current_target, None);
+        assert_eq!(info.commanded_horizontal, None);
+        telescope.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn idle_at_home_is_reported_as_parked() {
+        let telescope = test_telescope(None, None, 3600.0);
+        assert_eq!(
+            telescope.get_info().await.unwrap().status,
+            TelescopeStatus::Parked
+        );
+        telescope.inner.lock().await.horizontal = Direction {
+            azimuth: 1.0,
+            elevation: 0.5,
+        };
+        assert_eq!(
+            telescope.get_info().await.unwrap().status,
+            TelescopeStatus::Idle
+        );
+        telescope.shutdown().await;
+    }
+}
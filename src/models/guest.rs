@@ -1,12 +1,10 @@
-use std::sync::Arc;
-
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use rand::Rng;
-use rusqlite::{Connection, OptionalExtension};
-use tokio::sync::Mutex;
+use rusqlite::{OptionalExtension, TransactionBehavior};
 
+use crate::database::ConnectionPool;
 use crate::error::InternalError;
-use crate::models::session::Session;
+use crate::models::session::{DEFAULT_SESSION_LIFETIME_DAYS, Session, hash_session_token};
 use crate::models::user::User;
 
 /// Total wall-clock cap on a guest session. Any session that runs past this
@@ -94,7 +92,7 @@ impl GuestSession {
     /// Returns the new user, the active guest session row, and the cookie
     /// token to set in the response.
     pub async fn start(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         telescope_id: &str,
         country: Option<String>,
     ) -> Result<(User, GuestSession, Session), StartError> {
@@ -102,9 +100,11 @@ impl GuestSession {
         let now_ts = Utc::now().timestamp();
         let conflict_until_ts = now_ts + GUEST_START_PROTECT_SECS;
 
-        let mut conn_guard = connection.lock().await;
+        let mut conn_guard = connection.get().await;
+        // Immediate, so no other connection can book in between the
+        // checks and the inserts.
         let tx = conn_guard
-            .transaction()
+            .transaction_with_behavior(TransactionBehavior::Immediate)
             .map_err(|e| InternalError::new(format!("Failed to begin transaction: {e}")))?;
 
         // 1. Real booking conflict check.
@@ -157,10 +157,19 @@ impl GuestSession {
 
         // 5. Insert auth session token. Inlined here (rather than calling
         // Session::create) so the whole operation is one transaction.
+        // Guests leave through the idle timeout long before this expiry.
         let token = create_session_token();
+        let expires_at = DateTime::<Utc>::from_timestamp(now_ts, 0).unwrap_or_default()
+            + Duration::days(DEFAULT_SESSION_LIFETIME_DAYS.into());
         tx.execute(
-            "INSERT INTO session (token, user_id, created_at) VALUES (?1, ?2, ?3)",
-            (&token, user_id, now_ts),
+            "INSERT INTO session (token_hash, user_id, created_at, expires_at, last_used_at)
+             VALUES (?1, ?2, ?3, ?4, ?3)",
+            (
+                hash_session_token(&token),
+                user_id,
+                now_ts,
+                expires_at.timestamp(),
+            ),
         )
         .map_err(|e| InternalError::new(format!("Failed to insert session: {e}")))?;
 
@@ -174,11 +183,13 @@ impl GuestSession {
             is_admin: false,
             timezone: None,
             language: None,
+            display_name: None,
         };
         let started_at = DateTime::<Utc>::from_timestamp(now_ts, 0).unwrap_or_default();
         let session = Session {
             token: token.clone(),
             user: user.clone(),
+            expires_at,
         };
         Ok((
             user,
@@ -200,10 +211,10 @@ impl GuestSession {
     /// guest or has no active session — callers can fire this from any
     /// telescope-command handler without a prior provider check.
     pub async fn touch_activity(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
     ) -> Result<(), InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.execute(
             "UPDATE guest_session SET last_activity_at = ?1
              WHERE user_id = ?2 AND ended_at IS NULL",
@@ -219,11 +230,11 @@ impl GuestSession {
     /// means the next request from that browser cleanly reverts to
     /// anonymous instead of staying "logged in" as the dead guest user.
     pub async fn end(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         id: i64,
         reason: EndReason,
     ) -> Result<(), InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let updated = conn
             .execute(
                 "UPDATE guest_session SET ended_at = ?1, end_reason = ?2
@@ -251,9 +262,9 @@ impl GuestSession {
     /// Used by the guest_monitor task to scan for idle / ceiling / preempted
     /// sessions on each tick.
     pub async fn fetch_all_active(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
     ) -> Result<Vec<GuestSession>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let mut stmt = conn
             .prepare(
                 "SELECT id, user_id, telescope_id, started_at, ended_at, last_activity_at,
@@ -269,10 +280,10 @@ impl GuestSession {
     }
 
     pub async fn fetch_active_for_telescope(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         telescope_id: &str,
     ) -> Result<Option<GuestSession>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.query_row(
             "SELECT id, user_id, telescope_id, started_at, ended_at, last_activity_at,
                     end_reason, country
@@ -286,10 +297,10 @@ impl GuestSession {
     }
 
     pub async fn fetch_active_for_user(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
     ) -> Result<Option<GuestSession>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.query_row(
             "SELECT id, user_id, telescope_id, started_at, ended_at, last_activity_at,
                     end_reason, country
@@ -303,11 +314,11 @@ impl GuestSession {
     }
 
     pub async fn fetch_in_range(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         from: DateTime<Utc>,
         to: DateTime<Utc>,
     ) -> Result<Vec<GuestSession>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let mut stmt = conn
             .prepare(
                 "SELECT id, user_id, telescope_id, started_at, ended_at, last_activity_at,
@@ -328,7 +339,7 @@ impl GuestSession {
 /// caller is a guest. Best-effort — logs but does not propagate errors,
 /// since failing to refresh the idle timestamp is not worth blocking a
 /// telescope command on.
-pub async fn touch_if_guest(connection: Arc<Mutex<Connection>>, user: &User) {
+pub async fn touch_if_guest(connection: ConnectionPool, user: &User) {
     if user.provider != "guest" {
         return;
     }
@@ -341,14 +352,14 @@ pub async fn touch_if_guest(connection: Arc<Mutex<Connection>>, user: &User) {
 /// Mirrors the role of `booking_is_active` for the booking table; callers in
 /// observe handlers should accept either as authorisation.
 pub async fn guest_is_active(
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
     user: &User,
     telescope_id: &str,
 ) -> Result<bool, InternalError> {
     if user.provider != "guest" {
         return Ok(false);
     }
-    let conn = connection.lock().await;
+    let conn = connection.get().await;
     let count: i64 = conn
         .query_row(
             "SELECT COUNT(*) FROM guest_session
@@ -388,29 +399,12 @@ fn create_session_token() -> String {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::database::{SqliteDatabaseError, apply_migrations};
+    use crate::database::{create_test_connection, create_test_user};
     use chrono::Duration;
 
-    fn create_connection() -> Result<Arc<Mutex<Connection>>, SqliteDatabaseError> {
-        let mut connection = Connection::open_in_memory()?;
-        apply_migrations(&mut connection)?;
-        Ok(Arc::new(Mutex::new(connection)))
-    }
-
-    async fn create_real_user(conn: &Arc<Mutex<Connection>>) -> User {
-        User::create_from_external(
-            conn.clone(),
-            "real".to_string(),
-            "google".to_string(),
-            "ext-1",
-        )
-        .await
-        .unwrap()
-    }
-
     #[tokio::test]
     async fn start_succeeds_on_free_telescope() {
-        let conn = create_connection().unwrap();
+        let conn = create_test_connection();
         let (user, gs, _session) = GuestSession::start(conn.clone(), "vale", None)
             .await
             .unwrap();
@@ -422,8 +416,8 @@ mod test {
 
     #[tokio::test]
     async fn start_refuses_when_real_booking_active() {
-        let conn = create_connection().unwrap();
-        let real = create_real_user(&conn).await;
+        let conn = create_test_connection();
+        let real = create_test_user(&conn, "real").await;
         let now = Utc::now();
         crate::models::booking::Booking::create(
             conn.clone(),
@@ -445,8 +439,8 @@ mod test {
 
     #[tokio::test]
     async fn start_refuses_when_real_booking_starts_within_protect_window() {
-        let conn = create_connection().unwrap();
-        let real = create_real_user(&conn).await;
+        let conn = create_test_connection();
+        let real = create_test_user(&conn, "real").await;
         let now = Utc::now();
         // Booking starts in 2 minutes — inside the 5-min protect window.
         crate::models::booking::Booking::create(
@@ -469,8 +463,8 @@ mod test {
 
     #[tokio::test]
     async fn start_allows_when_real_booking_far_in_future() {
-        let conn = create_connection().unwrap();
-        let real = create_real_user(&conn).await;
+        let conn = create_test_connection();
+        let real = create_test_user(&conn, "real").await;
         let now = Utc::now();
         // Booking starts in 30 minutes — well outside the protect window.
         crate::models::booking::Booking::create(
@@ -491,7 +485,7 @@ mod test {
 
     #[tokio::test]
     async fn start_refuses_second_concurrent_guest() {
-        let conn = create_connection().unwrap();
+        let conn = create_test_connection();
         let _first = GuestSession::start(conn.clone(), "vale", None)
             .await
             .unwrap();
@@ -503,7 +497,7 @@ mod test {
 
     #[tokio::test]
     async fn start_allows_guest_on_different_telescope() {
-        let conn = create_connection().unwrap();
+        let conn = create_test_connection();
         let _first = GuestSession::start(conn.clone(), "vale", None)
             .await
             .unwrap();
@@ -513,14 +507,14 @@ mod test {
 
     #[tokio::test]
     async fn touch_activity_updates_timestamp() {
-        let conn = create_connection().unwrap();
+        let conn = create_test_connection();
         let (user, gs, _) = GuestSession::start(conn.clone(), "vale", None)
             .await
             .unwrap();
 
         // Backdate last_activity_at to simulate idle.
         let stale = Utc::now().timestamp() - 1000;
-        conn.lock()
+        conn.get()
             .await
             .execute(
                 "UPDATE guest_session SET last_activity_at = ?1 WHERE id = ?2",
@@ -544,7 +538,7 @@ mod test {
 
     #[tokio::test]
     async fn end_marks_session_inactive_with_reason() {
-        let conn = create_connection().unwrap();
+        let conn = create_test_connection();
         let (_user, gs, _) = GuestSession::start(conn.clone(), "vale", None)
             .await
             .unwrap();
@@ -572,7 +566,7 @@ mod test {
 
     #[tokio::test]
     async fn end_is_idempotent_first_reason_wins() {
-        let conn = create_connection().unwrap();
+        let conn = create_test_connection();
         let (_user, gs, _) = GuestSession::start(conn.clone(), "vale", None)
             .await
             .unwrap();
@@ -596,7 +590,7 @@ mod test {
 
     #[tokio::test]
     async fn guest_is_active_only_for_matching_user_and_telescope() {
-        let conn = create_connection().unwrap();
+        let conn = create_test_connection();
         let (user, _, _) = GuestSession::start(conn.clone(), "vale", None)
             .await
             .unwrap();
@@ -604,7 +598,7 @@ mod test {
         assert!(!guest_is_active(conn.clone(), &user, "brage").await.unwrap());
 
         // Non-guest user always returns false even on the right telescope.
-        let real = create_real_user(&conn).await;
+        let real = create_test_user(&conn, "real").await;
         assert!(!guest_is_active(conn.clone(), &real, "vale").await.unwrap());
     }
 }
@@ -1,10 +1,8 @@
-use std::sync::Arc;
-
 use chrono::{DateTime, Utc};
-use rusqlite::{Connection, OptionalExtension};
+use rusqlite::OptionalExtension;
 use serde::Serialize;
-use tokio::sync::Mutex;
 
+use crate::database::ConnectionPool;
 use crate::error::InternalError;
 use crate::models::user::User;
 
@@ -30,7 +28,7 @@ pub struct InterferometrySession {
 impl InterferometrySession {
     #[allow(clippy::too_many_arguments)]
     pub async fn create(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user: &User,
         telescope_a: String,
         telescope_b: String,
@@ -40,7 +38,7 @@ impl InterferometrySession {
         center_freq_hz: f64,
         bandwidth_hz: f64,
     ) -> Result<i64, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let now = Utc::now().timestamp();
         conn.execute(
             "INSERT INTO interferometry_session
@@ -63,11 +61,8 @@ impl InterferometrySession {
         Ok(conn.last_insert_rowid())
     }
 
-    pub async fn finalize(
-        connection: Arc<Mutex<Connection>>,
-        id: i64,
-    ) -> Result<(), InternalError> {
-        let conn = connection.lock().await;
+    pub async fn finalize(connection: ConnectionPool, id: i64) -> Result<(), InternalError> {
+        let conn = connection.get().await;
         let now = Utc::now().timestamp();
         conn.execute(
             "UPDATE interferometry_session SET end_time = ?1 WHERE id = ?2",
@@ -78,10 +73,10 @@ impl InterferometrySession {
     }
 
     pub async fn fetch_for_user(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
     ) -> Result<Vec<Self>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let mut stmt = conn
             .prepare(
                 "SELECT id, user_id, start_time, end_time, telescope_a, telescope_b,
@@ -98,7 +93,7 @@ impl InterferometrySession {
     }
 
     pub async fn fetch_one(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         id: i64,
         user_id_filter: Option<i64>,
     ) -> Result<Option<Self>, InternalError> {
@@ -110,7 +105,7 @@ impl InterferometrySession {
             "SELECT id, user_id, start_time, end_time, telescope_a, telescope_b,
                     coordinate_system, target_x, target_y, center_freq_hz, bandwidth_hz
              FROM interferometry_session WHERE id = ?1 AND user_id = ?2";
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let result = match user_id_filter {
             Some(uid) => conn.prepare(SELECT_BY_ID_AND_USER).and_then(|mut stmt| {
                 stmt.query_row(rusqlite::params![id, uid], map_session_row)
@@ -125,13 +120,13 @@ impl InterferometrySession {
     }
 
     pub async fn delete(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         id: i64,
         user: &User,
     ) -> Result<bool, InternalError> {
         // Child `interferometry_visibility` rows are removed by ON DELETE CASCADE
         // (enforced globally by `PRAGMA foreign_keys = ON` in database.rs).
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let rows = if user.is_admin {
             conn.execute(
                 "DELETE FROM interferometry_session WHERE id = ?1",
@@ -148,10 +143,10 @@ impl InterferometrySession {
     }
 
     pub async fn count_for_user(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
     ) -> Result<i64, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.query_row(
             "SELECT COUNT(*) FROM interferometry_session WHERE user_id = ?1",
             [user_id],
@@ -242,7 +237,7 @@ pub struct InterferometryVisibility {
 impl InterferometryVisibility {
     #[allow(clippy::too_many_arguments)]
     pub async fn insert(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         session_id: i64,
         time: DateTime<Utc>,
         mean_amplitude: f64,
@@ -252,7 +247,7 @@ impl InterferometryVisibility {
         phases_json: String,
         frequencies_json: String,
     ) -> Result<(), InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.execute(
             "INSERT INTO interferometry_visibility
              (session_id, time, mean_amplitude, mean_phase_deg, delay_ns,
@@ -274,12 +269,12 @@ impl InterferometryVisibility {
     }
 
     pub async fn fetch_for_session(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         session_id: i64,
         after_id: i64,
         limit: i64,
     ) -> Result<Vec<Self>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let mut stmt = conn
             .prepare(
                 "SELECT id, session_id, time, mean_amplitude, mean_phase_deg, delay_ns,
@@ -309,10 +304,10 @@ impl InterferometryVisibility {
     }
 
     pub async fn count_for_session(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         session_id: i64,
     ) -> Result<i64, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.query_row(
             "SELECT COUNT(*) FROM interferometry_visibility WHERE session_id = ?1",
             [session_id],
@@ -1,15 +1,12 @@
 use std::collections::HashSet;
-use std::sync::Arc;
-
-use rusqlite::Connection;
-use tokio::sync::Mutex;
 
+use crate::database::ConnectionPool;
 use crate::error::InternalError;
 
 pub async fn fetch_maintenance_set(
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
 ) -> Result<HashSet<String>, InternalError> {
-    let conn = connection.lock().await;
+    let conn = connection.get().await;
     let mut stmt = conn
         .prepare("SELECT telescope_id FROM telescope_maintenance")
         .map_err(|err| InternalError::new(format!("Failed to prepare statement: {err}")))?;
@@ -24,11 +21,11 @@ pub async fn fetch_maintenance_set(
 }
 
 pub async fn set_maintenance(
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
     telescope_id: &str,
     in_maintenance: bool,
 ) -> Result<(), InternalError> {
-    let conn = connection.lock().await;
+    let conn = connection.get().await;
     if in_maintenance {
         conn.execute(
             "INSERT OR IGNORE INTO telescope_maintenance (telescope_id) VALUES (?1)",
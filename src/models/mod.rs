@@ -1,12 +1,18 @@
+pub mod audit;
 pub mod booking;
 pub mod fake_telescope;
 pub mod guest;
 pub mod interferometry;
 pub mod maintenance;
 pub mod observation;
+pub mod observation_queue;
 pub mod salsa_telescope;
 pub mod session;
+pub mod spectrum;
 pub mod support_announcement;
 pub mod telescope;
+pub mod telescope_position;
 pub mod telescope_types;
 pub mod user;
+pub mod user_identity;
+pub mod user_token;
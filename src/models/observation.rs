@@ -1,12 +1,12 @@
-use std::sync::Arc;
-
 use chrono::{DateTime, Utc};
-use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
 
-use crate::coords::{ONSALA_LOCATION, horizontal_from_equatorial, horizontal_from_galactic};
+use crate::coords::{Location, horizontal_from_equatorial, horizontal_from_galactic};
+use crate::database::ConnectionPool;
 use crate::error::InternalError;
+use crate::models::telescope_types::{
+    DriftScan, PositionSwitch, ReceiverCalibration, ScanSpectrum,
+};
 use crate::models::user::User;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -24,12 +24,43 @@ pub struct Observation {
     pub vlsr_correction_mps: Option<f64>,
     pub az_offset_deg: Option<f64>,
     pub el_offset_deg: Option<f64>,
+    /// Receiver bandwidth and channel count the spectrum was taken with;
+    /// None for observations stored before these were recorded.
+    pub bandwidth_hz: Option<f64>,
+    pub spectral_channels: Option<u32>,
+    pub system_temperature_k: Option<f64>,
+    pub receiver_gain_db: Option<f64>,
+    /// Where the telescope was, in degrees. Older observations lack it.
+    pub site_longitude_deg: Option<f64>,
+    pub site_latitude_deg: Option<f64>,
+    /// Name given by the user; see [`Observation::display_label`].
+    pub label: Option<String>,
+    /// Reference offset and cycle time of a position-switched observation,
+    /// whose spectrum is on minus off; None for all other modes.
+    pub switch_az_offset_deg: Option<f64>,
+    pub switch_el_offset_deg: Option<f64>,
+    pub switch_cycle_secs: Option<f64>,
+    /// Seconds per spectrum of a drift scan, whose slices are fetched with
+    /// [`Observation::fetch_scan`]; None for all other observations.
+    pub drift_cadence_secs: Option<f64>,
+}
+
+/// One slice of a drift scan as stored.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredScanSpectrum {
+    /// From the observation's `start_time` to the start of the slice.
+    pub start_offset_secs: f64,
+    pub integration_time_secs: f64,
+    pub amplitudes_json: String,
 }
 
+/// Longest label accepted, in characters.
+pub const MAX_LABEL_LEN: usize = 100;
+
 impl Observation {
     #[allow(clippy::too_many_arguments)]
     pub async fn create(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user: &User,
         telescope_id: &str,
         start_time: DateTime<Utc>,
@@ -42,12 +73,23 @@ impl Observation {
         vlsr_correction_mps: Option<f64>,
         az_offset_deg: Option<f64>,
         el_offset_deg: Option<f64>,
-    ) -> Result<(), InternalError> {
-        let conn = connection.lock().await;
-        conn.execute(
-            "INSERT INTO observation (user_id, telescope_id, start_time, coordinate_system, target_x, target_y, integration_time_secs, frequencies_json, amplitudes_json, vlsr_correction_mps, az_offset_deg, el_offset_deg)
-                 VALUES ((?1), (?2), (?3), (?4), (?5), (?6), (?7), (?8), (?9), (?10), (?11), (?12))",
-            (
+        bandwidth_hz: Option<f64>,
+        spectral_channels: Option<u32>,
+        receiver_calibration: Option<ReceiverCalibration>,
+        location: Option<Location>,
+        label: Option<&str>,
+        position_switch: Option<PositionSwitch>,
+        drift_scan: Option<DriftScan>,
+        scan: &[ScanSpectrum],
+    ) -> Result<i64, InternalError> {
+        let mut conn = connection.get().await;
+        let tx = conn
+            .transaction()
+            .map_err(|err| InternalError::new(format!("Failed to begin transaction: {err}")))?;
+        tx.execute(
+            "INSERT INTO observation (user_id, telescope_id, start_time, coordinate_system, target_x, target_y, integration_time_secs, frequencies_json, amplitudes_json, vlsr_correction_mps, az_offset_deg, el_offset_deg, bandwidth_hz, spectral_channels, system_temperature_k, receiver_gain_db, site_longitude_deg, site_latitude_deg, label, switch_az_offset_deg, switch_el_offset_deg, switch_cycle_secs, drift_cadence_secs)
+                 VALUES ((?1), (?2), (?3), (?4), (?5), (?6), (?7), (?8), (?9), (?10), (?11), (?12), (?13), (?14), (?15), (?16), (?17), (?18), (?19), (?20), (?21), (?22), (?23))",
+            rusqlite::params![
                 &user.id,
                 telescope_id,
                 start_time.timestamp(),
@@ -60,22 +102,78 @@ impl Observation {
                 vlsr_correction_mps,
                 az_offset_deg,
                 el_offset_deg,
-            ),
+                bandwidth_hz,
+                spectral_channels,
+                receiver_calibration.map(|c| c.system_temperature_k),
+                receiver_calibration.map(|c| c.gain_db),
+                location.map(|l| l.longitude.to_degrees()),
+                location.map(|l| l.latitude.to_degrees()),
+                label,
+                position_switch.map(|p| p.az_offset_rad.to_degrees()),
+                position_switch.map(|p| p.el_offset_rad.to_degrees()),
+                position_switch.map(|p| p.cycle_secs),
+                drift_scan.map(|d| d.cadence_secs),
+            ],
         )
         .map_err(|err| InternalError::new(format!("Failed to insert observation in db: {err}")))?;
-        Ok(())
+        let id = tx.last_insert_rowid();
+        for slice in scan {
+            let amplitudes_json = serde_json::to_string(&slice.spectra)
+                .map_err(|err| InternalError::new(format!("Failed to serialize scan: {err}")))?;
+            tx.execute(
+                "INSERT INTO drift_scan_spectrum (observation_id, start_offset_secs, integration_time_secs, amplitudes_json)
+                     VALUES ((?1), (?2), (?3), (?4))",
+                rusqlite::params![
+                    id,
+                    slice.start_offset.as_secs_f64(),
+                    slice.observation_time.as_secs_f64(),
+                    amplitudes_json,
+                ],
+            )
+            .map_err(|err| InternalError::new(format!("Failed to insert scan in db: {err}")))?;
+        }
+        tx.commit()
+            .map_err(|err| InternalError::new(format!("Failed to commit observation: {err}")))?;
+        Ok(id)
+    }
+
+    /// The slices of drift scan `id`, oldest first. Empty for other
+    /// observations. Check access with `fetch_one` first.
+    pub async fn fetch_scan(
+        connection: ConnectionPool,
+        id: i64,
+    ) -> Result<Vec<StoredScanSpectrum>, InternalError> {
+        let conn = connection.get().await;
+        let mut stmt = conn
+            .prepare(
+                "SELECT start_offset_secs, integration_time_secs, amplitudes_json
+                 FROM drift_scan_spectrum
+                 WHERE observation_id = (?1)
+                 ORDER BY start_offset_secs",
+            )
+            .map_err(|err| InternalError::new(format!("Failed to prepare statement: {err}")))?;
+        stmt.query_map([id], |row| {
+            Ok(StoredScanSpectrum {
+                start_offset_secs: row.get(0)?,
+                integration_time_secs: row.get(1)?,
+                amplitudes_json: row.get(2)?,
+            })
+        })
+        .map_err(|err| InternalError::new(format!("Failed to query_map: {err}")))?
+        .map(|r| r.map_err(|err| InternalError::new(format!("Failed to map row: {err}"))))
+        .collect()
     }
 
     pub async fn fetch_for_user_page(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
         page_size: i64,
         offset: i64,
     ) -> Result<Vec<Observation>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let mut stmt = conn
             .prepare(
-                "SELECT id, user_id, telescope_id, start_time, coordinate_system, target_x, target_y, integration_time_secs, frequencies_json, amplitudes_json, vlsr_correction_mps, az_offset_deg, el_offset_deg
+                "SELECT id, user_id, telescope_id, start_time, coordinate_system, target_x, target_y, integration_time_secs, frequencies_json, amplitudes_json, vlsr_correction_mps, az_offset_deg, el_offset_deg, bandwidth_hz, spectral_channels, system_temperature_k, receiver_gain_db, site_longitude_deg, site_latitude_deg, label, switch_az_offset_deg, switch_el_offset_deg, switch_cycle_secs, drift_cadence_secs
                  FROM observation
                  WHERE user_id = (?1)
                  ORDER BY start_time DESC
@@ -98,6 +196,17 @@ impl Observation {
                     vlsr_correction_mps: row.get(10)?,
                     az_offset_deg: row.get(11)?,
                     el_offset_deg: row.get(12)?,
+                    bandwidth_hz: row.get(13)?,
+                    spectral_channels: row.get(14)?,
+                    system_temperature_k: row.get(15)?,
+                    receiver_gain_db: row.get(16)?,
+                    site_longitude_deg: row.get(17)?,
+                    site_latitude_deg: row.get(18)?,
+                    label: row.get(19)?,
+                    switch_az_offset_deg: row.get(20)?,
+                    switch_el_offset_deg: row.get(21)?,
+                    switch_cycle_secs: row.get(22)?,
+                    drift_cadence_secs: row.get(23)?,
                 })
             })
             .map_err(|err| InternalError::new(format!("Failed to query_map: {err}")))?;
@@ -115,10 +224,10 @@ impl Observation {
     }
 
     pub async fn count_for_user(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
     ) -> Result<i64, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.query_row(
             "SELECT COUNT(*) FROM observation WHERE user_id = (?1)",
             [user_id],
@@ -127,29 +236,59 @@ impl Observation {
         .map_err(|err| InternalError::new(format!("Failed to count observations: {err}")))
     }
 
+    pub async fn count_all(connection: ConnectionPool) -> Result<i64, InternalError> {
+        let conn = connection.get().await;
+        conn.query_row("SELECT COUNT(*) FROM observation", [], |row| row.get(0))
+            .map_err(|err| InternalError::new(format!("Failed to count observations: {err}")))
+    }
+
+    /// Delete one of `user`'s observations, or any observation if `user`
+    /// is an admin. Returns whether a row was deleted.
     pub async fn delete(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         id: i64,
         user: &User,
-    ) -> Result<(), InternalError> {
-        let conn = connection.lock().await;
-        conn.execute(
-            "DELETE FROM observation WHERE id = (?1) AND user_id = (?2)",
-            [&id, &user.id],
-        )
+    ) -> Result<bool, InternalError> {
+        let conn = connection.get().await;
+        let rows = if user.is_admin {
+            conn.execute("DELETE FROM observation WHERE id = (?1)", [&id])
+        } else {
+            conn.execute(
+                "DELETE FROM observation WHERE id = (?1) AND user_id = (?2)",
+                [&id, &user.id],
+            )
+        }
         .map_err(|err| InternalError::new(format!("Failed to delete observation: {err}")))?;
-        Ok(())
+        Ok(rows > 0)
+    }
+
+    /// Rename one of `user_id`'s observations; None clears the label.
+    /// Returns whether a row was updated.
+    pub async fn set_label(
+        connection: ConnectionPool,
+        id: i64,
+        user_id: i64,
+        label: Option<&str>,
+    ) -> Result<bool, InternalError> {
+        let conn = connection.get().await;
+        let rows = conn
+            .execute(
+                "UPDATE observation SET label = (?1) WHERE id = (?2) AND user_id = (?3)",
+                rusqlite::params![label, id, user_id],
+            )
+            .map_err(|err| InternalError::new(format!("Failed to update observation: {err}")))?;
+        Ok(rows > 0)
     }
 
     pub async fn fetch_one(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         id: i64,
         user_id: Option<i64>,
     ) -> Result<Option<Observation>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let mut stmt = conn
             .prepare(
-                "SELECT id, user_id, telescope_id, start_time, coordinate_system, target_x, target_y, integration_time_secs, frequencies_json, amplitudes_json, vlsr_correction_mps, az_offset_deg, el_offset_deg
+                "SELECT id, user_id, telescope_id, start_time, coordinate_system, target_x, target_y, integration_time_secs, frequencies_json, amplitudes_json, vlsr_correction_mps, az_offset_deg, el_offset_deg, bandwidth_hz, spectral_channels, system_temperature_k, receiver_gain_db, site_longitude_deg, site_latitude_deg, label, switch_az_offset_deg, switch_el_offset_deg, switch_cycle_secs, drift_cadence_secs
                  FROM observation
                  WHERE id = (?1) AND ((?2) IS NULL OR user_id = (?2))",
             )
@@ -170,6 +309,17 @@ impl Observation {
                     vlsr_correction_mps: row.get(10)?,
                     az_offset_deg: row.get(11)?,
                     el_offset_deg: row.get(12)?,
+                    bandwidth_hz: row.get(13)?,
+                    spectral_channels: row.get(14)?,
+                    system_temperature_k: row.get(15)?,
+                    receiver_gain_db: row.get(16)?,
+                    site_longitude_deg: row.get(17)?,
+                    site_latitude_deg: row.get(18)?,
+                    label: row.get(19)?,
+                    switch_az_offset_deg: row.get(20)?,
+                    switch_el_offset_deg: row.get(21)?,
+                    switch_cycle_secs: row.get(22)?,
+                    drift_cadence_secs: row.get(23)?,
                 })
             })
             .map_err(|err| InternalError::new(format!("Failed to query_map: {err}")))?;
@@ -181,20 +331,49 @@ impl Observation {
         }
     }
 
+    /// The user's label, or a description of the target for observations
+    /// without one.
+    pub fn display_label(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => format!(
+                "{} ({:.1}, {:.1})",
+                self.coordinate_system, self.target_x, self.target_y
+            ),
+        }
+    }
+
+    /// The on/off switching used, for position-switched observations.
+    pub fn position_switch(&self) -> Option<PositionSwitch> {
+        Some(PositionSwitch {
+            az_offset_rad: self.switch_az_offset_deg?.to_radians(),
+            el_offset_rad: self.switch_el_offset_deg?.to_radians(),
+            cycle_secs: self.switch_cycle_secs?,
+        })
+    }
+
+    /// The telescope's location when the observation was taken, if stored.
+    pub fn location(&self) -> Option<Location> {
+        Some(Location {
+            longitude: self.site_longitude_deg?.to_radians(),
+            latitude: self.site_latitude_deg?.to_radians(),
+        })
+    }
+
     /// Commanded azimuth/elevation in degrees at the start of the
     /// observation, including any pointing offsets. Horizontal-type
     /// targets (horizontal, sun, gnss) store az/el as the target
     /// coordinates; equatorial and galactic targets are converted for
-    /// the SALSA site at `start_time`, reconstructing the same pointing
+    /// the telescope's `location` at `start_time`, reconstructing the same pointing
     /// math the telescope used. This is the commanded direction, not a
     /// readback — a mechanically stuck telescope would still report it.
-    pub fn horizontal(&self) -> Option<(f64, f64)> {
+    pub fn horizontal(&self, location: Location) -> Option<(f64, f64)> {
         let (az_deg, el_deg) = match self.coordinate_system.as_str() {
-            "horizontal" | "sun" => (self.target_x, self.target_y),
+            "horizontal" | "sun" | "moon" => (self.target_x, self.target_y),
             s if s.starts_with("gnss") => (self.target_x, self.target_y),
             "equatorial" => {
                 let dir = horizontal_from_equatorial(
-                    ONSALA_LOCATION,
+                    location,
                     self.start_time,
                     self.target_x.to_radians(),
                     self.target_y.to_radians(),
@@ -203,7 +382,7 @@ impl Observation {
             }
             "galactic" => {
                 let dir = horizontal_from_galactic(
-                    ONSALA_LOCATION,
+                    location,
                     self.start_time,
                     self.target_x.to_radians(),
                     self.target_y.to_radians(),
@@ -222,7 +401,10 @@ impl Observation {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::coords::ONSALA_LOCATION;
+    use crate::database::{create_test_connection, create_test_user};
     use chrono::TimeZone;
+    use std::time::Duration;
 
     fn observation(coordinate_system: &str, target_x: f64, target_y: f64) -> Observation {
         Observation {
@@ -239,6 +421,17 @@ mod tests {
             vlsr_correction_mps: None,
             az_offset_deg: None,
             el_offset_deg: None,
+            bandwidth_hz: None,
+            spectral_channels: None,
+            system_temperature_k: None,
+            receiver_gain_db: None,
+            site_longitude_deg: None,
+            site_latitude_deg: None,
+            label: None,
+            switch_az_offset_deg: None,
+            switch_el_offset_deg: None,
+            switch_cycle_secs: None,
+            drift_cadence_secs: None,
         }
     }
 
@@ -247,7 +440,7 @@ mod tests {
         let obs = observation("galactic", 140.0, 0.0);
         let dir =
             horizontal_from_galactic(ONSALA_LOCATION, obs.start_time, 140.0_f64.to_radians(), 0.0);
-        let (az, el) = obs.horizontal().unwrap();
+        let (az, el) = obs.horizontal(ONSALA_LOCATION).unwrap();
         assert!((az - dir.azimuth.to_degrees()).abs() < 1e-9);
         assert!((el - dir.elevation.to_degrees()).abs() < 1e-9);
     }
@@ -257,23 +450,201 @@ mod tests {
         let mut obs = observation("sun", 180.0, 45.0);
         obs.az_offset_deg = Some(1.5);
         obs.el_offset_deg = Some(-0.5);
-        assert_eq!(obs.horizontal(), Some((181.5, 44.5)));
+        assert_eq!(obs.horizontal(ONSALA_LOCATION), Some((181.5, 44.5)));
     }
 
     #[test]
     fn azimuth_wraps_around_north() {
         let mut obs = observation("horizontal", 359.0, 30.0);
         obs.az_offset_deg = Some(2.0);
-        let (az, _) = obs.horizontal().unwrap();
+        let (az, _) = obs.horizontal(ONSALA_LOCATION).unwrap();
         assert!((az - 1.0).abs() < 1e-9);
     }
 
     #[test]
     fn gnss_and_unknown_systems() {
         assert_eq!(
-            observation("gnss:GPS BIII-6", 120.0, 60.0).horizontal(),
+            observation("gnss:GPS BIII-6", 120.0, 60.0).horizontal(ONSALA_LOCATION),
             Some((120.0, 60.0))
         );
-        assert_eq!(observation("stow", 0.0, 0.0).horizontal(), None);
+        assert_eq!(
+            observation("stow", 0.0, 0.0).horizontal(ONSALA_LOCATION),
+            None
+        );
+    }
+
+    async fn store(
+        connection: &ConnectionPool,
+        user: &User,
+        position_switch: Option<PositionSwitch>,
+        drift_scan: Option<DriftScan>,
+        scan: &[ScanSpectrum],
+    ) -> i64 {
+        Observation::create(
+            connection.clone(),
+            user,
+            "test",
+            Utc::now(),
+            "galactic",
+            140.0,
+            0.0,
+            60.0,
+            "[]",
+            "[]",
+            None,
+            None,
+            None,
+            Some(2.5e6),
+            Some(512),
+            None,
+            None,
+            None,
+            position_switch,
+            drift_scan,
+            scan,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn delete_is_scoped_to_owner_unless_admin() {
+        let connection = create_test_connection();
+        let owner = create_test_user(&connection, "owner").await;
+        let mut other = create_test_user(&connection, "other").await;
+        let id = store(&connection, &owner, None, None, &[]).await;
+
+        assert!(
+            !Observation::delete(connection.clone(), id, &other)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            Observation::count_for_user(connection.clone(), owner.id)
+                .await
+                .unwrap(),
+            1
+        );
+
+        other.is_admin = true;
+        assert!(
+            Observation::delete(connection.clone(), id, &other)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !Observation::delete(connection.clone(), id, &owner)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            Observation::count_for_user(connection.clone(), owner.id)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn label_is_set_by_owner_only_and_falls_back_to_target() {
+        let connection = create_test_connection();
+        let owner = create_test_user(&connection, "owner").await;
+        let other = create_test_user(&connection, "other").await;
+        let id = store(&connection, &owner, None, None, &[]).await;
+        let fetch = || async {
+            Observation::fetch_one(connection.clone(), id, None)
+                .await
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(fetch().await.display_label(), "galactic (140.0, 0.0)");
+
+        assert!(
+            !Observation::set_label(connection.clone(), id, other.id, Some("mine"))
+                .await
+                .unwrap()
+        );
+        assert!(
+            Observation::set_label(connection.clone(), id, owner.id, Some("Cas A, run 2"))
+                .await
+                .unwrap()
+        );
+        assert_eq!(fetch().await.display_label(), "Cas A, run 2");
+
+        Observation::set_label(connection.clone(), id, owner.id, None)
+            .await
+            .unwrap();
+        assert_eq!(fetch().await.display_label(), "galactic (140.0, 0.0)");
+    }
+
+    #[tokio::test]
+    async fn position_switch_is_stored_with_the_observation() {
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "owner").await;
+        let switch = PositionSwitch {
+            az_offset_rad: 5f64.to_radians(),
+            el_offset_rad: -2f64.to_radians(),
+            cycle_secs: 20.0,
+        };
+        let switched = store(&connection, &user, Some(switch), None, &[]).await;
+        let plain = store(&connection, &user, None, None, &[]).await;
+        let fetch = |id| {
+            let connection = connection.clone();
+            async move {
+                Observation::fetch_one(connection, id, None)
+                    .await
+                    .unwrap()
+                    .unwrap()
+            }
+        };
+
+        let stored = fetch(switched).await;
+        assert!((stored.switch_az_offset_deg.unwrap() - 5.0).abs() < 1e-9);
+        assert!((stored.switch_el_offset_deg.unwrap() + 2.0).abs() < 1e-9);
+        assert_eq!(stored.position_switch().unwrap().cycle_secs, 20.0);
+        assert!(fetch(plain).await.position_switch().is_none());
+    }
+
+    #[tokio::test]
+    async fn drift_scan_slices_are_stored_in_order() {
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "owner").await;
+        let slice = |start, spectra| ScanSpectrum {
+            start_offset: Duration::from_secs(start),
+            spectra,
+            observation_time: Duration::from_secs(10),
+        };
+        let scan = [slice(0, vec![1.0, 2.0]), slice(10, vec![3.0, 4.0])];
+        let drift = store(
+            &connection,
+            &user,
+            None,
+            Some(DriftScan { cadence_secs: 10.0 }),
+            &scan,
+        )
+        .await;
+        let plain = store(&connection, &user, None, None, &[]).await;
+
+        let stored = Observation::fetch_one(connection.clone(), drift, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.drift_cadence_secs, Some(10.0));
+        let slices = Observation::fetch_scan(connection.clone(), drift)
+            .await
+            .unwrap();
+        assert_eq!(
+            slices
+                .iter()
+                .map(|s| (s.start_offset_secs, s.amplitudes_json.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(0.0, "[1.0,2.0]"), (10.0, "[3.0,4.0]")]
+        );
+        assert!(
+            Observation::fetch_scan(connection, plain)
+                .await
+                .unwrap()
+                .is_empty()
+        );
     }
 }
@@ -0,0 +1,309 @@
+//! Targets lined up to be observed unattended. Each item belongs to a user
+//! and a telescope, and `queue_runner` works through a user's waiting items
+//! in order while they have an active booking on it.
+
+use chrono::{DateTime, Utc};
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+use crate::database::ConnectionPool;
+use crate::error::InternalError;
+use crate::models::telescope_types::TelescopeTarget;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueStatus {
+    /// Waiting for its turn.
+    Queued,
+    Running,
+    /// Observed, and the observation saved.
+    Done,
+    /// Gave up; `QueueItem::error` says why.
+    Failed,
+    Cancelled,
+}
+
+impl QueueStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            QueueStatus::Queued => "queued",
+            QueueStatus::Running => "running",
+            QueueStatus::Done => "done",
+            QueueStatus::Failed => "failed",
+            QueueStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(name: &str) -> Option<QueueStatus> {
+        match name {
+            "queued" => Some(QueueStatus::Queued),
+            "running" => Some(QueueStatus::Running),
+            "done" => Some(QueueStatus::Done),
+            "failed" => Some(QueueStatus::Failed),
+            "cancelled" => Some(QueueStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// What to observe: a fixed-duration integration on `target`, with the
+/// observe page's default receiver settings.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct QueuedObservation {
+    pub target: TelescopeTarget,
+    pub az_offset_deg: f64,
+    pub el_offset_deg: f64,
+    pub integration_time_secs: f64,
+    pub label: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct QueueItem {
+    pub id: i64,
+    pub telescope_id: String,
+    #[serde(flatten)]
+    pub observation: QueuedObservation,
+    pub status: QueueStatus,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+const SELECT_ITEMS: &str = "SELECT id, telescope_id, target, az_offset_deg, el_offset_deg, \
+     integration_time_secs, label, status, error, created_at FROM observation_queue";
+
+fn item_from_row(row: &rusqlite::Row) -> rusqlite::Result<QueueItem> {
+    let target: String = row.get(2)?;
+    let status: String = row.get(7)?;
+    Ok(QueueItem {
+        id: row.get(0)?,
+        telescope_id: row.get(1)?,
+        observation: QueuedObservation {
+            target: serde_json::from_str(&target).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    2,
+                    rusqlite::types::Type::Text,
+                    err.into(),
+                )
+            })?,
+            az_offset_deg: row.get(3)?,
+            el_offset_deg: row.get(4)?,
+            integration_time_secs: row.get(5)?,
+            label: row.get(6)?,
+        },
+        status: QueueStatus::parse(&status).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(
+                7,
+                rusqlite::types::Type::Text,
+                format!("Unknown queue status {status}").into(),
+            )
+        })?,
+        error: row.get(8)?,
+        created_at: DateTime::from_timestamp(row.get(9)?, 0).unwrap_or_default(),
+    })
+}
+
+/// Add `observation` to the end of `user_id`'s queue on `telescope_id`.
+pub async fn enqueue(
+    connection: ConnectionPool,
+    user_id: i64,
+    telescope_id: &str,
+    observation: &QueuedObservation,
+) -> Result<i64, InternalError> {
+    let target = serde_json::to_string(&observation.target)
+        .map_err(|e| InternalError::new(format!("Failed to serialize target: {e}")))?;
+    let conn = connection.get().await;
+    conn.execute(
+        "INSERT INTO observation_queue (user_id, telescope_id, target, az_offset_deg, \
+         el_offset_deg, integration_time_secs, label, status, created_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        (
+            user_id,
+            telescope_id,
+            &target,
+            observation.az_offset_deg,
+            observation.el_offset_deg,
+            observation.integration_time_secs,
+            &observation.label,
+            QueueStatus::Queued.as_str(),
+            Utc::now().timestamp(),
+        ),
+    )
+    .map_err(|e| InternalError::new(format!("Failed to enqueue observation: {e}")))?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// All of `user_id`'s items on `telescope_id`, in queue order.
+pub async fn fetch_for_user(
+    connection: ConnectionPool,
+    user_id: i64,
+    telescope_id: &str,
+) -> Result<Vec<QueueItem>, InternalError> {
+    let conn = connection.get().await;
+    let mut stmt = conn
+        .prepare(&format!(
+            "{SELECT_ITEMS} WHERE user_id = ?1 AND telescope_id = ?2 ORDER BY id"
+        ))
+        .map_err(|e| InternalError::new(format!("Failed to prepare statement: {e}")))?;
+    stmt.query_map((user_id, telescope_id), item_from_row)
+        .map_err(|e| InternalError::new(format!("Failed to query_map: {e}")))?
+        .map(|r| r.map_err(|err| InternalError::new(format!("Failed to map row: {err}"))))
+        .collect()
+}
+
+/// The first of `user_id`'s items on `telescope_id` still waiting.
+pub async fn next_queued(
+    connection: ConnectionPool,
+    user_id: i64,
+    telescope_id: &str,
+) -> Result<Option<QueueItem>, InternalError> {
+    let conn = connection.get().await;
+    conn.query_row(
+        &format!(
+            "{SELECT_ITEMS} WHERE user_id = ?1 AND telescope_id = ?2 AND status = ?3 \
+             ORDER BY id LIMIT 1"
+        ),
+        (user_id, telescope_id, QueueStatus::Queued.as_str()),
+        item_from_row,
+    )
+    .optional()
+    .map_err(|e| InternalError::new(format!("Failed to fetch next queue item: {e}")))
+}
+
+pub async fn set_status(
+    connection: ConnectionPool,
+    id: i64,
+    status: QueueStatus,
+    error: Option<&str>,
+) -> Result<(), InternalError> {
+    let conn = connection.get().await;
+    conn.execute(
+        "UPDATE observation_queue SET status = ?1, error = ?2 WHERE id = ?3",
+        (status.as_str(), error, id),
+    )
+    .map_err(|e| InternalError::new(format!("Failed to update queue item: {e}")))?;
+    Ok(())
+}
+
+/// Cancel `user_id`'s item `id` on `telescope_id` if it is still waiting.
+/// Returns whether it was.
+pub async fn cancel(
+    connection: ConnectionPool,
+    user_id: i64,
+    telescope_id: &str,
+    id: i64,
+) -> Result<bool, InternalError> {
+    let conn = connection.get().await;
+    let changed = conn
+        .execute(
+            "UPDATE observation_queue SET status = ?1 \
+             WHERE id = ?2 AND user_id = ?3 AND telescope_id = ?4 AND status = ?5",
+            (
+                QueueStatus::Cancelled.as_str(),
+                id,
+                user_id,
+                telescope_id,
+                QueueStatus::Queued.as_str(),
+            ),
+        )
+        .map_err(|e| InternalError::new(format!("Failed to cancel queue item: {e}")))?;
+    Ok(changed > 0)
+}
+
+/// Mark items on `telescope_id` still running as failed. For when no
+/// runner is working on the telescope, so they were left behind by a
+/// restart or a crashed runner.
+pub async fn fail_abandoned(
+    connection: ConnectionPool,
+    telescope_id: &str,
+) -> Result<(), InternalError> {
+    let conn = connection.get().await;
+    conn.execute(
+        "UPDATE observation_queue SET status = ?1, error = ?2 \
+         WHERE telescope_id = ?3 AND status = ?4",
+        (
+            QueueStatus::Failed.as_str(),
+            "Interrupted",
+            telescope_id,
+            QueueStatus::Running.as_str(),
+        ),
+    )
+    .map_err(|e| InternalError::new(format!("Failed to fail abandoned queue items: {e}")))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::{create_test_connection, create_test_user};
+
+    #[tokio::test]
+    async fn items_run_in_order_and_only_waiting_ones_cancel() {
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "test").await;
+        let observation = |label: &str| QueuedObservation {
+            target: TelescopeTarget::Sun,
+            az_offset_deg: 0.0,
+            el_offset_deg: 1.0,
+            integration_time_secs: 10.0,
+            label: label.to_string(),
+        };
+
+        let first = enqueue(connection.clone(), user.id, "fake1", &observation("first"))
+            .await
+            .unwrap();
+        let second = enqueue(connection.clone(), user.id, "fake1", &observation("second"))
+            .await
+            .unwrap();
+        let third = enqueue(connection.clone(), user.id, "fake1", &observation("third"))
+            .await
+            .unwrap();
+
+        let next = next_queued(connection.clone(), user.id, "fake1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.id, first);
+        assert_eq!(next.observation, observation("first"));
+        assert!(
+            next_queued(connection.clone(), user.id, "fake2")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        set_status(connection.clone(), first, QueueStatus::Running, None)
+            .await
+            .unwrap();
+        assert!(
+            !cancel(connection.clone(), user.id, "fake1", first)
+                .await
+                .unwrap()
+        );
+        assert!(
+            cancel(connection.clone(), user.id, "fake1", second)
+                .await
+                .unwrap()
+        );
+        let next = next_queued(connection.clone(), user.id, "fake1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.id, third);
+
+        fail_abandoned(connection.clone(), "fake1").await.unwrap();
+        let statuses: Vec<_> = fetch_for_user(connection.clone(), user.id, "fake1")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| (item.status, item.error))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (QueueStatus::Failed, Some("Interrupted".to_string())),
+                (QueueStatus::Cancelled, None),
+                (QueueStatus::Queued, None),
+            ]
+        );
+    }
+}
@@ -1,20 +1,24 @@
-use crate::coords::{Direction, Location};
+use crate::coords::{Direction, Location, angular_separation};
+use crate::models::spectrum::median;
 use crate::models::telescope::Telescope;
 use crate::models::telescope_types::{
     CalibrationResult, IQ_BLOCK_SIZE, IqBlock, Measurement, ObservationMode, ObservedSpectra,
-    ReceiverConfiguration, ReceiverError, TelescopeError, TelescopeInfo, TelescopeTarget,
+    PARKED_TOLERANCE_DEG, PositionUpdate, ReceiverCalibration, ReceiverConfiguration,
+    ReceiverError, ScanSpectrum, SubsystemStatus, TelescopeError, TelescopeInfo, TelescopeStatus,
+    TelescopeTarget,
 };
+use crate::telescope_controller::ControllerSettings;
 use crate::telescope_tracker::TelescopeTracker;
 use crate::tle_cache::TleCacheHandle;
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use std::iter::zip;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, broadcast};
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error, info, warn};
+use tracing::{Instrument, debug, error, info, info_span, warn};
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use rustfft::{FftPlanner, num_complex::Complex};
 use uhd::{self, StreamCommand, StreamCommandType, StreamTime, TuneRequest, Usrp};
@@ -33,6 +37,7 @@ pub struct ActiveIntegration {
     cancellation_token: CancellationToken,
     measurement_task: tokio::task::JoinHandle<Result<(), TelescopeError>>,
     kind: IntegrationKind,
+    started: Instant,
 }
 
 struct Inner {
@@ -49,11 +54,14 @@ struct Inner {
     location: Location,
     min_elevation_rad: f64,
     max_elevation_rad: f64,
+    azimuth_range_rad: Option<(f64, f64)>,
     webcam_crop: Option<[f64; 4]>,
     default_ref_freq_hz: f64,
     default_gain_db: f64,
+    rest_freq_hz: f64,
     tsys_k: f64,
     wind_warning_ms: Option<f64>,
+    max_integration_secs: f64,
     receiver_connected: Arc<tokio::sync::Mutex<bool>>,
     controller_connected: bool,
 }
@@ -66,7 +74,7 @@ pub struct SalsaTelescope {
 #[allow(clippy::too_many_arguments)]
 pub fn create(
     name: String,
-    controller_address: String,
+    controller: ControllerSettings,
     receiver_address: String,
     gpsdo_enabled: bool,
     stow_position: Option<Direction>,
@@ -74,28 +82,40 @@ pub fn create(
     location: Location,
     min_elevation_rad: f64,
     max_elevation_rad: f64,
+    keyhole_elevation_rad: Option<f64>,
+    azimuth_range_rad: Option<(f64, f64)>,
     webcam_crop: Option<[f64; 4]>,
     default_ref_freq_hz: f64,
     default_gain_db: f64,
+    rest_freq_hz: f64,
     tsys_k: f64,
     wind_warning_ms: Option<f64>,
+    max_integration_secs: f64,
+    apply_refraction: bool,
     tle_cache: TleCacheHandle,
 ) -> SalsaTelescope {
     let receiver_connected = Arc::new(tokio::sync::Mutex::new(false));
     let ping_connected = receiver_connected.clone();
     let ping_address = receiver_address.clone();
+    // Not a child of whatever request (re)loaded the telescope.
+    let span = info_span!(parent: None, "telescope", telescope_id = %name);
 
     let inner = Arc::new(Mutex::new(Inner {
         name,
         receiver_address,
         gpsdo_enabled,
-        controller: TelescopeTracker::new(
-            controller_address,
-            location,
-            min_elevation_rad,
-            max_elevation_rad,
-            tle_cache.clone(),
-        ),
+        controller: span.in_scope(|| {
+            TelescopeTracker::new(
+                controller,
+                location,
+                min_elevation_rad,
+                max_elevation_rad,
+                keyhole_elevation_rad,
+                azimuth_range_rad,
+                apply_refraction,
+                tle_cache.clone(),
+            )
+        }),
         receiver_configuration: ReceiverConfiguration {
             integrate: false,
             ref_freq_hz: default_ref_freq_hz,
@@ -110,34 +130,40 @@ pub fn create(
         location,
         min_elevation_rad,
         max_elevation_rad,
+        azimuth_range_rad,
         webcam_crop,
         default_ref_freq_hz,
         default_gain_db,
+        rest_freq_hz,
         tsys_k,
         wind_warning_ms,
+        max_integration_secs,
         receiver_connected,
         controller_connected: false,
     }));
 
     let task_inner = inner.clone();
-    let update_task = tokio::spawn(async move {
-        loop {
-            {
-                let mut inner = task_inner.lock().await;
-                if let Err(error) = inner.update(TELESCOPE_UPDATE_INTERVAL).await {
-                    error!("Failed to update telescope: {}", error);
+    let update_task = tokio::spawn(
+        async move {
+            loop {
+                {
+                    let mut inner = task_inner.lock().await;
+                    if let Err(error) = inner.update(TELESCOPE_UPDATE_INTERVAL).await {
+                        error!("Failed to update telescope: {}", error);
+                    }
                 }
+                tokio::time::sleep(TELESCOPE_UPDATE_INTERVAL).await;
             }
-            tokio::time::sleep(TELESCOPE_UPDATE_INTERVAL).await;
         }
-    });
-
-    let ping_task = tokio::spawn(async move {
-        let mut prev_reachable = false;
-        loop {
-            let addr = ping_address.clone();
-            let reachable =
-                tokio::task::spawn_blocking(move || {
+        .instrument(span.clone()),
+    );
+
+    let ping_task = tokio::spawn(
+        async move {
+            let mut prev_reachable = false;
+            loop {
+                let addr = ping_address.clone();
+                let reachable = tokio::task::spawn_blocking(move || {
                     match std::process::Command::new("/usr/bin/ping")
                         .args(["-c", "1", "-W", "1", &addr])
                         .output()
@@ -151,18 +177,20 @@ pub fn create(
                 })
                 .await
                 .unwrap_or(false);
-            if reachable != prev_reachable {
-                if reachable {
-                    info!("Receiver at {} is reachable", ping_address);
-                } else {
-                    warn!("Receiver at {} is no longer reachable", ping_address);
+                if reachable != prev_reachable {
+                    if reachable {
+                        info!("Receiver at {} is reachable", ping_address);
+                    } else {
+                        warn!("Receiver at {} is no longer reachable", ping_address);
+                    }
+                    prev_reachable = reachable;
                 }
-                prev_reachable = reachable;
+                *ping_connected.lock().await = reachable;
+                tokio::time::sleep(Duration::from_secs(5)).await;
             }
-            *ping_connected.lock().await = reachable;
-            tokio::time::sleep(Duration::from_secs(5)).await;
         }
-    });
+        .instrument(span),
+    );
 
     SalsaTelescope {
         inner,
@@ -184,11 +212,34 @@ impl Telescope for SalsaTelescope {
             .set_target(target, az_offset_rad, el_offset_rad)
     }
 
+    async fn repoint(
+        &self,
+        target: TelescopeTarget,
+        az_offset_rad: f64,
+        el_offset_rad: f64,
+    ) -> Result<TelescopeTarget, TelescopeError> {
+        // The measurement task runs independently of the rotor, so moving
+        // is all there is to it.
+        let mut inner = self.inner.lock().await;
+        inner
+            .controller
+            .set_target(target, az_offset_rad, el_offset_rad)
+    }
+
     async fn stop(&self) -> Result<(), TelescopeError> {
         let mut inner = self.inner.lock().await;
         inner.controller.stop()
     }
 
+    async fn stow(&self) -> Result<(), TelescopeError> {
+        self.stop_integration().await;
+        let mut inner = self.inner.lock().await;
+        match inner.stow_position {
+            Some(position) => inner.controller.stow(position),
+            None => inner.controller.stop(),
+        }
+    }
+
     async fn calibrate(
         &self,
         az_offset_rad: f64,
@@ -223,9 +274,19 @@ impl Telescope for SalsaTelescope {
             if inner.active_integration.is_some() {
                 return Err(ReceiverError::IntegrationAlreadyRunning);
             }
+            receiver_configuration.validate()?;
+            // Switching position needs the integration to follow the
+            // controller through the slews, which measure() cannot do yet.
+            // There is no noise source in the SALSA receivers.
+            if receiver_configuration.mode == ObservationMode::PositionSwitched
+                || receiver_configuration.cal_diode
+            {
+                return Err(ReceiverError::UnsupportedMode);
+            }
 
             info!("Starting integration on {}", inner.name);
             inner.receiver_configuration.integrate = true;
+            inner.receiver_configuration.drift_scan = receiver_configuration.drift_scan;
             inner.last_receiver_error = None;
             inner.measurements.lock().await.clear();
             let cancellation_token = CancellationToken::new();
@@ -248,6 +309,7 @@ impl Telescope for SalsaTelescope {
                 cancellation_token,
                 measurement_task,
                 kind: IntegrationKind::Spectrum,
+                started: Instant::now(),
             });
         } else if !receiver_configuration.integrate && inner.receiver_configuration.integrate {
             info!("Stopping integration on {}", inner.name);
@@ -275,14 +337,17 @@ impl Telescope for SalsaTelescope {
     }
 
     async fn stop_integration(&self) -> Option<ObservedSpectra> {
-        let active_integration = {
+        let (active_integration, drift_scan) = {
             let mut inner = self.inner.lock().await;
             if !inner.receiver_configuration.integrate {
                 return None;
             }
             info!("Stopping integration on {}", inner.name);
             inner.receiver_configuration.integrate = false;
-            inner.active_integration.take()
+            (
+                inner.active_integration.take(),
+                inner.receiver_configuration.drift_scan.is_some(),
+            )
         };
         // Lock is dropped — safe to await the task without risk of deadlock.
         let kind = active_integration.as_ref().map(|ai| ai.kind);
@@ -304,10 +369,13 @@ impl Telescope for SalsaTelescope {
         }
         let inner = self.inner.lock().await;
         let measurements = inner.measurements.lock().await;
-        measurements.last().map(|m| ObservedSpectra {
-            frequencies: m.freqs.clone(),
-            spectra: m.amps.clone(),
-            observation_time: m.duration,
+        combined_spectra(&measurements).map(|spectra| ObservedSpectra {
+            scan: if drift_scan {
+                scan_spectra(&measurements)
+            } else {
+                Vec::new()
+            },
+            ..spectra
         })
     }
 
@@ -316,6 +384,19 @@ impl Telescope for SalsaTelescope {
         inner.measurements.lock().await.clear();
     }
 
+    async fn latest_spectrum(&self) -> Option<ObservedSpectra> {
+        let inner = self.inner.lock().await;
+        if !inner
+            .active_integration
+            .as_ref()
+            .is_some_and(|ai| ai.kind == IntegrationKind::Spectrum)
+        {
+            return None;
+        }
+        let measurements = inner.measurements.lock().await;
+        combined_spectra(&measurements)
+    }
+
     async fn interferometry_capable(&self) -> bool {
         self.inner.lock().await.gpsdo_enabled
     }
@@ -334,36 +415,40 @@ impl Telescope for SalsaTelescope {
         let receiver_connected = *inner.receiver_connected.lock().await;
         let controller_info = inner.controller.info()?;
 
-        let latest_observation = {
-            let measurements = inner.measurements.lock().await;
-            match measurements.last() {
-                None => None,
-                Some(measurement) => {
-                    let measurement = measurement.clone();
-                    let latest_observation = ObservedSpectra {
-                        frequencies: measurement.freqs,
-                        spectra: measurement.amps,
-                        observation_time: measurement.duration,
-                    };
-                    Some(latest_observation)
-                }
-            }
-        };
+        let latest_observation = combined_spectra(&inner.measurements.lock().await);
 
         Ok(TelescopeInfo {
             id: inner.name.clone(),
-            status: controller_info.status,
+            status: match (controller_info.status, inner.stow_position) {
+                (TelescopeStatus::Idle, Some(stow))
+                    if controller_info.current_horizontal.is_some_and(|current| {
+                        angular_separation(current, stow).to_degrees() < PARKED_TOLERANCE_DEG
+                    }) =>
+                {
+                    TelescopeStatus::Parked
+                }
+                (status, _) => status,
+            },
             current_horizontal: controller_info.current_horizontal,
+            estimated_seconds_to_target: controller_info.estimated_seconds_to_target,
+            pointing_error_degrees: controller_info.pointing_error_degrees,
+            controller_reconnect_in_secs: controller_info.reconnect_in_secs,
+            last_successful_update: controller_info.last_successful_update,
             commanded_horizontal: controller_info.commanded_horizontal,
             current_target: controller_info.target,
             most_recent_error: inner
                 .last_receiver_error
                 .clone()
-                .or(controller_info.most_recent_error),
+                .or(controller_info.most_recent_error.clone()),
             measurement_in_progress: inner
                 .active_integration
                 .as_ref()
                 .is_some_and(|ai| ai.kind == IntegrationKind::Spectrum),
+            integration_elapsed: inner
+                .active_integration
+                .as_ref()
+                .filter(|ai| ai.kind == IntegrationKind::Spectrum)
+                .map(|ai| ai.started.elapsed()),
             latest_observation,
             stow_position: inner.stow_position,
             service_position: inner.service_position,
@@ -372,14 +457,42 @@ impl Telescope for SalsaTelescope {
             location: inner.location,
             min_elevation_rad: inner.min_elevation_rad,
             max_elevation_rad: inner.max_elevation_rad,
+            azimuth_range_rad: inner.azimuth_range_rad,
             webcam_crop: inner.webcam_crop,
-            receiver_connected: Some(receiver_connected),
-            controller_connected: Some(inner.controller_connected),
+            mount_status: SubsystemStatus {
+                connected: !is_link_failure(controller_info.most_recent_error.as_ref()),
+                error: controller_info.most_recent_error,
+            },
+            receiver_status: SubsystemStatus {
+                connected: receiver_connected,
+                error: inner.last_receiver_error.clone(),
+            },
             wind_warning_ms: inner.wind_warning_ms,
+            max_integration_secs: inner.max_integration_secs,
             default_ref_freq_mhz: inner.default_ref_freq_hz / 1e6,
             default_gain_db: inner.default_gain_db,
+            rest_freq_mhz: inner.rest_freq_hz / 1e6,
+            receiver_calibration: ReceiverCalibration {
+                system_temperature_k: inner.tsys_k,
+                gain_db: inner.receiver_configuration.gain_db,
+            },
+            position_switch: None,
+            supports_position_switching: false,
+            drift_scan: if inner.receiver_configuration.integrate {
+                inner.receiver_configuration.drift_scan
+            } else {
+                None
+            },
         })
     }
+    async fn error_history(&self) -> Vec<(DateTime<Utc>, TelescopeError)> {
+        self.inner.lock().await.controller.error_history()
+    }
+
+    async fn subscribe_position(&self) -> Option<broadcast::Receiver<PositionUpdate>> {
+        self.inner.lock().await.controller.subscribe_position()
+    }
+
     async fn shutdown(&self) {
         if let Some(tasks) = self.background_tasks.lock().await.take() {
             for task in tasks {
@@ -387,6 +500,20 @@ impl Telescope for SalsaTelescope {
                 let _ = task.await;
             }
         }
+        // End a running integration or IQ stream so the USRP is released
+        // before the process exits. Awaited without the lock, as in
+        // stop_integration().
+        let active_integration = {
+            let mut inner = self.inner.lock().await;
+            inner.receiver_configuration.integrate = false;
+            inner.active_integration.take()
+        };
+        if let Some(ai) = active_integration {
+            ai.cancellation_token.cancel();
+            if let Ok(Err(err)) = ai.measurement_task.await {
+                error!("Measurement task failed before shutdown: {err}");
+            }
+        }
         let inner = self.inner.lock().await;
         debug!("Shutting down {}", inner.name);
         inner.controller.shutdown().await;
@@ -415,11 +542,21 @@ impl Telescope for SalsaTelescope {
             cancellation_token,
             measurement_task,
             kind: IntegrationKind::Iq,
+            started: Instant::now(),
         });
         Ok(rx)
     }
 }
 
+/// Whether `error` means the controller can't be reached, as opposed to
+/// it refusing a target.
+fn is_link_failure(error: Option<&TelescopeError>) -> bool {
+    matches!(
+        error,
+        Some(TelescopeError::TelescopeIOError(_) | TelescopeError::TelescopeNotConnected)
+    )
+}
+
 impl Inner {
     async fn update(&mut self, _delta_time: Duration) -> Result<(), TelescopeError> {
         if let Some(active_integration) = self.active_integration.take() {
@@ -440,12 +577,10 @@ impl Inner {
                 self.active_integration = Some(active_integration);
             }
         }
-        let connected = !matches!(
-            self.controller.info().map(|i| i.most_recent_error),
-            Ok(Some(
-                TelescopeError::TelescopeIOError(_) | TelescopeError::TelescopeNotConnected
-            )) | Err(TelescopeError::TelescopeIOError(_) | TelescopeError::TelescopeNotConnected)
-        );
+        let connected = match self.controller.info() {
+            Ok(info) => !is_link_failure(info.most_recent_error.as_ref()),
+            Err(err) => !is_link_failure(Some(&err)),
+        };
         if connected != self.controller_connected {
             if connected {
                 info!("Controller for {} is now connected", self.name);
@@ -730,19 +865,6 @@ fn clip_rfi_spikes(spec: &mut [f64]) {
     }
 }
 
-fn median(mut xs: Vec<f64>) -> f64 {
-    if xs.is_empty() {
-        return 0.0;
-    }
-    xs.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
-    let n = xs.len();
-    if n.is_multiple_of(2) {
-        (xs[n / 2] + xs[n / 2 - 1]) / 2.0
-    } else {
-        xs[n / 2]
-    }
-}
-
 // Sync function intended to run on tokio's blocking thread pool via
 // `spawn_blocking`. The hot loop is pure FFI (`receive_simple`) and CPU work
 // (FFT, RFI filter); running it on an async worker would block one of the
@@ -828,7 +950,8 @@ fn measure(
                 config.rfi_filter,
                 &mut spec,
             )?,
-            ObservationMode::Interferometry => break,
+            // Rejected in set_receiver_configuration.
+            ObservationMode::Interferometry | ObservationMode::PositionSwitched => break,
         };
         // measure_switched leaves spec empty when it bails on cancellation.
         // Skip the averaging update; the outer while-condition re-checks the
@@ -849,10 +972,67 @@ fn measure(
             .signed_duration_since(measurement.start)
             .to_std()
             .unwrap();
+        // A drift scan closes off a measurement every cadence and starts
+        // averaging the next.
+        if let Some(scan) = config.drift_scan
+            && measurement.duration.as_secs_f64() >= scan.cadence_secs
+        {
+            let next = Measurement {
+                amps: vec![0.0; avg_pts],
+                freqs: measurement.freqs.clone(),
+                start: Utc::now(),
+                duration: Duration::from_secs(0),
+            };
+            measurements.push(next);
+            n = 0.0;
+        }
     }
     Ok(())
 }
 
+/// `measurements` as one spectrum, each weighted by its duration. A drift
+/// scan has a measurement per slice, any other integration just the one.
+fn combined_spectra(measurements: &[Measurement]) -> Option<ObservedSpectra> {
+    let first = measurements.first()?;
+    let observation_time: Duration = measurements.iter().map(|m| m.duration).sum();
+    let total_secs = observation_time.as_secs_f64();
+    let spectra = if measurements.len() == 1 || total_secs == 0.0 {
+        first.amps.clone()
+    } else {
+        (0..first.amps.len())
+            .map(|channel| {
+                measurements
+                    .iter()
+                    .map(|m| m.amps[channel] * m.duration.as_secs_f64())
+                    .sum::<f64>()
+                    / total_secs
+            })
+            .collect()
+    };
+    Some(ObservedSpectra {
+        frequencies: first.freqs.clone(),
+        spectra,
+        observation_time,
+        scan: Vec::new(),
+    })
+}
+
+/// A drift scan's slices, one per measurement with data in it.
+fn scan_spectra(measurements: &[Measurement]) -> Vec<ScanSpectrum> {
+    let Some(first) = measurements.first() else {
+        return Vec::new();
+    };
+    measurements
+        .iter()
+        .filter(|m| !m.duration.is_zero())
+        .map(|m| ScanSpectrum {
+            start_offset: (m.start - first.start).to_std().unwrap_or_default(),
+            spectra: m.amps.clone(),
+            observation_time: m.duration,
+        })
+        .collect()
+}
+
 /// Stream raw IQ blocks for interferometry cross-correlation.
 /// Sends blocks of IQ_BLOCK_SIZE Complex<f32> samples over `tx`.
 /// Exits when `cancellation_token` fires or the receiver drops the channel.
@@ -979,6 +1159,29 @@ fn measure_iq(
 mod test {
     use super::*;
 
+    #[test]
+    fn drift_scan_slices_are_combined_by_duration() {
+        let start = Utc::now();
+        let measurement = |offset_secs: i64, amp: f64, duration_secs: u64| Measurement {
+            amps: vec![amp],
+            freqs: vec![1420e6],
+            start: start + chrono::Duration::seconds(offset_secs),
+            duration: Duration::from_secs(duration_secs),
+        };
+        let measurements = vec![
+            measurement(0, 1.0, 3),
+            measurement(3, 3.0, 1),
+            measurement(4, 0.0, 0),
+        ];
+        let combined = combined_spectra(&measurements).unwrap();
+        assert_eq!(combined.spectra, vec![1.5]);
+        assert_eq!(combined.observation_time, Duration::from_secs(4));
+        let scan = scan_spectra(&measurements);
+        assert_eq!(scan.len(), 2);
+        assert_eq!(scan[1].start_offset, Duration::from_secs(3));
+        assert_eq!(scan[1].spectra, vec![3.0]);
+    }
+
     #[test]
     fn clip_rfi_keeps_broad_signal() {
         // Synthetic 512-channel power spectrum modelled on vale's sig.csv
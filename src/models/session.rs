@@ -1,17 +1,16 @@
 use base64::{Engine, prelude::BASE64_STANDARD};
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
 use oauth2::CsrfToken;
 use rand::Rng;
-use rusqlite::Connection;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use sha2::{Digest, Sha256};
 
+use crate::database::ConnectionPool;
 use crate::{error::InternalError, i18n::Language, models::user::User};
 
-/// How long a session cookie is valid for. Matches the cookie Max-Age set on
-/// login, and is enforced server-side so a leaked token can't be replayed
-/// indefinitely.
-pub const SESSION_LIFETIME_SECS: i64 = 30 * 24 * 60 * 60;
+/// How long a session is valid for unless `[sessions] lifetime_days` says
+/// otherwise. The expiry is stored with the session and enforced
+/// server-side so a leaked token can't be replayed indefinitely.
+pub const DEFAULT_SESSION_LIFETIME_DAYS: u32 = 30;
 
 /// How long a pending OAuth2 CSRF token stays valid. The user has this long
 /// to bounce off the provider and come back to the callback.
@@ -27,15 +26,32 @@ fn create_session_token() -> String {
     BASE64_STANDARD.encode(generate_random_bytes(20))
 }
 
+/// What the session table stores in place of a token, so that a copy of
+/// the database doesn't hand out live sessions. The token is random
+/// enough that a plain unsalted hash is all it needs.
+pub(crate) fn hash_session_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Remember an OAuth2 login we sent someone off to `provider` for. With
+/// `link_user_id`, the identity they come back with is to be linked to
+/// that account rather than logged in with.
 pub async fn start_oauth2_login(
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
     provider: &str,
     csrf_token: &CsrfToken,
+    link_user_id: Option<i64>,
 ) -> Result<(), InternalError> {
-    let conn = connection.lock().await;
+    let conn = connection.get().await;
     conn.execute(
-        "INSERT INTO pending_oauth2 (csrf_token, provider, created_at) VALUES ((?1), (?2), (?3))",
-        (csrf_token.secret(), provider, Utc::now().timestamp()),
+        "INSERT INTO pending_oauth2 (csrf_token, provider, created_at, link_user_id) \
+         VALUES ((?1), (?2), (?3), (?4))",
+        (
+            csrf_token.secret(),
+            provider,
+            Utc::now().timestamp(),
+            link_user_id,
+        ),
     )
     .map_err(|err| {
         InternalError::new(format!(
@@ -45,15 +61,17 @@ pub async fn start_oauth2_login(
     Ok(())
 }
 
+/// The provider and account to link of the pending login `csrf_token`
+/// belongs to. Each pending login can be completed once.
 pub async fn complete_oauth2_login(
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
     csrf_token: &str,
-) -> Result<String, InternalError> {
-    let conn = connection.lock().await;
+) -> Result<(String, Option<i64>), InternalError> {
+    let conn = connection.get().await;
     let oldest_allowed = Utc::now().timestamp() - OAUTH2_PENDING_LIFETIME_SECS;
-    let (id, provider) = conn
+    let (id, provider, link_user_id) = conn
         .query_row(
-            "SELECT id, provider FROM pending_oauth2 \
+            "SELECT id, provider, link_user_id FROM pending_oauth2 \
              WHERE csrf_token = (?1) AND created_at > (?2)",
             (csrf_token, oldest_allowed),
             |row| {
@@ -62,6 +80,8 @@ pub async fn complete_oauth2_login(
                         .expect("Table 'pending_oauth2' has known layout"),
                     row.get::<usize, String>(1)
                         .expect("Table 'pending_oauth2' has known layout"),
+                    row.get::<usize, Option<i64>>(2)
+                        .expect("Table 'pending_oauth2' has known layout"),
                 ))
             },
         )
@@ -73,15 +93,13 @@ pub async fn complete_oauth2_login(
             ))
         })?;
 
-    Ok(provider)
+    Ok((provider, link_user_id))
 }
 
 /// Delete pending OAuth2 rows past their TTL. Called at startup to keep the
 /// table from accumulating abandoned flows over time.
-pub async fn purge_expired_pending_oauth2(
-    connection: Arc<Mutex<Connection>>,
-) -> Result<(), InternalError> {
-    let conn = connection.lock().await;
+pub async fn purge_expired_pending_oauth2(connection: ConnectionPool) -> Result<(), InternalError> {
+    let conn = connection.get().await;
     let oldest_allowed = Utc::now().timestamp() - OAUTH2_PENDING_LIFETIME_SECS;
     conn.execute(
         "DELETE FROM pending_oauth2 WHERE created_at <= (?1)",
@@ -93,14 +111,11 @@ pub async fn purge_expired_pending_oauth2(
 
 /// Delete sessions past their TTL. Called at startup so leaked-but-unused
 /// tokens don't sit around indefinitely.
-pub async fn purge_expired_sessions(
-    connection: Arc<Mutex<Connection>>,
-) -> Result<(), InternalError> {
-    let conn = connection.lock().await;
-    let oldest_allowed = Utc::now().timestamp() - SESSION_LIFETIME_SECS;
+pub async fn purge_expired_sessions(connection: ConnectionPool) -> Result<(), InternalError> {
+    let conn = connection.get().await;
     conn.execute(
-        "DELETE FROM session WHERE created_at <= (?1)",
-        (oldest_allowed,),
+        "DELETE FROM session WHERE expires_at <= (?1)",
+        (Utc::now().timestamp(),),
     )
     .map_err(|err| InternalError::new(format!("Failed to purge sessions: {err}")))?;
     Ok(())
@@ -110,20 +125,23 @@ pub async fn purge_expired_sessions(
 pub struct Session {
     pub token: String,
     pub user: User,
+    pub expires_at: DateTime<Utc>,
 }
 
 impl Session {
+    /// Look up an unexpired session and bump its `last_used_at`. An expired
+    /// session is deleted and reported as missing.
     pub async fn fetch(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         token: &str,
     ) -> Result<Option<Session>, InternalError> {
-        let conn = connection.lock().await;
-        let oldest_allowed = Utc::now().timestamp() - SESSION_LIFETIME_SECS;
-        match conn.query_row(
-            "SELECT token, user.id, username, provider, timezone, language FROM session \
-             INNER JOIN user ON session.user_id = user.id \
-             WHERE session.token = (?1) AND session.created_at > (?2)",
-            (token, oldest_allowed),
+        let conn = connection.get().await;
+        let now = Utc::now().timestamp();
+        let found = conn.query_row(
+            "SELECT token_hash, user.id, username, provider, timezone, language, expires_at, display_name \
+             FROM session INNER JOIN user ON session.user_id = user.id \
+             WHERE session.token_hash = (?1)",
+            (hash_session_token(token),),
             |row| {
                 Ok((
                     row.get::<usize, String>(0)
@@ -138,57 +156,109 @@ impl Session {
                         .expect("Table 'user' has known layout"),
                     row.get::<usize, Option<String>>(5)
                         .expect("Table 'user' has known layout"),
+                    row.get::<usize, i64>(6)
+                        .expect("Table 'session' has known layout"),
+                    row.get::<usize, Option<String>>(7)
+                        .expect("Table 'user' has known layout"),
                 ))
             },
-        ) {
-            Ok((token, user_id, username, provider, timezone, language)) => Ok(Some(Session {
-                token: token.to_string(),
-                user: User {
-                    id: user_id,
-                    name: username,
-                    provider,
-                    is_admin: false,
-                    // Stored names are validated on write; ignore anything
-                    // unparseable (treated as UTC) rather than failing login.
-                    timezone: timezone.and_then(|name| name.parse().ok()),
-                    // Same: validated on write, ignore junk.
-                    language: language.as_deref().and_then(Language::from_code),
-                },
-            })),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(err) => Err(InternalError::new(format!(
-                "Failed to fetch session from db: {err}"
-            ))),
+        );
+        let (token_hash, user_id, username, provider, timezone, language, expires_at, display_name) =
+            match found {
+                Ok(row) => row,
+                Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+                Err(err) => {
+                    return Err(InternalError::new(format!(
+                        "Failed to fetch session from db: {err}"
+                    )));
+                }
+            };
+        if expires_at <= now {
+            conn.execute(
+                "DELETE FROM session WHERE token_hash = (?1)",
+                (&token_hash,),
+            )
+            .map_err(|err| {
+                InternalError::new(format!("Failed to delete expired session: {err}"))
+            })?;
+            return Ok(None);
         }
+        conn.execute(
+            "UPDATE session SET last_used_at = (?1) WHERE token_hash = (?2)",
+            (now, &token_hash),
+        )
+        .map_err(|err| InternalError::new(format!("Failed to update session: {err}")))?;
+        Ok(Some(Session {
+            token: token.to_string(),
+            user: User {
+                id: user_id,
+                name: username,
+                provider,
+                is_admin: false,
+                // Stored names are validated on write; ignore anything
+                // unparseable (treated as UTC) rather than failing login.
+                timezone: timezone.and_then(|name| name.parse().ok()),
+                // Same: validated on write, ignore junk.
+                language: language.as_deref().and_then(Language::from_code),
+                display_name,
+            },
+            expires_at: DateTime::<Utc>::from_timestamp(expires_at, 0).unwrap_or_default(),
+        }))
     }
 
+    /// Start a session for `user`. The returned session holds the token,
+    /// which is the only time it's available; the database keeps its hash.
     pub async fn create(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user: &User,
+        lifetime: Duration,
     ) -> Result<Session, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let token = create_session_token();
+        let now = Utc::now();
+        let expires_at = now + lifetime;
         conn.execute(
-            "INSERT INTO session (token, user_id, created_at) VALUES ((?1), (?2), (?3))",
-            (&token, &user.id, Utc::now().timestamp()),
+            "INSERT INTO session (token_hash, user_id, created_at, expires_at, last_used_at) \
+             VALUES ((?1), (?2), (?3), (?4), (?3))",
+            (
+                hash_session_token(&token),
+                &user.id,
+                now.timestamp(),
+                expires_at.timestamp(),
+            ),
         )
         .map_err(|err| InternalError::new(format!("Failed to insert session in db: {err}")))?;
 
         Ok(Session {
             token: token.to_string(),
             user: user.clone(),
+            expires_at,
         })
     }
 
-    pub async fn delete(self, connection: Arc<Mutex<Connection>>) -> Result<(), InternalError> {
-        let conn = connection.lock().await;
+    /// End every session `user` has, on all devices.
+    pub async fn delete_all_for_user(
+        connection: ConnectionPool,
+        user: &User,
+    ) -> Result<(), InternalError> {
+        let conn = connection.get().await;
+        conn.execute("DELETE FROM session WHERE user_id = (?1)", (user.id,))
+            .map_err(|err| InternalError::new(format!("Failed to delete sessions: {err}")))?;
+        Ok(())
+    }
 
-        conn.execute("DELETE FROM session WHERE token = (?1)", (self.token,))
-            .map_err(|err| {
-                InternalError::new(format!(
-                    "Failed to clear complete pending oauth2 action in db: {err}"
-                ))
-            })?;
+    pub async fn delete(self, connection: ConnectionPool) -> Result<(), InternalError> {
+        let conn = connection.get().await;
+
+        conn.execute(
+            "DELETE FROM session WHERE token_hash = (?1)",
+            (hash_session_token(&self.token),),
+        )
+        .map_err(|err| {
+            InternalError::new(format!(
+                "Failed to clear complete pending oauth2 action in db: {err}"
+            ))
+        })?;
         Ok(())
     }
 }
@@ -196,18 +266,12 @@ impl Session {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::database::{SqliteDatabaseError, apply_migrations};
-    use crate::models::user::User;
-    fn create_connection() -> Result<Arc<Mutex<Connection>>, SqliteDatabaseError> {
-        let mut connection = Connection::open_in_memory()?;
-        apply_migrations(&mut connection)?;
-        Ok(Arc::new(Mutex::new(connection)))
-    }
+    use crate::database::{create_test_connection, create_test_user};
 
     #[tokio::test]
     async fn test_complete_oauth2_with_incorrect_csrf_token_fails() {
-        let connection = create_connection().unwrap();
-        start_oauth2_login(connection.clone(), "test", &CsrfToken::new_random())
+        let connection = create_test_connection();
+        start_oauth2_login(connection.clone(), "test", &CsrfToken::new_random(), None)
             .await
             .unwrap();
         assert!(
@@ -219,14 +283,14 @@ mod test {
 
     #[tokio::test]
     async fn test_complete_oauth2_clears_request() {
-        let connection = create_connection().unwrap();
+        let connection = create_test_connection();
         let csrf_token = CsrfToken::new_random();
-        start_oauth2_login(connection.clone(), "test", &csrf_token)
+        start_oauth2_login(connection.clone(), "test", &csrf_token, None)
             .await
             .unwrap();
         // First completion is valid
         assert_eq!(
-            "test",
+            ("test".to_string(), None),
             complete_oauth2_login(connection.clone(), csrf_token.secret())
                 .await
                 .unwrap()
@@ -241,16 +305,11 @@ mod test {
 
     #[tokio::test]
     async fn test_create_session() {
-        let connection = create_connection().unwrap();
-        let user = User::create_from_external(
-            connection.clone(),
-            "test".to_string(),
-            "test".to_string(),
-            "1",
-        )
-        .await
-        .unwrap();
-        let created_session = Session::create(connection.clone(), &user).await.unwrap();
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "test").await;
+        let created_session = Session::create(connection.clone(), &user, Duration::days(30))
+            .await
+            .unwrap();
         let fetched_sesssion = Session::fetch(connection.clone(), &created_session.token)
             .await
             .unwrap()
@@ -258,26 +317,44 @@ mod test {
         assert_eq!(created_session.token, fetched_sesssion.token);
     }
 
+    #[tokio::test]
+    async fn only_the_token_hash_is_stored() {
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "test").await;
+        let session = Session::create(connection.clone(), &user, Duration::days(30))
+            .await
+            .unwrap();
+        let stored: String = connection
+            .get()
+            .await
+            .query_row("SELECT token_hash FROM session", [], |row| row.get(0))
+            .unwrap();
+        assert_ne!(stored, session.token);
+        assert!(!stored.contains(&session.token));
+        // Presenting the stored hash as a cookie gets nowhere.
+        assert!(
+            Session::fetch(connection.clone(), &stored)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
     #[tokio::test]
     async fn test_expired_session_is_rejected() {
-        let connection = create_connection().unwrap();
-        let user = User::create_from_external(
-            connection.clone(),
-            "test".to_string(),
-            "test".to_string(),
-            "1",
-        )
-        .await
-        .unwrap();
-        let session = Session::create(connection.clone(), &user).await.unwrap();
-        // Backdate the session past the lifetime
-        let stale = Utc::now().timestamp() - SESSION_LIFETIME_SECS - 1;
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "test").await;
+        let session = Session::create(connection.clone(), &user, Duration::days(30))
+            .await
+            .unwrap();
+        // Move the expiry into the past
+        let stale = Utc::now().timestamp() - 1;
         connection
-            .lock()
+            .get()
             .await
             .execute(
-                "UPDATE session SET created_at = (?1) WHERE token = (?2)",
-                (stale, &session.token),
+                "UPDATE session SET expires_at = (?1) WHERE token_hash = (?2)",
+                (stale, hash_session_token(&session.token)),
             )
             .unwrap();
         assert!(
@@ -286,18 +363,82 @@ mod test {
                 .unwrap()
                 .is_none()
         );
+        // The expired row is gone, not just hidden
+        let count: i64 = connection
+            .get()
+            .await
+            .query_row("SELECT COUNT(*) FROM session", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_bumps_last_used_at() {
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "test").await;
+        let session = Session::create(connection.clone(), &user, Duration::days(30))
+            .await
+            .unwrap();
+        connection
+            .get()
+            .await
+            .execute("UPDATE session SET last_used_at = 0", [])
+            .unwrap();
+        Session::fetch(connection.clone(), &session.token)
+            .await
+            .unwrap()
+            .unwrap();
+        let last_used_at: i64 = connection
+            .get()
+            .await
+            .query_row("SELECT last_used_at FROM session", [], |row| row.get(0))
+            .unwrap();
+        assert!(last_used_at >= Utc::now().timestamp() - 5);
+    }
+
+    #[tokio::test]
+    async fn test_delete_all_for_user_spares_other_users() {
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "test").await;
+        let other = create_test_user(&connection, "other").await;
+        let first = Session::create(connection.clone(), &user, Duration::days(30))
+            .await
+            .unwrap();
+        let second = Session::create(connection.clone(), &user, Duration::days(30))
+            .await
+            .unwrap();
+        let others = Session::create(connection.clone(), &other, Duration::days(30))
+            .await
+            .unwrap();
+        Session::delete_all_for_user(connection.clone(), &user)
+            .await
+            .unwrap();
+        for token in [&first.token, &second.token] {
+            assert!(
+                Session::fetch(connection.clone(), token)
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        }
+        assert!(
+            Session::fetch(connection.clone(), &others.token)
+                .await
+                .unwrap()
+                .is_some()
+        );
     }
 
     #[tokio::test]
     async fn test_expired_pending_oauth2_is_rejected() {
-        let connection = create_connection().unwrap();
+        let connection = create_test_connection();
         let csrf_token = CsrfToken::new_random();
-        start_oauth2_login(connection.clone(), "test", &csrf_token)
+        start_oauth2_login(connection.clone(), "test", &csrf_token, None)
             .await
             .unwrap();
         let stale = Utc::now().timestamp() - OAUTH2_PENDING_LIFETIME_SECS - 1;
         connection
-            .lock()
+            .get()
             .await
             .execute(
                 "UPDATE pending_oauth2 SET created_at = (?1) WHERE csrf_token = (?2)",
@@ -311,30 +452,58 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_purge_expired_pending_oauth2_keeps_fresh_rows() {
+        let connection = create_test_connection();
+        let stale_token = CsrfToken::new_random();
+        let fresh_token = CsrfToken::new_random();
+        start_oauth2_login(connection.clone(), "test", &stale_token, None)
+            .await
+            .unwrap();
+        start_oauth2_login(connection.clone(), "test", &fresh_token, None)
+            .await
+            .unwrap();
+        let stale = Utc::now().timestamp() - OAUTH2_PENDING_LIFETIME_SECS - 1;
+        connection
+            .get()
+            .await
+            .execute(
+                "UPDATE pending_oauth2 SET created_at = (?1) WHERE csrf_token = (?2)",
+                (stale, stale_token.secret()),
+            )
+            .unwrap();
+        purge_expired_pending_oauth2(connection.clone())
+            .await
+            .unwrap();
+        let remaining: String = connection
+            .get()
+            .await
+            .query_row("SELECT csrf_token FROM pending_oauth2", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(&remaining, fresh_token.secret());
+    }
+
     #[tokio::test]
     async fn test_purge_expired_sessions_removes_old_rows() {
-        let connection = create_connection().unwrap();
-        let user = User::create_from_external(
-            connection.clone(),
-            "test".to_string(),
-            "test".to_string(),
-            "1",
-        )
-        .await
-        .unwrap();
-        let session = Session::create(connection.clone(), &user).await.unwrap();
-        let stale = Utc::now().timestamp() - SESSION_LIFETIME_SECS - 1;
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "test").await;
+        let session = Session::create(connection.clone(), &user, Duration::days(30))
+            .await
+            .unwrap();
+        let stale = Utc::now().timestamp() - 1;
         connection
-            .lock()
+            .get()
             .await
             .execute(
-                "UPDATE session SET created_at = (?1) WHERE token = (?2)",
-                (stale, &session.token),
+                "UPDATE session SET expires_at = (?1) WHERE token_hash = (?2)",
+                (stale, hash_session_token(&session.token)),
             )
             .unwrap();
         purge_expired_sessions(connection.clone()).await.unwrap();
         let count: i64 = connection
-            .lock()
+            .get()
             .await
             .query_row("SELECT COUNT(*) FROM session", [], |row| row.get(0))
             .unwrap();
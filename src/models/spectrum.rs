@@ -0,0 +1,357 @@
+//! Post-processing of stored spectra. Everything here works on plain
+//! channel arrays so it can be tested without a database or HTTP.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// Highest polynomial degree accepted for a baseline fit. Beyond this the
+/// fit starts chasing noise and the normal equations lose precision.
+pub const MAX_BASELINE_DEGREE: usize = 8;
+
+/// Parse channel ranges such as `"120-180,300-310"` (inclusive, 0-based).
+/// A single number is a one-channel range. Returns None on malformed input.
+pub fn parse_channel_ranges(text: &str) -> Option<Vec<RangeInclusive<usize>>> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.split_once('-') {
+            Some((start, end)) => {
+                let start = start.trim().parse().ok()?;
+                let end = end.trim().parse().ok()?;
+                (start <= end).then_some(start..=end)
+            }
+            None => part.parse().ok().map(|channel| channel..=channel),
+        })
+        .collect()
+}
+
+/// Fit a polynomial of `degree` to `amplitudes` by least squares, skipping
+/// the channels in `exclude` (typically where the line is), and return the
+/// fitted baseline for every channel. None if too few channels are left to
+/// determine the polynomial.
+pub fn fit_baseline(
+    amplitudes: &[f64],
+    degree: usize,
+    exclude: &[RangeInclusive<usize>],
+) -> Option<Vec<f64>> {
+    let n = amplitudes.len();
+    // Channel index mapped to [-1, 1] keeps the powers well scaled.
+    let x = |channel: usize| {
+        if n > 1 {
+            2.0 * channel as f64 / (n - 1) as f64 - 1.0
+        } else {
+            0.0
+        }
+    };
+    let terms = degree + 1;
+    // Normal equations A c = b, with A[i][j] = Σ x^(i+j) and b[i] = Σ y x^i.
+    let mut a = vec![vec![0.0; terms]; terms];
+    let mut b = vec![0.0; terms];
+    let mut used = 0;
+    for (channel, &y) in amplitudes.iter().enumerate() {
+        if exclude.iter().any(|range| range.contains(&channel)) {
+            continue;
+        }
+        used += 1;
+        let powers: Vec<f64> = (0..2 * terms - 1)
+            .scan(1.0, |p, _| {
+                let current = *p;
+                *p *= x(channel);
+                Some(current)
+            })
+            .collect();
+        for (i, (row, rhs)) in a.iter_mut().zip(b.iter_mut()).enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                *cell += powers[i + j];
+            }
+            *rhs += y * powers[i];
+        }
+    }
+    if used < terms {
+        return None;
+    }
+    let coefficients = solve(a, b)?;
+    Some(
+        (0..n)
+            .map(|channel| {
+                coefficients
+                    .iter()
+                    .rev()
+                    .fold(0.0, |acc, c| acc * x(channel) + c)
+            })
+            .collect(),
+    )
+}
+
+/// Gaussian elimination with partial pivoting. None if the system is
+/// singular.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let (upper, lower) = a.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        let pivot_rhs = b[col];
+        for (row, rhs) in lower.iter_mut().zip(&mut b[col + 1..]) {
+            let factor = row[col] / pivot_row[col];
+            for (cell, p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *cell -= factor * p;
+            }
+            *rhs -= factor * pivot_rhs;
+        }
+    }
+    let mut solution = vec![0.0; n];
+    for row in (0..n).rev() {
+        let rest: f64 = (row + 1..n).map(|k| a[row][k] * solution[k]).sum();
+        solution[row] = (b[row] - rest) / a[row][row];
+    }
+    Some(solution)
+}
+
+/// Median of `xs`; 0 for an empty slice.
+pub fn median(mut xs: Vec<f64>) -> f64 {
+    if xs.is_empty() {
+        return 0.0;
+    }
+    xs.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+    let n = xs.len();
+    if n.is_multiple_of(2) {
+        (xs[n / 2] + xs[n / 2 - 1]) / 2.0
+    } else {
+        xs[n / 2]
+    }
+}
+
+/// Default threshold, in robust standard deviations, for [`flag_rfi`].
+pub const DEFAULT_RFI_SIGMA: f64 = 5.0;
+
+/// Half-width in channels of the running median that [`flag_rfi`] treats as
+/// the smooth part of the spectrum. Wide enough to ignore spikes a few
+/// channels across, narrow enough to follow the bandpass and real lines.
+const RFI_BASELINE_HALF_WIDTH: usize = 8;
+
+/// Indices of channels that stand out from a running median by more than
+/// `sigma` robust standard deviations, where the deviation is estimated from
+/// the median absolute deviation (MAD) of the residuals. Narrow RFI spikes
+/// are flagged while broad features follow the running median and are kept.
+pub fn flag_rfi(amplitudes: &[f64], sigma: f64) -> Vec<usize> {
+    let n = amplitudes.len();
+    let residual: Vec<f64> = (0..n)
+        .map(|i| {
+            let lo = i.saturating_sub(RFI_BASELINE_HALF_WIDTH);
+            let hi = (i + RFI_BASELINE_HALF_WIDTH + 1).min(n);
+            amplitudes[i] - median(amplitudes[lo..hi].to_vec())
+        })
+        .collect();
+    let center = median(residual.clone());
+    // 1.4826 turns the MAD into a standard deviation for Gaussian noise.
+    let spread = 1.4826 * median(residual.iter().map(|r| (r - center).abs()).collect());
+    if spread == 0.0 {
+        return vec![];
+    }
+    residual
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| (*r - center).abs() > sigma * spread)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// How far, as a fraction of the channel spacing, the frequency of a
+/// channel may differ between spectra that are stacked.
+pub const STACK_FREQUENCY_TOLERANCE: f64 = 0.1;
+
+/// One spectrum to stack, weighted by its integration time.
+pub struct StackInput<'a> {
+    pub frequencies: &'a [f64],
+    pub amplitudes: &'a [f64],
+    pub integration_time_secs: f64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StackError {
+    Empty,
+    /// Spectrum `index` has a different number of channels than the first.
+    ChannelCountMismatch {
+        index: usize,
+    },
+    /// Spectrum `index` deviates from the first at `channel` by more than
+    /// the tolerance.
+    FrequencyMismatch {
+        index: usize,
+        channel: usize,
+    },
+    NoIntegrationTime,
+}
+
+impl fmt::Display for StackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StackError::Empty => write!(f, "No spectra to stack"),
+            StackError::ChannelCountMismatch { index } => write!(
+                f,
+                "Spectrum {} has a different number of channels than the first",
+                index + 1
+            ),
+            StackError::FrequencyMismatch { index, channel } => write!(
+                f,
+                "Spectrum {} has a different frequency axis than the first (channel {channel})",
+                index + 1
+            ),
+            StackError::NoIntegrationTime => write!(f, "Total integration time is zero"),
+        }
+    }
+}
+
+/// Average `spectra` channel by channel, weighted by integration time. All
+/// spectra must share the frequency axis of the first to within
+/// [`STACK_FREQUENCY_TOLERANCE`] of a channel. Returns that axis and the
+/// averaged amplitudes.
+pub fn stack_spectra(spectra: &[StackInput]) -> Result<(Vec<f64>, Vec<f64>), StackError> {
+    let first = spectra.first().ok_or(StackError::Empty)?;
+    let spacing = match first.frequencies {
+        [a, b, ..] => (b - a).abs(),
+        _ => 0.0,
+    };
+    let tolerance = STACK_FREQUENCY_TOLERANCE * spacing;
+    for (index, spectrum) in spectra.iter().enumerate() {
+        if spectrum.frequencies.len() != first.frequencies.len()
+            || spectrum.amplitudes.len() != first.frequencies.len()
+        {
+            return Err(StackError::ChannelCountMismatch { index });
+        }
+        if let Some(channel) = spectrum
+            .frequencies
+            .iter()
+            .zip(first.frequencies)
+            .position(|(f, f0)| (f - f0).abs() > tolerance)
+        {
+            return Err(StackError::FrequencyMismatch { index, channel });
+        }
+    }
+    let total_time: f64 = spectra.iter().map(|s| s.integration_time_secs).sum();
+    if total_time <= 0.0 {
+        return Err(StackError::NoIntegrationTime);
+    }
+    let mut amplitudes = vec![0.0; first.frequencies.len()];
+    for spectrum in spectra {
+        let weight = spectrum.integration_time_secs / total_time;
+        for (sum, a) in amplitudes.iter_mut().zip(spectrum.amplitudes) {
+            *sum += weight * a;
+        }
+    }
+    Ok((first.frequencies.to_vec(), amplitudes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_channel_ranges() {
+        assert_eq!(
+            parse_channel_ranges("120-180, 300-310,5"),
+            Some(vec![120..=180, 300..=310, 5..=5])
+        );
+        assert_eq!(parse_channel_ranges(""), Some(vec![]));
+        assert_eq!(parse_channel_ranges("10-5"), None);
+        assert_eq!(parse_channel_ranges("a-b"), None);
+    }
+
+    #[test]
+    fn baseline_fit_recovers_polynomial_under_a_line() {
+        // Quadratic bandpass with a Gaussian line in channels 40..60.
+        let bandpass = |c: usize| 5.0 + 0.02 * c as f64 - 0.0003 * (c as f64).powi(2);
+        let line = |c: usize| 3.0 * (-0.5 * ((c as f64 - 50.0) / 3.0).powi(2)).exp();
+        let amplitudes: Vec<f64> = (0..100).map(|c| bandpass(c) + line(c)).collect();
+
+        let baseline = fit_baseline(&amplitudes, 2, &[35..=65]).unwrap();
+        for (c, fitted) in baseline.iter().enumerate() {
+            assert!((fitted - bandpass(c)).abs() < 1e-4, "channel {c}");
+        }
+        let subtracted: Vec<f64> = amplitudes
+            .iter()
+            .zip(&baseline)
+            .map(|(a, b)| a - b)
+            .collect();
+        assert!((subtracted[50] - 3.0).abs() < 1e-4);
+        assert!(subtracted[10].abs() < 1e-4);
+    }
+
+    #[test]
+    fn baseline_fit_needs_enough_free_channels() {
+        let amplitudes = vec![1.0; 10];
+        assert!(fit_baseline(&amplitudes, 3, &[0..=7]).is_none());
+        assert!(fit_baseline(&amplitudes, 3, &[0..=5]).is_some());
+    }
+
+    #[test]
+    fn stack_weights_by_integration_time() {
+        let frequencies = [1.0e9, 1.1e9, 1.2e9];
+        let shifted = [1.0e9 + 5e6, 1.1e9 + 5e6, 1.2e9 + 5e6];
+        let stacked = stack_spectra(&[
+            StackInput {
+                frequencies: &frequencies,
+                amplitudes: &[1.0, 2.0, 3.0],
+                integration_time_secs: 30.0,
+            },
+            StackInput {
+                frequencies: &shifted,
+                amplitudes: &[5.0, 6.0, 7.0],
+                integration_time_secs: 10.0,
+            },
+        ])
+        .unwrap();
+        assert_eq!(stacked.0, frequencies.to_vec());
+        assert_eq!(stacked.1, vec![2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn stack_rejects_mismatched_axes() {
+        let frequencies = [1.0e9, 1.1e9, 1.2e9];
+        let input = |frequencies| StackInput {
+            frequencies,
+            amplitudes: &[0.0, 0.0, 0.0],
+            integration_time_secs: 10.0,
+        };
+        assert_eq!(stack_spectra(&[]), Err(StackError::Empty));
+        assert_eq!(
+            stack_spectra(&[input(&frequencies), input(&[1.0e9, 1.1e9])]),
+            Err(StackError::ChannelCountMismatch { index: 1 })
+        );
+        assert_eq!(
+            stack_spectra(&[input(&frequencies), input(&[1.0e9, 1.1e9, 1.3e9])]),
+            Err(StackError::FrequencyMismatch {
+                index: 1,
+                channel: 2
+            })
+        );
+    }
+
+    #[test]
+    fn flags_injected_spikes_but_not_a_broad_line() {
+        let noise = |i: usize| {
+            let f = i as f64;
+            (f * 0.713).sin() * 0.8 + (f * 1.317).cos() * 0.7 + (f * 0.209).sin() * 0.6
+        };
+        let mut amplitudes: Vec<f64> = (0..256)
+            .map(|i| {
+                100.0
+                    + 0.1 * i as f64
+                    + 10.0 * (-0.5 * ((i as f64 - 128.0) / 8.0).powi(2)).exp()
+                    + noise(i)
+            })
+            .collect();
+        amplitudes[40] += 60.0;
+        amplitudes[200] += 25.0;
+        amplitudes[201] += 30.0;
+
+        assert_eq!(flag_rfi(&amplitudes, DEFAULT_RFI_SIGMA), vec![40, 200, 201]);
+        assert!(flag_rfi(&vec![3.0; 64], DEFAULT_RFI_SIGMA).is_empty());
+    }
+}
@@ -1,14 +1,12 @@
-use std::sync::Arc;
-
-use rusqlite::{Connection, OptionalExtension};
-use tokio::sync::Mutex;
+use rusqlite::OptionalExtension;
 
+use crate::database::ConnectionPool;
 use crate::error::InternalError;
 
 pub async fn fetch_support_announcement(
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
 ) -> Result<Option<String>, InternalError> {
-    let conn = connection.lock().await;
+    let conn = connection.get().await;
     let message = conn
         .query_row(
             "SELECT message FROM support_announcement WHERE id = 1",
@@ -21,10 +19,10 @@ pub async fn fetch_support_announcement(
 }
 
 pub async fn set_support_announcement(
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
     message: Option<&str>,
 ) -> Result<(), InternalError> {
-    let conn = connection.lock().await;
+    let conn = connection.get().await;
     match message {
         Some(text) if !text.trim().is_empty() => {
             conn.execute(
@@ -1,30 +1,54 @@
 use crate::coords::{Direction, Location};
 use crate::models::telescope_types::{
-    CalibrationResult, IqBlock, ObservedSpectra, ReceiverConfiguration, ReceiverError,
-    TelescopeDefinition, TelescopeError, TelescopeInfo, TelescopeTarget, TelescopeType,
-    TelescopesConfig,
+    CalibrationResult, IqBlock, ObservedSpectra, PositionUpdate, ReceiverConfiguration,
+    ReceiverError, TelescopeDefinition, TelescopeError, TelescopeInfo, TelescopeTarget,
+    TelescopeType, TelescopesConfig,
 };
 
 use crate::models::fake_telescope;
 use crate::models::salsa_telescope;
+use crate::telescope_controller::ControllerSettings;
 use crate::tle_cache::TleCacheHandle;
 use async_trait::async_trait;
-use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock, broadcast};
 use tracing::info;
 
 #[async_trait]
 pub trait Telescope: Send + Sync {
+    /// Track `target`, with the offsets added to its computed direction.
+    /// A new target starts a new observation, but the receiver is left
+    /// alone: callers stop and save a running integration once the target
+    /// is accepted (see `routes::observe::retarget`).
     async fn set_target(
         &self,
         target: TelescopeTarget,
         az_offset_rad: f64,
         el_offset_rad: f64,
     ) -> Result<TelescopeTarget, TelescopeError>;
+    /// Like `set_target`, but the running integration is meant to carry
+    /// on, and the spectra accumulated so far are kept.
+    /// The receiver keeps averaging while the antenna moves and while it
+    /// is on the new direction, so this is for deliberate schemes such as
+    /// moving off-source and back, where the caller accounts for that.
+    async fn repoint(
+        &self,
+        target: TelescopeTarget,
+        az_offset_rad: f64,
+        el_offset_rad: f64,
+    ) -> Result<TelescopeTarget, TelescopeError>;
     async fn stop(&self) -> Result<(), TelescopeError>;
+    /// Drive to the configured stow position and stop there, after which
+    /// the status is `Stowed` until another target is set. A telescope
+    /// without a stow position is stopped where it is. Either way the
+    /// running integration ends and its spectra are dropped; callers that
+    /// want it save it first.
+    async fn stow(&self) -> Result<(), TelescopeError>;
     /// Correct a measured pointing offset by rewriting the rotor
     /// controller's stored current position (without moving the rotor).
     /// The offsets are the observing offsets at which the peak of a strong
@@ -47,6 +71,10 @@ pub trait Telescope: Send + Sync {
     /// of this telescope does not see the previous user's data on the live
     /// page. Safe to call when no integration is active; idempotent.
     async fn clear_measurements(&self);
+    /// The averaged spectrum of the integration in progress, without
+    /// building a whole `TelescopeInfo`. `None` while not integrating or
+    /// before the first spectrum is in.
+    async fn latest_spectrum(&self) -> Option<ObservedSpectra>;
     /// Whether this telescope can participate in interferometry sessions.
     /// Real (Salsa) telescopes need an external 10 MHz / PPS reference (GPSDO
     /// enabled) so that the two N210s' sample clocks align — without it the
@@ -59,6 +87,17 @@ pub trait Telescope: Send + Sync {
     /// has already been stopped (manually or by another mechanism).
     async fn current_integration_token(&self) -> Option<tokio_util::sync::CancellationToken>;
     async fn get_info(&self) -> Result<TelescopeInfo, TelescopeError>;
+    /// Recent errors with the time they occurred, oldest first. Unlike
+    /// `TelescopeInfo::most_recent_error` this keeps transient failures
+    /// around after the telescope has recovered.
+    async fn error_history(&self) -> Vec<(DateTime<Utc>, TelescopeError)>;
+    /// Receive a `PositionUpdate` on every position update (about 1 Hz).
+    /// The stream ends when the telescope shuts down; `None` if it already
+    /// has.
+    async fn subscribe_position(&self) -> Option<broadcast::Receiver<PositionUpdate>>;
+    /// Stop background tasks and any running integration or IQ stream, and
+    /// end position streams. Called once on teardown; the telescope is not
+    /// expected to be used afterwards.
     async fn shutdown(&self);
     /// Start streaming raw IQ blocks for interferometry correlation.
     /// Each block carries a timestamp (seconds, relative to USRP time zero for
@@ -71,7 +110,14 @@ pub trait Telescope: Send + Sync {
     ) -> Result<tokio::sync::mpsc::Receiver<IqBlock>, ReceiverError>;
 }
 
-type TelescopeCollection = Arc<RwLock<HashMap<String, Arc<dyn Telescope>>>>;
+/// A running telescope and the definition it was created from, kept so a
+/// reload can tell whether the definition changed.
+struct TelescopeEntry {
+    definition: TelescopeDefinition,
+    telescope: Arc<dyn Telescope>,
+}
+
+type TelescopeCollection = Arc<RwLock<HashMap<String, TelescopeEntry>>>;
 
 // Hide all synchronization for handling telescopes inside this type. Exposes an
 // async api without any client-visible locks for managing the collection of
@@ -79,17 +125,72 @@ type TelescopeCollection = Arc<RwLock<HashMap<String, Arc<dyn Telescope>>>>;
 #[derive(Clone)]
 pub struct TelescopeCollectionHandle {
     telescopes: TelescopeCollection,
+    config_filepath: PathBuf,
+    tle_cache: TleCacheHandle,
+    /// Held through a reload, which drops the map lock while telescopes
+    /// are shut down.
+    reloading: Arc<Mutex<()>>,
+}
+
+/// Why the telescope config could not be loaded. On a reload the running
+/// telescopes are left as they were.
+#[derive(Debug)]
+pub enum TelescopeConfigError {
+    Read(std::io::Error),
+    Parse(toml::de::Error),
+    /// Every problem found in the definitions, one per entry.
+    Invalid(Vec<String>),
+}
+
+impl std::fmt::Display for TelescopeConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TelescopeConfigError::Read(err) => write!(f, "Failed to read telescope config: {err}"),
+            TelescopeConfigError::Parse(err) => write!(f, "Invalid telescope config: {err}"),
+            TelescopeConfigError::Invalid(problems) => {
+                write!(f, "Invalid telescope config: {}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for TelescopeConfigError {}
+
+/// Names of the telescopes a reload touched, each list sorted.
+#[derive(Debug, Default, PartialEq)]
+pub struct TelescopeReload {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Definition changed: shut down and created anew.
+    pub replaced: Vec<String>,
+    pub unchanged: Vec<String>,
 }
 
 impl TelescopeCollectionHandle {
     pub async fn get(&self, id: &str) -> Option<Arc<dyn Telescope>> {
         let telescopes = self.telescopes.read().await;
-        telescopes.get(id).cloned()
+        telescopes.get(id).map(|entry| entry.telescope.clone())
     }
 
     pub async fn get_all(&self) -> Vec<Arc<dyn Telescope>> {
         let telescopes = self.telescopes.read().await;
-        telescopes.values().cloned().collect()
+        telescopes
+            .values()
+            .map(|entry| entry.telescope.clone())
+            .collect()
+    }
+
+    /// Where telescope `id` is, from its definition, so it is known even
+    /// while the telescope can't be reached.
+    pub async fn location(&self, id: &str) -> Option<Location> {
+        let telescopes = self.telescopes.read().await;
+        telescopes.get(id).map(|entry| {
+            let [longitude, latitude] = entry.definition.location;
+            Location {
+                longitude: longitude.to_radians(),
+                latitude: latitude.to_radians(),
+            }
+        })
     }
 
     pub async fn contains_key(&self, id: &str) -> bool {
@@ -97,12 +198,221 @@ impl TelescopeCollectionHandle {
         telescopes.contains_key(id)
     }
 
+    /// Shut down every telescope, waiting for their background tasks.
+    pub async fn shutdown_all(&self) {
+        for telescope in self.get_all().await {
+            telescope.shutdown().await;
+        }
+    }
+
     pub async fn get_names(&self) -> Vec<String> {
         let telescopes = self.telescopes.read().await;
         let mut res: Vec<_> = telescopes.keys().cloned().collect();
         res.sort();
         res
     }
+
+    /// Re-read the config file and bring the running telescopes in line
+    /// with it: create new ones, shut down removed ones, and replace those
+    /// whose definition changed. Unchanged telescopes keep running
+    /// untouched, integrations included.
+    ///
+    /// `before_shutdown` is called with each telescope about to be shut
+    /// down, to save what it was doing. A replaced telescope is shut down
+    /// before its successor is created, so the two never drive the same
+    /// hardware at once.
+    pub async fn reload<F, Fut>(
+        &self,
+        before_shutdown: F,
+    ) -> Result<TelescopeReload, TelescopeConfigError>
+    where
+        F: Fn(String, Arc<dyn Telescope>) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let _reloading = self.reloading.lock().await;
+        let config_str =
+            fs::read_to_string(&self.config_filepath).map_err(TelescopeConfigError::Read)?;
+        let config = parse_config(&config_str)?;
+        let mut definitions: HashMap<_, _> = config
+            .telescopes
+            .into_iter()
+            .map(|definition| (definition.name.clone(), definition))
+            .collect();
+
+        let mut reload = TelescopeReload::default();
+        let mut retired = Vec::new();
+        let mut created = Vec::new();
+        {
+            let mut telescopes = self.telescopes.write().await;
+            let names: Vec<String> = telescopes.keys().cloned().collect();
+            for name in names {
+                match definitions.remove(&name) {
+                    Some(definition) if definition == telescopes[&name].definition => {
+                        reload.unchanged.push(name);
+                    }
+                    changed => {
+                        let entry = telescopes
+                            .remove(&name)
+                            .expect("name was taken from the map");
+                        retired.push((name.clone(), entry.telescope));
+                        match changed {
+                            Some(definition) => {
+                                created.push(definition);
+                                reload.replaced.push(name);
+                            }
+                            None => reload.removed.push(name),
+                        }
+                    }
+                }
+            }
+            for (name, definition) in definitions {
+                created.push(definition);
+                reload.added.push(name);
+            }
+        }
+        // Outside the lock: saving and shutting down wait on the telescopes.
+        for (name, telescope) in retired {
+            before_shutdown(name, telescope.clone()).await;
+            telescope.shutdown().await;
+        }
+        {
+            let mut telescopes = self.telescopes.write().await;
+            for definition in created {
+                telescopes.insert(
+                    definition.name.clone(),
+                    create_entry(definition, &self.tle_cache),
+                );
+            }
+        }
+        for names in [
+            &mut reload.added,
+            &mut reload.removed,
+            &mut reload.replaced,
+            &mut reload.unchanged,
+        ] {
+            names.sort();
+        }
+        info!(
+            "Reloaded telescopes: added {:?}, removed {:?}, replaced {:?}",
+            reload.added, reload.removed, reload.replaced
+        );
+        Ok(reload)
+    }
+}
+
+fn parse_config(config_str: &str) -> Result<TelescopesConfig, TelescopeConfigError> {
+    let config: TelescopesConfig =
+        toml::from_str(config_str).map_err(TelescopeConfigError::Parse)?;
+    let problems = config_problems(&config, dry_run_allowed());
+    if problems.is_empty() {
+        Ok(config)
+    } else {
+        Err(TelescopeConfigError::Invalid(problems))
+    }
+}
+
+/// Environment variable that must be set for `dry_run` telescopes to be
+/// accepted.
+pub const ALLOW_DRY_RUN_ENV: &str = "SALSA_ALLOW_DRY_RUN";
+
+fn dry_run_allowed() -> bool {
+    std::env::var(ALLOW_DRY_RUN_ENV).is_ok_and(|value| {
+        matches!(
+            value.to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes" | "on"
+        )
+    })
+}
+
+/// What is wrong with the definitions in `config`, checked before any
+/// telescope is created so a bad entry can't take down a running one.
+fn config_problems(config: &TelescopesConfig, allow_dry_run: bool) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    for def in &config.telescopes {
+        let name = &def.name;
+        if !seen.insert(name) {
+            problems.push(format!("telescope {name:?} is defined more than once"));
+        }
+        if def.dry_run && def.telescope_type != TelescopeType::Salsa {
+            problems.push(format!(
+                "telescope {name:?} has dry_run set, which only applies to Salsa telescopes"
+            ));
+        }
+        if def.dry_run && !allow_dry_run {
+            problems.push(format!(
+                "telescope {name:?} has dry_run set, which needs {ALLOW_DRY_RUN_ENV}=1 in the \
+                 environment"
+            ));
+        }
+        if def.telescope_type == TelescopeType::Salsa {
+            if def.controller_address.is_none() {
+                problems.push(format!("telescope {name:?} has no controller_address"));
+            }
+            if def.receiver_address.is_none() {
+                problems.push(format!("telescope {name:?} has no receiver_address"));
+            }
+        }
+        if def.controller_timeout_ms == 0 {
+            problems.push(format!(
+                "telescope {name:?} has controller_timeout_ms 0, which would never wait"
+            ));
+        }
+        if !(def.max_integration_secs.is_finite() && def.max_integration_secs > 0.0) {
+            problems.push(format!(
+                "telescope {name:?} has max_integration_secs {}, expected a positive number \
+                 of seconds",
+                def.max_integration_secs
+            ));
+        }
+        let [longitude, latitude] = def.location;
+        if !(-180.0..=180.0).contains(&longitude) || !(-90.0..=90.0).contains(&latitude) {
+            problems.push(format!(
+                "telescope {name:?} has location {:?}, expected [longitude, latitude] in degrees",
+                def.location
+            ));
+        }
+        // Mount elevations: a dish that can tip over the top reaches 180.
+        let elevation_range = -90.0..=180.0;
+        if !elevation_range.contains(&def.min_elevation)
+            || !elevation_range.contains(&def.max_elevation)
+            || def.min_elevation >= def.max_elevation
+        {
+            problems.push(format!(
+                "telescope {name:?} has min_elevation {} and max_elevation {}, expected \
+                 -90 <= min < max <= 180",
+                def.min_elevation, def.max_elevation
+            ));
+        }
+        for (field, position) in [
+            ("stow_position", def.stow_position),
+            ("service_position", def.service_position),
+        ] {
+            if let Some([_, elevation]) = position
+                && !elevation_range.contains(&elevation)
+            {
+                problems.push(format!(
+                    "telescope {name:?} has {field} elevation {elevation}, outside -90..180"
+                ));
+            }
+        }
+        if let Some([min, max]) = def.azimuth_range
+            && max - min < 360.0
+        {
+            problems.push(format!(
+                "telescope {name:?} has azimuth_range [{min}, {max}], which should span at \
+                 least 360°"
+            ));
+        }
+    }
+    problems
+}
+
+fn create_entry(definition: TelescopeDefinition, tle_cache: &TleCacheHandle) -> TelescopeEntry {
+    TelescopeEntry {
+        telescope: create_telescope(definition.clone(), tle_cache.clone()),
+        definition,
+    }
 }
 
 fn create_telescope(def: TelescopeDefinition, tle_cache: TleCacheHandle) -> Arc<dyn Telescope> {
@@ -121,29 +431,43 @@ fn create_telescope(def: TelescopeDefinition, tle_cache: TleCacheHandle) -> Arc<
     });
     let min_elevation_rad = def.min_elevation.to_radians();
     let max_elevation_rad = def.max_elevation.to_radians();
+    let keyhole_elevation_rad = def.keyhole_elevation.map(f64::to_radians);
+    let azimuth_range_rad = def
+        .azimuth_range
+        .map(|[min, max]| (min.to_radians(), max.to_radians()));
     let default_ref_freq_hz = def.default_ref_freq_mhz * 1e6;
     let default_gain_db = def.default_gain_db;
+    let rest_freq_hz = def.rest_freq_mhz * 1e6;
     let tsys_k = def.tsys_k;
+    let apply_refraction = def.apply_refraction();
     match def.telescope_type {
         TelescopeType::Salsa => Arc::new(salsa_telescope::create(
             def.name.clone(),
-            def.controller_address
-                .expect("Telescope of type Salsa should have controller_address.")
-                .clone(),
+            ControllerSettings {
+                address: def
+                    .controller_address
+                    .expect("config_problems requires a controller_address"),
+                io_timeout: Duration::from_millis(def.controller_timeout_ms),
+                dry_run: def.dry_run,
+            },
             def.receiver_address
-                .expect("Telescope of type Salsa should have receiver_address.")
-                .clone(),
+                .expect("config_problems requires a receiver_address"),
             def.gpsdo_enabled,
             stow_position,
             service_position,
             location,
             min_elevation_rad,
             max_elevation_rad,
+            keyhole_elevation_rad,
+            azimuth_range_rad,
             def.webcam_crop,
             default_ref_freq_hz,
             default_gain_db,
+            rest_freq_hz,
             tsys_k,
             def.wind_warning_ms,
+            def.max_integration_secs,
+            apply_refraction,
             tle_cache,
         )),
         TelescopeType::Fake => Arc::new(fake_telescope::create(
@@ -153,33 +477,251 @@ fn create_telescope(def: TelescopeDefinition, tle_cache: TleCacheHandle) -> Arc<
             location,
             min_elevation_rad,
             max_elevation_rad,
+            keyhole_elevation_rad,
             def.webcam_crop,
             default_ref_freq_hz,
             default_gain_db,
+            rest_freq_hz,
+            def.max_integration_secs,
+            apply_refraction,
             tle_cache,
         )),
     }
 }
 
+/// Create the telescopes defined in the config at `config_filepath`. A
+/// missing file means no telescopes.
 pub fn create_telescope_collection(
     config_filepath: impl Into<PathBuf>,
     tle_cache: TleCacheHandle,
-) -> TelescopeCollectionHandle {
-    let config: TelescopesConfig =
-        toml::from_str(&fs::read_to_string(config_filepath.into()).unwrap_or_default())
-            .expect("telescope config file should be valid toml.");
+) -> Result<TelescopeCollectionHandle, TelescopeConfigError> {
+    let config_filepath = config_filepath.into();
+    let config_str = match fs::read_to_string(&config_filepath) {
+        Ok(config_str) => config_str,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(TelescopeConfigError::Read(err)),
+    };
+    let config = parse_config(&config_str)?;
     let telescopes: HashMap<_, _> = config
         .telescopes
         .into_iter()
-        .map(|telescope_definition| {
+        .map(|definition| {
             (
-                telescope_definition.name.clone(),
-                create_telescope(telescope_definition, tle_cache.clone()),
+                definition.name.clone(),
+                create_entry(definition, &tle_cache),
             )
         })
         .collect();
 
-    TelescopeCollectionHandle {
+    Ok(TelescopeCollectionHandle {
         telescopes: Arc::new(RwLock::new(telescopes)),
+        config_filepath,
+        tle_cache,
+        reloading: Arc::new(Mutex::new(())),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn fake_direction_through_the_collection_until_shutdown() {
+        let telescopes =
+            create_telescope_collection("tests/test_config/config.toml", TleCacheHandle::new())
+                .unwrap();
+        let telescope = telescopes.get("fake1").await.unwrap();
+        let info = telescope.get_info().await.unwrap();
+        assert!(info.current_horizontal.is_some());
+
+        telescopes.shutdown_all().await;
+        assert_eq!(
+            telescope.get_info().await,
+            Err(TelescopeError::TelescopeNotConnected)
+        );
+    }
+
+    #[test]
+    fn min_elevation_defaults_to_five_degrees() {
+        let config: TelescopesConfig = toml::from_str(
+            "[[telescopes]]\nname = \"fake\"\nlocation = [11.9, 57.4]\n\
+             telescope_type = \"Fake\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.telescopes[0].min_elevation, 5.0);
+    }
+
+    #[tokio::test]
+    async fn reload_applies_only_the_differences() {
+        let fake = |name: &str, min_elevation: f64| {
+            format!(
+                "[[telescopes]]\nname = \"{name}\"\nlocation = [11.9, 57.4]\n\
+                 min_elevation = {min_elevation}\ntelescope_type = \"Fake\"\n"
+            )
+        };
+        let config = tempfile::NamedTempFile::new().unwrap();
+        fs::write(
+            config.path(),
+            [
+                fake("kept", 5.0),
+                fake("retuned", 5.0),
+                fake("dropped", 5.0),
+            ]
+            .concat(),
+        )
+        .unwrap();
+        let telescopes = create_telescope_collection(config.path(), TleCacheHandle::new()).unwrap();
+        let kept = telescopes.get("kept").await.unwrap();
+        let retuned = telescopes.get("retuned").await.unwrap();
+        let dropped = telescopes.get("dropped").await.unwrap();
+
+        fs::write(
+            config.path(),
+            [fake("kept", 5.0), fake("retuned", 10.0), fake("new", 5.0)].concat(),
+        )
+        .unwrap();
+        let saved = &Mutex::new(Vec::new());
+        let reloaded = telescopes
+            .reload(|name, telescope| async move {
+                // Still running when it is handed over to be saved.
+                assert!(telescope.get_info().await.is_ok());
+                saved.lock().await.push(name);
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            reloaded,
+            TelescopeReload {
+                added: vec!["new".to_string()],
+                removed: vec!["dropped".to_string()],
+                replaced: vec!["retuned".to_string()],
+                unchanged: vec!["kept".to_string()],
+            }
+        );
+        saved.lock().await.sort();
+        assert_eq!(*saved.lock().await, ["dropped", "retuned"]);
+        assert_eq!(telescopes.get_names().await, ["kept", "new", "retuned"]);
+        assert!(Arc::ptr_eq(&telescopes.get("kept").await.unwrap(), &kept));
+        assert!(kept.get_info().await.is_ok());
+        let info = telescopes.get("retuned").await.unwrap().get_info().await;
+        assert!((info.unwrap().min_elevation_rad - 10f64.to_radians()).abs() < 1e-12);
+        assert!(retuned.get_info().await.is_err());
+        assert!(dropped.get_info().await.is_err());
+
+        fs::write(config.path(), "[[telescopes]]\nname = ").unwrap();
+        assert!(matches!(
+            telescopes.reload(|_, _| async {}).await,
+            Err(TelescopeConfigError::Parse(_))
+        ));
+        assert_eq!(telescopes.get_names().await, ["kept", "new", "retuned"]);
+        telescopes.shutdown_all().await;
+    }
+
+    fn problems(config: &str) -> Vec<String> {
+        match parse_config(config) {
+            Ok(_) => Vec::new(),
+            Err(TelescopeConfigError::Invalid(problems)) => problems,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    const FAKE: &str = "[[telescopes]]\nname = \"fake\"\nlocation = [11.9, 57.4]\n\
+                        telescope_type = \"Fake\"\n";
+
+    #[test]
+    fn a_valid_config_has_no_problems() {
+        assert!(problems(FAKE).is_empty());
+        assert!(problems("").is_empty());
+    }
+
+    #[test]
+    fn salsa_telescopes_need_both_addresses() {
+        let problems = problems(
+            "[[telescopes]]\nname = \"dish\"\nlocation = [11.9, 57.4]\n\
+             telescope_type = \"Salsa\"\ncontroller_address = \"10.0.0.1:23\"\n",
+        );
+        assert_eq!(problems, ["telescope \"dish\" has no receiver_address"]);
+    }
+
+    #[test]
+    fn names_must_be_unique() {
+        let problems = problems(&format!("{FAKE}{FAKE}"));
+        assert_eq!(problems, ["telescope \"fake\" is defined more than once"]);
+    }
+
+    #[test]
+    fn location_must_be_longitude_then_latitude() {
+        let problems = problems(&FAKE.replace("[11.9, 57.4]", "[11.9, 157.4]"));
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("location"), "{problems:?}");
+    }
+
+    #[test]
+    fn max_integration_time_must_be_positive() {
+        assert!(problems(&format!("{FAKE}max_integration_secs = 600.0\n")).is_empty());
+        for bad in ["0.0", "-60.0", "nan", "inf"] {
+            assert_eq!(
+                problems(&format!("{FAKE}max_integration_secs = {bad}\n")).len(),
+                1,
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn elevation_limits_must_be_ordered_and_in_range() {
+        assert_eq!(
+            problems(&format!(
+                "{FAKE}min_elevation = 30.0\nmax_elevation = 20.0\n"
+            ))
+            .len(),
+            1
+        );
+        assert_eq!(problems(&format!("{FAKE}max_elevation = 185.0\n")).len(), 1);
+        assert_eq!(
+            problems(&format!("{FAKE}stow_position = [0.0, -100.0]\n")),
+            ["telescope \"fake\" has stow_position elevation -100, outside -90..180"]
+        );
+    }
+
+    #[test]
+    fn azimuth_range_must_cover_a_full_turn() {
+        assert!(problems(&format!("{FAKE}azimuth_range = [-90.0, 450.0]\n")).is_empty());
+        assert_eq!(
+            problems(&format!("{FAKE}azimuth_range = [0.0, 300.0]\n")).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        let config = format!(
+            "{}{}",
+            FAKE.replace("[11.9, 57.4]", "[200.0, 57.4]"),
+            "[[telescopes]]\nname = \"dish\"\nlocation = [11.9, 57.4]\n\
+             telescope_type = \"Salsa\"\n"
+        );
+        assert_eq!(problems(&config).len(), 3);
+    }
+
+    #[test]
+    fn dry_run_needs_to_be_allowed_explicitly() {
+        let config: TelescopesConfig = toml::from_str(
+            "[[telescopes]]\nname = \"dish\"\nlocation = [11.9, 57.4]\n\
+             telescope_type = \"Salsa\"\ncontroller_address = \"10.0.0.1:23\"\n\
+             receiver_address = \"10.0.0.2\"\ndry_run = true\n",
+        )
+        .unwrap();
+        assert!(config_problems(&config, true).is_empty());
+        assert_eq!(
+            config_problems(&config, false),
+            [
+                "telescope \"dish\" has dry_run set, which needs SALSA_ALLOW_DRY_RUN=1 in the \
+              environment"
+            ]
+        );
+
+        let fake: TelescopesConfig = toml::from_str(&format!("{FAKE}dry_run = true\n")).unwrap();
+        assert_eq!(config_problems(&fake, true).len(), 1);
     }
 }
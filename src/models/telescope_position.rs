@@ -0,0 +1,172 @@
+//! Where each telescope pointed over time, sampled by
+//! `position_history`, for plotting the dish's path and debugging
+//! tracking.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::database::ConnectionPool;
+use crate::error::InternalError;
+use crate::models::telescope_types::TelescopeStatus;
+
+/// Most samples returned by one `fetch_range`, about a day at the
+/// sampling rate.
+pub const MAX_SAMPLES: i64 = 20_000;
+
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct PositionSample {
+    pub timestamp: DateTime<Utc>,
+    pub azimuth_deg: f64,
+    pub elevation_deg: f64,
+    pub status: TelescopeStatus,
+}
+
+fn status_name(status: TelescopeStatus) -> &'static str {
+    match status {
+        TelescopeStatus::Idle => "idle",
+        TelescopeStatus::Slewing => "slewing",
+        TelescopeStatus::Tracking => "tracking",
+        TelescopeStatus::Stowed => "stowed",
+        TelescopeStatus::Parked => "parked",
+    }
+}
+
+fn parse_status(name: &str) -> Option<TelescopeStatus> {
+    match name {
+        "idle" => Some(TelescopeStatus::Idle),
+        "slewing" => Some(TelescopeStatus::Slewing),
+        "tracking" => Some(TelescopeStatus::Tracking),
+        "stowed" => Some(TelescopeStatus::Stowed),
+        "parked" => Some(TelescopeStatus::Parked),
+        _ => None,
+    }
+}
+
+pub async fn record(
+    connection: ConnectionPool,
+    telescope_id: &str,
+    sample: &PositionSample,
+) -> Result<(), InternalError> {
+    let conn = connection.get().await;
+    conn.execute(
+        "INSERT INTO telescope_position (telescope_id, timestamp, azimuth_deg, elevation_deg, status) \
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        (
+            telescope_id,
+            sample.timestamp.timestamp(),
+            sample.azimuth_deg,
+            sample.elevation_deg,
+            status_name(sample.status),
+        ),
+    )
+    .map_err(|err| InternalError::new(format!("Failed to record position: {err}")))?;
+    Ok(())
+}
+
+/// `telescope_id`'s samples from `from` up to but not including `to`,
+/// oldest first, at most `MAX_SAMPLES` of them.
+pub async fn fetch_range(
+    connection: ConnectionPool,
+    telescope_id: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<PositionSample>, InternalError> {
+    let conn = connection.get().await;
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp, azimuth_deg, elevation_deg, status FROM telescope_position \
+             WHERE telescope_id = ?1 AND timestamp >= ?2 AND timestamp < ?3 \
+             ORDER BY timestamp ASC LIMIT ?4",
+        )
+        .map_err(|err| InternalError::new(format!("Failed to prepare statement: {err}")))?;
+    let rows = stmt
+        .query_map(
+            (telescope_id, from.timestamp(), to.timestamp(), MAX_SAMPLES),
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, f64>(1)?,
+                    row.get::<_, f64>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            },
+        )
+        .map_err(|err| InternalError::new(format!("Failed to query positions: {err}")))?;
+    let mut samples = Vec::new();
+    for row in rows {
+        let (timestamp, azimuth_deg, elevation_deg, status) =
+            row.map_err(|err| InternalError::new(format!("Failed to read row: {err}")))?;
+        let status = parse_status(&status)
+            .ok_or_else(|| InternalError::new(format!("Unknown telescope status {status:?}")))?;
+        samples.push(PositionSample {
+            timestamp: DateTime::from_timestamp(timestamp, 0).unwrap_or_default(),
+            azimuth_deg,
+            elevation_deg,
+            status,
+        });
+    }
+    Ok(samples)
+}
+
+/// Delete every telescope's samples from before `before`.
+pub async fn purge_older_than(
+    connection: ConnectionPool,
+    before: DateTime<Utc>,
+) -> Result<usize, InternalError> {
+    let conn = connection.get().await;
+    conn.execute(
+        "DELETE FROM telescope_position WHERE timestamp < ?1",
+        (before.timestamp(),),
+    )
+    .map_err(|err| InternalError::new(format!("Failed to purge positions: {err}")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::create_test_connection;
+
+    #[tokio::test]
+    async fn samples_are_fetched_by_telescope_and_time_until_purged() {
+        let connection = create_test_connection();
+        let at = |ts| DateTime::from_timestamp(ts, 0).unwrap();
+        let sample = |ts, status| PositionSample {
+            timestamp: at(ts),
+            azimuth_deg: 180.0,
+            elevation_deg: ts as f64,
+            status,
+        };
+        for (telescope, ts, status) in [
+            ("a", 10, TelescopeStatus::Slewing),
+            ("a", 20, TelescopeStatus::Tracking),
+            ("b", 20, TelescopeStatus::Idle),
+            ("a", 30, TelescopeStatus::Idle),
+        ] {
+            record(connection.clone(), telescope, &sample(ts, status))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            fetch_range(connection.clone(), "a", at(10), at(30))
+                .await
+                .unwrap(),
+            [
+                sample(10, TelescopeStatus::Slewing),
+                sample(20, TelescopeStatus::Tracking)
+            ]
+        );
+
+        assert_eq!(
+            purge_older_than(connection.clone(), at(20)).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            fetch_range(connection, "a", at(0), at(100))
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
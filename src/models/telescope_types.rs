@@ -1,14 +1,24 @@
-use crate::coords::{Direction, Location};
+use crate::coords::{
+    Direction, HI_REST_FREQ_HZ, J2000, Location, equatorial_from_horizontal,
+    galactic_from_equatorial, horizontal_from_equatorial, horizontal_from_galactic, precess,
+    vlsr_correction,
+};
 use chrono::{DateTime, offset::Utc};
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 use std::time::Duration;
 
+fn default_epoch() -> f64 {
+    J2000
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
 pub enum TelescopeTarget {
     Equatorial {
         right_ascension: f64, // in radians
         declination: f64,     // in radians
+        #[serde(default = "default_epoch")]
+        epoch: f64, // Julian epoch the coordinates refer to, normally J2000
     },
     Galactic {
         longitude: f64, // in radians
@@ -19,6 +29,7 @@ pub enum TelescopeTarget {
         elevation: f64, // in radians
     },
     Sun,
+    Moon,
     Satellite {
         norad_id: u64,
     },
@@ -29,6 +40,34 @@ pub enum TelescopeStatus {
     Idle,
     Slewing,
     Tracking,
+    /// Stopped at the stow position after `Telescope::stow`.
+    Stowed,
+    /// No target, and resting at the telescope's home position: the stow
+    /// position, or zenith for a fake telescope without one.
+    Parked,
+}
+
+/// How close to its home position an idle dish counts as `Parked`.
+pub const PARKED_TOLERANCE_DEG: f64 = 0.5;
+
+/// Progress of a `Telescope::stow`, kept by the implementations.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum StowPhase {
+    /// On the way to the stow position.
+    Driving,
+    /// Arrived and stopped; lasts until another target is set.
+    Stowed,
+}
+
+/// Updates a slow position subscriber may fall behind before it skips ahead.
+pub const POSITION_UPDATE_BUFFER: usize = 16;
+
+/// Sent to live-position subscribers after each position update.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
+pub struct PositionUpdate {
+    pub status: TelescopeStatus,
+    pub current_horizontal: Option<Direction>,
+    pub commanded_horizontal: Option<Direction>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -36,6 +75,20 @@ pub struct ObservedSpectra {
     pub frequencies: Vec<f64>,
     pub spectra: Vec<f64>,
     pub observation_time: Duration,
+    /// The slices of a drift scan, oldest first; empty for all other
+    /// integrations. Only filled in when the integration is stopped, and
+    /// not sent to the browser with the live spectrum.
+    #[serde(skip)]
+    pub scan: Vec<ScanSpectrum>,
+}
+
+/// The average over one cadence of a drift scan.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ScanSpectrum {
+    /// From the start of the integration to the start of this slice.
+    pub start_offset: Duration,
+    pub spectra: Vec<f64>,
+    pub observation_time: Duration,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -44,9 +97,20 @@ pub struct TelescopeInfo {
     pub status: TelescopeStatus,
     pub commanded_horizontal: Option<Direction>,
     pub current_horizontal: Option<Direction>,
+    /// While slewing, roughly how long until `commanded_horizontal` is reached.
+    pub estimated_seconds_to_target: Option<f64>,
+    /// Great-circle angle between `commanded_horizontal` and
+    /// `current_horizontal`.
+    pub pointing_error_degrees: Option<f64>,
     pub current_target: Option<TelescopeTarget>,
+    /// The latest error from either subsystem. `mount_status` and
+    /// `receiver_status` tell which one it came from.
     pub most_recent_error: Option<TelescopeError>,
     pub measurement_in_progress: bool,
+    /// How long the running integration has been going, counted from when
+    /// it started rather than from completed cycles. `None` when not
+    /// integrating.
+    pub integration_elapsed: Option<Duration>,
     pub latest_observation: Option<ObservedSpectra>,
     pub stow_position: Option<Direction>,
     pub service_position: Option<Direction>,
@@ -55,12 +119,83 @@ pub struct TelescopeInfo {
     pub location: Location,
     pub min_elevation_rad: f64,
     pub max_elevation_rad: f64,
+    /// [min, max] rotor azimuth the cable wrap allows, `None` for rotors
+    /// that turn freely.
+    pub azimuth_range_rad: Option<(f64, f64)>,
     pub webcam_crop: Option<[f64; 4]>, // [x, y, w, h] as fractions of image, top-left origin
-    pub receiver_connected: Option<bool>,
-    pub controller_connected: Option<bool>,
+    /// The rotor controller that points the dish.
+    pub mount_status: SubsystemStatus,
+    /// The spectrometer the observations are read from.
+    pub receiver_status: SubsystemStatus,
+    /// While the rotor controller is unreachable, seconds until the next
+    /// reconnection attempt.
+    pub controller_reconnect_in_secs: Option<f64>,
+    /// When the telescope's position was last successfully read.
+    pub last_successful_update: Option<DateTime<Utc>>,
     pub wind_warning_ms: Option<f64>, // warn if 10-min avg wind exceeds this (m/s)
+    /// Longest an integration may run before it is stopped and saved.
+    pub max_integration_secs: f64,
     pub default_ref_freq_mhz: f64,
     pub default_gain_db: f64,
+    pub rest_freq_mhz: f64,
+    pub receiver_calibration: ReceiverCalibration,
+    /// Set while a position-switched integration is running.
+    pub position_switch: Option<PositionSwitch>,
+    /// Whether the receiver can observe position-switched. Only the fake
+    /// telescope simulates it so far; the observe form hides the mode
+    /// elsewhere.
+    pub supports_position_switching: bool,
+    /// Set while a drift scan is running.
+    pub drift_scan: Option<DriftScan>,
+}
+
+/// What is needed to turn receiver output into antenna temperature for
+/// the current configuration. Stored with each observation.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
+pub struct ReceiverCalibration {
+    pub system_temperature_k: f64,
+    pub gain_db: f64,
+}
+
+/// A direction expressed in every coordinate system targets can be given
+/// in. Angles in degrees; equatorial coordinates are J2000.
+#[derive(Serialize, PartialEq, Debug, Copy, Clone)]
+pub struct PointingCoordinates {
+    pub azimuth_deg: f64,
+    pub elevation_deg: f64,
+    pub right_ascension_deg: f64,
+    pub declination_deg: f64,
+    pub galactic_longitude_deg: f64,
+    pub galactic_latitude_deg: f64,
+}
+
+impl PointingCoordinates {
+    pub fn from_horizontal(location: Location, when: DateTime<Utc>, direction: Direction) -> Self {
+        let (ra, dec) = equatorial_from_horizontal(location, when, direction);
+        let (l, b) = galactic_from_equatorial(ra, dec);
+        PointingCoordinates {
+            azimuth_deg: direction.azimuth.to_degrees(),
+            elevation_deg: direction.elevation.to_degrees(),
+            right_ascension_deg: ra.to_degrees(),
+            declination_deg: dec.to_degrees(),
+            galactic_longitude_deg: l.to_degrees(),
+            galactic_latitude_deg: b.to_degrees(),
+        }
+    }
+}
+
+impl TelescopeInfo {
+    /// Where the dish currently points, converted for the telescope's own
+    /// location at the time the position was read.
+    pub fn current_pointing(&self) -> Option<PointingCoordinates> {
+        let direction = self.current_horizontal?;
+        let when = self.last_successful_update.unwrap_or_else(Utc::now);
+        Some(PointingCoordinates::from_horizontal(
+            self.location,
+            when,
+            direction,
+        ))
+    }
 }
 
 #[derive(Deserialize, PartialEq, Debug, Clone)]
@@ -73,26 +208,62 @@ pub enum TelescopeType {
 pub struct TelescopeDefinition {
     pub name: String,
     pub location: [f64; 2], // [longitude, latitude] in degrees
-    pub min_elevation: f64, // in degrees
+    #[serde(default = "default_min_elevation")]
+    pub min_elevation: f64, // in degrees; below-horizon limit of the mount
     #[serde(default = "default_max_elevation")]
     pub max_elevation: f64, // in degrees
+    /// Sky elevation in degrees above which the azimuth axis can't keep up
+    /// with a moving target (the keyhole around zenith). Unset for mounts
+    /// that track through zenith.
+    #[serde(default)]
+    pub keyhole_elevation: Option<f64>,
+    /// [min, max] rotor azimuth in degrees the cable wrap allows, e.g.
+    /// [-90, 450]. Must span at least 360°. Unset for rotors that turn
+    /// freely.
+    #[serde(default)]
+    pub azimuth_range: Option<[f64; 2]>,
     #[serde(default)]
     pub webcam_crop: Option<[f64; 4]>, // [x, y, w, h] fractions of image, top-left origin
     pub stow_position: Option<[f64; 2]>, // [azimuth, elevation] in degrees
     pub service_position: Option<[f64; 2]>, // [azimuth, elevation] in degrees
     pub telescope_type: TelescopeType,
     pub controller_address: Option<String>,
+    /// Milliseconds to wait for the rotor controller to connect or answer.
+    #[serde(default = "default_controller_timeout_ms")]
+    pub controller_timeout_ms: u64,
     pub receiver_address: Option<String>,
     #[serde(default = "default_ref_freq_mhz")]
     pub default_ref_freq_mhz: f64, // default reference frequency in MHz (for freq-switched mode)
     #[serde(default = "default_gain_db")]
     pub default_gain_db: f64, // default receiver gain in dB
+    #[serde(default = "default_rest_freq_mhz")]
+    pub rest_freq_mhz: f64, // rest frequency of the observed line in MHz (for the velocity axis)
     #[serde(default = "default_tsys_k")]
     pub tsys_k: f64, // system temperature in K; scales switched-mode spectra to antenna temperature (Ta)
     #[serde(default)]
     pub wind_warning_ms: Option<f64>, // warn if 10-min avg wind exceeds this (m/s); omit to disable
+    #[serde(default = "default_max_integration_secs")]
+    pub max_integration_secs: f64, // integrations are stopped and saved after this long
     #[serde(default)]
     pub gpsdo_enabled: bool, // use external GPSDO for clock/PPS sync (USRP N210)
+    #[serde(default)]
+    pub apply_refraction: Option<bool>, // correct commanded elevation for refraction; defaults to on for real hardware
+    /// Salsa telescopes only: answer rotor controller commands with a
+    /// simulated rotor instead of connecting to the controller. Refused
+    /// unless `SALSA_ALLOW_DRY_RUN` is set, so a staging setting can't
+    /// slip into production.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl TelescopeDefinition {
+    /// Whether to lift commanded elevations by atmospheric refraction.
+    /// Real dishes look through the atmosphere; the fake one does not
+    /// unless asked to.
+    pub fn apply_refraction(&self) -> bool {
+        self.apply_refraction
+            .unwrap_or(self.telescope_type == TelescopeType::Salsa)
+    }
 }
 
 #[derive(Deserialize, PartialEq, Debug, Clone)]
@@ -103,13 +274,39 @@ pub struct TelescopesConfig {
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub enum TelescopeError {
-    TargetOutOfElevationRange { min_deg: f64, max_deg: f64 },
+    TargetOutOfElevationRange {
+        min_deg: f64,
+        max_deg: f64,
+    },
+    /// The target is above the keyhole elevation, too close to zenith to
+    /// track.
+    TargetInKeyhole {
+        max_deg: f64,
+    },
     TelescopeIOError(String),
     TelescopeNotConnected,
     ReceiverFailed(String),
     TelescopeBusy,
 }
 
+/// Link state of one part of a telescope. The mount and the receiver are
+/// reached separately, so either can be down while the other works.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct SubsystemStatus {
+    pub connected: bool,
+    /// The subsystem's latest error, cleared once it recovers.
+    pub error: Option<TelescopeError>,
+}
+
+impl SubsystemStatus {
+    pub fn healthy() -> SubsystemStatus {
+        SubsystemStatus {
+            connected: true,
+            error: None,
+        }
+    }
+}
+
 /// Outcome of a pointing calibration: the controller's reported position
 /// before the adjustment and the corrected position written to it.
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -118,12 +315,40 @@ pub struct CalibrationResult {
     pub adjusted: Direction,
 }
 
+impl TelescopeTarget {
+    /// LSR velocity correction in m/s for observing this target from
+    /// `location` at `when`. Only defined for targets on the celestial
+    /// sphere; the Sun and satellites have no meaningful LSR velocity.
+    pub fn vlsr_correction(&self, location: Location, when: DateTime<Utc>) -> Option<f64> {
+        let direction = match *self {
+            TelescopeTarget::Equatorial {
+                right_ascension,
+                declination,
+                epoch,
+            } => {
+                let (ra, dec) = precess(right_ascension, declination, epoch, J2000);
+                horizontal_from_equatorial(location, when, ra, dec)
+            }
+            TelescopeTarget::Galactic {
+                longitude,
+                latitude,
+            } => horizontal_from_galactic(location, when, longitude, latitude),
+            TelescopeTarget::Horizontal { azimuth, elevation } => Direction { azimuth, elevation },
+            TelescopeTarget::Sun | TelescopeTarget::Moon | TelescopeTarget::Satellite { .. } => {
+                return None;
+            }
+        };
+        Some(vlsr_correction(location, when, direction))
+    }
+}
+
 impl Display for TelescopeTarget {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             TelescopeTarget::Equatorial {
                 right_ascension,
                 declination,
+                ..
             } => write!(
                 f,
                 "Equatorial(ra={:.2}°, dec={:.2}°)",
@@ -146,6 +371,7 @@ impl Display for TelescopeTarget {
                 elevation.to_degrees()
             ),
             TelescopeTarget::Sun => write!(f, "Sun"),
+            TelescopeTarget::Moon => write!(f, "Moon"),
             TelescopeTarget::Satellite { norad_id } => {
                 write!(f, "Satellite(norad_id={norad_id})")
             }
@@ -159,6 +385,10 @@ impl Display for TelescopeError {
             TelescopeError::TargetOutOfElevationRange { min_deg, max_deg } => f.write_str(
                 &format!("Failed to set target, target is out of elevation range ({min_deg:.0}–{max_deg:.0}°).")
             ),
+            TelescopeError::TargetInKeyhole { max_deg } => write!(
+                f,
+                "Target is above {max_deg:.0}° elevation, too close to zenith to track."
+            ),
             TelescopeError::TelescopeIOError(message) => f.write_str(&format!(
                 "Error in communication with telescope: {}",
                 message
@@ -184,12 +414,21 @@ impl From<std::io::Error> for TelescopeError {
 #[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
 pub enum ReceiverError {
     IntegrationAlreadyRunning,
+    InvalidConfiguration,
+    /// The receiver cannot observe in the requested mode.
+    UnsupportedMode,
 }
 
 impl Display for ReceiverError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             ReceiverError::IntegrationAlreadyRunning => f.write_str("Integration already running"),
+            ReceiverError::InvalidConfiguration => {
+                f.write_str("Receiver channels, bandwidth or switching out of range")
+            }
+            ReceiverError::UnsupportedMode => {
+                f.write_str("Observation mode not supported by this receiver")
+            }
         }
     }
 }
@@ -200,12 +439,69 @@ pub enum ObservationMode {
     FreqSwitched,
     Raw,
     Interferometry,
+    /// Total power alternating between the target and an offset reference
+    /// position; the stored spectrum is on minus off.
+    PositionSwitched,
+}
+
+/// Where the reference ("off") position is relative to the target, and how
+/// long one full on/off cycle takes. Half of each cycle is spent on each
+/// position.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
+pub struct PositionSwitch {
+    pub az_offset_rad: f64,
+    pub el_offset_rad: f64,
+    pub cycle_secs: f64,
+}
+
+/// Limits on position switching. The offset has to clear the beam but stay
+/// close enough that the sky background is comparable.
+pub const POSITION_SWITCH_MAX_OFFSET_DEG: f64 = 30.0;
+pub const POSITION_SWITCH_CYCLE_SECS_RANGE: std::ops::RangeInclusive<f64> = 2.0..=600.0;
+
+impl PositionSwitch {
+    pub fn is_valid(&self) -> bool {
+        let max_offset = POSITION_SWITCH_MAX_OFFSET_DEG.to_radians();
+        let offset = self.az_offset_rad.hypot(self.el_offset_rad);
+        offset > 0.0
+            && self.az_offset_rad.abs() <= max_offset
+            && self.el_offset_rad.abs() <= max_offset
+            && POSITION_SWITCH_CYCLE_SECS_RANGE.contains(&self.cycle_secs)
+    }
+}
+
+/// Keep a fixed horizontal pointing and let the sky drift through the
+/// beam, closing off a spectrum every `cadence_secs` so the integration
+/// comes back as a time series as well as its average.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
+pub struct DriftScan {
+    pub cadence_secs: f64,
+}
+
+pub const DRIFT_SCAN_CADENCE_SECS_RANGE: std::ops::RangeInclusive<f64> = 2.0..=600.0;
+
+impl DriftScan {
+    pub fn is_valid(&self) -> bool {
+        DRIFT_SCAN_CADENCE_SECS_RANGE.contains(&self.cadence_secs)
+    }
+}
+
+fn default_controller_timeout_ms() -> u64 {
+    crate::telescope_controller::DEFAULT_CONTROLLER_IO_TIMEOUT.as_millis() as u64
+}
+
+fn default_min_elevation() -> f64 {
+    5.0
 }
 
 fn default_max_elevation() -> f64 {
     175.0
 }
 
+fn default_max_integration_secs() -> f64 {
+    3600.0
+}
+
 fn default_tsys_k() -> f64 {
     300.0
 }
@@ -230,6 +526,10 @@ fn default_gain_db() -> f64 {
     60.0
 }
 
+fn default_rest_freq_mhz() -> f64 {
+    HI_REST_FREQ_HZ / 1e6
+}
+
 fn default_spectral_channels() -> usize {
     512
 }
@@ -255,6 +555,48 @@ pub struct ReceiverConfiguration {
     pub spectral_channels: usize,
     #[serde(default = "default_rfi_filter")]
     pub rfi_filter: bool,
+    /// Required in, and only used by, [`ObservationMode::PositionSwitched`].
+    #[serde(default)]
+    pub position_switch: Option<PositionSwitch>,
+    /// Switch on the calibration noise source, which adds a known
+    /// temperature step for measuring the system temperature. Only the
+    /// fake receiver has one.
+    #[serde(default)]
+    pub cal_diode: bool,
+    /// Slice the integration into a time series. Not combined with
+    /// position switching, which doesn't keep the pointing fixed.
+    #[serde(default)]
+    pub drift_scan: Option<DriftScan>,
+}
+
+/// Limits on what a receiver can be asked for. The observe form offers a
+/// fixed set of values inside these; the receivers check the bounds so
+/// nothing else can ask for a zero-length or absurdly large FFT.
+pub const SPECTRAL_CHANNELS_RANGE: std::ops::RangeInclusive<usize> = 16..=8192;
+pub const BANDWIDTH_HZ_RANGE: std::ops::RangeInclusive<f64> = 0.25e6..=25e6;
+
+impl ReceiverConfiguration {
+    pub fn validate(&self) -> Result<(), ReceiverError> {
+        let switch_ok = match self.mode {
+            ObservationMode::PositionSwitched => {
+                self.position_switch.is_some_and(|switch| switch.is_valid())
+            }
+            _ => true,
+        };
+        let drift_ok = match self.drift_scan {
+            Some(scan) => scan.is_valid() && self.mode != ObservationMode::PositionSwitched,
+            None => true,
+        };
+        if SPECTRAL_CHANNELS_RANGE.contains(&self.spectral_channels)
+            && BANDWIDTH_HZ_RANGE.contains(&self.bandwidth_hz)
+            && switch_ok
+            && drift_ok
+        {
+            Ok(())
+        } else {
+            Err(ReceiverError::InvalidConfiguration)
+        }
+    }
 }
 
 impl Default for ReceiverConfiguration {
@@ -268,6 +610,9 @@ impl Default for ReceiverConfiguration {
             gain_db: default_gain_db(),
             spectral_channels: default_spectral_channels(),
             rfi_filter: default_rfi_filter(),
+            position_switch: None,
+            cal_diode: false,
+            drift_scan: None,
         }
     }
 }
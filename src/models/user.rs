@@ -1,12 +1,11 @@
-use std::sync::Arc;
-
 use argon2::{
     Argon2,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
-use rusqlite::{Connection, Error, OptionalExtension};
-use tokio::sync::Mutex;
+use chrono::{DateTime, Utc};
+use rusqlite::{Error, OptionalExtension};
 
+use crate::database::ConnectionPool;
 use crate::error::InternalError;
 use crate::i18n::Language;
 
@@ -23,6 +22,42 @@ pub struct User {
     /// Preferred UI language. `None` means no preference — the language
     /// cookie or Accept-Language header decides instead.
     pub language: Option<Language>,
+    /// Name the user chose for themselves, shown instead of `name` (the
+    /// one the provider gave at first login) when set.
+    pub display_name: Option<String>,
+}
+
+/// A user as listed on the admin users page.
+#[derive(Debug, Clone)]
+pub struct UserSummary {
+    pub id: i64,
+    pub name: String,
+    pub provider: String,
+    /// `None` for accounts created before this was recorded.
+    pub created_at: Option<DateTime<Utc>>,
+    /// Admin granted from the admin page; admins from `config.toml` are
+    /// not reflected here.
+    pub is_admin: bool,
+    pub disabled: bool,
+    pub booking_count: usize,
+    pub display_name: Option<String>,
+}
+
+/// Longest display name a user may choose, in characters.
+pub const MAX_DISPLAY_NAME_CHARS: usize = 64;
+
+/// Tidy a display name typed by the user: control characters are dropped
+/// and surrounding whitespace trimmed. `Ok(None)` for an empty name, which
+/// clears the override; `Err` if it's too long.
+pub fn clean_display_name(input: &str) -> Result<Option<String>, String> {
+    let cleaned: String = input.chars().filter(|c| !c.is_control()).collect();
+    let cleaned = cleaned.trim();
+    if cleaned.chars().count() > MAX_DISPLAY_NAME_CHARS {
+        return Err(format!(
+            "Display name is longer than {MAX_DISPLAY_NAME_CHARS} characters"
+        ));
+    }
+    Ok((!cleaned.is_empty()).then(|| cleaned.to_string()))
 }
 
 async fn hash_password(password: String) -> Result<String, InternalError> {
@@ -44,18 +79,40 @@ impl User {
         self.timezone.unwrap_or(chrono_tz::UTC)
     }
 
+    /// The name to show for the user: their own choice if they made one,
+    /// otherwise the provider's.
+    pub fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Set or, with `None`, clear the user's display name. Callers clean
+    /// it with [`clean_display_name`] first.
+    pub async fn set_display_name(
+        connection: ConnectionPool,
+        user_id: i64,
+        display_name: Option<&str>,
+    ) -> Result<(), InternalError> {
+        let conn = connection.get().await;
+        conn.execute(
+            "UPDATE user SET display_name = ?1 WHERE id = ?2",
+            (display_name, user_id),
+        )
+        .map_err(|e| InternalError::new(format!("Failed to update display name: {e}")))?;
+        Ok(())
+    }
+
     /// Persist the user's preferred timezone. The value is validated
     /// against the IANA database before storing so we never keep junk, and
     /// the canonical name is written back.
     pub async fn set_timezone(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
         timezone: &str,
     ) -> Result<(), InternalError> {
         let tz: chrono_tz::Tz = timezone
             .parse()
             .map_err(|_| InternalError::new(format!("Invalid timezone: {timezone}")))?;
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.execute(
             "UPDATE user SET timezone = ?1 WHERE id = ?2",
             (tz.name(), user_id),
@@ -68,13 +125,13 @@ impl User {
     /// against the supported languages before storing so we never keep
     /// junk, and the canonical code is written back.
     pub async fn set_language(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
         language: &str,
     ) -> Result<(), InternalError> {
         let language = Language::from_code(language)
             .ok_or_else(|| InternalError::new(format!("Unsupported language: {language}")))?;
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.execute(
             "UPDATE user SET language = ?1 WHERE id = ?2",
             (language.code(), user_id),
@@ -84,36 +141,44 @@ impl User {
     }
 
     pub async fn create_from_external(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         name: String,
         provider: String,
         external_id: &str,
     ) -> Result<User, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.execute(
-            "INSERT INTO user (username, provider, external_id) values ((?1), (?2), (?3))",
-            (&name, &provider, external_id),
+            "INSERT INTO user (username, provider, external_id, created_at) \
+             values ((?1), (?2), (?3), (?4))",
+            (&name, &provider, external_id, Utc::now().timestamp()),
         )
         .map_err(|err| InternalError::new(format!("Failed to insert user in db: {err}")))?;
+        let id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO user_identity (provider, external_id, user_id) VALUES (?1, ?2, ?3)",
+            (&provider, external_id, id),
+        )
+        .map_err(|err| InternalError::new(format!("Failed to insert identity in db: {err}")))?;
         Ok(User {
-            id: conn.last_insert_rowid(),
+            id,
             name,
             provider,
             is_admin: false,
             timezone: None,
             language: None,
+            display_name: None,
         })
     }
 
     pub async fn create_local(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         username: String,
         password: String,
         comment: String,
     ) -> Result<User, InternalError> {
         // Check username is not already taken by another local user.
         {
-            let conn = connection.lock().await;
+            let conn = connection.get().await;
             let exists: bool = conn
                 .query_row(
                     "SELECT COUNT(*) FROM user WHERE provider = 'local' AND username = ?1",
@@ -131,10 +196,11 @@ impl User {
 
         let hash = hash_password(password).await?;
 
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.execute(
-            "INSERT INTO user (username, provider, external_id) VALUES (?1, 'local', NULL)",
-            [&username],
+            "INSERT INTO user (username, provider, external_id, created_at) \
+             VALUES (?1, 'local', NULL, ?2)",
+            (&username, Utc::now().timestamp()),
         )
         .map_err(|e| InternalError::new(format!("Failed to insert user: {e}")))?;
         let user_id = conn.last_insert_rowid();
@@ -151,16 +217,17 @@ impl User {
             is_admin: false,
             timezone: None,
             language: None,
+            display_name: None,
         })
     }
 
     pub async fn fetch_local_with_password(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         username: &str,
         password: &str,
     ) -> Result<Option<User>, InternalError> {
         let row = {
-            let conn = connection.lock().await;
+            let conn = connection.get().await;
             conn.query_row(
                 "SELECT u.id, u.username, l.password_hash
                  FROM user u JOIN local_user l ON u.id = l.user_id
@@ -203,6 +270,7 @@ impl User {
                 is_admin: false,
                 timezone: None,
                 language: None,
+                display_name: None,
             }))
         } else {
             Ok(None)
@@ -210,9 +278,9 @@ impl User {
     }
 
     pub async fn fetch_all_local(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
     ) -> Result<Vec<(i64, String, String)>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let mut stmt = conn
             .prepare(
                 "SELECT u.id, u.username, l.comment
@@ -237,12 +305,12 @@ impl User {
     }
 
     pub async fn set_local_password(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
         new_password: String,
     ) -> Result<(), InternalError> {
         let hash = hash_password(new_password).await?;
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let updated = conn
             .execute(
                 "UPDATE local_user SET password_hash = ?1 WHERE user_id = ?2",
@@ -264,11 +332,11 @@ impl User {
     }
 
     pub async fn set_local_comment(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
         new_comment: String,
     ) -> Result<(), InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let updated = conn
             .execute(
                 "UPDATE local_user SET comment = ?1 WHERE user_id = ?2",
@@ -282,11 +350,11 @@ impl User {
     }
 
     pub async fn delete_local_by_id(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         user_id: i64,
     ) -> Result<(), InternalError> {
         let now = chrono::Utc::now().timestamp();
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let is_local: bool = conn
             .query_row(
                 "SELECT COUNT(*) FROM local_user WHERE user_id = ?1",
@@ -312,12 +380,21 @@ impl User {
         .map_err(|e| InternalError::new(format!("Failed to delete bookings: {e}")))?;
         conn.execute("DELETE FROM session WHERE user_id = ?1", [user_id])
             .map_err(|e| InternalError::new(format!("Failed to delete sessions: {e}")))?;
+        conn.execute("DELETE FROM user_token WHERE user_id = ?1", [user_id])
+            .map_err(|e| InternalError::new(format!("Failed to delete API token: {e}")))?;
+        conn.execute("DELETE FROM user_identity WHERE user_id = ?1", [user_id])
+            .map_err(|e| InternalError::new(format!("Failed to delete identities: {e}")))?;
+        conn.execute(
+            "DELETE FROM observation_queue WHERE user_id = ?1 AND status = 'queued'",
+            [user_id],
+        )
+        .map_err(|e| InternalError::new(format!("Failed to delete queued observations: {e}")))?;
         Ok(())
     }
 
-    pub async fn delete(self, connection: Arc<Mutex<Connection>>) -> Result<(), InternalError> {
+    pub async fn delete(self, connection: ConnectionPool) -> Result<(), InternalError> {
         let now = chrono::Utc::now().timestamp();
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         conn.execute(
             "UPDATE user SET username = 'Deleted account', provider = '', external_id = '' WHERE id = (?1)",
             (self.id,),
@@ -330,6 +407,17 @@ impl User {
         .map_err(|err| InternalError::new(format!("Failed to delete upcoming bookings: {err}")))?;
         conn.execute("DELETE FROM session WHERE user_id = (?1)", (self.id,))
             .map_err(|err| InternalError::new(format!("Failed to delete sessions: {err}")))?;
+        conn.execute("DELETE FROM user_token WHERE user_id = (?1)", (self.id,))
+            .map_err(|err| InternalError::new(format!("Failed to delete API token: {err}")))?;
+        conn.execute(
+            "DELETE FROM observation_queue WHERE user_id = (?1) AND status = 'queued'",
+            (self.id,),
+        )
+        .map_err(|err| {
+            InternalError::new(format!("Failed to delete queued observations: {err}"))
+        })?;
+        conn.execute("DELETE FROM user_identity WHERE user_id = (?1)", (self.id,))
+            .map_err(|err| InternalError::new(format!("Failed to delete identities: {err}")))?;
         Ok(())
     }
 
@@ -337,9 +425,9 @@ impl User {
     /// provider, ordered by count descending then provider name. Used by
     /// the admin page to show how many real accounts exist per provider.
     pub async fn count_by_provider_non_guest(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
     ) -> Result<Vec<(String, usize)>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let mut stmt = conn
             .prepare(
                 "SELECT provider, COUNT(*) FROM user
@@ -360,13 +448,93 @@ impl User {
         Ok(res)
     }
 
+    /// Every account for the admin users page, ordered by id. Guests and
+    /// deleted accounts are left out.
+    pub async fn fetch_all(connection: ConnectionPool) -> Result<Vec<UserSummary>, InternalError> {
+        let conn = connection.get().await;
+        let mut stmt = conn
+            .prepare(
+                "SELECT u.id, u.username, u.provider, u.created_at, u.is_admin, u.disabled,
+                        (SELECT COUNT(*) FROM booking b WHERE b.user_id = u.id), u.display_name
+                 FROM user u
+                 WHERE u.provider != 'guest' AND u.provider != ''
+                 ORDER BY u.id ASC",
+            )
+            .map_err(|err| InternalError::new(format!("Failed to prepare statement: {err}")))?;
+        let users = stmt
+            .query_map([], |row| {
+                Ok(UserSummary {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    provider: row.get(2)?,
+                    created_at: row
+                        .get::<_, Option<i64>>(3)?
+                        .and_then(|ts| DateTime::from_timestamp(ts, 0)),
+                    is_admin: row.get(4)?,
+                    disabled: row.get(5)?,
+                    booking_count: row.get::<_, i64>(6)? as usize,
+                    display_name: row.get(7)?,
+                })
+            })
+            .map_err(|err| InternalError::new(format!("Failed to query users: {err}")))?;
+        let mut res = Vec::new();
+        for user in users {
+            res.push(user.map_err(|err| InternalError::new(format!("Failed to map row: {err}")))?);
+        }
+        Ok(res)
+    }
+
+    /// Whether `user_id` was made admin from the admin page, and whether
+    /// the account is disabled. `None` if there is no such user.
+    pub async fn fetch_status(
+        connection: ConnectionPool,
+        user_id: i64,
+    ) -> Result<Option<(bool, bool)>, InternalError> {
+        let conn = connection.get().await;
+        conn.query_row(
+            "SELECT is_admin, disabled FROM user WHERE id = ?1",
+            [user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|err| InternalError::new(format!("Failed to query user status: {err}")))
+    }
+
+    pub async fn set_admin(
+        connection: ConnectionPool,
+        user_id: i64,
+        is_admin: bool,
+    ) -> Result<(), InternalError> {
+        let conn = connection.get().await;
+        conn.execute(
+            "UPDATE user SET is_admin = ?1 WHERE id = ?2",
+            (is_admin, user_id),
+        )
+        .map_err(|err| InternalError::new(format!("Failed to update admin flag: {err}")))?;
+        Ok(())
+    }
+
+    pub async fn set_disabled(
+        connection: ConnectionPool,
+        user_id: i64,
+        disabled: bool,
+    ) -> Result<(), InternalError> {
+        let conn = connection.get().await;
+        conn.execute(
+            "UPDATE user SET disabled = ?1 WHERE id = ?2",
+            (disabled, user_id),
+        )
+        .map_err(|err| InternalError::new(format!("Failed to update disabled flag: {err}")))?;
+        Ok(())
+    }
+
     /// Every non-guest user, ordered by id. Guests are synthetic per-session
     /// rows that hold no bookings or observations, so they would only add
     /// noise to admin filter dropdowns.
     pub async fn fetch_all_non_guest(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
     ) -> Result<Vec<User>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         let mut stmt = conn
             .prepare(
                 "SELECT id, username, provider FROM user
@@ -382,6 +550,7 @@ impl User {
                     is_admin: false,
                     timezone: None,
                     language: None,
+                    display_name: None,
                 })
             })
             .map_err(|err| InternalError::new(format!("Failed to query users: {err}")))?;
@@ -392,31 +561,38 @@ impl User {
         Ok(res)
     }
 
+    /// The account `external_id` at `provider` logs in to, whether the
+    /// account was created with it or it was linked later.
     pub async fn fetch_with_user_with_external_id(
-        connection: Arc<Mutex<Connection>>,
+        connection: ConnectionPool,
         provider: String,
-        discord_id: &str,
+        external_id: &str,
     ) -> Result<Option<User>, InternalError> {
-        let conn = connection.lock().await;
+        let conn = connection.get().await;
         match conn.query_row(
-            "SELECT * FROM user WHERE provider = (?1) AND external_id = (?2)",
-            ((&provider), (discord_id)),
+            "SELECT u.id, u.username, u.provider \
+             FROM user_identity i INNER JOIN user u ON i.user_id = u.id \
+             WHERE i.provider = (?1) AND i.external_id = (?2)",
+            ((&provider), (external_id)),
             |row| {
                 Ok((
                     row.get::<usize, i64>(0)
                         .expect("Table 'user' has known layout"),
                     row.get::<usize, String>(1)
                         .expect("Table 'user' has known layout"),
+                    row.get::<usize, Option<String>>(2)
+                        .expect("Table 'user' has known layout"),
                 ))
             },
         ) {
-            Ok((id, name)) => Ok(Some(User {
+            Ok((id, name, account_provider)) => Ok(Some(User {
                 id,
                 name,
-                provider,
+                provider: account_provider.unwrap_or(provider),
                 is_admin: false,
                 timezone: None,
                 language: None,
+                display_name: None,
             })),
             Err(Error::QueryReturnedNoRows) => Ok(None),
             Err(err) => Err(InternalError::new(format!(
@@ -0,0 +1,120 @@
+//! External (OAuth2) identities linked to accounts. An account created by
+//! an OAuth2 login starts with that identity; others can be linked to it
+//! so the same person can log in with several providers.
+
+use rusqlite::{OptionalExtension, TransactionBehavior};
+
+use crate::database::ConnectionPool;
+use crate::error::InternalError;
+
+/// Link `external_id` at `provider` to `user_id`. Returns false, and
+/// changes nothing, if the identity already belongs to another account.
+pub async fn link(
+    connection: ConnectionPool,
+    user_id: i64,
+    provider: &str,
+    external_id: &str,
+) -> Result<bool, InternalError> {
+    let mut conn = connection.get().await;
+    // Immediate, so the identity can't be linked elsewhere between the
+    // check and the insert.
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| InternalError::new(format!("Failed to begin transaction: {e}")))?;
+    let owner = tx
+        .query_row(
+            "SELECT user_id FROM user_identity WHERE provider = ?1 AND external_id = ?2",
+            (provider, external_id),
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()
+        .map_err(|e| InternalError::new(format!("Failed to query identity: {e}")))?;
+    match owner {
+        Some(owner) => Ok(owner == user_id),
+        None => {
+            tx.execute(
+                "INSERT INTO user_identity (provider, external_id, user_id) VALUES (?1, ?2, ?3)",
+                (provider, external_id, user_id),
+            )
+            .map_err(|e| InternalError::new(format!("Failed to link identity: {e}")))?;
+            tx.commit()
+                .map_err(|e| InternalError::new(format!("Failed to commit transaction: {e}")))?;
+            Ok(true)
+        }
+    }
+}
+
+/// The providers `user_id` can log in with, alphabetically.
+pub async fn providers(
+    connection: ConnectionPool,
+    user_id: i64,
+) -> Result<Vec<String>, InternalError> {
+    let conn = connection.get().await;
+    let mut stmt = conn
+        .prepare(
+            "SELECT DISTINCT provider FROM user_identity WHERE user_id = ?1 ORDER BY provider ASC",
+        )
+        .map_err(|e| InternalError::new(format!("Failed to prepare statement: {e}")))?;
+    let rows = stmt
+        .query_map([user_id], |row| row.get::<_, String>(0))
+        .map_err(|e| InternalError::new(format!("Failed to query identities: {e}")))?;
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row.map_err(|e| InternalError::new(format!("Failed to map row: {e}")))?);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::create_test_connection;
+    use crate::models::user::User;
+
+    #[tokio::test]
+    async fn linked_identity_logs_in_to_the_same_account() {
+        let connection = create_test_connection();
+        let user = User::create_from_external(
+            connection.clone(),
+            "user".to_string(),
+            "discord".to_string(),
+            "d1",
+        )
+        .await
+        .unwrap();
+        let other = User::create_from_external(
+            connection.clone(),
+            "other".to_string(),
+            "github".to_string(),
+            "g2",
+        )
+        .await
+        .unwrap();
+
+        assert!(
+            link(connection.clone(), user.id, "github", "g1")
+                .await
+                .unwrap()
+        );
+        let found =
+            User::fetch_with_user_with_external_id(connection.clone(), "github".to_string(), "g1")
+                .await
+                .unwrap();
+        assert_eq!(found.map(|u| u.id), Some(user.id));
+        assert_eq!(
+            providers(connection.clone(), user.id).await.unwrap(),
+            vec!["discord", "github"]
+        );
+
+        // Someone else's identity is not taken over.
+        assert!(
+            !link(connection.clone(), user.id, "github", "g2")
+                .await
+                .unwrap()
+        );
+        let found = User::fetch_with_user_with_external_id(connection, "github".to_string(), "g2")
+            .await
+            .unwrap();
+        assert_eq!(found.map(|u| u.id), Some(other.id));
+    }
+}
@@ -0,0 +1,194 @@
+//! Personal API tokens, for scripts that can't log in through a browser.
+//! Each user has at most one. The token is `<user id>.<secret>`; only an
+//! argon2 hash of it is stored, like local users' passwords, and it is
+//! checked with argon2's constant-time verification.
+
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
+};
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use rusqlite::OptionalExtension;
+
+use crate::database::ConnectionPool;
+use crate::error::InternalError;
+use crate::i18n::Language;
+use crate::models::user::User;
+
+/// Random bytes in a token's secret part.
+const SECRET_LEN: usize = 32;
+
+fn hash_token(token: String) -> Result<String, InternalError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(token.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| InternalError::new(format!("Failed to hash token: {e}")))
+}
+
+/// Create a new token for `user_id`, replacing any previous one. The
+/// token is returned this once; it can't be recovered later.
+pub async fn create(connection: ConnectionPool, user_id: i64) -> Result<String, InternalError> {
+    let mut secret = [0; SECRET_LEN];
+    rand::rng().fill(&mut secret);
+    let token = format!("{user_id}.{}", BASE64_URL_SAFE_NO_PAD.encode(secret));
+    let to_hash = token.clone();
+    let hash = tokio::task::spawn_blocking(move || hash_token(to_hash))
+        .await
+        .map_err(|e| InternalError::new(format!("Task join error: {e}")))??;
+    let conn = connection.get().await;
+    conn.execute(
+        "INSERT OR REPLACE INTO user_token (user_id, token_hash, created_at) VALUES (?1, ?2, ?3)",
+        (user_id, &hash, Utc::now().timestamp()),
+    )
+    .map_err(|e| InternalError::new(format!("Failed to store token: {e}")))?;
+    Ok(token)
+}
+
+pub async fn revoke(connection: ConnectionPool, user_id: i64) -> Result<(), InternalError> {
+    let conn = connection.get().await;
+    conn.execute("DELETE FROM user_token WHERE user_id = ?1", (user_id,))
+        .map_err(|e| InternalError::new(format!("Failed to revoke token: {e}")))?;
+    Ok(())
+}
+
+/// When `user_id`'s token was created, if there is one.
+pub async fn created_at(
+    connection: ConnectionPool,
+    user_id: i64,
+) -> Result<Option<DateTime<Utc>>, InternalError> {
+    let conn = connection.get().await;
+    let created_at = conn
+        .query_row(
+            "SELECT created_at FROM user_token WHERE user_id = ?1",
+            (user_id,),
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()
+        .map_err(|e| InternalError::new(format!("Failed to query token: {e}")))?;
+    Ok(created_at.and_then(|ts| DateTime::from_timestamp(ts, 0)))
+}
+
+/// The user `token` belongs to, bumping the token's `last_used_at`. `None`
+/// for malformed, unknown or revoked tokens.
+pub async fn fetch_user(
+    connection: ConnectionPool,
+    token: &str,
+) -> Result<Option<User>, InternalError> {
+    let Some(user_id) = token
+        .split_once('.')
+        .and_then(|(id, _)| id.parse::<i64>().ok())
+    else {
+        return Ok(None);
+    };
+    let row = {
+        let conn = connection.get().await;
+        conn.query_row(
+            "SELECT t.token_hash, u.username, u.provider, u.timezone, u.language, u.display_name \
+             FROM user_token t INNER JOIN user u ON t.user_id = u.id \
+             WHERE t.user_id = ?1",
+            (user_id,),
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| InternalError::new(format!("Failed to query token: {e}")))?
+    };
+    let Some((stored_hash, name, provider, timezone, language, display_name)) = row else {
+        return Ok(None);
+    };
+
+    let token = token.to_string();
+    let valid = tokio::task::spawn_blocking(move || {
+        let parsed_hash = PasswordHash::new(&stored_hash)
+            .map_err(|e| InternalError::new(format!("Failed to parse hash: {e}")))?;
+        Ok::<bool, InternalError>(
+            Argon2::default()
+                .verify_password(token.as_bytes(), &parsed_hash)
+                .is_ok(),
+        )
+    })
+    .await
+    .map_err(|e| InternalError::new(format!("Task join error: {e}")))??;
+    if !valid {
+        return Ok(None);
+    }
+
+    connection
+        .get()
+        .await
+        .execute(
+            "UPDATE user_token SET last_used_at = ?1 WHERE user_id = ?2",
+            (Utc::now().timestamp(), user_id),
+        )
+        .map_err(|e| InternalError::new(format!("Failed to update token: {e}")))?;
+    Ok(Some(User {
+        id: user_id,
+        name,
+        provider,
+        is_admin: false,
+        timezone: timezone.and_then(|name| name.parse().ok()),
+        language: language.as_deref().and_then(Language::from_code),
+        display_name,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::{create_test_connection, create_test_user};
+
+    #[tokio::test]
+    async fn only_the_latest_token_is_accepted_until_revoked() {
+        let connection = create_test_connection();
+        let user = create_test_user(&connection, "test").await;
+
+        let first = create(connection.clone(), user.id).await.unwrap();
+        let second = create(connection.clone(), user.id).await.unwrap();
+        let found = fetch_user(connection.clone(), &second).await.unwrap();
+        assert_eq!(found.map(|u| u.id), Some(user.id));
+        assert!(
+            fetch_user(connection.clone(), &first)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            fetch_user(connection.clone(), &format!("{}.guess", user.id))
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            fetch_user(connection.clone(), "junk")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            created_at(connection.clone(), user.id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        revoke(connection.clone(), user.id).await.unwrap();
+        assert!(
+            fetch_user(connection.clone(), &second)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(created_at(connection, user.id).await.unwrap().is_none());
+    }
+}
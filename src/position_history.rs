@@ -0,0 +1,73 @@
+//! Samples every telescope's position into `telescope_position` so the
+//! dish's path can be plotted afterwards, and drops samples older than
+//! `RETENTION` so the table doesn't grow forever.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::warn;
+
+use crate::app::AppState;
+use crate::models::telescope_position::{self, PositionSample};
+
+/// Time between samples. The controller is polled every second; this is
+/// plenty to see what the dish did.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long samples are kept.
+const RETENTION: chrono::Duration = chrono::Duration::days(7);
+
+/// Samples between purges of old ones, an hour's worth.
+const SAMPLES_PER_PURGE: u32 = 720;
+
+pub fn start(state: AppState) {
+    crate::supervised_task::spawn_supervised("position_history", move || {
+        let state = state.clone();
+        async move {
+            let mut until_purge = 0;
+            loop {
+                if until_purge == 0 {
+                    if let Err(err) = telescope_position::purge_older_than(
+                        state.database_connection.clone(),
+                        Utc::now() - RETENTION,
+                    )
+                    .await
+                    {
+                        warn!("Failed to purge old telescope positions: {err:?}");
+                    }
+                    until_purge = SAMPLES_PER_PURGE;
+                }
+                until_purge -= 1;
+
+                for telescope_id in state.telescopes.get_names().await {
+                    let Some(telescope) = state.telescopes.get(&telescope_id).await else {
+                        continue;
+                    };
+                    // A disconnected telescope has no position to record.
+                    let Ok(info) = telescope.get_info().await else {
+                        continue;
+                    };
+                    let Some(current) = info.current_horizontal else {
+                        continue;
+                    };
+                    let sample = PositionSample {
+                        timestamp: Utc::now(),
+                        azimuth_deg: current.azimuth.to_degrees(),
+                        elevation_deg: current.elevation.to_degrees(),
+                        status: info.status,
+                    };
+                    if let Err(err) = telescope_position::record(
+                        state.database_connection.clone(),
+                        &telescope_id,
+                        &sample,
+                    )
+                    .await
+                    {
+                        warn!("Failed to record position of {telescope_id}: {err:?}");
+                    }
+                }
+                tokio::time::sleep(SAMPLE_INTERVAL).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,335 @@
+//! Works through observation queues. While a user has an active booking on
+//! a telescope and items waiting in their queue on it, a runner task takes
+//! them in order: it points the telescope at the target, waits for it to
+//! track, integrates for the requested time and lets the integration
+//! monitor save the observation, then moves on. Items left when the
+//! booking ends wait for the user's next one. When the telescope can't
+//! take the queue, because it is offline or someone else controls it,
+//! the items keep waiting and the runner tries again later.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::app::AppState;
+use crate::error::AppError;
+use crate::i18n::Language;
+use crate::models::audit::{self, AuditAction};
+use crate::models::booking::{Booking, booking_is_active};
+use crate::models::observation_queue::{self, QueueItem, QueueStatus};
+use crate::models::telescope_types::{TelescopeError, TelescopeStatus};
+use crate::models::user::User;
+use crate::routes::observe::{
+    ObserveForm, claim_control, retarget, start_integration, target_detail,
+};
+
+/// How often to look for queues to start on.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a slew may take before the item is given up on.
+const SLEW_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How long to leave a telescope alone after it couldn't take the queue.
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// How much shorter than requested an integration may end and still count
+/// as done, for the monitor's and our polling.
+const INTEGRATION_TIME_SLACK: Duration = Duration::from_secs(3);
+
+/// Why a queued observation didn't go through.
+#[derive(Debug)]
+enum QueueError {
+    /// Something about the item itself, like a target below the horizon.
+    /// The item fails and the next is taken.
+    Item(String),
+    /// The telescope can't observe for the queue right now. The item is
+    /// left waiting.
+    Telescope(String),
+}
+
+impl From<AppError> for QueueError {
+    fn from(err: AppError) -> QueueError {
+        if err.status() == StatusCode::BAD_REQUEST {
+            QueueError::Item(err.into_message())
+        } else {
+            QueueError::Telescope(err.into_message())
+        }
+    }
+}
+
+impl From<TelescopeError> for QueueError {
+    fn from(err: TelescopeError) -> QueueError {
+        AppError::from(err).into()
+    }
+}
+
+pub fn start(state: AppState) {
+    // Outside the supervised future, so that a restart of the loop still
+    // knows which runners are working.
+    let runners: Arc<Mutex<HashMap<String, JoinHandle<()>>>> = Arc::default();
+    crate::supervised_task::spawn_supervised("queue_runner", move || {
+        let state = state.clone();
+        let runners = runners.clone();
+        async move {
+            loop {
+                tokio::time::sleep(POLL_INTERVAL).await;
+
+                let mut runners = runners.lock().await;
+                runners.retain(|_, runner| !runner.is_finished());
+                for telescope_name in state.telescopes.get_names().await {
+                    if runners.contains_key(&telescope_name) {
+                        continue;
+                    }
+                    // Nothing runs here, so a running item was cut short by
+                    // a restart or a crashed runner.
+                    if let Err(err) = observation_queue::fail_abandoned(
+                        state.database_connection.clone(),
+                        &telescope_name,
+                    )
+                    .await
+                    {
+                        error!("Queue runner: failed to clean up {telescope_name}: {err:?}");
+                    }
+                }
+
+                let bookings = match Booking::fetch_active(state.database_connection.clone()).await
+                {
+                    Ok(b) => b,
+                    Err(err) => {
+                        error!("Queue runner: failed to fetch active bookings: {err:?}");
+                        continue;
+                    }
+                };
+                let now = Utc::now();
+                for booking in bookings.iter().filter(|b| b.active_at(&now)) {
+                    if runners.contains_key(&booking.telescope_name) {
+                        continue;
+                    }
+                    match observation_queue::next_queued(
+                        state.database_connection.clone(),
+                        booking.user_id,
+                        &booking.telescope_name,
+                    )
+                    .await
+                    {
+                        Ok(Some(_)) => {}
+                        Ok(None) => continue,
+                        Err(err) => {
+                            error!("Queue runner: failed to fetch queue: {err:?}");
+                            continue;
+                        }
+                    }
+                    let user = User {
+                        id: booking.user_id,
+                        name: booking.user_name.clone(),
+                        provider: booking.user_provider.clone(),
+                        is_admin: false,
+                        timezone: None,
+                        language: None,
+                        display_name: None,
+                    };
+                    info!(
+                        "Starting {}'s observation queue on {}",
+                        user.name, booking.telescope_name
+                    );
+                    runners.insert(
+                        booking.telescope_name.clone(),
+                        tokio::spawn(run_queue(
+                            state.clone(),
+                            booking.telescope_name.clone(),
+                            user,
+                        )),
+                    );
+                }
+            }
+        }
+    });
+}
+
+/// Observe `user`'s waiting items on `telescope_id` one after another,
+/// until none are left or their booking ends.
+async fn run_queue(state: AppState, telescope_id: String, user: User) {
+    let connection = state.database_connection.clone();
+    loop {
+        match booking_is_active(connection.clone(), &user, &telescope_id).await {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(err) => {
+                error!("Queue runner: failed to check booking: {err:?}");
+                return;
+            }
+        }
+        let item = match observation_queue::next_queued(connection.clone(), user.id, &telescope_id)
+            .await
+        {
+            Ok(Some(item)) => item,
+            Ok(None) => return,
+            Err(err) => {
+                error!("Queue runner: failed to fetch queue: {err:?}");
+                return;
+            }
+        };
+        if let Err(err) =
+            observation_queue::set_status(connection.clone(), item.id, QueueStatus::Running, None)
+                .await
+        {
+            error!("Queue runner: failed to start item {}: {err:?}", item.id);
+            return;
+        }
+        let (status, message) = match observe(&state, &telescope_id, &user, &item).await {
+            Ok(()) => (QueueStatus::Done, None),
+            Err(QueueError::Item(message)) => {
+                info!(
+                    "Queued observation {} on {telescope_id} failed: {message}",
+                    item.id
+                );
+                (QueueStatus::Failed, Some(message))
+            }
+            Err(QueueError::Telescope(message)) => {
+                info!("Pausing {}'s queue on {telescope_id}: {message}", user.name);
+                if let Err(err) = observation_queue::set_status(
+                    connection.clone(),
+                    item.id,
+                    QueueStatus::Queued,
+                    None,
+                )
+                .await
+                {
+                    error!("Queue runner: failed to requeue item {}: {err:?}", item.id);
+                }
+                // Holding the runner's place keeps a new one from starting
+                // on the telescope until then.
+                tokio::time::sleep(RETRY_DELAY).await;
+                return;
+            }
+        };
+        if let Err(err) =
+            observation_queue::set_status(connection.clone(), item.id, status, message.as_deref())
+                .await
+        {
+            error!("Queue runner: failed to finish item {}: {err:?}", item.id);
+            return;
+        }
+    }
+}
+
+/// Point at `item`'s target and integrate on it until the integration
+/// ends. The integration monitor saves the observation, whether it ends
+/// after the requested time or early, when tracking is lost, the booking
+/// ends or the user stops it; only the first counts as done.
+async fn observe(
+    state: &AppState,
+    telescope_id: &str,
+    user: &User,
+    item: &QueueItem,
+) -> Result<(), QueueError> {
+    // Messages are stored for the user to read later, when the language
+    // they will read them in isn't known.
+    let lang = Language::default();
+    let telescope = state
+        .telescopes
+        .get(telescope_id)
+        .await
+        .ok_or_else(|| QueueError::Telescope(AppError::NotFound.into_message()))?;
+    claim_control(&lang, state, user, telescope_id).await?;
+    let observation = &item.observation;
+    let az_offset_rad = observation.az_offset_deg.to_radians();
+    let el_offset_rad = observation.el_offset_deg.to_radians();
+    let target = retarget(
+        state,
+        user,
+        telescope_id,
+        telescope.as_ref(),
+        observation.target,
+        az_offset_rad,
+        el_offset_rad,
+    )
+    .await?;
+    audit::record(
+        state.database_connection.clone(),
+        user,
+        AuditAction::SetTarget,
+        &target_detail(telescope_id, target, az_offset_rad, el_offset_rad),
+    )
+    .await;
+
+    let slew_started = tokio::time::Instant::now();
+    loop {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let info = telescope.get_info().await?;
+        if info.current_target != Some(target) {
+            return Err(QueueError::Telescope(
+                "The telescope was pointed elsewhere".to_string(),
+            ));
+        }
+        if info.status == TelescopeStatus::Tracking {
+            break;
+        }
+        if slew_started.elapsed() > SLEW_TIMEOUT {
+            return Err(QueueError::Telescope(
+                "The telescope didn't reach the target".to_string(),
+            ));
+        }
+    }
+
+    start_integration(
+        &lang,
+        state,
+        user,
+        telescope_id,
+        telescope.clone(),
+        ObserveForm::fixed(observation.integration_time_secs, observation.label.clone()),
+    )
+    .await?;
+    let integration_started = tokio::time::Instant::now();
+    loop {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        // The monitor saves what was integrated, so the item isn't retried.
+        let info = telescope.get_info().await.map_err(|err| {
+            QueueError::Item(format!("Lost the telescope while integrating: {err}"))
+        })?;
+        if !info.measurement_in_progress {
+            break;
+        }
+    }
+    let integrated = integration_started.elapsed();
+    if integrated + INTEGRATION_TIME_SLACK
+        < Duration::from_secs_f64(observation.integration_time_secs)
+    {
+        return Err(QueueError::Item(format!(
+            "The integration ended after {} of {} s",
+            integrated.as_secs(),
+            observation.integration_time_secs
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_problems_with_the_item_fail_it() {
+        let is_item = |err: QueueError| matches!(err, QueueError::Item(_));
+        assert!(is_item(
+            TelescopeError::TargetOutOfElevationRange {
+                min_deg: 5.0,
+                max_deg: 85.0,
+            }
+            .into()
+        ));
+        assert!(is_item(AppError::BadRequest("too long".to_string()).into()));
+        assert!(!is_item(TelescopeError::TelescopeNotConnected.into()));
+        assert!(!is_item(TelescopeError::TelescopeBusy.into()));
+        assert!(!is_item(
+            AppError::Conflict("someone else".to_string()).into()
+        ));
+    }
+}
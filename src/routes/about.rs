@@ -1,25 +1,32 @@
 use axum::{
     Extension, Router,
+    extract::State,
     http::HeaderMap,
     response::{Html, IntoResponse},
     routing::get,
 };
 
+use crate::app::AppState;
 use crate::i18n::Language;
 use crate::models::user::User;
 use crate::routes::index::render_main;
 
-pub fn routes() -> Router {
-    Router::new().route("/", get(get_about))
+pub fn routes(state: AppState) -> Router {
+    Router::new().route("/", get(get_about)).with_state(state)
 }
 
 async fn get_about(
+    State(state): State<AppState>,
     Extension(lang): Extension<Language>,
     Extension(user): Extension<Option<User>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    let content =
-        crate::routes::read_content_page("about", lang, "<p>About information not available.</p>");
+    let content = crate::routes::read_content_page(
+        &state.assets_dir,
+        "about",
+        lang,
+        "<p>About information not available.</p>",
+    );
     let content = if headers.get("hx-request").is_some() {
         content
     } else {
@@ -1,26 +1,34 @@
 use askama::Template;
 use axum::{
     Extension, Form, Router,
-    extract::State,
+    extract::{Query, State},
     http::{HeaderMap, HeaderValue, StatusCode, header::SET_COOKIE},
     response::{Html, IntoResponse, Response},
-    routing::{get, post},
+    routing::{get, patch, post},
 };
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use serde::Deserialize;
 use tracing::{error, info};
 
 use crate::app::AppState;
+use crate::error::AppError;
 use crate::i18n::Language;
 use crate::middleware::language::language_cookie;
 use crate::middleware::session::clear_session_cookie;
-use crate::models::user::User;
+use crate::models::user::{MAX_DISPLAY_NAME_CHARS, User, clean_display_name};
+use crate::models::{user_identity, user_token};
 use crate::routes::index::render_main;
+use crate::timefmt::InTz;
 
 pub fn routes(state: AppState) -> Router {
     Router::new()
         .route("/", get(get_account))
+        .route("/profile", patch(set_display_name))
         .route("/timezone", post(set_timezone))
         .route("/language", post(set_language))
+        .route("/api-token", post(create_api_token))
+        .route("/api-token/revoke", post(revoke_api_token))
         .route("/delete", post(delete_account))
         .with_state(state)
 }
@@ -51,6 +59,9 @@ struct AccountTemplate {
     languages: Vec<LanguageOption>,
     /// Set after a successful save so the page can confirm it.
     saved: bool,
+    /// Same, for the display name.
+    name_saved: bool,
+    max_display_name_chars: usize,
 }
 
 impl AccountTemplate {
@@ -81,19 +92,77 @@ impl AccountTemplate {
             timezones,
             languages,
             saved,
+            name_saved: false,
+            max_display_name_chars: MAX_DISPLAY_NAME_CHARS,
         }
     }
 }
 
+/// The personal API token card. The token itself is only shown right
+/// after it was created.
+#[derive(Template)]
+#[template(path = "account_token.html")]
+struct ApiTokenTemplate {
+    lang: Language,
+    tz: Tz,
+    created_at: Option<DateTime<Utc>>,
+    new_token: Option<String>,
+}
+
+/// The OAuth2 providers the user can log in with, and buttons to link
+/// the others.
+#[derive(Template)]
+#[template(path = "account_identities.html")]
+struct IdentitiesTemplate {
+    lang: Language,
+    linked: Vec<String>,
+    linkable: Vec<String>,
+    /// How a link just attempted went: "linked" or "taken".
+    link_outcome: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AccountQuery {
+    link: Option<String>,
+}
+
 async fn get_account(
     Extension(lang): Extension<Language>,
     Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Query(query): Query<AccountQuery>,
     headers: HeaderMap,
 ) -> Result<impl IntoResponse, StatusCode> {
     let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+    let linked = user_identity::providers(state.database_connection.clone(), user.id).await?;
+    let linkable = state
+        .secrets
+        .get_auth_providers_for_login()
+        .into_iter()
+        .map(|(provider, _)| provider)
+        .filter(|provider| !linked.contains(provider))
+        .collect();
+    let identities_card = IdentitiesTemplate {
+        lang,
+        linked,
+        linkable,
+        link_outcome: query.link,
+    };
+    let token_card = ApiTokenTemplate {
+        lang,
+        tz: user.tz(),
+        created_at: user_token::created_at(state.database_connection, user.id).await?,
+        new_token: None,
+    };
     let content = AccountTemplate::new(user.clone(), lang, false)
         .render()
-        .expect("Template rendering should always succeed");
+        .expect("Template rendering should always succeed")
+        + &identities_card
+            .render()
+            .expect("Template rendering should always succeed")
+        + &token_card
+            .render()
+            .expect("Template rendering should always succeed");
     let content = if headers.get("hx-request").is_some() {
         content
     } else {
@@ -102,6 +171,37 @@ async fn get_account(
     Ok(Html(content))
 }
 
+#[derive(Deserialize)]
+struct ProfileForm {
+    display_name: String,
+}
+
+/// Set the name shown for the user instead of the one their login
+/// provider gave. An empty name goes back to the provider's.
+async fn set_display_name(
+    Extension(lang): Extension<Language>,
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Form(form): Form<ProfileForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut user = user.ok_or(AppError::Unauthorized)?;
+    let display_name = clean_display_name(&form.display_name).map_err(AppError::BadRequest)?;
+    User::set_display_name(
+        state.database_connection.clone(),
+        user.id,
+        display_name.as_deref(),
+    )
+    .await?;
+    user.display_name = display_name;
+    let content = AccountTemplate {
+        name_saved: true,
+        ..AccountTemplate::new(user, lang, false)
+    }
+    .render()
+    .expect("Template rendering should always succeed");
+    Ok(Html(content))
+}
+
 #[derive(Deserialize)]
 struct TimezoneForm {
     timezone: String,
@@ -166,6 +266,45 @@ async fn set_language(
     Ok(response)
 }
 
+/// Create a personal API token, replacing any previous one.
+async fn create_api_token(
+    Extension(lang): Extension<Language>,
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+    let token = user_token::create(state.database_connection, user.id).await?;
+    info!("Created API token for user {}", user.id);
+    let content = ApiTokenTemplate {
+        lang,
+        tz: user.tz(),
+        created_at: Some(Utc::now()),
+        new_token: Some(token),
+    }
+    .render()
+    .expect("Template rendering should always succeed");
+    Ok(Html(content))
+}
+
+async fn revoke_api_token(
+    Extension(lang): Extension<Language>,
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+    user_token::revoke(state.database_connection, user.id).await?;
+    info!("Revoked API token for user {}", user.id);
+    let content = ApiTokenTemplate {
+        lang,
+        tz: user.tz(),
+        created_at: None,
+        new_token: None,
+    }
+    .render()
+    .expect("Template rendering should always succeed");
+    Ok(Html(content))
+}
+
 async fn delete_account(
     Extension(user): Extension<Option<User>>,
     State(state): State<AppState>,
@@ -181,7 +320,7 @@ async fn delete_account(
             error!("Failed to delete account: {err:?}");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
-    let clear_cookie = clear_session_cookie();
+    let clear_cookie = clear_session_cookie(&state.session_config);
     let mut response = Response::new(axum::body::Body::empty());
     response.headers_mut().insert(
         SET_COOKIE,
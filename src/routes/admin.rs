@@ -10,14 +10,14 @@ use chrono::{NaiveDate, TimeZone, Utc};
 use serde::Deserialize;
 use tracing::info;
 
-use crate::app::AppState;
+use crate::app::{AppState, save_integration};
 use crate::i18n::Language;
+use crate::models::audit::{self, AuditAction, AuditEntry};
 use crate::models::booking::Booking;
 use crate::models::guest::GuestSession;
 use crate::models::maintenance::{fetch_maintenance_set, set_maintenance};
 use crate::models::support_announcement::{fetch_support_announcement, set_support_announcement};
-use crate::models::telescope_types::TelescopeError;
-use crate::models::user::User;
+use crate::models::user::{User, UserSummary};
 use crate::routes::index::render_main;
 
 pub fn routes(state: AppState) -> Router {
@@ -29,6 +29,8 @@ pub fn routes(state: AppState) -> Router {
             post(calibrate_preview_handler),
         )
         .route("/telescope/{name}/calibrate", post(calibrate_handler))
+        .route("/telescope/{name}/stow", post(stow_handler))
+        .route("/reload-telescopes", post(reload_telescopes_handler))
         .route("/announcement", post(save_announcement_handler))
         .route("/local-users", post(create_local_user_handler))
         .route("/local-users/{id}/delete", post(delete_local_user_handler))
@@ -37,6 +39,10 @@ pub fn routes(state: AppState) -> Router {
             post(set_local_password_handler),
         )
         .route("/local-users/{id}/comment", post(set_local_comment_handler))
+        .route("/users", get(get_users))
+        .route("/users/{id}/admin", post(toggle_admin_handler))
+        .route("/users/{id}/disabled", post(toggle_disabled_handler))
+        .route("/audit", get(get_audit))
         .with_state(state)
 }
 
@@ -47,6 +53,9 @@ const MAX_PASSWORD_BYTES: usize = 512;
 const MAX_COMMENT_CHARS: usize = 500;
 const MAX_ANNOUNCEMENT_CHARS: usize = 2000;
 
+/// Most audit log entries shown at once; narrow the filter to see more.
+const MAX_AUDIT_ENTRIES: usize = 500;
+
 /// Typo guard for pointing calibration: real pointing offsets are a few
 /// degrees at most, so anything larger is more likely a slipped decimal
 /// point than a measurement.
@@ -122,13 +131,8 @@ async fn get_admin(
         } else {
             None
         };
-        let is_connected = info.as_ref().is_some_and(|i| {
-            !matches!(
-                i.most_recent_error,
-                Some(TelescopeError::TelescopeIOError(_) | TelescopeError::TelescopeNotConnected)
-            )
-        });
-        let receiver_connected = info.and_then(|i| i.receiver_connected);
+        let is_connected = info.as_ref().is_some_and(|i| i.mount_status.connected);
+        let receiver_connected = info.map(|i| i.receiver_status.connected);
         telescopes.push((
             name,
             in_maintenance,
@@ -261,7 +265,7 @@ async fn create_local_user_handler(
     State(state): State<AppState>,
     Form(form): Form<CreateLocalUserForm>,
 ) -> Result<Response, StatusCode> {
-    require_admin(user)?;
+    let admin = require_admin(user)?;
     let username = form.username.trim().to_string();
     if username.chars().count() > MAX_USERNAME_CHARS
         || form.password.len() > MAX_PASSWORD_BYTES
@@ -270,14 +274,23 @@ async fn create_local_user_handler(
         return Ok(Redirect::to("/admin?error=input_too_long").into_response());
     }
     match User::create_local(
-        state.database_connection,
-        username,
+        state.database_connection.clone(),
+        username.clone(),
         form.password,
         form.comment.trim().to_string(),
     )
     .await
     {
-        Ok(_) => Ok(Redirect::to("/admin").into_response()),
+        Ok(created) => {
+            audit::record(
+                state.database_connection,
+                &admin,
+                AuditAction::CreateLocalUser,
+                &format!("user {}", created.id),
+            )
+            .await;
+            Ok(Redirect::to("/admin").into_response())
+        }
         Err(err) if err.message.contains("already exists") => {
             Ok(Redirect::to("/admin?error=username_taken").into_response())
         }
@@ -290,10 +303,17 @@ async fn delete_local_user_handler(
     State(state): State<AppState>,
     Path(id): Path<i64>,
 ) -> Result<Response, StatusCode> {
-    require_admin(user)?;
-    User::delete_local_by_id(state.database_connection, id)
+    let admin = require_admin(user)?;
+    User::delete_local_by_id(state.database_connection.clone(), id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    audit::record(
+        state.database_connection,
+        &admin,
+        AuditAction::DeleteLocalUser,
+        &format!("user {id}"),
+    )
+    .await;
     Ok(Redirect::to("/admin").into_response())
 }
 
@@ -308,13 +328,20 @@ async fn set_local_password_handler(
     Path(id): Path<i64>,
     Form(form): Form<SetPasswordForm>,
 ) -> Result<Response, StatusCode> {
-    require_admin(user)?;
+    let admin = require_admin(user)?;
     if form.password.len() > MAX_PASSWORD_BYTES {
         return Ok(Redirect::to("/admin?error=input_too_long").into_response());
     }
-    User::set_local_password(state.database_connection, id, form.password)
+    User::set_local_password(state.database_connection.clone(), id, form.password)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    audit::record(
+        state.database_connection,
+        &admin,
+        AuditAction::SetLocalPassword,
+        &format!("user {id}"),
+    )
+    .await;
     Ok(Redirect::to("/admin").into_response())
 }
 
@@ -343,6 +370,174 @@ async fn set_local_comment_handler(
     Ok(Redirect::to("/admin").into_response())
 }
 
+#[derive(Template)]
+#[template(path = "admin_users.html")]
+struct AdminUsersTemplate {
+    lang: Language,
+    /// With whether each user is an admin through `config.toml`.
+    users: Vec<(UserSummary, bool)>,
+    current_user_id: i64,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AdminUsersQuery {
+    error: Option<String>,
+}
+
+async fn get_users(
+    Extension(lang): Extension<Language>,
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Query(query): Query<AdminUsersQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    let user = require_admin(user)?;
+    let users = User::fetch_all(state.database_connection)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .map(|summary| {
+            let config_admin = state.admin_config.user_ids.contains(&summary.id);
+            (summary, config_admin)
+        })
+        .collect();
+    let content = AdminUsersTemplate {
+        lang,
+        users,
+        current_user_id: user.id,
+        error: query.error,
+    }
+    .render()
+    .expect("Template rendering should always succeed");
+    let content = if headers.get("hx-request").is_some() {
+        content
+    } else {
+        render_main(Some(user), lang, content)
+    };
+    Ok(Html(content))
+}
+
+#[derive(Deserialize)]
+struct ToggleForm {
+    enable: bool,
+}
+
+async fn toggle_admin_handler(
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Form(form): Form<ToggleForm>,
+) -> Result<Response, StatusCode> {
+    let user = require_admin(user)?;
+    // Admins can't lock themselves out.
+    if id == user.id {
+        return Ok(Redirect::to("/admin/users?error=self").into_response());
+    }
+    User::set_admin(state.database_connection.clone(), id, form.enable)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    info!("Admin {} set admin={} for user {id}", user.id, form.enable);
+    audit::record(
+        state.database_connection,
+        &user,
+        AuditAction::SetAdmin,
+        &format!("user {id}: {}", form.enable),
+    )
+    .await;
+    Ok(Redirect::to("/admin/users").into_response())
+}
+
+async fn toggle_disabled_handler(
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Form(form): Form<ToggleForm>,
+) -> Result<Response, StatusCode> {
+    let user = require_admin(user)?;
+    if id == user.id {
+        return Ok(Redirect::to("/admin/users?error=self").into_response());
+    }
+    User::set_disabled(state.database_connection.clone(), id, form.enable)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    info!(
+        "Admin {} set disabled={} for user {id}",
+        user.id, form.enable
+    );
+    audit::record(
+        state.database_connection,
+        &user,
+        AuditAction::SetDisabled,
+        &format!("user {id}: {}", form.enable),
+    )
+    .await;
+    Ok(Redirect::to("/admin/users").into_response())
+}
+
+#[derive(Template)]
+#[template(path = "admin_audit.html")]
+struct AdminAuditTemplate {
+    lang: Language,
+    entries: Vec<AuditEntry>,
+    user_filter: String,
+    from: NaiveDate,
+    to: NaiveDate,
+    truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct AuditQuery {
+    user: Option<String>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+async fn get_audit(
+    Extension(lang): Extension<Language>,
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Query(query): Query<AuditQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    let user = require_admin(user)?;
+    let now = Utc::now();
+    let to = query.to.unwrap_or(now.date_naive());
+    let from = query
+        .from
+        .unwrap_or_else(|| (now - chrono::Duration::days(30)).date_naive());
+    let from_dt = Utc.from_utc_datetime(&from.and_hms_opt(0, 0, 0).unwrap());
+    let to_dt = Utc.from_utc_datetime(&to.succ_opt().unwrap_or(to).and_hms_opt(0, 0, 0).unwrap());
+    let user_filter = query.user.unwrap_or_default().trim().to_string();
+    let mut entries = audit::fetch(
+        state.database_connection,
+        Some(user_filter.as_str()).filter(|name| !name.is_empty()),
+        from_dt,
+        to_dt,
+        MAX_AUDIT_ENTRIES + 1,
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let truncated = entries.len() > MAX_AUDIT_ENTRIES;
+    entries.truncate(MAX_AUDIT_ENTRIES);
+    let content = AdminAuditTemplate {
+        lang,
+        entries,
+        user_filter,
+        from,
+        to,
+        truncated,
+    }
+    .render()
+    .expect("Template rendering should always succeed");
+    let content = if headers.get("hx-request").is_some() {
+        content
+    } else {
+        render_main(Some(user), lang, content)
+    };
+    Ok(Html(content))
+}
+
 #[derive(Deserialize)]
 struct AnnouncementForm {
     message: String,
@@ -371,9 +566,16 @@ async fn save_announcement_handler(
         admin.provider,
         stored.is_none()
     );
-    set_support_announcement(state.database_connection, stored)
+    set_support_announcement(state.database_connection.clone(), stored)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    audit::record(
+        state.database_connection,
+        &admin,
+        AuditAction::Announcement,
+        stored.unwrap_or("(cleared)"),
+    )
+    .await;
     Ok(Redirect::to("/admin").into_response())
 }
 
@@ -536,6 +738,16 @@ async fn calibrate_handler(
                 format_deg(calibration.previous.elevation),
                 format_deg(calibration.adjusted.elevation),
             );
+            audit::record(
+                state.database_connection.clone(),
+                &admin,
+                AuditAction::Calibrate,
+                &format!(
+                    "{name}: offsets az {:.2}°, el {:.2}°",
+                    form.az_offset_deg, form.el_offset_deg
+                ),
+            )
+            .await;
             let content = CalibrateResultTemplate {
                 previous_az: format_deg(calibration.previous.azimuth),
                 previous_el: format_deg(calibration.previous.elevation),
@@ -558,6 +770,50 @@ async fn calibrate_handler(
     }
 }
 
+#[derive(Template)]
+#[template(path = "admin_stow_result.html")]
+struct StowResultTemplate {
+    name: String,
+    error: Option<String>,
+}
+
+/// Send a telescope to its stow position and stop it there. A running
+/// integration is saved first for whoever holds the telescope.
+async fn stow_handler(
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Response, StatusCode> {
+    let admin = require_admin(user)?;
+    let telescope = state
+        .telescopes
+        .get(&name)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    save_integration(&state, &name, telescope.as_ref()).await;
+    let error = telescope.stow().await.err().map(|err| err.to_string());
+    match &error {
+        None => {
+            info!("Admin {} ({}) stowed {}", admin.name, admin.provider, name);
+            audit::record(
+                state.database_connection.clone(),
+                &admin,
+                AuditAction::Stow,
+                &name,
+            )
+            .await;
+        }
+        Some(err) => info!(
+            "Admin {} ({}) failed to stow {}: {}",
+            admin.name, admin.provider, name, err
+        ),
+    }
+    let content = StowResultTemplate { name, error }
+        .render()
+        .expect("Template rendering should always succeed");
+    Ok(Html(content).into_response())
+}
+
 async fn toggle_maintenance(
     Extension(user): Extension<Option<User>>,
     State(state): State<AppState>,
@@ -576,9 +832,16 @@ async fn toggle_maintenance(
         "Admin {} ({}) set telescope {} maintenance: {}",
         user.name, user.provider, name, new_state
     );
-    set_maintenance(state.database_connection, &name, new_state)
+    set_maintenance(state.database_connection.clone(), &name, new_state)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    audit::record(
+        state.database_connection,
+        &user,
+        AuditAction::Maintenance,
+        &format!("{name}: {new_state}"),
+    )
+    .await;
     let mut response = Response::new(axum::body::Body::empty());
     response
         .headers_mut()
@@ -586,6 +849,64 @@ async fn toggle_maintenance(
     Ok(response)
 }
 
+#[derive(Template, Default)]
+#[template(path = "admin_reload_result.html")]
+struct ReloadResultTemplate {
+    error: Option<String>,
+    added: Vec<String>,
+    removed: Vec<String>,
+    replaced: Vec<String>,
+    unchanged: Vec<String>,
+}
+
+/// Re-read the telescope definitions from config.toml and apply the
+/// differences to the running collection.
+async fn reload_telescopes_handler(
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+) -> Result<Response, StatusCode> {
+    let admin = require_admin(user)?;
+    info!(
+        "Admin {} ({}) reloading telescope config",
+        admin.name, admin.provider
+    );
+    let content = match state
+        .telescopes
+        .reload(|name, telescope| {
+            let state = &state;
+            async move { save_integration(state, &name, telescope.as_ref()).await }
+        })
+        .await
+    {
+        Ok(reload) => {
+            audit::record(
+                state.database_connection.clone(),
+                &admin,
+                AuditAction::ReloadTelescopes,
+                &format!(
+                    "added {:?}, removed {:?}, replaced {:?}",
+                    reload.added, reload.removed, reload.replaced
+                ),
+            )
+            .await;
+            ReloadResultTemplate {
+                error: None,
+                added: reload.added,
+                removed: reload.removed,
+                replaced: reload.replaced,
+                unchanged: reload.unchanged,
+            }
+        }
+        Err(err) => ReloadResultTemplate {
+            error: Some(err.to_string()),
+            ..Default::default()
+        },
+    }
+    .render()
+    .expect("Template rendering should always succeed");
+    Ok(Html(content).into_response())
+}
+
 /// Collapse runs of adjacent slots reserved by the same user on the same
 /// telescope into a single booking. Since the calendar UI stores each slot
 /// as its own row, the raw row count tracks total booked hours, not how
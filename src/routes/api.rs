@@ -0,0 +1,491 @@
+//! JSON endpoints. The GET endpoints are read-only, for external
+//! dashboards: no login or booking is needed, so nothing user-specific is
+//! exposed. The POST endpoints control a telescope for scripted observing
+//! and need the same session and booking as the observe page. The queue
+//! endpoints manage the user's own observation queue and need a login but
+//! no booking.
+
+use crate::app::AppState;
+use crate::coords::{
+    Direction, J2000, equatorial_from_galactic, equatorial_from_horizontal,
+    galactic_from_equatorial, horizontal_from_equatorial, normalize_equatorial, normalize_galactic,
+    normalize_horizontal,
+};
+use crate::error::AppError;
+use crate::i18n::Language;
+use crate::models::audit::{self, AuditAction};
+use crate::models::observation::MAX_LABEL_LEN;
+use crate::models::observation_queue::{self, QueueItem, QueueStatus, QueuedObservation};
+use crate::models::telescope_position::{self, PositionSample};
+use crate::models::telescope_types::{TelescopeInfo, TelescopeTarget};
+use crate::models::user::User;
+use crate::routes::observe::{
+    ObserveForm, authorized_telescope, claim_control, retarget, start_integration,
+    stop_and_save_observation, take_observation_label, target_detail,
+};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json, Response};
+use axum::routing::{delete, get, post};
+use axum::{Extension, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub fn routes(state: AppState) -> Router {
+    Router::new()
+        .route("/telescopes", get(get_telescopes))
+        .route("/telescopes/{telescope_id}/info", get(get_telescope_info))
+        .route(
+            "/telescopes/{telescope_id}/history",
+            get(get_telescope_history),
+        )
+        .route("/telescopes/{telescope_id}/target", post(post_target))
+        .route(
+            "/telescopes/{telescope_id}/integration",
+            post(post_integration),
+        )
+        .route(
+            "/telescopes/{telescope_id}/queue",
+            get(get_queue).post(post_queue),
+        )
+        .route(
+            "/telescopes/{telescope_id}/queue/{item_id}",
+            delete(delete_queue_item),
+        )
+        .route("/convert", get(get_convert))
+        .with_state(state)
+}
+
+async fn get_telescopes(State(state): State<AppState>) -> Json<Vec<String>> {
+    let mut names = state.telescopes.get_names().await;
+    names.sort();
+    Json(names)
+}
+
+async fn get_telescope_info(
+    State(state): State<AppState>,
+    Path(telescope_id): Path<String>,
+) -> Result<Json<TelescopeInfo>, StatusCode> {
+    let telescope = state
+        .telescopes
+        .get(&telescope_id)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let mut info = telescope
+        .get_info()
+        .await
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    // The spectrum belongs to whoever is observing.
+    info.latest_observation = None;
+    Ok(Json(info))
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+/// How far back the history goes when `from` isn't given.
+const DEFAULT_HISTORY_SPAN: chrono::Duration = chrono::Duration::hours(1);
+
+/// Where the telescope pointed between `from` and `to`, sampled every few
+/// seconds. `to` defaults to now and `from` to an hour before `to`.
+async fn get_telescope_history(
+    State(state): State<AppState>,
+    Path(telescope_id): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<PositionSample>>, StatusCode> {
+    if state.telescopes.get(&telescope_id).await.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - DEFAULT_HISTORY_SPAN);
+    if from > to {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let samples =
+        telescope_position::fetch_range(state.database_connection.clone(), &telescope_id, from, to)
+            .await?;
+    Ok(Json(samples))
+}
+
+/// An `AppError` as `{"error": message}`, with the status the observe
+/// page would answer with.
+struct ApiError(AppError);
+
+impl<E: Into<AppError>> From<E> for ApiError {
+    fn from(value: E) -> Self {
+        ApiError(value.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.0.status();
+        (status, Json(json!({ "error": self.0.into_message() }))).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct TargetRequest {
+    /// "equatorial", "galactic", "horizontal", "sun", "moon", "name" or
+    /// "gnss".
+    system: String,
+    x: Option<f64>, // degrees
+    y: Option<f64>, // degrees
+    /// Catalog name for "name".
+    name: Option<String>,
+    /// Satellite for "gnss".
+    norad_id: Option<u64>,
+    #[serde(default)]
+    az_offset_deg: f64,
+    #[serde(default)]
+    el_offset_deg: f64,
+}
+
+/// Point a telescope at a new target, ending and saving any running
+/// integration, like Track on the observe page.
+async fn post_target(
+    Extension(lang): Extension<Language>,
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Path(telescope_id): Path<String>,
+    Json(request): Json<TargetRequest>,
+) -> Result<Json<TelescopeInfo>, ApiError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
+    let telescope = authorized_telescope(&state, &user, &telescope_id).await?;
+    claim_control(&lang, &state, &user, &telescope_id).await?;
+    let target = parse_target(&state, &request)?;
+    let az_offset_rad = request.az_offset_deg.to_radians();
+    let el_offset_rad = request.el_offset_deg.to_radians();
+    retarget(
+        &state,
+        &user,
+        &telescope_id,
+        telescope.as_ref(),
+        target,
+        az_offset_rad,
+        el_offset_rad,
+    )
+    .await?;
+    audit::record(
+        state.database_connection.clone(),
+        &user,
+        AuditAction::SetTarget,
+        &target_detail(&telescope_id, target, az_offset_rad, el_offset_rad),
+    )
+    .await;
+    Ok(Json(telescope.get_info().await?))
+}
+
+/// The target `request` describes, with names looked up in the catalog.
+fn parse_target(state: &AppState, request: &TargetRequest) -> Result<TelescopeTarget, AppError> {
+    let coordinates = || {
+        request
+            .x
+            .zip(request.y)
+            .ok_or_else(|| AppError::BadRequest("Give the coordinates as x and y".to_string()))
+    };
+    let target = match request.system.as_str() {
+        "equatorial" => {
+            let (x, y) = coordinates()?;
+            let (right_ascension, declination) =
+                normalize_equatorial(x, y).map_err(|err| AppError::BadRequest(err.to_string()))?;
+            TelescopeTarget::Equatorial {
+                right_ascension,
+                declination,
+                epoch: J2000,
+            }
+        }
+        "galactic" => {
+            let (x, y) = coordinates()?;
+            let (longitude, latitude) =
+                normalize_galactic(x, y).map_err(|err| AppError::BadRequest(err.to_string()))?;
+            TelescopeTarget::Galactic {
+                longitude,
+                latitude,
+            }
+        }
+        "horizontal" => {
+            let (x, y) = coordinates()?;
+            let direction =
+                normalize_horizontal(x, y).map_err(|err| AppError::BadRequest(err.to_string()))?;
+            TelescopeTarget::Horizontal {
+                azimuth: direction.azimuth,
+                elevation: direction.elevation,
+            }
+        }
+        "sun" => TelescopeTarget::Sun,
+        "moon" => TelescopeTarget::Moon,
+        "name" => {
+            let name = request.name.as_deref().unwrap_or_default();
+            state
+                .source_catalog
+                .lookup(name, Utc::now())
+                .ok_or_else(|| {
+                    AppError::BadRequest(format!("No source named \"{name}\" in the catalog"))
+                })?
+        }
+        "gnss" => TelescopeTarget::Satellite {
+            norad_id: request.norad_id.ok_or_else(|| {
+                AppError::BadRequest("Give the satellite as norad_id".to_string())
+            })?,
+        },
+        system => {
+            return Err(AppError::BadRequest(format!(
+                "Unknown coordinate system {system}"
+            )));
+        }
+    };
+    Ok(target)
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum IntegrationRequest {
+    /// With the receiver settings of the observe page's form, all optional.
+    Start(ObserveForm),
+    Stop,
+}
+
+/// Start or stop an integration, like Start and End on the observe page.
+/// Stopping saves the observation.
+async fn post_integration(
+    Extension(lang): Extension<Language>,
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Path(telescope_id): Path<String>,
+    Json(request): Json<IntegrationRequest>,
+) -> Result<Json<TelescopeInfo>, ApiError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
+    let telescope = authorized_telescope(&state, &user, &telescope_id).await?;
+    match request {
+        IntegrationRequest::Start(form) => {
+            start_integration(&lang, &state, &user, &telescope_id, telescope.clone(), form).await?;
+        }
+        IntegrationRequest::Stop => {
+            let label = take_observation_label(&state, &telescope_id).await;
+            stop_and_save_observation(
+                telescope.as_ref(),
+                state.database_connection.clone(),
+                &user,
+                &state.tle_cache,
+                label.as_deref(),
+            )
+            .await;
+        }
+    }
+    Ok(Json(telescope.get_info().await?))
+}
+
+/// Most observations a user can have waiting on one telescope.
+const MAX_QUEUED_ITEMS: usize = 50;
+
+#[derive(Deserialize)]
+struct QueueRequest {
+    #[serde(flatten)]
+    target: TargetRequest,
+    integration_time_secs: f64,
+    #[serde(default)]
+    label: String,
+}
+
+/// The queue's owner. Guests can't book, so they have no use for one.
+fn queue_owner(user: Option<User>) -> Result<User, AppError> {
+    user.filter(|user| user.provider != "guest")
+        .ok_or(AppError::Unauthorized)
+}
+
+/// The user's observation queue on a telescope, finished items included.
+/// `queue_runner` observes the waiting ones in order, unattended, while the
+/// user has an active booking on it.
+async fn get_queue(
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Path(telescope_id): Path<String>,
+) -> Result<Json<Vec<QueueItem>>, ApiError> {
+    let user = queue_owner(user)?;
+    if state.telescopes.get(&telescope_id).await.is_none() {
+        return Err(AppError::NotFound.into());
+    }
+    let items = observation_queue::fetch_for_user(
+        state.database_connection.clone(),
+        user.id,
+        &telescope_id,
+    )
+    .await?;
+    Ok(Json(items))
+}
+
+/// Add a target to the end of the queue, to be integrated on for
+/// `integration_time_secs` with the observe page's default receiver
+/// settings. Needs no booking yet; the item waits for the next one.
+async fn post_queue(
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Path(telescope_id): Path<String>,
+    Json(request): Json<QueueRequest>,
+) -> Result<(StatusCode, Json<Vec<QueueItem>>), ApiError> {
+    let user = queue_owner(user)?;
+    let telescope = state
+        .telescopes
+        .get(&telescope_id)
+        .await
+        .ok_or(AppError::NotFound)?;
+    let target = parse_target(&state, &request.target)?;
+    let max_integration_secs = telescope.get_info().await?.max_integration_secs;
+    let secs = request.integration_time_secs;
+    if !(secs.is_finite() && secs > 0.0 && secs <= max_integration_secs) {
+        return Err(AppError::BadRequest(format!(
+            "Integration time must be between 0 and {max_integration_secs} s"
+        ))
+        .into());
+    }
+    let label = request.label.trim();
+    if label.chars().count() > MAX_LABEL_LEN {
+        return Err(AppError::BadRequest(format!(
+            "Label is longer than {MAX_LABEL_LEN} characters"
+        ))
+        .into());
+    }
+    let waiting = observation_queue::fetch_for_user(
+        state.database_connection.clone(),
+        user.id,
+        &telescope_id,
+    )
+    .await?
+    .into_iter()
+    .filter(|item| item.status == QueueStatus::Queued)
+    .count();
+    if waiting >= MAX_QUEUED_ITEMS {
+        return Err(AppError::BadRequest(format!(
+            "At most {MAX_QUEUED_ITEMS} observations can wait in the queue"
+        ))
+        .into());
+    }
+    observation_queue::enqueue(
+        state.database_connection.clone(),
+        user.id,
+        &telescope_id,
+        &QueuedObservation {
+            target,
+            az_offset_deg: request.target.az_offset_deg,
+            el_offset_deg: request.target.el_offset_deg,
+            integration_time_secs: secs,
+            label: label.to_string(),
+        },
+    )
+    .await?;
+    let items = observation_queue::fetch_for_user(
+        state.database_connection.clone(),
+        user.id,
+        &telescope_id,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(items)))
+}
+
+/// Cancel a queued observation that hasn't started. One already running
+/// is ended like any other, with `integration`'s stop action.
+async fn delete_queue_item(
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Path((telescope_id, item_id)): Path<(String, i64)>,
+) -> Result<Response, ApiError> {
+    let user = queue_owner(user)?;
+    let connection = state.database_connection.clone();
+    if observation_queue::cancel(connection.clone(), user.id, &telescope_id, item_id).await? {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+    let exists = observation_queue::fetch_for_user(connection, user.id, &telescope_id)
+        .await?
+        .iter()
+        .any(|item| item.id == item_id);
+    if exists {
+        return Err(AppError::Conflict(
+            "Only observations that haven't started can be cancelled".to_string(),
+        )
+        .into());
+    }
+    Ok((
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": "No such queued observation" })),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+struct ConvertQuery {
+    from: String,
+    to: String,
+    x: f64, // degrees
+    y: f64, // degrees
+    when: Option<DateTime<Utc>>,
+    /// Telescope whose site horizontal coordinates are seen from.
+    telescope: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Converted {
+    system: String,
+    x: f64, // degrees
+    y: f64, // degrees
+    when: DateTime<Utc>,
+}
+
+/// Convert a position between the equatorial (J2000), galactic and
+/// horizontal systems. Horizontal coordinates are as seen from `telescope`
+/// at `when`, which defaults to now; converting to or from them without a
+/// telescope is a bad request.
+async fn get_convert(
+    State(state): State<AppState>,
+    Query(query): Query<ConvertQuery>,
+) -> Result<Json<Converted>, StatusCode> {
+    if !(query.x.is_finite() && query.y.is_finite()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let location = match &query.telescope {
+        Some(telescope) => Some(
+            state
+                .telescopes
+                .location(telescope)
+                .await
+                .ok_or(StatusCode::NOT_FOUND)?,
+        ),
+        None => None,
+    };
+    let site = || location.ok_or(StatusCode::BAD_REQUEST);
+    let when = query.when.unwrap_or_else(Utc::now);
+    let (x, y) = (query.x.to_radians(), query.y.to_radians());
+    // Everything goes through J2000 equatorial.
+    let (ra, dec) = match query.from.as_str() {
+        "equatorial" => (x, y),
+        "galactic" => equatorial_from_galactic(x, y),
+        "horizontal" => equatorial_from_horizontal(
+            site()?,
+            when,
+            Direction {
+                azimuth: x,
+                elevation: y,
+            },
+        ),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    let (x, y) = match query.to.as_str() {
+        "equatorial" => (ra, dec),
+        "galactic" => galactic_from_equatorial(ra, dec),
+        "horizontal" => {
+            let direction = horizontal_from_equatorial(site()?, when, ra, dec);
+            (direction.azimuth, direction.elevation)
+        }
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    Ok(Json(Converted {
+        system: query.to,
+        x: x.to_degrees(),
+        y: y.to_degrees(),
+        when,
+    }))
+}
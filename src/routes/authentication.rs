@@ -4,38 +4,56 @@ use askama::Template;
 use axum::{
     Extension, Router,
     extract::{ConnectInfo, Form, Path, Query, State},
-    http::{HeaderMap, StatusCode, header::SET_COOKIE},
+    http::{
+        HeaderMap, StatusCode,
+        header::{HOST, SET_COOKIE},
+    },
+    middleware,
     response::{Html, IntoResponse, Redirect, Response},
     routing::{get, post},
 };
 use oauth2::{
     AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, RedirectUrl, Scope,
-    TokenResponse, TokenUrl, basic::BasicClient,
+    TokenResponse, TokenUrl, basic::BasicClient, url::Url,
 };
 use rand::Rng;
 use reqwest::header::USER_AGENT;
 use rusqlite::Result;
 use serde::Deserialize;
-use serde_json::{Map, Value};
+use serde_json::Value;
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{debug, info, warn};
 
 use crate::i18n::Language;
 use crate::models::session::{Session, complete_oauth2_login, start_oauth2_login};
+use crate::models::user_identity;
 use crate::routes::index::render_main;
 use crate::{app::AppState, error::InternalError};
-use crate::{middleware::session::session_cookie, models::user::User};
+use crate::{
+    middleware::rate_limit::rate_limit,
+    middleware::session::{clear_session_cookie, session_cookie},
+    models::user::User,
+};
 
 pub fn routes(state: AppState) -> Router {
-    Router::new()
+    // Unauthenticated and each hit writes to the database, so throttled.
+    let oauth_routes = Router::new()
         .route("/authorized", get(authenticate_from_oauth2))
+        .route("/redirect/{provider_name}", get(redirect_to_auth_provider))
+        .route("/link/{provider_name}", post(link_auth_provider))
+        .route_layer(middleware::from_fn_with_state(
+            state.oauth_rate_limiter.clone(),
+            rate_limit,
+        ));
+    Router::new()
         .route("/login", get(login))
         .route("/local", post(local_login))
         // POST so a cross-site link can't log users out (SameSite=Lax
         // still sends cookies on top-level GET navigations).
         .route("/logout", post(logout))
-        .route("/redirect/{provider_name}", get(redirect_to_auth_provider))
+        .route("/logout-all", post(logout_all))
+        .merge(oauth_routes)
         .with_state(state)
 }
 
@@ -50,6 +68,24 @@ async fn logout(
     Ok(Redirect::to("/"))
 }
 
+/// Log out on every device, for when the account may be compromised.
+async fn logout_all(
+    State(state): State<AppState>,
+    Extension(session): Extension<Option<Session>>,
+) -> Result<impl IntoResponse, InternalError> {
+    if let Some(session) = session {
+        Session::delete_all_for_user(state.database_connection.clone(), &session.user).await?;
+    }
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        SET_COOKIE,
+        clear_session_cookie(&state.session_config)
+            .parse()
+            .expect("Cookie should be parseable always."),
+    );
+    Ok((headers, Redirect::to("/")))
+}
+
 // Basic Oath2 flow
 //
 // 1. User is prompted to select oauth2 provider.
@@ -147,8 +183,13 @@ async fn local_login(
         "successful local login"
     );
 
-    let session = Session::create(state.database_connection.clone(), &user).await?;
-    let cookie = session_cookie(&session.token);
+    let session = Session::create(
+        state.database_connection.clone(),
+        &user,
+        state.session_config.lifetime(),
+    )
+    .await?;
+    let cookie = session_cookie(&state.session_config, &session);
     let mut headers = HeaderMap::new();
     headers.insert(
         SET_COOKIE,
@@ -157,21 +198,122 @@ async fn local_login(
     Ok((headers, Redirect::to("/")).into_response())
 }
 
+/// The value at `path` in a provider's user object: keys separated by
+/// dots, so `data.user.id` reaches into nested objects. Numeric parts
+/// index arrays.
+fn json_field<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// The first value of a header a proxy may have appended to.
+fn forwarded_header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .next()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Where the provider should send the user back to: the configured
+/// redirect URI's path on the host the request came in on, if that host is
+/// one of `allowed_hosts`, so one deployment can answer to several names.
+/// Behind a proxy, X-Forwarded-Proto and X-Forwarded-Host say what the
+/// browser used; otherwise the Host header and the configured URI's scheme.
+/// Those headers can be set by anyone, hence the allow list: any other
+/// host, or anything missing or malformed, gets the configured URI.
+fn redirect_uri_for(headers: &HeaderMap, configured: &str, allowed_hosts: &[String]) -> String {
+    let Ok(configured_url) = Url::parse(configured) else {
+        return configured.to_string();
+    };
+    let scheme = match forwarded_header(headers, "x-forwarded-proto") {
+        Some(proto @ ("http" | "https")) => proto,
+        _ => configured_url.scheme(),
+    };
+    let Some(host) = forwarded_header(headers, "x-forwarded-host").or_else(|| {
+        headers
+            .get(HOST)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    }) else {
+        return configured.to_string();
+    };
+    if !allowed_hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    {
+        return configured.to_string();
+    }
+    // A host with anything that isn't part of a hostname or port would let
+    // the header rewrite the rest of the URL.
+    if host.is_empty()
+        || !host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".-:[]".contains(c))
+    {
+        return configured.to_string();
+    }
+    match Url::parse(&format!("{scheme}://{host}")) {
+        Ok(mut url) => {
+            url.set_path(configured_url.path());
+            url.to_string()
+        }
+        Err(_) => configured.to_string(),
+    }
+}
+
 // 2. We redirect the user to auth provider (e.g. Discord) where they authorize our app.
 async fn redirect_to_auth_provider(
     Path(provider): Path<String>,
     State(state): State<AppState>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, InternalError> {
+    oauth2_redirect(&state, &headers, &provider, None).await
+}
+
+/// Like logging in with `provider`, but the identity the user comes back
+/// with is linked to their current account so they can log in with either.
+async fn link_auth_provider(
+    Path(provider): Path<String>,
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, InternalError> {
+    let Some(user) = user.filter(|user| user.provider != "guest") else {
+        return Ok(StatusCode::UNAUTHORIZED.into_response());
+    };
+    Ok(oauth2_redirect(&state, &headers, &provider, Some(user.id))
+        .await?
+        .into_response())
+}
+
+async fn oauth2_redirect(
+    state: &AppState,
+    headers: &HeaderMap,
+    provider: &str,
+    link_user_id: Option<i64>,
+) -> Result<Redirect, InternalError> {
     // To know that we're the originator of the request when the user comes back from OAuth2 provider
 
-    let auth_provider = state.secrets.get_auth_provider(&provider)?;
+    let auth_provider = state.secrets.get_auth_provider(provider)?;
     let client = BasicClient::new(ClientId::new(auth_provider.client_id.clone()))
         .set_auth_uri(
-            AuthUrl::new(auth_provider.auth_uri.clone()).expect("Hardcoded URL should always work"),
+            AuthUrl::new(auth_provider.auth_uri.clone())
+                .expect("Provider URLs are checked at startup"),
         )
         .set_redirect_uri(
-            RedirectUrl::new(auth_provider.redirect_uri.clone())
-                .expect("Hardcoded URL should always work."),
+            RedirectUrl::new(redirect_uri_for(
+                headers,
+                &auth_provider.redirect_uri,
+                &auth_provider.redirect_hosts,
+            ))
+            .expect("Redirect URI is a configured or freshly parsed URL"),
         );
     let (url, token) = auth_provider
         .scopes
@@ -181,7 +323,13 @@ async fn redirect_to_auth_provider(
         })
         .url();
 
-    start_oauth2_login(state.database_connection.clone(), &provider, &token).await?;
+    start_oauth2_login(
+        state.database_connection.clone(),
+        provider,
+        &token,
+        link_user_id,
+    )
+    .await?;
 
     info!("Sending user to {provider} to authenticate");
 
@@ -201,22 +349,36 @@ struct AuthRequest {
 // 3. User comes back with an authorization code.
 async fn authenticate_from_oauth2(
     Query(query): Query<AuthRequest>,
+    Extension(current_user): Extension<Option<User>>,
     State(state): State<AppState>,
+    headers: HeaderMap,
 ) -> Result<Response, InternalError> {
     debug!("Coming back from OAuth2 provider");
-    let provider_name =
+    let (provider_name, link_user_id) =
         match complete_oauth2_login(state.database_connection.clone(), &query.state).await {
-            Ok(provider) => provider,
+            Ok(pending) => pending,
             Err(err) => {
                 warn!("Failed to validate CSRF token from oauth2 provider: {err:?}");
                 return Ok(StatusCode::UNAUTHORIZED.into_response());
             }
         };
 
+    // Guest accounts are throwaway; nothing gets linked to them.
+    let current_user = current_user.filter(|user| user.provider != "guest");
+    if let Some(link_user_id) = link_user_id
+        && current_user.as_ref().map(|user| user.id) != Some(link_user_id)
+    {
+        warn!("OAuth2 link for user {link_user_id} completed by someone else");
+        return Ok(StatusCode::UNAUTHORIZED.into_response());
+    }
+
     let Some(code) = query.code else {
         if let Some(error) = &query.error {
             debug!("OAuth2 provider {provider_name} returned error: {error}");
         }
+        if link_user_id.is_some() {
+            return Ok(Redirect::to("/account").into_response());
+        }
         return Ok(Redirect::to("/auth/login").into_response());
     };
 
@@ -230,11 +392,18 @@ async fn authenticate_from_oauth2(
     let client = BasicClient::new(ClientId::new(provider.client_id.clone()))
         .set_client_secret(ClientSecret::new(provider.client_secret.clone()))
         .set_redirect_uri(
-            RedirectUrl::new(provider.redirect_uri.clone())
-                .expect("Hardcoded URL should always work."),
+            // Must match the one the user was sent off with, which it does
+            // as they come back to the same host.
+            RedirectUrl::new(redirect_uri_for(
+                &headers,
+                &provider.redirect_uri,
+                &provider.redirect_hosts,
+            ))
+            .expect("Redirect URI is a configured or freshly parsed URL"),
         )
         .set_token_uri(
-            TokenUrl::new(provider.token_uri.clone()).expect("Hardcoded URL should always work."),
+            TokenUrl::new(provider.token_uri.clone())
+                .expect("Provider URLs are checked at startup"),
         );
     let token = match client
         .exchange_code(AuthorizationCode::new(code))
@@ -253,7 +422,7 @@ async fn authenticate_from_oauth2(
     debug!("Code authenticated");
 
     // 5. We use the token to get the identity of the user from oauth2 provider.
-    let user_data: Map<String, Value> = http_client
+    let user_data: Value = http_client
         .get(&provider.user_uri)
         .header(USER_AGENT, "salsa")
         .bearer_auth(token.access_token().secret())
@@ -262,7 +431,7 @@ async fn authenticate_from_oauth2(
         .map_err(|err| {
             InternalError::new(format!("Failed to fetch token from {provider_name}: {err}"))
         })?
-        .json::<Map<String, Value>>()
+        .json::<Value>()
         .await
         .map_err(|err| {
             InternalError::new(format!(
@@ -270,7 +439,7 @@ async fn authenticate_from_oauth2(
             ))
         })?;
 
-    let user_id = match user_data.get(&provider.id_field) {
+    let user_id = match json_field(&user_data, &provider.id_field) {
         Some(Value::Number(user_id)) => format!("{}", user_id),
         Some(Value::String(user_id)) => user_id.clone(),
         None => {
@@ -287,6 +456,10 @@ async fn authenticate_from_oauth2(
         }
     };
 
+    if let Some(link_user_id) = link_user_id {
+        return link_identity(&state, link_user_id, &provider_name, &user_id).await;
+    }
+
     let user = match User::fetch_with_user_with_external_id(
         state.database_connection.clone(),
         provider_name.clone(),
@@ -296,12 +469,17 @@ async fn authenticate_from_oauth2(
     {
         Some(user) => user,
         None => {
+            // Identities are only linked through `/auth/link`, whose state
+            // is tied to the account that started it. A callback reaching a
+            // logged-in browser could have been started by anyone.
             info!("Creating new user from {provider_name} login");
-            let Some(Some(username)) = user_data
-                .get(&provider.display_name_field)
+            let Some(Some(username)) = json_field(&user_data, &provider.display_name_field)
                 .map(|username| username.as_str())
             else {
-                let keys: Vec<&String> = user_data.keys().collect();
+                let keys: Vec<&String> = user_data
+                    .as_object()
+                    .map(|map| map.keys().collect())
+                    .unwrap_or_default();
                 return Err(InternalError::new(format!(
                     "No '{}' field in user object returned from {}; available keys: {keys:?}",
                     provider.display_name_field, provider.user_uri,
@@ -323,10 +501,15 @@ async fn authenticate_from_oauth2(
         "successful OAuth2 login"
     );
 
-    let session = Session::create(state.database_connection.clone(), &user).await?;
+    let session = Session::create(
+        state.database_connection.clone(),
+        &user,
+        state.session_config.lifetime(),
+    )
+    .await?;
     // Note: We reuse the same session cookie name here. So we don't need to
     // reset that cookie.
-    let cookie = session_cookie(&session.token);
+    let cookie = session_cookie(&state.session_config, &session);
 
     let mut headers = HeaderMap::new();
     headers.insert(
@@ -335,3 +518,142 @@ async fn authenticate_from_oauth2(
     );
     Ok((headers, Redirect::to("/")).into_response())
 }
+
+async fn link_identity(
+    state: &AppState,
+    user_id: i64,
+    provider_name: &str,
+    external_id: &str,
+) -> Result<Response, InternalError> {
+    if user_identity::link(
+        state.database_connection.clone(),
+        user_id,
+        provider_name,
+        external_id,
+    )
+    .await?
+    {
+        info!(user_id, provider = provider_name, "linked OAuth2 identity");
+        Ok(Redirect::to("/account?link=linked").into_response())
+    } else {
+        info!(
+            user_id,
+            provider = provider_name,
+            "refused to link OAuth2 identity of another account"
+        );
+        Ok(Redirect::to("/account?link=taken").into_response())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIGURED: &str = "https://salsa.example.org/auth/authorized";
+
+    #[test]
+    fn json_field_follows_dotted_paths() {
+        let user = serde_json::json!({
+            "id": 7,
+            "data": {"user": {"id": "abc", "emails": ["a@example.org"]}},
+            "dotted.key": 1,
+        });
+        assert_eq!(Some(&Value::from(7)), json_field(&user, "id"));
+        assert_eq!(Some(&Value::from("abc")), json_field(&user, "data.user.id"));
+        assert_eq!(
+            Some(&Value::from("a@example.org")),
+            json_field(&user, "data.user.emails.0")
+        );
+        assert_eq!(None, json_field(&user, "data.user.missing"));
+        assert_eq!(None, json_field(&user, "id.deeper"));
+        assert_eq!(None, json_field(&user, "data.user.emails.x"));
+        // Keys containing dots can't be reached; no provider uses them.
+        assert_eq!(None, json_field(&user, "dotted.key"));
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn hosts(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn direct_requests_use_the_host_header() {
+        assert_eq!(
+            "https://other.example.org/auth/authorized",
+            redirect_uri_for(
+                &headers(&[("host", "Other.example.org")]),
+                CONFIGURED,
+                &hosts(&["other.example.org"])
+            )
+        );
+        assert_eq!(
+            "http://localhost:3000/auth/authorized",
+            redirect_uri_for(
+                &headers(&[("host", "localhost:3000")]),
+                "http://127.0.0.1:3000/auth/authorized",
+                &hosts(&["localhost:3000"])
+            )
+        );
+    }
+
+    #[test]
+    fn proxied_requests_use_the_forwarded_headers() {
+        assert_eq!(
+            "http://dev.example.org:8080/auth/authorized",
+            redirect_uri_for(
+                &headers(&[
+                    ("host", "127.0.0.1:3000"),
+                    ("x-forwarded-proto", "http"),
+                    ("x-forwarded-host", "dev.example.org:8080, inner.proxy"),
+                ]),
+                CONFIGURED,
+                &hosts(&["dev.example.org:8080"])
+            )
+        );
+    }
+
+    #[test]
+    fn hosts_not_allowed_get_the_configured_uri() {
+        let forged = headers(&[
+            ("host", "evil.example"),
+            ("x-forwarded-host", "evil.example"),
+        ]);
+        assert_eq!(CONFIGURED, redirect_uri_for(&forged, CONFIGURED, &[]));
+        assert_eq!(
+            CONFIGURED,
+            redirect_uri_for(&forged, CONFIGURED, &hosts(&["other.example.org"]))
+        );
+    }
+
+    #[test]
+    fn missing_or_odd_hosts_fall_back_to_the_configured_uri() {
+        let allowed = hosts(&["other.example.org", "evil.example/path?"]);
+        assert_eq!(
+            CONFIGURED,
+            redirect_uri_for(&HeaderMap::new(), CONFIGURED, &allowed)
+        );
+        assert_eq!(
+            CONFIGURED,
+            redirect_uri_for(
+                &headers(&[("host", "evil.example/path?")]),
+                CONFIGURED,
+                &allowed
+            )
+        );
+        assert_eq!(
+            "https://other.example.org/auth/authorized",
+            redirect_uri_for(
+                &headers(&[("host", "other.example.org"), ("x-forwarded-proto", "ftp")]),
+                CONFIGURED,
+                &allowed
+            )
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use crate::app::AppState;
+use crate::error::AppError;
 use crate::geoip::lookup_country;
 use crate::i18n::Language;
-use crate::models::booking::Booking;
+use crate::models::audit::{self, AuditAction};
+use crate::models::booking::{Booking, QUOTA_WINDOW_DAYS, contiguous_run_with};
 use crate::models::maintenance::fetch_maintenance_set;
 use crate::models::support_announcement::fetch_support_announcement;
 use crate::models::user::User;
@@ -197,7 +199,7 @@ async fn get_bookings(
     headers: HeaderMap,
     Query(query): Query<WeekQuery>,
     State(state): State<AppState>,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, AppError> {
     let Some(user) = user else {
         return Ok(if headers.get("hx-request").is_some() {
             ([("HX-Redirect", "/auth/login")], "").into_response()
@@ -243,14 +245,14 @@ async fn create_booking(
     headers: HeaderMap,
     State(state): State<AppState>,
     Form(form): Form<SlotBookingForm>,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, AppError> {
     let Some(user) = user else {
         return Ok(StatusCode::UNAUTHORIZED.into_response());
     };
 
     let now = Utc::now();
-    let start_time =
-        DateTime::<Utc>::from_timestamp(form.start_timestamp, 0).ok_or(StatusCode::BAD_REQUEST)?;
+    let start_time = DateTime::<Utc>::from_timestamp(form.start_timestamp, 0)
+        .ok_or_else(|| AppError::BadRequest("Invalid start time".to_string()))?;
     let end_time = start_time + Duration::hours(1);
 
     if !state.telescopes.contains_key(&form.telescope).await {
@@ -266,15 +268,27 @@ async fn create_booking(
         .filter(|s| !s.is_empty())
         .map(str::to_string);
 
-    let maintenance = fetch_maintenance_set(state.database_connection.clone())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let maintenance = fetch_maintenance_set(state.database_connection.clone()).await?;
     let max_upcoming = state.booking_config.max_upcoming_bookings;
-    let upcoming_count = Booking::fetch_for_user(state.database_connection.clone(), &user)
-        .await?
-        .into_iter()
-        .filter(|b| b.end_time > now)
-        .count();
+    let max_hours = state.booking_config.max_booking_hours;
+    let max_advance_days = state.booking_config.max_advance_days;
+    let user_bookings = Booking::fetch_for_user(state.database_connection.clone(), &user).await?;
+    let upcoming_count = user_bookings.iter().filter(|b| b.end_time > now).count();
+    let (run_start, run_end) =
+        contiguous_run_with(&user_bookings, &form.telescope, start_time, end_time);
+    let max_weekly_hours = state.booking_config.max_weekly_hours;
+    // Every week containing the new slot is checked, from the one ending
+    // with it to the one starting with it.
+    let quota_from = (end_time - Duration::days(QUOTA_WINDOW_DAYS)).max(now);
+    let weekly_hours = Booking::max_booked_hours_for_user(
+        state.database_connection.clone(),
+        &user,
+        &form.telescope,
+        quota_from,
+        start_time.max(quota_from),
+    )
+    .await?;
+    let slot_hours = (end_time - start_time).num_seconds() as f64 / 3600.0;
 
     let error = if end_time <= now {
         Some(fl!(lang.loader(), "booking-error-slot-ended"))
@@ -299,6 +313,25 @@ async fn create_booking(
             "booking-error-limit",
             max = max_upcoming
         ))
+    } else if !user.is_admin && start_time > now + Duration::days(max_advance_days.into()) {
+        Some(fl!(
+            lang.loader(),
+            "booking-error-too-far-ahead",
+            days = max_advance_days
+        ))
+    } else if !user.is_admin && run_end - run_start > Duration::hours(max_hours.into()) {
+        Some(fl!(
+            lang.loader(),
+            "booking-error-too-long",
+            max = max_hours
+        ))
+    } else if !user.is_admin && weekly_hours + slot_hours > f64::from(max_weekly_hours) {
+        Some(fl!(
+            lang.loader(),
+            "booking-error-weekly-quota",
+            max = max_weekly_hours,
+            telescope = form.telescope.as_str()
+        ))
     } else {
         let inserted = Booking::create(
             state.database_connection.clone(),
@@ -311,6 +344,18 @@ async fn create_booking(
         )
         .await?;
         if inserted {
+            audit::record(
+                state.database_connection.clone(),
+                &user,
+                AuditAction::CreateBooking,
+                &format!(
+                    "{}: {} to {}",
+                    form.telescope,
+                    start_time.format("%Y-%m-%d %H:%M UTC"),
+                    end_time.format("%Y-%m-%d %H:%M UTC")
+                ),
+            )
+            .await;
             None
         } else {
             let local = start_time.with_timezone(&user.tz());
@@ -350,19 +395,35 @@ async fn delete_booking(
     Path(booking_id): Path<i64>,
     Query(query): Query<DeleteQuery>,
     State(state): State<AppState>,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, AppError> {
     let Some(user) = user else {
-        return Err(StatusCode::UNAUTHORIZED);
+        return Err(AppError::Unauthorized);
     };
     let booking = Booking::fetch_one(state.database_connection.clone(), booking_id)
         .await?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .ok_or(AppError::NotFound)?;
+    let detail = format!(
+        "{}: {} to {}, booked by {}",
+        booking.telescope_name,
+        booking.start_time.format("%Y-%m-%d %H:%M UTC"),
+        booking.end_time.format("%Y-%m-%d %H:%M UTC"),
+        booking.user_name
+    );
     let success = booking
         .delete(state.database_connection.clone(), &user)
         .await?;
+    // Someone else's booking: answer as if it did not exist rather than
+    // confirming the id.
     if !success {
-        return Err(StatusCode::UNAUTHORIZED);
+        return Err(AppError::NotFound);
     }
+    audit::record(
+        state.database_connection.clone(),
+        &user,
+        AuditAction::CancelBooking,
+        &detail,
+    )
+    .await;
 
     let now = Utc::now();
     let week_start = week_monday(
@@ -389,9 +450,9 @@ async fn delete_booking(
 async fn export_bookings_ical(
     Extension(user): Extension<Option<User>>,
     State(state): State<AppState>,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, AppError> {
     let Some(user) = user else {
-        return Err(StatusCode::UNAUTHORIZED);
+        return Err(AppError::Unauthorized);
     };
 
     let now = Utc::now();
@@ -444,7 +505,7 @@ async fn build_bookings_page(
     week_start: NaiveDate,
     error: Option<String>,
     lang: Language,
-) -> Result<String, StatusCode> {
+) -> Result<String, AppError> {
     let tz = user.tz();
     // Minute component of the current UTC offset (0, 30 or 45). The whole
     // grid is laid out at this minute past the local hour so every cell
@@ -477,9 +538,7 @@ async fn build_bookings_page(
         let pos = preferred_order.iter().position(|&p| p == lower.as_str());
         (pos.is_none(), pos.unwrap_or(usize::MAX), lower)
     });
-    let maintenance_set = fetch_maintenance_set(state.database_connection.clone())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let maintenance_set = fetch_maintenance_set(state.database_connection.clone()).await?;
     let maintenance_telescopes: Vec<bool> = telescope_names
         .iter()
         .map(|name| maintenance_set.contains(name.as_str()))
@@ -492,9 +551,7 @@ async fn build_bookings_page(
             .filter(|b| b.end_time > now)
             .collect();
     let all_users = if user.is_admin {
-        User::fetch_all_non_guest(state.database_connection.clone())
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        User::fetch_all_non_guest(state.database_connection.clone()).await?
     } else {
         vec![]
     };
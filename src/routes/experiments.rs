@@ -1,27 +1,32 @@
 use axum::{
     Extension, Router,
+    extract::State,
     http::HeaderMap,
     response::{Html, IntoResponse},
     routing::get,
 };
 
+use crate::app::AppState;
 use crate::i18n::Language;
 use crate::models::user::User;
 use crate::routes::index::render_main;
 
-pub fn routes() -> Router {
+pub fn routes(state: AppState) -> Router {
     Router::new()
         .route("/hi", get(get_experiments_hi))
         .route("/gnss", get(get_experiments_gnss))
         .route("/sun", get(get_experiments_sun))
+        .with_state(state)
 }
 
 async fn get_experiments_sun(
+    State(state): State<AppState>,
     Extension(lang): Extension<Language>,
     Extension(user): Extension<Option<User>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
     let content = crate::routes::read_content_page(
+        &state.assets_dir,
         "experiments-sun",
         lang,
         "<p>Sun experiment page not available.</p>",
@@ -35,11 +40,13 @@ async fn get_experiments_sun(
 }
 
 async fn get_experiments_gnss(
+    State(state): State<AppState>,
     Extension(lang): Extension<Language>,
     Extension(user): Extension<Option<User>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
     let content = crate::routes::read_content_page(
+        &state.assets_dir,
         "experiments-gnss",
         lang,
         "<p>GNSS experiment page not available.</p>",
@@ -53,11 +60,13 @@ async fn get_experiments_gnss(
 }
 
 async fn get_experiments_hi(
+    State(state): State<AppState>,
     Extension(lang): Extension<Language>,
     Extension(user): Extension<Option<User>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
     let content = crate::routes::read_content_page(
+        &state.assets_dir,
         "experiments-hi",
         lang,
         "<p>HI experiment page not available.</p>",
@@ -0,0 +1,70 @@
+//! Machine-readable health check for service monitoring. Like the JSON API
+//! it is public and exposes nothing user-specific.
+
+use crate::app::AppState;
+use axum::Router;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json};
+use axum::routing::get;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A telescope whose position has not been read for longer than this is
+/// reported as stale. Positions are normally polled once a second.
+const STALE_AFTER_SECS: f64 = 10.0;
+
+pub fn routes(state: AppState) -> Router {
+    Router::new().route("/", get(get_health)).with_state(state)
+}
+
+#[derive(Serialize)]
+struct HealthReport {
+    status: &'static str,
+    telescopes: Vec<TelescopeHealth>,
+}
+
+#[derive(Serialize)]
+struct TelescopeHealth {
+    id: String,
+    healthy: bool,
+    /// `None` when the telescope couldn't be queried at all.
+    controller_connected: Option<bool>,
+    last_successful_update: Option<DateTime<Utc>>,
+    seconds_since_update: Option<f64>,
+}
+
+/// 200 when every telescope has reported its position recently, 503 when
+/// any is stale or unreachable.
+async fn get_health(State(state): State<AppState>) -> impl IntoResponse {
+    let mut names = state.telescopes.get_names().await;
+    names.sort();
+    let now = Utc::now();
+    let mut telescopes = Vec::new();
+    for name in names {
+        let Some(telescope) = state.telescopes.get(&name).await else {
+            continue;
+        };
+        let info = telescope.get_info().await.ok();
+        let controller_connected = info.as_ref().map(|i| i.mount_status.connected);
+        let last_successful_update = info.and_then(|i| i.last_successful_update);
+        let seconds_since_update =
+            last_successful_update.map(|t| (now - t).num_milliseconds() as f64 / 1000.0);
+        let healthy = controller_connected != Some(false)
+            && seconds_since_update.is_some_and(|age| age <= STALE_AFTER_SECS);
+        telescopes.push(TelescopeHealth {
+            id: name,
+            healthy,
+            controller_connected,
+            last_successful_update,
+            seconds_since_update,
+        });
+    }
+    let all_healthy = telescopes.iter().all(|t| t.healthy);
+    let (code, status) = if all_healthy {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "degraded")
+    };
+    (code, Json(HealthReport { status, telescopes }))
+}
@@ -194,7 +194,7 @@ pub fn render_main(user: Option<User>, lang: Language, content: String) -> Strin
         .as_ref()
         .is_some_and(|u| u.provider != "guest" && u.timezone.is_none());
     let name = match &user {
-        Some(u) => u.name.clone(),
+        Some(u) => u.display_name().to_string(),
         None => String::new(),
     };
     let languages = Language::ALL
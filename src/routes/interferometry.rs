@@ -1,5 +1,5 @@
 use crate::app::AppState;
-use crate::coords::Location;
+use crate::coords::{J2000, Location, precess};
 use crate::correlator::CorrelatorHandle;
 use crate::i18n::Language;
 use crate::models::booking::{booking_is_active, consecutive_booking_end};
@@ -213,6 +213,8 @@ async fn tel_status_entry(state: &AppState, user: &User, tel_id: &str) -> Option
         TelescopeStatus::Idle => "Idle",
         TelescopeStatus::Slewing => "Slewing",
         TelescopeStatus::Tracking => "Tracking",
+        TelescopeStatus::Stowed => "Stowed",
+        TelescopeStatus::Parked => "Parked",
     }
     .to_string();
     let booking_end_ms = consecutive_booking_end(state.database_connection.clone(), user, tel_id)
@@ -301,6 +303,7 @@ async fn post_track(
         "equatorial" => TelescopeTarget::Equatorial {
             right_ascension: form.target_x.to_radians(),
             declination: form.target_y.to_radians(),
+            epoch: J2000,
         },
         "horizontal" => TelescopeTarget::Horizontal {
             azimuth: form.target_x.to_radians(),
@@ -410,11 +413,11 @@ fn target_to_session_fields(target: TelescopeTarget) -> (String, f64, f64) {
         TelescopeTarget::Equatorial {
             right_ascension,
             declination,
-        } => (
-            "equatorial".into(),
-            right_ascension.to_degrees(),
-            declination.to_degrees(),
-        ),
+            epoch,
+        } => {
+            let (ra, dec) = precess(right_ascension, declination, epoch, J2000);
+            ("equatorial".into(), ra.to_degrees(), dec.to_degrees())
+        }
         TelescopeTarget::Galactic {
             longitude,
             latitude,
@@ -429,6 +432,7 @@ fn target_to_session_fields(target: TelescopeTarget) -> (String, f64, f64) {
             elevation.to_degrees(),
         ),
         TelescopeTarget::Sun => ("sun".into(), 0.0, 0.0),
+        TelescopeTarget::Moon => ("moon".into(), 0.0, 0.0),
         TelescopeTarget::Satellite { norad_id } => ("gnss".into(), norad_id as f64, 0.0),
     }
 }
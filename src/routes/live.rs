@@ -387,10 +387,13 @@ async fn get_telescopes_status(
                         TelescopeStatus::Idle => "Idle",
                         TelescopeStatus::Slewing => "Slewing",
                         TelescopeStatus::Tracking => "Tracking",
+                        TelescopeStatus::Stowed => "Stowed",
+                        TelescopeStatus::Parked => "Parked",
                     }
                     .to_string();
                     let target = info.current_target.map(|t| match t {
                         TelescopeTarget::Sun => "Sun".to_string(),
+                        TelescopeTarget::Moon => "Moon".to_string(),
                         TelescopeTarget::Satellite { .. } => "GNSS".to_string(),
                         TelescopeTarget::Galactic { .. } => "Galactic".to_string(),
                         TelescopeTarget::Equatorial { .. } => "Equatorial".to_string(),
@@ -411,8 +414,8 @@ async fn get_telescopes_status(
                         target,
                         position,
                         error,
-                        info.controller_connected,
-                        info.receiver_connected,
+                        Some(info.mount_status.connected),
+                        Some(info.receiver_status.connected),
                     )
                 }
                 Err(_) => ("Offline".to_string(), None, None, String::new(), None, None),
@@ -0,0 +1,75 @@
+//! Prometheus scrape endpoint. Public like `/health`; only aggregate
+//! numbers are exposed.
+
+use crate::app::AppState;
+use crate::metrics::{METRICS, escape_label};
+use crate::models::booking::Booking;
+use crate::models::observation::Observation;
+use crate::models::telescope_types::TelescopeStatus;
+use axum::Router;
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use std::fmt::Write;
+
+pub fn routes(state: AppState) -> Router {
+    Router::new().route("/", get(get_metrics)).with_state(state)
+}
+
+/// The states `salsa_telescope_status` reports, one series each.
+const STATUSES: [&str; 6] = ["idle", "slewing", "tracking", "stowed", "parked", "offline"];
+
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let mut out = String::new();
+
+    // A failed query leaves the metric out rather than reporting zero.
+    if let Ok(bookings) = Booking::fetch_active(state.database_connection.clone()).await {
+        let _ = writeln!(
+            out,
+            "# HELP salsa_active_bookings Bookings in progress right now.\n\
+             # TYPE salsa_active_bookings gauge\n\
+             salsa_active_bookings {}",
+            bookings.len()
+        );
+    }
+    if let Ok(count) = Observation::count_all(state.database_connection.clone()).await {
+        let _ = writeln!(
+            out,
+            "# HELP salsa_observations Saved observations.\n\
+             # TYPE salsa_observations gauge\n\
+             salsa_observations {count}"
+        );
+    }
+
+    out.push_str(
+        "# HELP salsa_telescope_status 1 for the telescope's current status, 0 otherwise.\n\
+         # TYPE salsa_telescope_status gauge\n",
+    );
+    let mut names = state.telescopes.get_names().await;
+    names.sort();
+    for name in names {
+        let Some(telescope) = state.telescopes.get(&name).await else {
+            continue;
+        };
+        let current = match telescope.get_info().await.map(|info| info.status) {
+            Ok(TelescopeStatus::Idle) => "idle",
+            Ok(TelescopeStatus::Slewing) => "slewing",
+            Ok(TelescopeStatus::Tracking) => "tracking",
+            Ok(TelescopeStatus::Stowed) => "stowed",
+            Ok(TelescopeStatus::Parked) => "parked",
+            Err(_) => "offline",
+        };
+        for status in STATUSES {
+            let _ = writeln!(
+                out,
+                "salsa_telescope_status{{telescope=\"{}\",status=\"{status}\"}} {}",
+                escape_label(&name),
+                u8::from(status == current)
+            );
+        }
+    }
+
+    METRICS.render(&mut out);
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], out)
+}
@@ -1,8 +1,15 @@
 use crate::app::AppState;
+use crate::coords::{Direction, HI_REST_FREQ_HZ, Location, ONSALA_LOCATION, vlsr_correction};
+use crate::error::AppError;
 use crate::fits::{SpectrumMeta, write_spectrum_fits};
 use crate::i18n::Language;
 use crate::models::interferometry::InterferometrySession;
-use crate::models::observation::Observation;
+use crate::models::observation::{MAX_LABEL_LEN, Observation};
+use crate::models::spectrum::{
+    DEFAULT_RFI_SIGMA, MAX_BASELINE_DEGREE, StackInput, fit_baseline, flag_rfi,
+    parse_channel_ranges, stack_spectra,
+};
+use crate::models::telescope::TelescopeCollectionHandle;
 use crate::models::user::User;
 use crate::routes::index::render_main;
 use crate::timefmt::InTz;
@@ -11,26 +18,45 @@ use axum::extract::{Path, Query, State};
 use axum::http::header;
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::{Html, IntoResponse, Json, Redirect, Response};
-use axum::{Extension, Router, routing::get};
+use axum::{
+    Extension, Form, Router,
+    routing::{get, post},
+};
 use chrono::{DateTime, Utc};
 use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
-const PAGE_SIZE: i64 = 10;
+const PAGE_SIZE: i64 = 25;
+
+/// Number of pages needed for `total_count` observations; at least one so
+/// an empty list still renders page 1.
+fn total_pages(total_count: i64) -> usize {
+    (total_count.max(0) as usize)
+        .div_ceil(PAGE_SIZE as usize)
+        .max(1)
+}
 
 pub fn routes(state: AppState) -> Router {
     Router::new()
         .route("/", get(get_observations))
+        .route("/stack", post(post_stack_observations))
         .route(
             "/interferometry/{session_id}",
             axum::routing::delete(delete_interferometry_session),
         )
         .route(
             "/{observation_id}",
-            get(get_observation_data).delete(delete_observation),
+            get(get_observation_data)
+                .patch(patch_observation)
+                .delete(delete_observation),
         )
         .route("/{observation_id}/csv", get(get_observation_csv))
         .route("/{observation_id}/fits", get(get_observation_fits))
+        .route(
+            "/{observation_id}/waterfall",
+            get(get_observation_waterfall),
+        )
         .with_state(state)
 }
 
@@ -57,6 +83,8 @@ struct ObservationsTemplate {
     lang: Language,
     mode: String,
     is_admin: bool,
+    /// The logged-in user, who alone may rename their observations.
+    user_id: i64,
     viewed_user_id: i64,
     all_users: Vec<User>,
     show_interferometry_tab: bool,
@@ -103,14 +131,15 @@ fn build_observations_template(
     total_count: i64,
     current_page: usize,
     is_admin: bool,
+    user_id: i64,
     viewed_user_id: i64,
     all_users: Vec<User>,
     show_interferometry_tab: bool,
     interferometry_sessions: Vec<InterfSessionRow>,
     tz: Tz,
 ) -> ObservationsTemplate {
-    let total_pages = ((total_count as usize).saturating_sub(1) / PAGE_SIZE as usize) + 1;
-    let current_page = current_page.min(total_pages.max(1));
+    let total_pages = total_pages(total_count);
+    let current_page = current_page.min(total_pages);
     let prev_page = if current_page > 1 {
         Some(current_page - 1)
     } else {
@@ -131,6 +160,7 @@ fn build_observations_template(
         next_page,
         total_count,
         is_admin,
+        user_id,
         viewed_user_id,
         all_users,
         show_interferometry_tab,
@@ -145,7 +175,7 @@ async fn get_observations(
     headers: HeaderMap,
     Query(query): Query<PageQuery>,
     State(state): State<AppState>,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, AppError> {
     let Some(user) = user else {
         return Ok(if headers.get("hx-request").is_some() {
             ([("HX-Redirect", "/auth/login")], "").into_response()
@@ -159,9 +189,7 @@ async fn get_observations(
         user.id
     };
     let all_users = if user.is_admin {
-        User::fetch_all_non_guest(state.database_connection.clone())
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        User::fetch_all_non_guest(state.database_connection.clone()).await?
     } else {
         vec![]
     };
@@ -190,8 +218,8 @@ async fn get_observations(
         let current_page = query.page.unwrap_or(1).max(1);
         let total_count =
             Observation::count_for_user(state.database_connection.clone(), viewed_user_id).await?;
-        let total_pages = ((total_count as usize).saturating_sub(1) / PAGE_SIZE as usize) + 1;
-        let current_page = current_page.min(total_pages.max(1));
+        let total_pages = total_pages(total_count);
+        let current_page = current_page.min(total_pages);
         let offset = ((current_page - 1) as i64) * PAGE_SIZE;
         let obs = Observation::fetch_for_user_page(
             state.database_connection.clone(),
@@ -209,6 +237,7 @@ async fn get_observations(
         total_count,
         current_page,
         user.is_admin,
+        user.id,
         viewed_user_id,
         all_users,
         show_interferometry_tab,
@@ -231,19 +260,21 @@ async fn delete_observation(
     Path(observation_id): Path<i64>,
     Query(query): Query<PageQuery>,
     State(state): State<AppState>,
-) -> Result<Response, StatusCode> {
-    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+) -> Result<Response, AppError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
     let viewed_user_id = if user.is_admin {
         query.user_id.unwrap_or(user.id)
     } else {
         user.id
     };
-    Observation::delete(state.database_connection.clone(), observation_id, &user).await?;
+    if !Observation::delete(state.database_connection.clone(), observation_id, &user).await? {
+        return Err(AppError::NotFound);
+    }
     let current_page = query.page.unwrap_or(1).max(1);
     let total_count =
         Observation::count_for_user(state.database_connection.clone(), viewed_user_id).await?;
-    let total_pages = ((total_count as usize).saturating_sub(1) / PAGE_SIZE as usize) + 1;
-    let current_page = current_page.min(total_pages.max(1));
+    let total_pages = total_pages(total_count);
+    let current_page = current_page.min(total_pages);
     let offset = ((current_page - 1) as i64) * PAGE_SIZE;
     let observations = Observation::fetch_for_user_page(
         state.database_connection.clone(),
@@ -263,6 +294,7 @@ async fn delete_observation(
         total_count,
         current_page,
         user.is_admin,
+        user.id,
         viewed_user_id,
         vec![],
         interf_count > 0,
@@ -274,6 +306,44 @@ async fn delete_observation(
     Ok(Html(content).into_response())
 }
 
+#[derive(Deserialize)]
+struct LabelForm {
+    label: String,
+}
+
+/// Rename one of the user's observations. An empty label goes back to
+/// describing the target. Returns the label as shown in the list.
+async fn patch_observation(
+    Extension(user): Extension<Option<User>>,
+    Path(observation_id): Path<i64>,
+    State(state): State<AppState>,
+    Form(form): Form<LabelForm>,
+) -> Result<Response, AppError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
+    let label = form.label.trim();
+    if label.chars().count() > MAX_LABEL_LEN {
+        return Err(AppError::BadRequest(format!(
+            "Labels are at most {MAX_LABEL_LEN} characters"
+        )));
+    }
+    let label = (!label.is_empty()).then_some(label);
+    if !Observation::set_label(
+        state.database_connection.clone(),
+        observation_id,
+        user.id,
+        label,
+    )
+    .await?
+    {
+        return Err(AppError::NotFound);
+    }
+    let observation =
+        Observation::fetch_one(state.database_connection, observation_id, Some(user.id))
+            .await?
+            .ok_or(AppError::NotFound)?;
+    Ok(observation.display_label().into_response())
+}
+
 async fn delete_interferometry_session(
     Extension(lang): Extension<Language>,
     Extension(user): Extension<Option<User>>,
@@ -334,6 +404,7 @@ async fn delete_interferometry_session(
         total_count,
         1,
         user.is_admin,
+        user.id,
         viewed_user_id,
         vec![],
         show_interferometry_tab,
@@ -347,6 +418,7 @@ async fn delete_interferometry_session(
 
 #[derive(Serialize)]
 struct ObservationData {
+    label: String,
     frequencies: Vec<f64>,
     amplitudes: Vec<f64>,
     telescope_id: String,
@@ -356,31 +428,154 @@ struct ObservationData {
     integration_time_secs: f64,
     start_time: String,
     vlsr_correction_mps: Option<f64>,
+    /// LSR correction in km/s: the stored value, or computed from the
+    /// pointing at start for older observations of sky targets.
+    vlsr_kms: Option<f64>,
+    rest_freq_hz: f64,
+    /// LSR velocity of each channel in km/s, relative to `rest_freq_hz`.
+    velocities_kms: Option<Vec<f64>>,
     az_offset_deg: Option<f64>,
     el_offset_deg: Option<f64>,
     azimuth_deg: Option<f64>,
     elevation_deg: Option<f64>,
+    bandwidth_hz: Option<f64>,
+    spectral_channels: Option<u32>,
+    system_temperature_k: Option<f64>,
+    receiver_gain_db: Option<f64>,
+    site_longitude_deg: f64,
+    site_latitude_deg: f64,
+    /// Reference offset and cycle time when the spectrum is position
+    /// switched (on minus off).
+    switch_az_offset_deg: Option<f64>,
+    switch_el_offset_deg: Option<f64>,
+    switch_cycle_secs: Option<f64>,
+    /// Seconds per spectrum when the observation is a drift scan, whose
+    /// spectra are at `/waterfall`.
+    drift_cadence_secs: Option<f64>,
+    /// `amplitudes` minus the fitted baseline, when one was requested.
+    baseline_subtracted: Option<Vec<f64>>,
+    /// Per channel, whether it looks like RFI, when flagging was requested.
+    rfi_mask: Option<Vec<bool>>,
+}
+
+#[derive(Deserialize)]
+struct DataQuery {
+    /// Degree of the polynomial baseline to fit; no fit when absent.
+    baseline_degree: Option<usize>,
+    /// Channels left out of the fit, e.g. `"120-180,300-310"`.
+    #[serde(default)]
+    exclude_ranges: String,
+    #[serde(default)]
+    flag_rfi: bool,
+    /// Threshold in robust standard deviations for RFI flagging.
+    rfi_sigma: Option<f64>,
+}
+
+/// Location and rest frequency (Hz) for `observation`. The location is the
+/// one stored with the observation; older observations take it from the
+/// telescope's configuration like the rest frequency, falling back to
+/// Onsala and the HI line for telescopes that are no longer configured.
+async fn telescope_site(
+    telescopes: &TelescopeCollectionHandle,
+    observation: &Observation,
+) -> (Location, f64) {
+    let (location, rest_freq_hz) = match telescopes.get(&observation.telescope_id).await {
+        Some(telescope) => match telescope.get_info().await {
+            Ok(info) => (info.location, info.rest_freq_mhz * 1e6),
+            Err(_) => (ONSALA_LOCATION, HI_REST_FREQ_HZ),
+        },
+        None => (ONSALA_LOCATION, HI_REST_FREQ_HZ),
+    };
+    (observation.location().unwrap_or(location), rest_freq_hz)
+}
+
+/// Radial velocity in km/s of a line at `rest_freq_hz` observed at
+/// `freq_hz`, plus the LSR correction.
+fn lsr_velocity_kms(freq_hz: f64, rest_freq_hz: f64, vlsr_mps: f64) -> f64 {
+    const C: f64 = 299_792_458.0;
+    (-(freq_hz - rest_freq_hz) * C / rest_freq_hz + vlsr_mps) / 1000.0
 }
 
 async fn get_observation_data(
     Extension(user): Extension<Option<User>>,
     Path(observation_id): Path<i64>,
     State(state): State<AppState>,
-) -> Result<Response, StatusCode> {
-    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+    Query(query): Query<DataQuery>,
+) -> Result<Response, AppError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
     let user_id_filter = if user.is_admin { None } else { Some(user.id) };
     let observation =
         Observation::fetch_one(state.database_connection, observation_id, user_id_filter)
             .await?
-            .ok_or(StatusCode::NOT_FOUND)?;
+            .ok_or(AppError::NotFound)?;
+
+    let frequencies: Vec<f64> = serde_json::from_str(&observation.frequencies_json)?;
+    let amplitudes: Vec<f64> = serde_json::from_str(&observation.amplitudes_json)?;
+
+    let baseline_subtracted = match query.baseline_degree {
+        Some(degree) if degree > MAX_BASELINE_DEGREE => {
+            return Err(AppError::BadRequest(format!(
+                "Baseline degree must be at most {MAX_BASELINE_DEGREE}"
+            )));
+        }
+        Some(degree) => {
+            let exclude = parse_channel_ranges(&query.exclude_ranges)
+                .ok_or_else(|| AppError::BadRequest("Malformed channel ranges".to_string()))?;
+            let baseline = fit_baseline(&amplitudes, degree, &exclude).ok_or_else(|| {
+                AppError::BadRequest(
+                    "Too few channels outside the excluded ranges to fit the baseline".to_string(),
+                )
+            })?;
+            Some(
+                amplitudes
+                    .iter()
+                    .zip(&baseline)
+                    .map(|(a, b)| a - b)
+                    .collect(),
+            )
+        }
+        None => None,
+    };
 
-    let frequencies: Vec<f64> = serde_json::from_str(&observation.frequencies_json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let amplitudes: Vec<f64> = serde_json::from_str(&observation.amplitudes_json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let rfi_mask = if query.flag_rfi {
+        let sigma = query.rfi_sigma.unwrap_or(DEFAULT_RFI_SIGMA);
+        if sigma.is_nan() || sigma <= 0.0 {
+            return Err(AppError::BadRequest(
+                "RFI threshold must be positive".to_string(),
+            ));
+        }
+        let mut mask = vec![false; amplitudes.len()];
+        for channel in flag_rfi(&amplitudes, sigma) {
+            mask[channel] = true;
+        }
+        Some(mask)
+    } else {
+        None
+    };
 
-    let horizontal = observation.horizontal();
+    let (location, rest_freq_hz) = telescope_site(&state.telescopes, &observation).await;
+    let horizontal = observation.horizontal(location);
+    let vlsr_mps =
+        observation
+            .vlsr_correction_mps
+            .or_else(|| match observation.coordinate_system.as_str() {
+                "equatorial" | "galactic" | "horizontal" => horizontal.map(|(az, el)| {
+                    let direction = Direction {
+                        azimuth: az.to_radians(),
+                        elevation: el.to_radians(),
+                    };
+                    vlsr_correction(location, observation.start_time, direction)
+                }),
+                _ => None,
+            });
+    let velocities_kms = vlsr_mps.map(|vlsr_mps| {
+        frequencies
+            .iter()
+            .map(|&f| lsr_velocity_kms(f, rest_freq_hz, vlsr_mps))
+            .collect()
+    });
     Ok(Json(ObservationData {
+        label: observation.display_label(),
         frequencies,
         amplitudes,
         telescope_id: observation.telescope_id,
@@ -390,10 +585,81 @@ async fn get_observation_data(
         integration_time_secs: observation.integration_time_secs,
         start_time: observation.start_time.to_rfc3339(),
         vlsr_correction_mps: observation.vlsr_correction_mps,
+        vlsr_kms: vlsr_mps.map(|v| v / 1000.0),
+        rest_freq_hz,
+        velocities_kms,
         az_offset_deg: observation.az_offset_deg,
         el_offset_deg: observation.el_offset_deg,
         azimuth_deg: horizontal.map(|(az, _)| az),
         elevation_deg: horizontal.map(|(_, el)| el),
+        bandwidth_hz: observation.bandwidth_hz,
+        spectral_channels: observation.spectral_channels,
+        system_temperature_k: observation.system_temperature_k,
+        receiver_gain_db: observation.receiver_gain_db,
+        site_longitude_deg: location.longitude.to_degrees(),
+        site_latitude_deg: location.latitude.to_degrees(),
+        switch_az_offset_deg: observation.switch_az_offset_deg,
+        switch_el_offset_deg: observation.switch_el_offset_deg,
+        switch_cycle_secs: observation.switch_cycle_secs,
+        drift_cadence_secs: observation.drift_cadence_secs,
+        baseline_subtracted,
+        rfi_mask,
+    })
+    .into_response())
+}
+
+#[derive(Deserialize)]
+struct StackRequest {
+    observation_ids: Vec<i64>,
+}
+
+#[derive(Serialize)]
+struct StackedSpectrum {
+    observation_ids: Vec<i64>,
+    frequencies: Vec<f64>,
+    amplitudes: Vec<f64>,
+    /// Sum of the integration times of the stacked observations.
+    integration_time_secs: f64,
+}
+
+/// Average several of the user's observations, weighted by integration
+/// time. The observations must share a frequency axis. An id given twice
+/// is stacked once.
+async fn post_stack_observations(
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Json(mut request): Json<StackRequest>,
+) -> Result<Response, AppError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
+    let mut seen = HashSet::new();
+    request.observation_ids.retain(|id| seen.insert(*id));
+    let mut spectra = Vec::with_capacity(request.observation_ids.len());
+    for &id in &request.observation_ids {
+        let observation =
+            Observation::fetch_one(state.database_connection.clone(), id, Some(user.id))
+                .await?
+                .ok_or(AppError::NotFound)?;
+        let frequencies: Vec<f64> = serde_json::from_str(&observation.frequencies_json)?;
+        let amplitudes: Vec<f64> = serde_json::from_str(&observation.amplitudes_json)?;
+        spectra.push((frequencies, amplitudes, observation.integration_time_secs));
+    }
+    let inputs: Vec<StackInput> = spectra
+        .iter()
+        .map(
+            |(frequencies, amplitudes, integration_time_secs)| StackInput {
+                frequencies,
+                amplitudes,
+                integration_time_secs: *integration_time_secs,
+            },
+        )
+        .collect();
+    let (frequencies, amplitudes) =
+        stack_spectra(&inputs).map_err(|err| AppError::BadRequest(err.to_string()))?;
+    Ok(Json(StackedSpectrum {
+        observation_ids: request.observation_ids,
+        frequencies,
+        amplitudes,
+        integration_time_secs: inputs.iter().map(|s| s.integration_time_secs).sum(),
     })
     .into_response())
 }
@@ -402,29 +668,27 @@ async fn get_observation_csv(
     Extension(user): Extension<Option<User>>,
     Path(observation_id): Path<i64>,
     State(state): State<AppState>,
-) -> Result<Response, StatusCode> {
-    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+) -> Result<Response, AppError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
     let user_id_filter = if user.is_admin { None } else { Some(user.id) };
     let observation =
         Observation::fetch_one(state.database_connection, observation_id, user_id_filter)
             .await?
-            .ok_or(StatusCode::NOT_FOUND)?;
+            .ok_or(AppError::NotFound)?;
 
-    let frequencies: Vec<f64> = serde_json::from_str(&observation.frequencies_json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let amplitudes: Vec<f64> = serde_json::from_str(&observation.amplitudes_json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let frequencies: Vec<f64> = serde_json::from_str(&observation.frequencies_json)?;
+    let amplitudes: Vec<f64> = serde_json::from_str(&observation.amplitudes_json)?;
 
     let has_vlsr = observation.vlsr_correction_mps.is_some();
     let vlsr_mps = observation.vlsr_correction_mps.unwrap_or(0.0);
-    let c = 299_792_458.0_f64;
-    let f_rest = 1_420_405_751.77_f64;
+    let (location, rest_freq_hz) = telescope_site(&state.telescopes, &observation).await;
 
     let tag = observation.start_time.format("%Y%m%dT%H%M%S").to_string();
     let filename = format!("SALSA-{}-{}.csv", observation.telescope_id, tag);
 
     let mut csv = String::new();
     csv.push_str("# Origin: SALSA\n");
+    csv.push_str(&format!("# Label: {}\n", observation.display_label()));
     csv.push_str(&format!("# Telescope: {}\n", observation.telescope_id));
     csv.push_str(&format!(
         "# Date: {}\n",
@@ -438,7 +702,7 @@ async fn get_observation_csv(
         "# Target: {:.4}, {:.4} deg\n",
         observation.target_x, observation.target_y
     ));
-    if let Some((az, el)) = observation.horizontal() {
+    if let Some((az, el)) = observation.horizontal(location) {
         csv.push_str(&format!("# Azimuth at start: {az:.2} deg\n"));
         csv.push_str(&format!("# Elevation at start: {el:.2} deg\n"));
     }
@@ -446,12 +710,43 @@ async fn get_observation_csv(
         "# Integration time: {:.0} s\n",
         observation.integration_time_secs
     ));
+    if let Some(bandwidth_hz) = observation.bandwidth_hz {
+        csv.push_str(&format!("# Bandwidth: {bandwidth_hz:.0} Hz\n"));
+    }
+    if let Some(channels) = observation.spectral_channels {
+        csv.push_str(&format!("# Channels: {channels}\n"));
+    }
+    if let Some(tsys) = observation.system_temperature_k {
+        csv.push_str(&format!("# System temperature: {tsys:.1} K\n"));
+    }
+    if let Some(gain_db) = observation.receiver_gain_db {
+        csv.push_str(&format!("# Receiver gain: {gain_db:.1} dB\n"));
+    }
+    if let Some(switch) = observation.position_switch() {
+        csv.push_str(&format!(
+            "# Position switched: off at {:.2}, {:.2} deg az/el, {:.0} s cycle\n",
+            switch.az_offset_rad.to_degrees(),
+            switch.el_offset_rad.to_degrees(),
+            switch.cycle_secs
+        ));
+    }
+    if let Some(cadence_secs) = observation.drift_cadence_secs {
+        csv.push_str(&format!(
+            "# Drift scan: a spectrum every {cadence_secs:.0} s; this is their average\n"
+        ));
+    }
+    csv.push_str(&format!(
+        "# Site: {:.4} E, {:.4} N deg\n",
+        location.longitude.to_degrees(),
+        location.latitude.to_degrees()
+    ));
     if has_vlsr {
         csv.push_str(&format!("# VLSR correction: {:.2} m/s\n", vlsr_mps));
+        csv.push_str(&format!("# Rest frequency: {rest_freq_hz:.3} Hz\n"));
         csv.push_str("# Columns: frequency_hz,amplitude,vlsr_mps\n");
         csv.push_str("frequency_hz,amplitude,vlsr_mps\n");
         for (freq, amp) in frequencies.iter().zip(amplitudes.iter()) {
-            let vlsr = -(freq - f_rest) * c / f_rest + vlsr_mps;
+            let vlsr = lsr_velocity_kms(*freq, rest_freq_hz, vlsr_mps) * 1000.0;
             csv.push_str(&format!("{},{},{:.4}\n", freq, amp, vlsr));
         }
     } else {
@@ -476,27 +771,90 @@ async fn get_observation_csv(
         .into_response())
 }
 
+#[derive(Serialize)]
+struct WaterfallRow {
+    /// When the slice started.
+    time: String,
+    offset_secs: f64,
+    integration_time_secs: f64,
+    amplitudes: Vec<f64>,
+}
+
+#[derive(Serialize)]
+struct WaterfallData {
+    frequencies: Vec<f64>,
+    cadence_secs: f64,
+    /// The fixed pointing the sky drifted through.
+    azimuth_deg: Option<f64>,
+    elevation_deg: Option<f64>,
+    rows: Vec<WaterfallRow>,
+}
+
+/// The spectra of a drift scan, oldest first, one row per cadence.
+async fn get_observation_waterfall(
+    Extension(user): Extension<Option<User>>,
+    Path(observation_id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
+    let user_id_filter = if user.is_admin { None } else { Some(user.id) };
+    let observation = Observation::fetch_one(
+        state.database_connection.clone(),
+        observation_id,
+        user_id_filter,
+    )
+    .await?
+    .ok_or(AppError::NotFound)?;
+    let cadence_secs = observation.drift_cadence_secs.ok_or(AppError::NotFound)?;
+
+    let frequencies: Vec<f64> = serde_json::from_str(&observation.frequencies_json)?;
+    let (location, _) = telescope_site(&state.telescopes, &observation).await;
+    let horizontal = observation.horizontal(location);
+    let rows = Observation::fetch_scan(state.database_connection, observation_id)
+        .await?
+        .into_iter()
+        .map(|slice| {
+            let time = observation.start_time
+                + chrono::Duration::milliseconds((slice.start_offset_secs * 1000.0) as i64);
+            Ok(WaterfallRow {
+                time: time.to_rfc3339(),
+                offset_secs: slice.start_offset_secs,
+                integration_time_secs: slice.integration_time_secs,
+                amplitudes: serde_json::from_str(&slice.amplitudes_json)?,
+            })
+        })
+        .collect::<Result<Vec<_>, serde_json::Error>>()?;
+
+    Ok(Json(WaterfallData {
+        frequencies,
+        cadence_secs,
+        azimuth_deg: horizontal.map(|(az, _)| az),
+        elevation_deg: horizontal.map(|(_, el)| el),
+        rows,
+    })
+    .into_response())
+}
+
 async fn get_observation_fits(
     Extension(user): Extension<Option<User>>,
     Path(observation_id): Path<i64>,
     State(state): State<AppState>,
-) -> Result<Response, StatusCode> {
-    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+) -> Result<Response, AppError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
     let user_id_filter = if user.is_admin { None } else { Some(user.id) };
     let observation =
         Observation::fetch_one(state.database_connection, observation_id, user_id_filter)
             .await?
-            .ok_or(StatusCode::NOT_FOUND)?;
+            .ok_or(AppError::NotFound)?;
 
-    let frequencies: Vec<f64> = serde_json::from_str(&observation.frequencies_json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let amplitudes: Vec<f64> = serde_json::from_str(&observation.amplitudes_json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let frequencies: Vec<f64> = serde_json::from_str(&observation.frequencies_json)?;
+    let amplitudes: Vec<f64> = serde_json::from_str(&observation.amplitudes_json)?;
 
     let tag = observation.start_time.format("%Y%m%dT%H%M%S").to_string();
     let filename = format!("SALSA-{}-{}.fits", observation.telescope_id, tag);
 
-    let horizontal = observation.horizontal();
+    let (location, rest_freq_hz) = telescope_site(&state.telescopes, &observation).await;
+    let horizontal = observation.horizontal(location);
     let fits_bytes = write_spectrum_fits(&SpectrumMeta {
         frequencies: &frequencies,
         amplitudes: &amplitudes,
@@ -510,8 +868,12 @@ async fn get_observation_fits(
             .format("%Y-%m-%dT%H:%M:%S")
             .to_string(),
         vlsr_correction_mps: observation.vlsr_correction_mps,
+        rest_freq_hz,
         azimuth_deg: horizontal.map(|(az, _)| az),
         elevation_deg: horizontal.map(|(_, el)| el),
+        system_temperature_k: observation.system_temperature_k,
+        receiver_gain_db: observation.receiver_gain_db,
+        location,
     });
 
     Ok((
@@ -1,19 +1,30 @@
 use crate::app::AppState;
+use crate::catalog::SourceCatalog;
 use crate::coords::{
-    Direction, Location, horizontal_from_equatorial, horizontal_from_galactic, horizontal_from_sun,
-    vlsrcorr_from_galactic,
+    Coordinate, CoordinateOutOfRange, Direction, J2000, Location, equatorial_from_galactic,
+    horizontal_from_equatorial, horizontal_from_galactic, horizontal_from_moon,
+    horizontal_from_sun, normalize_equatorial, normalize_galactic, normalize_horizontal, precess,
+    rise_set_transit,
 };
+use crate::database::ConnectionPool;
+use crate::error::{AppError, error_callout};
 use crate::geoip::lookup_country;
 use crate::i18n::Language;
+use crate::middleware::rate_limit::rate_limit;
 use crate::middleware::session::{clear_session_cookie, session_cookie};
-use crate::models::booking::{consecutive_booking_end, is_authorized_for_telescope};
+use crate::models::audit::{self, AuditAction};
+use crate::models::booking::{
+    booking_is_active, consecutive_booking_end, is_authorized_for_telescope,
+};
 use crate::models::guest::{EndReason, GuestSession, StartError, touch_if_guest};
 use crate::models::maintenance::fetch_maintenance_set;
-use crate::models::observation::Observation;
+use crate::models::observation::{MAX_LABEL_LEN, Observation};
 use crate::models::telescope::Telescope;
 use crate::models::telescope_types::{
-    ObservationMode, ObservedSpectra, ReceiverConfiguration, ReceiverError, TelescopeError,
-    TelescopeInfo, TelescopeStatus, TelescopeTarget,
+    DRIFT_SCAN_CADENCE_SECS_RANGE, DriftScan, ObservationMode, ObservedSpectra,
+    POSITION_SWITCH_CYCLE_SECS_RANGE, POSITION_SWITCH_MAX_OFFSET_DEG, PositionSwitch,
+    ReceiverConfiguration, ReceiverError, TelescopeError, TelescopeInfo, TelescopeStatus,
+    TelescopeTarget,
 };
 use crate::models::user::User;
 use crate::routes::index::render_main;
@@ -22,24 +33,39 @@ use crate::tle_cache::TleCacheHandle;
 use i18n_embed_fl::fl;
 
 use askama::Template;
-use axum::body::Body;
 use axum::extract::{ConnectInfo, Path, Query, State};
 use axum::http::{HeaderMap, StatusCode, header::SET_COOKIE};
+use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum::{Extension, Form};
 use axum::{
-    Router,
+    Router, middleware,
     routing::{get, post},
 };
-use chrono::{Duration, Utc};
-use rusqlite::Connection;
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use futures_util::stream;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, warn};
 
+/// Largest jog step in either axis, in degrees.
+pub const MAX_JOG_DEG: f64 = 5.0;
+/// Jogs allowed per client: a short burst of clicks, then about one a
+/// second, so a held key or a script can't flood the rotor controller.
+pub const JOG_REQUESTS_PER_MINUTE: u32 = 60;
+pub const JOG_BURST: u32 = 5;
+
 pub fn routes(state: AppState) -> Router {
+    let jog_routes = Router::new()
+        .route("/jog", post(jog_telescope))
+        .route_layer(middleware::from_fn_with_state(
+            state.jog_rate_limiter.clone(),
+            rate_limit,
+        ));
     let observe_routes = Router::new()
         .route("/", get(get_observe))
         .route("/not-available", get(get_observe_not_available))
@@ -47,10 +73,14 @@ pub fn routes(state: AppState) -> Router {
         .route("/preview", get(get_preview))
         .route("/booking-end-time", get(get_booking_end_time))
         .route("/set-target", post(set_target))
+        .route("/repoint", post(repoint_telescope))
         .route("/stop-telescope", post(stop_telescope))
+        .route("/park", post(park_telescope))
         .route("/observe", post(start_observe))
         .route("/stop", post(stop_observe))
-        .route("/satellites", get(get_satellites));
+        .route("/spectrum/stream", get(get_spectrum_stream))
+        .route("/satellites", get(get_satellites))
+        .merge(jog_routes);
     Router::new()
         .route("/", get(get_observe_landing))
         .route("/guest/start", post(start_guest_session_auto))
@@ -162,7 +192,99 @@ async fn get_booking_end_time(
         .unwrap_or_default()
 }
 
+/// How often the spectrum stream pushes the running average.
+const SPECTRUM_STREAM_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Server-Sent Events feed of the averaged spectrum of the integration in
+/// progress. The stream ends when that integration stops, or right away if
+/// none is running.
+async fn get_spectrum_stream(
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Path(telescope_id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+    if !is_authorized_for_telescope(state.database_connection, &user, &telescope_id).await? {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    let telescope = state
+        .telescopes
+        .get(&telescope_id)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let token = telescope.current_integration_token().await;
+    let events = stream::unfold((telescope, token), |(telescope, token)| async move {
+        let token = token?;
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => return None,
+                _ = tokio::time::sleep(SPECTRUM_STREAM_INTERVAL) => {}
+            }
+            if let Some(spectrum) = telescope.latest_spectrum().await {
+                let event = Event::default().json_data(&spectrum);
+                return Some((event, (telescope, Some(token))));
+            }
+        }
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// "Up until HH:MM" style line for a fixed sky position (J2000 `ra`,
+/// `dec` in radians) as seen from a telescope with the given elevation
+/// limit.
+fn rise_set_hint(
+    location: Location,
+    min_elevation_rad: f64,
+    ra: f64,
+    dec: f64,
+    tz: Tz,
+    lang: Language,
+) -> String {
+    let now = Utc::now();
+    let fmt_time = |t: DateTime<Utc>| t.with_timezone(&tz).format("%H:%M %Z").to_string();
+    let limit = format!("{:.0}", min_elevation_rad.to_degrees());
+    let today = now.date_naive();
+    let (rise, _, transit) = rise_set_transit(location, ra, dec, min_elevation_rad, today);
+    if rise.is_none() {
+        let transit_el = horizontal_from_equatorial(location, transit, ra, dec).elevation;
+        return if transit_el >= min_elevation_rad {
+            fl!(lang.loader(), "observe-never-sets", limit = limit)
+        } else {
+            fl!(lang.loader(), "observe-never-rises", limit = limit)
+        };
+    }
+    // Today's window may already be over or not started; the neighbouring
+    // days' windows cover the rest.
+    let windows: Vec<_> = [today.pred_opt(), Some(today), today.succ_opt()]
+        .into_iter()
+        .flatten()
+        .filter_map(
+            |date| match rise_set_transit(location, ra, dec, min_elevation_rad, date) {
+                (Some(rise), Some(set), _) => Some((rise, set)),
+                _ => None,
+            },
+        )
+        .collect();
+    if let Some(&(_, set)) = windows
+        .iter()
+        .find(|&&(rise, set)| rise <= now && now <= set)
+    {
+        return fl!(lang.loader(), "observe-up-until", time = fmt_time(set));
+    }
+    match windows
+        .iter()
+        .map(|&(rise, _)| rise)
+        .filter(|&rise| rise > now)
+        .min()
+    {
+        Some(rise) => fl!(lang.loader(), "observe-rises-at", time = fmt_time(rise)),
+        None => String::new(),
+    }
+}
+
 async fn get_preview(
+    Extension(user): Extension<Option<User>>,
+    Extension(lang): Extension<Language>,
     State(state): State<AppState>,
     Path(telescope_id): Path<String>,
     Query(query): Query<PreviewQuery>,
@@ -178,10 +300,41 @@ async fn get_preview(
             longitude: 0.0,
             latitude: 0.0,
         });
+    let min_elevation_rad = telescope_info
+        .as_ref()
+        .map(|i| i.min_elevation_rad)
+        .unwrap_or(0.0);
 
     let x = query.x.as_deref().and_then(|s| s.parse::<f64>().ok());
     let y = query.y.as_deref().and_then(|s| s.parse::<f64>().ok());
 
+    // J2000 position of targets fixed on the sky, for the rise/set hint.
+    let sky_position = match query.coordinate_system.as_deref() {
+        Some("equatorial") => x.zip(y).map(|(x, y)| (x.to_radians(), y.to_radians())),
+        Some("galactic") => x
+            .zip(y)
+            .map(|(x, y)| equatorial_from_galactic(x.to_radians(), y.to_radians())),
+        Some("name") => match query
+            .x
+            .as_deref()
+            .and_then(|name| state.source_catalog.lookup(name, Utc::now()))
+        {
+            Some(TelescopeTarget::Equatorial {
+                right_ascension,
+                declination,
+                epoch,
+            }) => Some(precess(right_ascension, declination, epoch, J2000)),
+            _ => None,
+        },
+        _ => None,
+    };
+    let hint = sky_position
+        .map(|(ra, dec)| {
+            let tz = user.as_ref().map(|u| u.tz()).unwrap_or(chrono_tz::UTC);
+            rise_set_hint(location, min_elevation_rad, ra, dec, tz, lang)
+        })
+        .unwrap_or_default();
+
     let az_offset_rad = query.az_offset_deg.to_radians();
     let el_offset_rad = query.el_offset_deg.to_radians();
 
@@ -191,6 +344,26 @@ async fn get_preview(
         telescope_info.and_then(|i| i.service_position)
     } else if query.coordinate_system.as_deref() == Some("sun") {
         Some(horizontal_from_sun(location, Utc::now()))
+    } else if query.coordinate_system.as_deref() == Some("moon") {
+        Some(horizontal_from_moon(location, Utc::now()))
+    } else if query.coordinate_system.as_deref() == Some("name") {
+        match query
+            .x
+            .as_deref()
+            .and_then(|name| state.source_catalog.lookup(name, Utc::now()))
+        {
+            Some(TelescopeTarget::Equatorial {
+                right_ascension,
+                declination,
+                ..
+            }) => Some(horizontal_from_equatorial(
+                location,
+                Utc::now(),
+                right_ascension,
+                declination,
+            )),
+            _ => None,
+        }
     } else if query.coordinate_system.as_deref() == Some("gnss") {
         query
             .x
@@ -246,7 +419,7 @@ async fn get_preview(
             .get_info()
             .await
             .ok()
-            .and_then(|i| i.current_horizontal),
+            .and_then(|i| i.current_pointing()),
         None => None,
     };
 
@@ -259,7 +432,14 @@ async fn get_preview(
     };
 
     let (calc_az, calc_el) = fmt(calculated, 3);
-    let (cur_az, cur_el) = fmt(current, 1);
+    let fmt_pair = |pair: Option<(f64, f64)>| match pair {
+        Some((x, y)) => (format!("{x:.1}"), format!("{y:.1}")),
+        None => ("&mdash;".to_string(), "&mdash;".to_string()),
+    };
+    let (cur_az, cur_el) = fmt_pair(current.map(|p| (p.azimuth_deg, p.elevation_deg)));
+    let (cur_ra, cur_dec) = fmt_pair(current.map(|p| (p.right_ascension_deg, p.declination_deg)));
+    let (cur_l, cur_b) =
+        fmt_pair(current.map(|p| (p.galactic_longitude_deg, p.galactic_latitude_deg)));
 
     Html(format!(
         r#"<span class="text-gray-400">Calc.</span>
@@ -271,7 +451,18 @@ async fn get_preview(
 <span class="text-gray-400">Az</span>
 <span class="font-mono">{cur_az}&deg;</span>
 <span class="text-gray-400">El</span>
-<span class="font-mono">{cur_el}&deg;</span>"#
+<span class="font-mono">{cur_el}&deg;</span>
+<span></span>
+<span class="text-gray-400">RA</span>
+<span class="font-mono">{cur_ra}&deg;</span>
+<span class="text-gray-400">Dec</span>
+<span class="font-mono">{cur_dec}&deg;</span>
+<span></span>
+<span class="text-gray-400">l</span>
+<span class="font-mono">{cur_l}&deg;</span>
+<span class="text-gray-400">b</span>
+<span class="font-mono">{cur_b}&deg;</span>
+<span class="text-xs text-gray-500" style="grid-column: 1 / -1">{hint}</span>"#
     ))
 }
 
@@ -311,8 +502,8 @@ async fn get_satellites(
 
 #[derive(Deserialize, Debug)]
 struct Target {
-    x: Option<String>, // Degrees; not required when coordinate_system == "sun", "stow", or "service"
-    y: Option<String>, // Degrees; not required when coordinate_system == "sun", "stow", or "service"
+    x: Option<String>, // Degrees, or the source name when coordinate_system == "name"; not required when coordinate_system == "sun", "moon", "stow", or "service"
+    y: Option<String>, // Degrees; not required when coordinate_system == "sun", "moon", "stow", or "service"
     coordinate_system: String,
     #[serde(default)]
     az_offset_deg: f64,
@@ -320,29 +511,115 @@ struct Target {
     el_offset_deg: f64,
 }
 
+/// Signed steps in degrees for `/jog`.
+#[derive(Deserialize, Debug)]
+struct Jog {
+    #[serde(default)]
+    az_deg: f64,
+    #[serde(default)]
+    el_deg: f64,
+}
+
 impl IntoResponse for ReceiverError {
     fn into_response(self) -> Response {
         error_response(format!("{self}"))
     }
 }
 
-#[derive(Template)]
-#[template(path = "error_callout.html")]
-struct ErrorCallout {
-    message: String,
+impl IntoResponse for TelescopeError {
+    fn into_response(self) -> Response {
+        AppError::Telescope(self).into_response()
+    }
+}
+
+/// Which coordinate the user entered out of range, and its valid range.
+fn coordinate_range_response(lang: &Language, err: CoordinateOutOfRange) -> Response {
+    let min = format!("{:.0}", err.min_deg);
+    let max = format!("{:.0}", err.max_deg);
+    let message = match err.coordinate {
+        Coordinate::RightAscension => {
+            fl!(
+                lang.loader(),
+                "observe-error-ra-range",
+                min = min,
+                max = max
+            )
+        }
+        Coordinate::Declination => {
+            fl!(
+                lang.loader(),
+                "observe-error-dec-range",
+                min = min,
+                max = max
+            )
+        }
+        Coordinate::GalacticLongitude => {
+            fl!(
+                lang.loader(),
+                "observe-error-long-range",
+                min = min,
+                max = max
+            )
+        }
+        Coordinate::GalacticLatitude => {
+            fl!(
+                lang.loader(),
+                "observe-error-lat-range",
+                min = min,
+                max = max
+            )
+        }
+        Coordinate::Elevation => {
+            fl!(
+                lang.loader(),
+                "observe-error-el-range",
+                min = min,
+                max = max
+            )
+        }
+    };
+    error_response_with_status(StatusCode::BAD_REQUEST, message)
+}
+
+/// `err` in the user's language, shown in the observe page's error box.
+fn telescope_error_response(lang: &Language, err: TelescopeError) -> Response {
+    let message = match &err {
+        TelescopeError::TargetOutOfElevationRange { min_deg, max_deg } => fl!(
+            lang.loader(),
+            "observe-error-elevation-range",
+            min = format!("{min_deg:.0}"),
+            max = format!("{max_deg:.0}")
+        ),
+        TelescopeError::TargetInKeyhole { max_deg } => fl!(
+            lang.loader(),
+            "observe-error-keyhole",
+            max = format!("{max_deg:.0}")
+        ),
+        TelescopeError::TelescopeNotConnected => {
+            fl!(lang.loader(), "observe-error-telescope-not-connected")
+        }
+        TelescopeError::TelescopeIOError(_) => {
+            fl!(lang.loader(), "observe-error-telescope-io")
+        }
+        TelescopeError::TelescopeBusy => fl!(lang.loader(), "observe-error-telescope-busy"),
+        TelescopeError::ReceiverFailed(detail) => fl!(
+            lang.loader(),
+            "observe-error-receiver-failed",
+            detail = detail.as_str()
+        ),
+    };
+    error_response_with_status(AppError::Telescope(err).status(), message)
 }
 
 fn error_response(message: String) -> Response {
-    // Create a response that will specifically update the error box on the page.
-    let body = ErrorCallout { message }
-        .render()
-        .expect("Rendering error_callout.html should never fail");
-    Response::builder()
-        .status(StatusCode::OK) // Needs to be ok to be picked up by htmx.
-        .header("HX-Retarget", "#errors")
-        .header("HX-Reswap", "innerHTML")
-        .body(Body::from(body))
-        .expect("Building a response should never fail")
+    // Needs to be ok to be picked up by htmx.
+    error_response_with_status(StatusCode::OK, message)
+}
+
+/// Like `error_response`, for errors that should also carry a 4xx status.
+/// observe.html lets htmx swap these into the error box.
+fn error_response_with_status(status: StatusCode, message: String) -> Response {
+    error_callout(status, message)
 }
 
 async fn set_target(
@@ -351,39 +628,58 @@ async fn set_target(
     Path(telescope_id): Path<String>,
     Extension(user): Extension<Option<User>>,
     Form(target): Form<Target>,
-) -> Result<Response, StatusCode> {
-    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
-    if !is_authorized_for_telescope(state.database_connection.clone(), &user, &telescope_id).await?
-    {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-    touch_if_guest(state.database_connection.clone(), &user).await;
+) -> Result<Response, AppError> {
+    point_telescope(lang, state, telescope_id, user, target, false).await
+}
 
-    let telescope = state
-        .telescopes
-        .get(&telescope_id)
-        .await
-        .ok_or(StatusCode::NOT_FOUND)?;
+/// Move to a new direction without ending the running integration, for
+/// switching between on- and off-source positions by hand. See
+/// `Telescope::repoint`.
+async fn repoint_telescope(
+    Extension(lang): Extension<Language>,
+    State(state): State<AppState>,
+    Path(telescope_id): Path<String>,
+    Extension(user): Extension<Option<User>>,
+    Form(target): Form<Target>,
+) -> Result<Response, AppError> {
+    point_telescope(lang, state, telescope_id, user, target, true).await
+}
+
+async fn point_telescope(
+    lang: Language,
+    state: AppState,
+    telescope_id: String,
+    user: Option<User>,
+    target: Target,
+    keep_integration: bool,
+) -> Result<Response, AppError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
+    let telescope = authorized_telescope(&state, &user, &telescope_id).await?;
+    claim_control(&lang, &state, &user, &telescope_id).await?;
 
     let az_offset_rad = target.az_offset_deg.to_radians();
     let el_offset_rad = target.el_offset_deg.to_radians();
 
+    if keep_integration && ["stow", "service"].contains(&target.coordinate_system.as_str()) {
+        return Err(AppError::BadRequest(
+            "Parking ends the integration; it cannot be kept".to_string(),
+        ));
+    }
     let telescope_target = if target.coordinate_system == "stow" {
-        let info = telescope.get_info().await.map_err(|err| {
-            error!("Failed to get telescope info: {err}");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        let info = telescope.get_info().await?;
         let stow = info.stow_position.ok_or_else(|| {
             error!("No stow position configured for telescope {telescope_id}");
-            StatusCode::NOT_FOUND
+            AppError::NotFound
         })?;
         if let Some(spectra) = telescope.stop_integration().await {
+            let label = take_observation_label(&state, &telescope_id).await;
             save_observation(
-                state.database_connection,
+                state.database_connection.clone(),
                 &user,
                 &info,
                 &spectra,
                 &state.tle_cache,
+                label.as_deref(),
             )
             .await;
         }
@@ -392,21 +688,20 @@ async fn set_target(
             elevation: stow.elevation,
         }
     } else if target.coordinate_system == "service" {
-        let info = telescope.get_info().await.map_err(|err| {
-            error!("Failed to get telescope info: {err}");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        let info = telescope.get_info().await?;
         let service = info.service_position.ok_or_else(|| {
             error!("No service position configured for telescope {telescope_id}");
-            StatusCode::NOT_FOUND
+            AppError::NotFound
         })?;
         if let Some(spectra) = telescope.stop_integration().await {
+            let label = take_observation_label(&state, &telescope_id).await;
             save_observation(
-                state.database_connection,
+                state.database_connection.clone(),
                 &user,
                 &info,
                 &spectra,
                 &state.tle_cache,
+                label.as_deref(),
             )
             .await;
         }
@@ -416,6 +711,22 @@ async fn set_target(
         }
     } else if target.coordinate_system == "sun" {
         TelescopeTarget::Sun
+    } else if target.coordinate_system == "moon" {
+        TelescopeTarget::Moon
+    } else if target.coordinate_system == "name" {
+        let name = target.x.as_deref().unwrap_or_default().trim();
+        let Some(target) = state.source_catalog.lookup(name, Utc::now()) else {
+            return Ok(error_response_with_status(
+                StatusCode::BAD_REQUEST,
+                fl!(
+                    lang.loader(),
+                    "observe-error-unknown-source",
+                    name = name,
+                    examples = state.source_catalog.example_names()
+                ),
+            ));
+        };
+        target
     } else if target.coordinate_system == "gnss" {
         let Some(norad_id) = target.x.as_deref().and_then(|s| s.parse::<u64>().ok()) else {
             return Ok(error_response(fl!(
@@ -425,88 +736,312 @@ async fn set_target(
         };
         TelescopeTarget::Satellite { norad_id }
     } else {
-        let Some(x_rad) = target
-            .x
-            .as_deref()
-            .and_then(|s| s.parse::<f64>().ok())
-            .map(f64::to_radians)
-        else {
+        let Some(x) = target.x.as_deref().and_then(|s| s.parse::<f64>().ok()) else {
             return Ok(error_response(fl!(
                 lang.loader(),
                 "observe-error-invalid-coords"
             )));
         };
-        let Some(y_rad) = target
-            .y
-            .as_deref()
-            .and_then(|s| s.parse::<f64>().ok())
-            .map(f64::to_radians)
-        else {
+        let Some(y) = target.y.as_deref().and_then(|s| s.parse::<f64>().ok()) else {
             return Ok(error_response(fl!(
                 lang.loader(),
                 "observe-error-invalid-coords"
             )));
         };
-        match target.coordinate_system.as_str() {
-            "galactic" => TelescopeTarget::Galactic {
-                longitude: x_rad,
-                latitude: y_rad,
-            },
-            "equatorial" => TelescopeTarget::Equatorial {
-                right_ascension: x_rad,
-                declination: y_rad,
-            },
-            "horizontal" => TelescopeTarget::Horizontal {
-                azimuth: x_rad,
-                elevation: y_rad,
-            },
+        let checked = match target.coordinate_system.as_str() {
+            "galactic" => {
+                normalize_galactic(x, y).map(|(longitude, latitude)| TelescopeTarget::Galactic {
+                    longitude,
+                    latitude,
+                })
+            }
+            "equatorial" => normalize_equatorial(x, y).map(|(right_ascension, declination)| {
+                TelescopeTarget::Equatorial {
+                    right_ascension,
+                    declination,
+                    epoch: J2000,
+                }
+            }),
+            "horizontal" => {
+                normalize_horizontal(x, y).map(|direction| TelescopeTarget::Horizontal {
+                    azimuth: direction.azimuth,
+                    elevation: direction.elevation,
+                })
+            }
             coordinate_system => {
                 debug!("Unkown coordinate system {coordinate_system}");
-                return Err(StatusCode::BAD_REQUEST);
+                return Err(AppError::BadRequest(format!(
+                    "Unknown coordinate system {coordinate_system}"
+                )));
             }
+        };
+        match checked {
+            Ok(target) => target,
+            Err(err) => return Ok(coordinate_range_response(&lang, err)),
         }
     };
 
-    match telescope
-        .set_target(telescope_target, az_offset_rad, el_offset_rad)
+    let result = if keep_integration {
+        telescope
+            .repoint(telescope_target, az_offset_rad, el_offset_rad)
+            .await
+    } else {
+        retarget(
+            &state,
+            &user,
+            &telescope_id,
+            telescope.as_ref(),
+            telescope_target,
+            az_offset_rad,
+            el_offset_rad,
+        )
         .await
+    };
+    if let Err(err) = result {
+        info!("Failed to set target on {telescope_id}: {err}.");
+        return Ok(telescope_error_response(&lang, err));
+    }
+    audit::record(
+        state.database_connection.clone(),
+        &user,
+        AuditAction::SetTarget,
+        &target_detail(
+            &telescope_id,
+            telescope_target,
+            az_offset_rad,
+            el_offset_rad,
+        ),
+    )
+    .await;
+    Ok(error_response(String::new()))
+}
+
+/// Step the dish a few degrees from where it points now, for aligning it
+/// by hand while commissioning. Unlike `set_target` this is open to admins
+/// without a booking, and not to guests.
+async fn jog_telescope(
+    Extension(lang): Extension<Language>,
+    State(state): State<AppState>,
+    Path(telescope_id): Path<String>,
+    Extension(user): Extension<Option<User>>,
+    Form(jog): Form<Jog>,
+) -> Result<Response, AppError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
+    if !user.is_admin
+        && !booking_is_active(state.database_connection.clone(), &user, &telescope_id).await?
     {
-        Err(TelescopeError::TargetOutOfElevationRange { min_deg, max_deg }) => {
-            return Ok(error_response(fl!(
-                lang.loader(),
-                "observe-error-elevation-range",
-                min = format!("{min_deg:.0}"),
-                max = format!("{max_deg:.0}")
-            )));
-        }
-        Err(err) => {
-            error!("Failed to set target: {err}.");
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-        Ok(_) => {}
+        return Err(AppError::Unauthorized);
     }
+    let telescope = state
+        .telescopes
+        .get(&telescope_id)
+        .await
+        .ok_or(AppError::NotFound)?;
+    let step_ok = |deg: f64| deg.is_finite() && deg.abs() <= MAX_JOG_DEG;
+    if !step_ok(jog.az_deg) || !step_ok(jog.el_deg) {
+        return Err(AppError::BadRequest(fl!(
+            lang.loader(),
+            "observe-error-jog-step",
+            max = format!("{MAX_JOG_DEG:.0}")
+        )));
+    }
+    // Only after the step is known to be good, so a rejected jog doesn't
+    // take control of the telescope.
+    claim_control(&lang, &state, &user, &telescope_id).await?;
+
+    let info = telescope.get_info().await?;
+    let current = info
+        .current_horizontal
+        .ok_or(TelescopeError::TelescopeNotConnected)?;
+    let direction = jog_direction(
+        current,
+        jog.az_deg.to_radians(),
+        jog.el_deg.to_radians(),
+        (info.min_elevation_rad, info.max_elevation_rad),
+        info.azimuth_range_rad,
+    );
+    let result = retarget(
+        &state,
+        &user,
+        &telescope_id,
+        telescope.as_ref(),
+        TelescopeTarget::Horizontal {
+            azimuth: direction.azimuth,
+            elevation: direction.elevation,
+        },
+        0.0,
+        0.0,
+    )
+    .await;
+    if let Err(err) = result {
+        info!("Failed to jog {telescope_id}: {err}.");
+        return Ok(telescope_error_response(&lang, err));
+    }
+    audit::record(
+        state.database_connection.clone(),
+        &user,
+        AuditAction::Jog,
+        &format!(
+            "{telescope_id}: az {:+.2}°, el {:+.2}°",
+            jog.az_deg, jog.el_deg
+        ),
+    )
+    .await;
     Ok(error_response(String::new()))
 }
 
+/// `current` moved by the given steps, held within `elevation_range` and,
+/// for rotors with a cable wrap, within `azimuth_range` of rotor azimuth.
+/// The tracker picks the wrap closest to the current position, so a step
+/// clamped at the limit is not turned into a long unwind the other way.
+fn jog_direction(
+    current: Direction,
+    az_step_rad: f64,
+    el_step_rad: f64,
+    elevation_range: (f64, f64),
+    azimuth_range: Option<(f64, f64)>,
+) -> Direction {
+    let (min_el, max_el) = elevation_range;
+    let mut azimuth = current.azimuth + az_step_rad;
+    if let Some((min_az, max_az)) = azimuth_range {
+        azimuth = azimuth.clamp(min_az, max_az);
+    }
+    Direction {
+        azimuth: azimuth.rem_euclid(2.0 * std::f64::consts::PI),
+        elevation: (current.elevation + el_step_rad).clamp(min_el, max_el),
+    }
+}
+
+/// What the audit log says about pointing `telescope_id` at `target`.
+pub(crate) fn target_detail(
+    telescope_id: &str,
+    target: TelescopeTarget,
+    az_offset_rad: f64,
+    el_offset_rad: f64,
+) -> String {
+    if az_offset_rad == 0.0 && el_offset_rad == 0.0 {
+        format!("{telescope_id}: {target}")
+    } else {
+        format!(
+            "{telescope_id}: {target}, offset az {:.2}°, el {:.2}°",
+            az_offset_rad.to_degrees(),
+            el_offset_rad.to_degrees()
+        )
+    }
+}
+
+/// The telescope `telescope_id`, if `user` has a booking or guest session
+/// on it right now. Counts as activity for a guest.
+pub(crate) async fn authorized_telescope(
+    state: &AppState,
+    user: &User,
+    telescope_id: &str,
+) -> Result<Arc<dyn Telescope>, AppError> {
+    if !is_authorized_for_telescope(state.database_connection.clone(), user, telescope_id).await? {
+        return Err(AppError::Unauthorized);
+    }
+    touch_if_guest(state.database_connection.clone(), user).await;
+    state
+        .telescopes
+        .get(telescope_id)
+        .await
+        .ok_or(AppError::NotFound)
+}
+
+/// Record `user` as the one steering `telescope_id` if they have a booking
+/// on it, so that two (mistakenly) overlapping bookings can't both command
+/// the dish. Another user's claim holds until their booking ends, and is
+/// refused with a `Conflict` until then. Guests never overlap a booking,
+/// so they don't claim.
+pub(crate) async fn claim_control(
+    lang: &Language,
+    state: &AppState,
+    user: &User,
+    telescope_id: &str,
+) -> Result<(), AppError> {
+    let mut controllers = state.telescope_controllers.lock().await;
+    if let Some(holder) = controllers.get(telescope_id)
+        && holder.id != user.id
+        && booking_is_active(state.database_connection.clone(), holder, telescope_id).await?
+    {
+        info!(
+            "Refused {} control of {telescope_id}, held by {}",
+            user.name, holder.name
+        );
+        return Err(AppError::Conflict(fl!(
+            lang.loader(),
+            "observe-error-controlled-by-other"
+        )));
+    }
+    if booking_is_active(state.database_connection.clone(), user, telescope_id).await? {
+        controllers.insert(telescope_id.to_string(), user.clone());
+    } else {
+        controllers.remove(telescope_id);
+    }
+    Ok(())
+}
+
+/// Point `telescope` at a new target, which ends the observation. It is
+/// stopped and saved only once the new target is accepted, with the info
+/// from before the move so it is stored under the target it was taken on.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn retarget(
+    state: &AppState,
+    user: &User,
+    telescope_id: &str,
+    telescope: &dyn Telescope,
+    target: TelescopeTarget,
+    az_offset_rad: f64,
+    el_offset_rad: f64,
+) -> Result<TelescopeTarget, TelescopeError> {
+    let before = telescope.get_info().await;
+    let result = telescope
+        .set_target(target, az_offset_rad, el_offset_rad)
+        .await;
+    if result.is_ok()
+        && let Ok(info) = before
+        && let Some(spectra) = telescope.stop_integration().await
+    {
+        let label = take_observation_label(state, telescope_id).await;
+        save_observation(
+            state.database_connection.clone(),
+            user,
+            &info,
+            &spectra,
+            &state.tle_cache,
+            label.as_deref(),
+        )
+        .await;
+    }
+    result
+}
+
+/// Take the label the user gave the integration running on `telescope_id`.
+pub(crate) async fn take_observation_label(state: &AppState, telescope_id: &str) -> Option<String> {
+    state.observation_labels.lock().await.remove(telescope_id)
+}
+
 /// Stop the in-flight integration on `telescope` and persist the resulting
 /// spectrum to the database. Three call sites use this: the End button
 /// handler, the booking_monitor at handover, and the fixed-duration auto-stop
-/// task started by `start_observe`. Each previously inlined the same
-/// `get_info → stop_integration → save_observation` sequence; centralising it
-/// keeps the behaviour identical and makes future changes (e.g. adding
-/// instrumentation) a one-place edit.
+/// task started by `start_observe`. Only the first of concurrent stops gets
+/// the spectrum from `stop_integration`, so an integration is saved once.
+/// Each previously inlined the same `get_info → stop_integration →
+/// save_observation` sequence; centralising it keeps the behaviour identical
+/// and makes future changes (e.g. adding instrumentation) a one-place edit.
 pub(crate) async fn stop_and_save_observation(
     telescope: &dyn Telescope,
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
     user: &User,
     tle_cache: &TleCacheHandle,
+    label: Option<&str>,
 ) {
     // get_info before stop so the snapshot reflects the integration's target.
     let info_result = telescope.get_info().await;
     if let Some(spectra) = telescope.stop_integration().await {
         match info_result {
             Ok(info) => {
-                save_observation(connection, user, &info, &spectra, tle_cache).await;
+                save_observation(connection, user, &info, &spectra, tle_cache, label).await;
             }
             Err(err) => {
                 error!("Failed to get telescope info while stopping integration: {err}");
@@ -516,11 +1051,12 @@ pub(crate) async fn stop_and_save_observation(
 }
 
 pub(crate) async fn save_observation(
-    connection: Arc<Mutex<Connection>>,
+    connection: ConnectionPool,
     user: &User,
     info: &TelescopeInfo,
     spectra: &ObservedSpectra,
     tle_cache: &TleCacheHandle,
+    label: Option<&str>,
 ) {
     // Guest sessions are explicitly ephemeral — the live spectrum is shown
     // in the chart while observing, but nothing is persisted to the DB.
@@ -547,21 +1083,19 @@ pub(crate) async fn save_observation(
         None
     };
     let location = info.location;
-    let (coordinate_system, target_x, target_y, vlsr_correction_mps): (
-        String,
-        f64,
-        f64,
-        Option<f64>,
-    ) = match current_target {
+    let receiver_calibration = info.receiver_calibration;
+    let vlsr_correction_mps = current_target.vlsr_correction(location, start_time);
+    let (coordinate_system, target_x, target_y): (String, f64, f64) = match current_target {
+        // Stored coordinates are J2000, which is what
+        // `Observation::horizontal` assumes when reconstructing the pointing.
         TelescopeTarget::Equatorial {
             right_ascension,
             declination,
-        } => (
-            "equatorial".into(),
-            right_ascension.to_degrees(),
-            declination.to_degrees(),
-            None,
-        ),
+            epoch,
+        } => {
+            let (ra, dec) = precess(right_ascension, declination, epoch, J2000);
+            ("equatorial".into(), ra.to_degrees(), dec.to_degrees())
+        }
         TelescopeTarget::Galactic {
             longitude,
             latitude,
@@ -569,13 +1103,11 @@ pub(crate) async fn save_observation(
             "galactic".into(),
             longitude.to_degrees(),
             latitude.to_degrees(),
-            Some(vlsrcorr_from_galactic(longitude, latitude, start_time)),
         ),
         TelescopeTarget::Horizontal { azimuth, elevation } => (
             "horizontal".into(),
             azimuth.to_degrees(),
             elevation.to_degrees(),
-            None,
         ),
         TelescopeTarget::Sun => {
             let sun = horizontal_from_sun(location, start_time);
@@ -583,7 +1115,14 @@ pub(crate) async fn save_observation(
                 "sun".into(),
                 sun.azimuth.to_degrees(),
                 sun.elevation.to_degrees(),
-                None,
+            )
+        }
+        TelescopeTarget::Moon => {
+            let moon = horizontal_from_moon(location, start_time);
+            (
+                "moon".into(),
+                moon.azimuth.to_degrees(),
+                moon.elevation.to_degrees(),
             )
         }
         TelescopeTarget::Satellite { norad_id } => {
@@ -594,7 +1133,7 @@ pub(crate) async fn save_observation(
             let name = tle_cache
                 .satellite_name(norad_id)
                 .unwrap_or_else(|| norad_id.to_string());
-            (format!("gnss:{name}"), az, el, None)
+            (format!("gnss:{name}"), az, el)
         }
     };
 
@@ -612,6 +1151,15 @@ pub(crate) async fn save_observation(
             return;
         }
     };
+    // The effective receiver settings, read off the frequency axis rather
+    // than the request so they describe the data as it came back.
+    let spectral_channels = spectra.frequencies.len();
+    let bandwidth_hz = match spectra.frequencies.as_slice() {
+        [first, .., last] => {
+            Some((last - first) * spectral_channels as f64 / (spectral_channels - 1) as f64)
+        }
+        _ => None,
+    };
 
     if let Err(err) = Observation::create(
         connection,
@@ -627,6 +1175,14 @@ pub(crate) async fn save_observation(
         vlsr_correction_mps,
         stored_az_offset,
         stored_el_offset,
+        bandwidth_hz,
+        u32::try_from(spectral_channels).ok(),
+        Some(receiver_calibration),
+        Some(location),
+        label,
+        info.position_switch,
+        info.drift_scan,
+        &spectra.scan,
     )
     .await
     {
@@ -637,12 +1193,15 @@ pub(crate) async fn save_observation(
 /// Watch a running integration and stop+save it early on either of two events:
 /// the antenna leaving Tracking (a cable-unwrap slew, or the target sinking out
 /// of the elevation range — either way `measure()` would keep averaging
-/// off-source samples into the block), or an optional fixed duration being
-/// reached. Both race the integration's cancellation token, so a manual End or
-/// booking handover pre-empts the monitor and it exits without touching a
-/// later run.
+/// off-source samples into the block), or its duration being reached: the
+/// optional fixed duration, or else the telescope's `max_integration_secs`, so
+/// a forgotten integration doesn't hold the dish indefinitely. Both race the
+/// integration's cancellation token, so a manual End or booking handover
+/// pre-empts the monitor and it exits without touching a later run. Slewing
+/// after a repoint (`Telescope::repoint`) is not a loss of tracking: the
+/// antenna is moving to where it was told to go.
 ///
-/// The fixed-duration check compares against the *completed* measurement
+/// The duration check compares against the *completed* measurement
 /// cycle's accumulated `observation_time` (from `get_info()`), not a
 /// wall-clock deadline. Each cycle is an uninterruptible ~1 s block (USRP
 /// hardware read, or the fake telescope's tick), and a wall-clock deadline set
@@ -657,34 +1216,61 @@ async fn monitor_integration(
     telescope: Arc<dyn Telescope>,
     token: tokio_util::sync::CancellationToken,
     fixed_duration: Option<std::time::Duration>,
-    db: Arc<Mutex<Connection>>,
+    db: ConnectionPool,
     user: User,
     tle_cache: TleCacheHandle,
     telescope_id: String,
+    labels: Arc<Mutex<HashMap<String, String>>>,
 ) {
     let mut ticker = tokio::time::interval(std::time::Duration::from_secs(1));
     ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    // Target and offsets last tracked, and those seen on the previous tick.
+    let mut tracked_pointing = None;
+    let mut previous_pointing = None;
     loop {
         tokio::select! {
             _ = token.cancelled() => break,
             _ = ticker.tick() => {
                 match telescope.get_info().await {
-                    Ok(info) if info.status != TelescopeStatus::Tracking => {
-                        warn!(
-                            "Stopping integration on {telescope_id}: antenna left tracking (status {:?}) mid-integration",
-                            info.status
-                        );
-                        stop_and_save_observation(telescope.as_ref(), db.clone(), &user, &tle_cache).await;
-                        break;
-                    }
                     Ok(info) => {
-                        let reached = fixed_duration.is_some_and(|target| {
-                            info.latest_observation
-                                .as_ref()
-                                .is_some_and(|obs| obs.observation_time >= target)
+                        let pointing = (info.current_target, info.az_offset_rad, info.el_offset_rad);
+                        // After a repoint, slewing to the new pointing is
+                        // expected; stopping, or an error on the way, is not.
+                        let repointing = tracked_pointing.is_some_and(|tracked| tracked != pointing)
+                            && info.status == TelescopeStatus::Slewing
+                            && info.most_recent_error.is_none();
+                        if info.status == TelescopeStatus::Tracking {
+                            // Right after a repoint the status can still be
+                            // the old one, so a pointing only counts as
+                            // tracked once seen on two ticks in a row.
+                            if tracked_pointing.is_none() || previous_pointing == Some(pointing) {
+                                tracked_pointing = Some(pointing);
+                            }
+                        } else if !repointing {
+                            warn!(
+                                "Stopping integration on {telescope_id}: antenna left tracking (status {:?}) mid-integration",
+                                info.status
+                            );
+                            let label = labels.lock().await.remove(&telescope_id);
+                            stop_and_save_observation(telescope.as_ref(), db.clone(), &user, &tle_cache, label.as_deref()).await;
+                            break;
+                        }
+                        previous_pointing = Some(pointing);
+                        let target = fixed_duration.unwrap_or_else(|| {
+                            std::time::Duration::from_secs_f64(info.max_integration_secs)
                         });
+                        let reached = info.latest_observation
+                            .as_ref()
+                            .is_some_and(|obs| obs.observation_time >= target);
                         if reached {
-                            stop_and_save_observation(telescope.as_ref(), db.clone(), &user, &tle_cache).await;
+                            if fixed_duration.is_none() {
+                                info!(
+                                    "Stopping integration on {telescope_id}: reached the {} s limit",
+                                    info.max_integration_secs
+                                );
+                            }
+                            let label = labels.lock().await.remove(&telescope_id);
+                            stop_and_save_observation(telescope.as_ref(), db.clone(), &user, &tle_cache, label.as_deref()).await;
                             break;
                         }
                     }
@@ -703,27 +1289,22 @@ async fn stop_telescope(
     Path(telescope_id): Path<String>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
-    if !is_authorized_for_telescope(state.database_connection.clone(), &user, &telescope_id).await?
-    {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-    touch_if_guest(state.database_connection.clone(), &user).await;
-    let telescope = state
-        .telescopes
-        .get(&telescope_id)
+    let telescope = authorized_telescope(&state, &user, &telescope_id)
         .await
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .map_err(|err| err.status())?;
     let info = telescope.get_info().await.map_err(|err| {
         error!("Failed to get telescope info: {err}");
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
     if let Some(spectra) = telescope.stop_integration().await {
+        let label = take_observation_label(&state, &telescope_id).await;
         save_observation(
             state.database_connection,
             &user,
             &info,
             &spectra,
             &state.tle_cache,
+            label.as_deref(),
         )
         .await;
     }
@@ -734,6 +1315,50 @@ async fn stop_telescope(
     Ok(error_response(String::new()))
 }
 
+/// Send the telescope to its stow position and stop it there, ending and
+/// saving any running integration first. Telescopes without a stow
+/// position are stopped where they are instead.
+async fn park_telescope(
+    Extension(lang): Extension<Language>,
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Path(telescope_id): Path<String>,
+) -> Result<Response, AppError> {
+    let user = user.ok_or(AppError::Unauthorized)?;
+    let telescope = authorized_telescope(&state, &user, &telescope_id).await?;
+    let info = match telescope.get_info().await {
+        Ok(info) => info,
+        Err(err) => {
+            info!("Failed to get info for {telescope_id}: {err}.");
+            return Ok(telescope_error_response(&lang, err));
+        }
+    };
+    if let Some(spectra) = telescope.stop_integration().await {
+        let label = take_observation_label(&state, &telescope_id).await;
+        save_observation(
+            state.database_connection.clone(),
+            &user,
+            &info,
+            &spectra,
+            &state.tle_cache,
+            label.as_deref(),
+        )
+        .await;
+    }
+    if let Err(err) = telescope.stow().await {
+        info!("Failed to park {telescope_id}: {err}.");
+        return Ok(telescope_error_response(&lang, err));
+    }
+    audit::record(
+        state.database_connection.clone(),
+        &user,
+        AuditAction::Park,
+        &telescope_id,
+    )
+    .await;
+    Ok(error_response(String::new()))
+}
+
 fn default_center_freq_mhz() -> f64 {
     1420.4
 }
@@ -758,8 +1383,16 @@ fn default_rfi_filter() -> bool {
     true
 }
 
+fn default_switch_cycle_secs() -> f64 {
+    20.0
+}
+
+fn default_drift_cadence_secs() -> f64 {
+    10.0
+}
+
 #[derive(Deserialize)]
-struct ObserveForm {
+pub(crate) struct ObserveForm {
     #[serde(default)]
     mode: ObservationMode,
     #[serde(default = "default_center_freq_mhz")]
@@ -775,9 +1408,50 @@ struct ObserveForm {
     #[serde(default = "default_rfi_filter")]
     rfi_filter: bool,
     #[serde(default)]
+    cal_diode: bool,
+    #[serde(default)]
     integration_mode: Option<String>, // "interactive" (default) or "fixed"
     #[serde(default)]
     integration_time_secs: Option<f64>,
+    #[serde(default)]
+    label: String,
+    /// Reference position and cycle for `ObservationMode::PositionSwitched`.
+    #[serde(default)]
+    switch_az_offset_deg: f64,
+    #[serde(default)]
+    switch_el_offset_deg: f64,
+    #[serde(default = "default_switch_cycle_secs")]
+    switch_cycle_secs: f64,
+    /// Save a spectrum every `drift_cadence_secs` while the sky drifts
+    /// through a fixed horizontal pointing.
+    #[serde(default)]
+    drift_scan: bool,
+    #[serde(default = "default_drift_cadence_secs")]
+    drift_cadence_secs: f64,
+}
+
+impl ObserveForm {
+    /// The form's defaults, integrating for `integration_time_secs`.
+    pub(crate) fn fixed(integration_time_secs: f64, label: String) -> ObserveForm {
+        ObserveForm {
+            mode: ObservationMode::default(),
+            center_freq_mhz: default_center_freq_mhz(),
+            ref_freq_mhz: default_ref_freq_mhz(),
+            bandwidth_mhz: default_bandwidth_mhz(),
+            gain_db: default_gain_db(),
+            spectral_channels: default_spectral_channels(),
+            rfi_filter: default_rfi_filter(),
+            cal_diode: false,
+            integration_mode: Some("fixed".to_string()),
+            integration_time_secs: Some(integration_time_secs),
+            label,
+            switch_az_offset_deg: 0.0,
+            switch_el_offset_deg: 0.0,
+            switch_cycle_secs: default_switch_cycle_secs(),
+            drift_scan: false,
+            drift_cadence_secs: default_drift_cadence_secs(),
+        }
+    }
 }
 
 async fn start_observe(
@@ -788,30 +1462,58 @@ async fn start_observe(
     Form(form): Form<ObserveForm>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
-    if !is_authorized_for_telescope(state.database_connection.clone(), &user, &telescope_id).await?
-    {
-        return Err(StatusCode::UNAUTHORIZED);
+    let telescope = authorized_telescope(&state, &user, &telescope_id)
+        .await
+        .map_err(|err| err.status())?;
+
+    match start_integration(&lang, &state, &user, &telescope_id, telescope.clone(), form).await {
+        Ok(()) => {}
+        Err(AppError::Telescope(err)) => return Ok(telescope_error_response(&lang, err)),
+        Err(AppError::BadRequest(message)) => return Ok(error_response(message)),
+        Err(err @ AppError::Conflict(_)) => return Ok(err.into_response()),
+        Err(err) => return Err(err.status()),
     }
-    touch_if_guest(state.database_connection.clone(), &user).await;
 
-    let telescope = state
-        .telescopes
-        .get(&telescope_id)
+    let guest_session = maybe_guest_session_for(&state, &user).await;
+    let in_maintenance = fetch_maintenance_set(state.database_connection.clone())
         .await
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .contains(&telescope_id);
+    let content = observe(
+        lang,
+        telescope.as_ref(),
+        in_maintenance,
+        user.is_admin,
+        &state.weather_cache,
+        &state.source_catalog,
+        guest_session.as_ref(),
+    )
+    .await?;
+    Ok(Html(content).into_response())
+}
 
-    let info = telescope.get_info().await.map_err(|err| {
-        error!("Failed to get telescope info: {err}");
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+/// Configure the receiver from `form` and start integrating on a tracking
+/// telescope. A background task stops and saves the integration if
+/// tracking is lost or its fixed duration has passed. Rejections meant for
+/// the user come back as `BadRequest` in `lang`.
+pub(crate) async fn start_integration(
+    lang: &Language,
+    state: &AppState,
+    user: &User,
+    telescope_id: &str,
+    telescope: Arc<dyn Telescope>,
+    form: ObserveForm,
+) -> Result<(), AppError> {
+    claim_control(lang, state, user, telescope_id).await?;
+    let info = telescope.get_info().await?;
     if info.status != TelescopeStatus::Tracking {
-        return Ok(error_response(fl!(
+        return Err(AppError::BadRequest(fl!(
             lang.loader(),
             "observe-error-not-tracking"
         )));
     }
-    if info.receiver_connected == Some(false) {
-        return Ok(error_response(fl!(
+    if !info.receiver_status.connected {
+        return Err(AppError::BadRequest(fl!(
             lang.loader(),
             "observe-error-receiver-unreachable"
         )));
@@ -823,7 +1525,7 @@ async fn start_observe(
         (FREQ_MIN_USER_MHZ, FREQ_MAX_USER_MHZ)
     };
     if form.center_freq_mhz < freq_min as f64 || form.center_freq_mhz > freq_max as f64 {
-        return Ok(error_response(fl!(
+        return Err(AppError::BadRequest(fl!(
             lang.loader(),
             "observe-error-center-freq",
             min = freq_min,
@@ -831,7 +1533,7 @@ async fn start_observe(
         )));
     }
     if form.ref_freq_mhz < freq_min as f64 || form.ref_freq_mhz > freq_max as f64 {
-        return Ok(error_response(fl!(
+        return Err(AppError::BadRequest(fl!(
             lang.loader(),
             "observe-error-ref-freq",
             min = freq_min,
@@ -839,27 +1541,89 @@ async fn start_observe(
         )));
     }
     if form.gain_db < GAIN_MIN_DB || form.gain_db > GAIN_MAX_DB {
-        return Ok(error_response(fl!(
+        return Err(AppError::BadRequest(fl!(
             lang.loader(),
             "observe-error-gain",
             min = GAIN_MIN_DB,
             max = GAIN_MAX_DB
         )));
     }
+    // The form only offers valid choices for these, so they aren't
+    // translated.
     if !VALID_BANDWIDTH_MHZ.contains(&form.bandwidth_mhz) {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(AppError::BadRequest(format!(
+            "Unsupported bandwidth {} MHz",
+            form.bandwidth_mhz
+        )));
     }
     if !VALID_SPECTRAL_CHANNELS.contains(&form.spectral_channels) {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(AppError::BadRequest(format!(
+            "Unsupported number of spectral channels {}",
+            form.spectral_channels
+        )));
     }
     if form.integration_mode.as_deref() == Some("fixed")
         && let Some(secs) = form.integration_time_secs
-        && !(secs.is_finite() && secs > 0.0 && secs <= MAX_INTEGRATION_TIME_SECS)
+        && !(secs.is_finite() && secs > 0.0 && secs <= info.max_integration_secs)
     {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(AppError::BadRequest(format!(
+            "Integration time must be between 0 and {} s",
+            info.max_integration_secs
+        )));
+    }
+    let label = form.label.trim();
+    if label.chars().count() > MAX_LABEL_LEN {
+        return Err(AppError::BadRequest(format!(
+            "Label is longer than {MAX_LABEL_LEN} characters"
+        )));
     }
+    let position_switch = if form.mode == ObservationMode::PositionSwitched {
+        let switch = PositionSwitch {
+            az_offset_rad: form.switch_az_offset_deg.to_radians(),
+            el_offset_rad: form.switch_el_offset_deg.to_radians(),
+            cycle_secs: form.switch_cycle_secs,
+        };
+        if !switch.is_valid() {
+            let min_cycle = *POSITION_SWITCH_CYCLE_SECS_RANGE.start();
+            let max_cycle = *POSITION_SWITCH_CYCLE_SECS_RANGE.end();
+            return Err(AppError::BadRequest(fl!(
+                lang.loader(),
+                "observe-error-position-switch",
+                max_offset = POSITION_SWITCH_MAX_OFFSET_DEG,
+                min_cycle = min_cycle,
+                max_cycle = max_cycle
+            )));
+        }
+        Some(switch)
+    } else {
+        None
+    };
+    let drift_scan = if form.drift_scan {
+        let drift_scan = DriftScan {
+            cadence_secs: form.drift_cadence_secs,
+        };
+        // Only a horizontal target stays put while the sky drifts past.
+        if !matches!(
+            info.current_target,
+            Some(TelescopeTarget::Horizontal { .. })
+        ) || form.mode == ObservationMode::PositionSwitched
+            || !drift_scan.is_valid()
+        {
+            let min = *DRIFT_SCAN_CADENCE_SECS_RANGE.start();
+            let max = *DRIFT_SCAN_CADENCE_SECS_RANGE.end();
+            return Err(AppError::BadRequest(fl!(
+                lang.loader(),
+                "observe-error-drift-scan",
+                min = min,
+                max = max
+            )));
+        }
+        Some(drift_scan)
+    } else {
+        None
+    };
 
-    telescope
+    let configured = telescope
         .set_receiver_configuration(ReceiverConfiguration {
             integrate: true,
             mode: form.mode,
@@ -869,12 +1633,49 @@ async fn start_observe(
             gain_db: form.gain_db,
             spectral_channels: form.spectral_channels,
             rfi_filter: form.rfi_filter,
+            position_switch,
+            cal_diode: form.cal_diode,
+            drift_scan,
         })
-        .await
-        .map_err(|err| {
-            error!("Failed to set target {err}.");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .await;
+    match configured {
+        Ok(_) => {}
+        Err(ReceiverError::UnsupportedMode) if form.cal_diode => {
+            return Err(AppError::BadRequest(fl!(
+                lang.loader(),
+                "observe-error-cal-unsupported"
+            )));
+        }
+        Err(ReceiverError::UnsupportedMode) => {
+            return Err(AppError::BadRequest(fl!(
+                lang.loader(),
+                "observe-error-mode-unsupported"
+            )));
+        }
+        Err(err) => {
+            return Err(AppError::Internal(format!(
+                "Failed to configure the receiver: {err}"
+            )));
+        }
+    }
+    audit::record(
+        state.database_connection.clone(),
+        user,
+        AuditAction::StartIntegration,
+        &format!(
+            "{telescope_id}: {:?} at {} MHz",
+            form.mode, form.center_freq_mhz
+        ),
+    )
+    .await;
+    {
+        let mut labels = state.observation_labels.lock().await;
+        if label.is_empty() {
+            labels.remove(telescope_id);
+        } else {
+            labels.insert(telescope_id.to_string(), label.to_string());
+        }
+    }
 
     // Monitor the running integration and stop+save it early on two events:
     //   * The antenna loses track (e.g. a cable-unwrap slew swings it far off
@@ -899,25 +1700,11 @@ async fn start_observe(
             state.database_connection.clone(),
             user.clone(),
             state.tle_cache.clone(),
-            telescope_id.clone(),
+            telescope_id.to_string(),
+            state.observation_labels.clone(),
         ));
     }
-
-    let guest_session = maybe_guest_session_for(&state, &user).await;
-    let in_maintenance = fetch_maintenance_set(state.database_connection.clone())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .contains(&telescope_id);
-    let content = observe(
-        lang,
-        telescope.as_ref(),
-        in_maintenance,
-        user.is_admin,
-        &state.weather_cache,
-        guest_session.as_ref(),
-    )
-    .await?;
-    Ok(Html(content).into_response())
+    Ok(())
 }
 
 async fn stop_observe(
@@ -927,23 +1714,17 @@ async fn stop_observe(
     Path(telescope_id): Path<String>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
-    if !is_authorized_for_telescope(state.database_connection.clone(), &user, &telescope_id).await?
-    {
-        return Err(StatusCode::UNAUTHORIZED);
-    }
-    touch_if_guest(state.database_connection.clone(), &user).await;
-
-    let telescope = state
-        .telescopes
-        .get(&telescope_id)
+    let telescope = authorized_telescope(&state, &user, &telescope_id)
         .await
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .map_err(|err| err.status())?;
 
+    let label = take_observation_label(&state, &telescope_id).await;
     stop_and_save_observation(
         telescope.as_ref(),
         state.database_connection.clone(),
         &user,
         &state.tle_cache,
+        label.as_deref(),
     )
     .await;
     let guest_session = maybe_guest_session_for(&state, &user).await;
@@ -957,6 +1738,7 @@ async fn stop_observe(
         in_maintenance,
         user.is_admin,
         &state.weather_cache,
+        &state.source_catalog,
         guest_session.as_ref(),
     )
     .await?;
@@ -995,6 +1777,7 @@ async fn get_observe(
         in_maintenance,
         user.is_admin,
         &state.weather_cache,
+        &state.source_catalog,
         guest_session.as_ref(),
     )
     .await?;
@@ -1072,7 +1855,6 @@ const GAIN_MAX_DB: f64 = 88.0;
 // sizes the IQ sample buffer, an unbounded value can OOM-abort the process.
 const VALID_BANDWIDTH_MHZ: &[f64] = &[1.0, 2.5, 5.0, 12.5, 25.0];
 const VALID_SPECTRAL_CHANNELS: &[usize] = &[64, 128, 256, 512, 1024, 2048, 4096, 8192];
-const MAX_INTEGRATION_TIME_SECS: f64 = 3600.0;
 
 #[derive(Template)]
 #[template(path = "observe.html")]
@@ -1107,25 +1889,37 @@ async fn observe(
     in_maintenance: bool,
     is_admin: bool,
     weather_cache: &crate::weather_cache::WeatherCacheHandle,
+    source_catalog: &SourceCatalog,
     guest_session: Option<&GuestSession>,
 ) -> Result<String, StatusCode> {
     let info = telescope.get_info().await.map_err(|err| {
         error!("Failed to get info {err}");
         StatusCode::NOT_FOUND
     })?;
+    // A catalog source is shown by name, as it was entered.
+    let source_name = info
+        .current_target
+        .as_ref()
+        .and_then(|target| source_catalog.name_of(target, Utc::now()));
     let target_mode = match &info.current_target {
+        Some(TelescopeTarget::Equatorial { .. }) if source_name.is_some() => "name",
         Some(TelescopeTarget::Equatorial { .. }) => "equatorial",
         Some(TelescopeTarget::Galactic { .. }) => "galactic",
         Some(TelescopeTarget::Horizontal { .. }) => "horizontal",
         Some(TelescopeTarget::Sun) => "sun",
+        Some(TelescopeTarget::Moon) => "moon",
         Some(TelescopeTarget::Satellite { .. }) => "gnss",
         None => "galactic",
     }
     .to_string();
     let (commanded_x, commanded_y) = match info.current_target {
+        Some(TelescopeTarget::Equatorial { .. }) if let Some(name) = source_name => {
+            (name.to_string(), String::new())
+        }
         Some(TelescopeTarget::Equatorial {
             right_ascension,
             declination,
+            ..
         }) => (
             fmt_deg(right_ascension.to_degrees()),
             fmt_deg(declination.to_degrees()),
@@ -1142,7 +1936,7 @@ async fn observe(
             fmt_deg(elevation.to_degrees()),
         ),
         Some(TelescopeTarget::Satellite { norad_id }) => (norad_id.to_string(), String::new()),
-        Some(TelescopeTarget::Sun) => (String::new(), String::new()),
+        Some(TelescopeTarget::Sun | TelescopeTarget::Moon) => (String::new(), String::new()),
         // Idle telescope (fresh booking, no continuation): pre-fill a
         // bright HI-line target in the Galactic disk so a first-time
         // visitor can hit Track and see real signal without first
@@ -1222,7 +2016,7 @@ async fn start_guest_session_auto(
                     "Guest session {} started (auto-pick): user_id={} telescope={}",
                     gs.id, gs.user_id, gs.telescope_id
                 );
-                let cookie = session_cookie(&session.token);
+                let cookie = session_cookie(&state.session_config, &session);
                 let mut headers = HeaderMap::new();
                 headers.insert(
                     SET_COOKIE,
@@ -1282,7 +2076,7 @@ async fn start_guest_session(
                 "Guest session {} started: user_id={} telescope={}",
                 gs.id, user.id, gs.telescope_id
             );
-            let cookie = session_cookie(&session.token);
+            let cookie = session_cookie(&state.session_config, &session);
             let mut headers = HeaderMap::new();
             headers.insert(
                 SET_COOKIE,
@@ -1361,7 +2155,7 @@ async fn end_guest_session(
     let mut headers = HeaderMap::new();
     headers.insert(
         SET_COOKIE,
-        clear_session_cookie()
+        clear_session_cookie(&state.session_config)
             .parse()
             .expect("Cookie should be parseable"),
     );
@@ -1372,8 +2166,10 @@ async fn end_guest_session(
 mod tests {
     use super::*;
     use crate::coords::{Direction, Location};
-    use crate::models::telescope_types::IqBlock;
+    use crate::models::telescope_types::{IqBlock, ReceiverCalibration, SubsystemStatus};
     use async_trait::async_trait;
+    use chrono::DateTime;
+    use rusqlite::Connection;
     use std::sync::atomic::{AtomicBool, Ordering};
     use tokio_util::sync::CancellationToken;
 
@@ -1398,9 +2194,12 @@ mod tests {
                 azimuth: 3.0,
                 elevation: 1.0,
             }),
+            estimated_seconds_to_target: Some(10.0),
+            pointing_error_degrees: None,
             current_target: None,
             most_recent_error: None,
             measurement_in_progress: true,
+            integration_elapsed: Some(std::time::Duration::from_secs(1)),
             latest_observation: None,
             stow_position: None,
             service_position: None,
@@ -1412,12 +2211,24 @@ mod tests {
             },
             min_elevation_rad: 0.0,
             max_elevation_rad: std::f64::consts::PI,
+            azimuth_range_rad: None,
             webcam_crop: None,
-            receiver_connected: None,
-            controller_connected: None,
+            mount_status: SubsystemStatus::healthy(),
+            receiver_status: SubsystemStatus::healthy(),
+            controller_reconnect_in_secs: None,
+            last_successful_update: None,
             wind_warning_ms: None,
+            max_integration_secs: 3600.0,
             default_ref_freq_mhz: 1417.9,
             default_gain_db: 60.0,
+            rest_freq_mhz: 1420.405751768,
+            receiver_calibration: ReceiverCalibration {
+                system_temperature_k: 300.0,
+                gain_db: 60.0,
+            },
+            position_switch: None,
+            supports_position_switching: true,
+            drift_scan: None,
         }
     }
 
@@ -1432,6 +2243,7 @@ mod tests {
                 frequencies: vec![0.0],
                 spectra: vec![0.0],
                 observation_time: std::time::Duration::from_secs(1),
+                scan: Vec::new(),
             })
         }
         async fn set_target(
@@ -1442,6 +2254,14 @@ mod tests {
         ) -> Result<TelescopeTarget, TelescopeError> {
             unimplemented!()
         }
+        async fn repoint(
+            &self,
+            _t: TelescopeTarget,
+            _az: f64,
+            _el: f64,
+        ) -> Result<TelescopeTarget, TelescopeError> {
+            unimplemented!()
+        }
         async fn calibrate(
             &self,
             _az_offset_rad: f64,
@@ -1452,6 +2272,9 @@ mod tests {
         async fn stop(&self) -> Result<(), TelescopeError> {
             unimplemented!()
         }
+        async fn stow(&self) -> Result<(), TelescopeError> {
+            unimplemented!()
+        }
         async fn set_receiver_configuration(
             &self,
             _c: ReceiverConfiguration,
@@ -1461,12 +2284,24 @@ mod tests {
         async fn clear_measurements(&self) {
             unimplemented!()
         }
+        async fn latest_spectrum(&self) -> Option<ObservedSpectra> {
+            unimplemented!()
+        }
         async fn interferometry_capable(&self) -> bool {
             unimplemented!()
         }
         async fn current_integration_token(&self) -> Option<CancellationToken> {
             unimplemented!()
         }
+        async fn error_history(&self) -> Vec<(DateTime<Utc>, TelescopeError)> {
+            unimplemented!()
+        }
+        async fn subscribe_position(
+            &self,
+        ) -> Option<tokio::sync::broadcast::Receiver<crate::models::telescope_types::PositionUpdate>>
+        {
+            unimplemented!()
+        }
         async fn shutdown(&self) {
             unimplemented!()
         }
@@ -1488,17 +2323,8 @@ mod tests {
         let telescope: Arc<dyn Telescope> = Arc::new(SlewingMock {
             stop_called: stop_called.clone(),
         });
-        let db = Arc::new(Mutex::new(
-            Connection::open_in_memory().expect("in-memory sqlite"),
-        ));
-        let guest = User {
-            id: 1,
-            name: "guest".to_string(),
-            provider: "guest".to_string(),
-            is_admin: false,
-            timezone: None,
-            language: None,
-        };
+        let db = ConnectionPool::single(Connection::open_in_memory().expect("in-memory sqlite"));
+        let guest = guest_user();
 
         let finished = tokio::time::timeout(
             std::time::Duration::from_secs(5),
@@ -1510,6 +2336,7 @@ mod tests {
                 guest,
                 TleCacheHandle::new(),
                 "mock".to_string(),
+                Arc::new(Mutex::new(HashMap::new())),
             ),
         )
         .await;
@@ -1523,4 +2350,214 @@ mod tests {
             "monitor should have called stop_integration"
         );
     }
+
+    // An integration nobody ends is stopped once it reaches the telescope's
+    // max_integration_secs, here one second on the fake telescope.
+    #[tokio::test]
+    async fn monitor_stops_integration_at_the_time_limit() {
+        let telescope = integrating_fake(1.0).await;
+        let guest = guest_user();
+
+        let finished = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            monitor_integration(
+                telescope.clone(),
+                telescope.current_integration_token().await.unwrap(),
+                None,
+                ConnectionPool::single(Connection::open_in_memory().unwrap()),
+                guest,
+                TleCacheHandle::new(),
+                "fake".to_string(),
+                Arc::new(Mutex::new(HashMap::new())),
+            ),
+        )
+        .await;
+
+        assert!(finished.is_ok(), "monitor should stop the integration");
+        assert!(!telescope.get_info().await.unwrap().measurement_in_progress);
+        telescope.shutdown().await;
+    }
+
+    // Slewing after a repoint is tolerated, but a dish stopped on the way
+    // to its new pointing no longer observes anything it was asked to.
+    #[tokio::test]
+    async fn monitor_stops_integration_when_a_repoint_is_cut_short() {
+        let telescope = integrating_fake(3600.0).await;
+        let guest = guest_user();
+        let monitor = tokio::spawn(monitor_integration(
+            telescope.clone(),
+            telescope.current_integration_token().await.unwrap(),
+            None,
+            ConnectionPool::single(Connection::open_in_memory().unwrap()),
+            guest,
+            TleCacheHandle::new(),
+            "fake".to_string(),
+            Arc::new(Mutex::new(HashMap::new())),
+        ));
+        tokio::time::sleep(std::time::Duration::from_millis(2500)).await;
+
+        telescope
+            .repoint(
+                TelescopeTarget::Horizontal {
+                    azimuth: 2.0,
+                    elevation: 0.5,
+                },
+                0.0,
+                0.0,
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(2500)).await;
+        assert!(
+            !monitor.is_finished(),
+            "slewing to the new pointing is fine"
+        );
+        assert!(telescope.get_info().await.unwrap().measurement_in_progress);
+
+        telescope.stop().await.unwrap();
+        let finished = tokio::time::timeout(std::time::Duration::from_secs(5), monitor).await;
+        assert!(finished.is_ok(), "monitor should stop the integration");
+        assert!(!telescope.get_info().await.unwrap().measurement_in_progress);
+        telescope.shutdown().await;
+    }
+
+    /// A user logged in through a guest session.
+    fn guest_user() -> User {
+        User {
+            id: 1,
+            name: "guest".to_string(),
+            provider: "guest".to_string(),
+            is_admin: false,
+            timezone: None,
+            language: None,
+            display_name: None,
+        }
+    }
+
+    /// A fake telescope tracking a fixed position and integrating, stopping
+    /// integrations after `max_integration_secs`.
+    async fn integrating_fake(max_integration_secs: f64) -> Arc<dyn Telescope> {
+        let telescope: Arc<dyn Telescope> = Arc::new(
+            crate::models::fake_telescope::test_telescope(None, None, max_integration_secs),
+        );
+        telescope
+            .set_target(
+                TelescopeTarget::Horizontal {
+                    azimuth: 0.0,
+                    elevation: 1.5,
+                },
+                0.0,
+                0.0,
+            )
+            .await
+            .unwrap();
+        while telescope.get_info().await.unwrap().status != TelescopeStatus::Tracking {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        telescope
+            .set_receiver_configuration(ReceiverConfiguration {
+                integrate: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        telescope
+    }
+
+    #[test]
+    fn jog_stays_within_the_limits() {
+        let deg = f64::to_radians;
+        let jog = |az: f64, el: f64, step_az: f64, step_el: f64| {
+            let direction = jog_direction(
+                Direction {
+                    azimuth: deg(az),
+                    elevation: deg(el),
+                },
+                deg(step_az),
+                deg(step_el),
+                (deg(5.0), deg(85.0)),
+                Some((deg(-90.0), deg(450.0))),
+            );
+            (
+                direction.azimuth.to_degrees().round(),
+                direction.elevation.to_degrees().round(),
+            )
+        };
+        assert_eq!(jog(100.0, 40.0, 2.0, -3.0), (102.0, 37.0));
+        assert_eq!(jog(358.0, 40.0, 4.0, 0.0), (2.0, 40.0));
+        // Held at the end of the cable wrap rather than past it, where the
+        // tracker would unwind.
+        assert_eq!(jog(448.0, 40.0, 5.0, 0.0), (90.0, 40.0));
+        assert_eq!(jog(-88.0, 40.0, -5.0, 0.0), (270.0, 40.0));
+        assert_eq!(jog(100.0, 7.0, 0.0, -5.0), (100.0, 5.0));
+        assert_eq!(jog(100.0, 84.0, 0.0, 5.0), (100.0, 85.0));
+    }
+
+    #[test]
+    fn telescope_errors_get_their_own_message() {
+        let lang = Language::English;
+        let busy = telescope_error_response(&lang, TelescopeError::TelescopeBusy);
+        assert_eq!(busy.status(), StatusCode::CONFLICT);
+        assert_eq!(busy.headers()["HX-Retarget"], "#errors");
+
+        let offline = telescope_error_response(&lang, TelescopeError::TelescopeNotConnected);
+        assert_eq!(offline.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(offline.headers()["HX-Retarget"], "#errors");
+
+        assert_ne!(
+            fl!(lang.loader(), "observe-error-telescope-busy"),
+            fl!(lang.loader(), "observe-error-telescope-not-connected")
+        );
+    }
+
+    #[test]
+    fn current_pointing_is_converted_for_the_telescope_site_and_read_time() {
+        let location = Location {
+            longitude: 11.9f64.to_radians(),
+            latitude: 57.4f64.to_radians(),
+        };
+        let when = DateTime::parse_from_rfc3339("2026-03-20T21:15:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let (l, b) = (120.0f64.to_radians(), 5.0f64.to_radians());
+        let info = TelescopeInfo {
+            location,
+            last_successful_update: Some(when),
+            current_horizontal: Some(horizontal_from_galactic(location, when, l, b)),
+            ..info_slewing_and_measuring()
+        };
+        let pointing = info.current_pointing().unwrap();
+        assert!((pointing.galactic_longitude_deg - 120.0).abs() < 1e-6);
+        assert!((pointing.galactic_latitude_deg - 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn position_switching_is_only_offered_where_supported() {
+        let render = |supports_position_switching| {
+            ObserveTemplate {
+                lang: Language::English,
+                info: TelescopeInfo {
+                    supports_position_switching,
+                    ..info_slewing_and_measuring()
+                },
+                target_mode: "galactic".to_string(),
+                commanded_x: String::new(),
+                commanded_y: String::new(),
+                state_html: String::new(),
+                in_maintenance: false,
+                is_admin: false,
+                freq_min_mhz: 1400,
+                freq_max_mhz: 1440,
+                wind_warning: false,
+                guest_started_at: None,
+                guest_last_activity_at: None,
+                guest_idle_secs: 0,
+                guest_ceiling_secs: 0,
+            }
+            .render()
+            .unwrap()
+        };
+        assert!(render(true).contains("value=\"PositionSwitched\""));
+        assert!(!render(false).contains("value=\"PositionSwitched\""));
+    }
 }
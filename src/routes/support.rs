@@ -22,12 +22,17 @@ pub fn routes(state: AppState) -> Router {
 }
 
 async fn get_support_manual(
+    State(state): State<AppState>,
     Extension(lang): Extension<Language>,
     Extension(user): Extension<Option<User>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    let content =
-        crate::routes::read_content_page("user-manual", lang, "<p>User manual not available.</p>");
+    let content = crate::routes::read_content_page(
+        &state.assets_dir,
+        "user-manual",
+        lang,
+        "<p>User manual not available.</p>",
+    );
     let content = if headers.get("hx-request").is_some() {
         content
     } else {
@@ -37,11 +42,13 @@ async fn get_support_manual(
 }
 
 async fn get_google_sheets_guide(
+    State(state): State<AppState>,
     Extension(lang): Extension<Language>,
     Extension(user): Extension<Option<User>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
     let content = crate::routes::read_content_page(
+        &state.assets_dir,
         "google-sheets-guide",
         lang,
         "<p>Google Sheets guide not available.</p>",
@@ -61,6 +68,7 @@ async fn get_support(
     headers: HeaderMap,
 ) -> impl IntoResponse {
     let body = crate::routes::read_content_page(
+        &state.assets_dir,
         "support",
         lang,
         "<p>Support information not available.</p>",
@@ -1,27 +1,32 @@
 use axum::{
     Extension, Router,
+    extract::State,
     http::HeaderMap,
     response::{Html, IntoResponse},
     routing::get,
 };
 
+use crate::app::AppState;
 use crate::i18n::Language;
 use crate::models::user::User;
 use crate::routes::index::render_main;
 
-pub fn routes() -> Router {
+pub fn routes(state: AppState) -> Router {
     Router::new()
         .route("/", get(get_technical))
         .route("/rot2prog", get(get_rot2prog))
         .route("/lna", get(get_lna))
+        .with_state(state)
 }
 
 async fn get_rot2prog(
+    State(state): State<AppState>,
     Extension(lang): Extension<Language>,
     Extension(user): Extension<Option<User>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
     let content = crate::routes::read_content_page(
+        &state.assets_dir,
         "rot2prog",
         lang,
         "<p>ROT2PROG documentation not available.</p>",
@@ -35,12 +40,17 @@ async fn get_rot2prog(
 }
 
 async fn get_lna(
+    State(state): State<AppState>,
     Extension(lang): Extension<Language>,
     Extension(user): Extension<Option<User>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    let content =
-        crate::routes::read_content_page("lna", lang, "<p>LNA documentation not available.</p>");
+    let content = crate::routes::read_content_page(
+        &state.assets_dir,
+        "lna",
+        lang,
+        "<p>LNA documentation not available.</p>",
+    );
     let content = if headers.get("hx-request").is_some() {
         content
     } else {
@@ -50,11 +60,13 @@ async fn get_lna(
 }
 
 async fn get_technical(
+    State(state): State<AppState>,
     Extension(lang): Extension<Language>,
     Extension(user): Extension<Option<User>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
     let content = crate::routes::read_content_page(
+        &state.assets_dir,
         "technical",
         lang,
         "<p>Technical information not available.</p>",
@@ -1,12 +1,12 @@
 use std::sync::Arc;
 
 use crate::app::AppState;
-use crate::coords::{PRACTICAL_ELEVATION_LIMIT_DEG, vlsrcorr_from_galactic};
+use crate::coords::PRACTICAL_ELEVATION_LIMIT_DEG;
 use crate::i18n::Language;
 use crate::models::booking::is_authorized_for_telescope;
 use crate::models::telescope::Telescope;
 use crate::models::telescope_types::TelescopeStatus;
-use crate::models::telescope_types::{TelescopeError, TelescopeInfo, TelescopeTarget};
+use crate::models::telescope_types::{TelescopeError, TelescopeInfo};
 use crate::models::user::User;
 use askama::Template;
 use axum::Extension;
@@ -16,11 +16,13 @@ use axum::{
     extract::ws::{WebSocket, WebSocketUpgrade},
     extract::{Path, State},
     http::StatusCode,
-    response::{Html, IntoResponse, Response},
+    response::{Html, IntoResponse, Json, Response},
     routing::{any, get},
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use i18n_embed_fl::fl;
+use serde::Serialize;
+use tokio::sync::broadcast;
 use tokio::time::Duration;
 use tokio_util::bytes::Bytes;
 use tracing::debug;
@@ -28,7 +30,9 @@ use tracing::debug;
 pub fn routes(state: AppState) -> Router {
     let telescope_routes = Router::new()
         .route("/state", get(get_state))
-        .route("/spectrum", any(spectrum_handle_upgrade));
+        .route("/errors", get(get_errors))
+        .route("/spectrum", any(spectrum_handle_upgrade))
+        .route("/ws", any(position_handle_upgrade));
     Router::new()
         .nest("/{telescope_id}", telescope_routes)
         .with_state(state)
@@ -58,13 +62,9 @@ async fn spectrum_handle_upgrade(
 async fn spectrum_handle_websocket(mut socket: WebSocket, telescope: Arc<dyn Telescope>) {
     // Send one-time JSON metadata with VLSR correction
     if let Ok(info) = telescope.get_info().await {
-        let vlsr_correction_mps = match info.current_target {
-            Some(TelescopeTarget::Galactic {
-                longitude,
-                latitude,
-            }) => Some(vlsrcorr_from_galactic(longitude, latitude, Utc::now())),
-            _ => None,
-        };
+        let vlsr_correction_mps = info
+            .current_target
+            .and_then(|target| target.vlsr_correction(info.location, Utc::now()));
         let json = serde_json::json!({ "vlsr_correction_mps": vlsr_correction_mps });
         if socket
             .send(Message::Text(json.to_string().into()))
@@ -109,6 +109,48 @@ async fn spectrum_handle_websocket(mut socket: WebSocket, telescope: Arc<dyn Tel
     }
 }
 
+/// Live position is public, like the state fragment, so no login is needed.
+async fn position_handle_upgrade(
+    upgrade: WebSocketUpgrade,
+    Path(telescope_id): Path<String>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let telescope = state
+        .telescopes
+        .get(&telescope_id)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    debug!("Setting up position websocket for {}", telescope_id);
+    Ok(upgrade.on_upgrade(move |socket| position_handle_websocket(socket, telescope)))
+}
+
+/// Forward every position update as a JSON text message until the client
+/// goes away or the telescope shuts down.
+async fn position_handle_websocket(mut socket: WebSocket, telescope: Arc<dyn Telescope>) {
+    let Some(mut updates) = telescope.subscribe_position().await else {
+        let _ = socket.send(Message::Close(None)).await;
+        return;
+    };
+    loop {
+        let update = match updates.recv().await {
+            Ok(update) => update,
+            // A slow client only cares about the newest position.
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => {
+                let _ = socket.send(Message::Close(None)).await;
+                return;
+            }
+        };
+        let json = match serde_json::to_string(&update) {
+            Ok(json) => json,
+            Err(_) => continue,
+        };
+        if socket.send(Message::Text(json.into())).await.is_err() {
+            return;
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct TelescopeNotFound;
 
@@ -133,14 +175,50 @@ pub async fn get_state(
     ))
 }
 
+#[derive(Serialize)]
+struct ErrorLogEntry {
+    time: DateTime<Utc>,
+    message: String,
+    error: TelescopeError,
+}
+
+/// Recent errors for one telescope, oldest first, for operators chasing
+/// intermittent controller problems. Admin only.
+async fn get_errors(
+    Extension(user): Extension<Option<User>>,
+    State(state): State<AppState>,
+    Path(telescope_id): Path<String>,
+) -> Result<Json<Vec<ErrorLogEntry>>, StatusCode> {
+    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+    if !user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let telescope = state
+        .telescopes
+        .get(&telescope_id)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let entries = telescope
+        .error_history()
+        .await
+        .into_iter()
+        .map(|(time, error)| ErrorLogEntry {
+            time,
+            message: error.to_string(),
+            error,
+        })
+        .collect();
+    Ok(Json(entries))
+}
+
 #[derive(Template)]
 #[template(path = "telescope_state.html")]
 struct TelescopeStateTemplate {
     lang: Language,
     info: TelescopeInfo,
-    /// Machine-readable status ("Idle"/"Slewing"/"Tracking"), emitted as a
-    /// data attribute for the observe-page JS; the visible text is
-    /// translated separately.
+    /// Machine-readable status ("Idle"/"Slewing"/"Tracking"/"Stowed"/
+    /// "Parked"), emitted as a data attribute for the observe-page JS; the
+    /// visible text is translated separately.
     status: String,
     error: String,
     /// Machine-readable error category for the observe-page JS ("" when
@@ -154,6 +232,7 @@ struct TelescopeStateTemplate {
 struct TelescopeOfflineTemplate {
     lang: Language,
     id: String,
+    reconnect_in_secs: Option<f64>,
 }
 
 pub async fn telescope_state(
@@ -161,20 +240,25 @@ pub async fn telescope_state(
     telescope: &dyn Telescope,
     lang: Language,
 ) -> String {
-    match telescope.get_info().await {
-        Ok(info)
-            if matches!(
-                info.most_recent_error,
-                Some(TelescopeError::TelescopeIOError(_) | TelescopeError::TelescopeNotConnected)
-            ) =>
-        {
-            TelescopeOfflineTemplate {
-                lang,
-                id: telescope_id.to_string(),
-            }
-            .render()
-            .expect("Template rendering should always succeed")
+    render_telescope_state(telescope_id, telescope.get_info().await, lang)
+}
+
+/// The status fragment for `info`. A mount that can't be reached takes the
+/// whole fragment; a missing receiver only adds a line, since the dish can
+/// still be pointed.
+fn render_telescope_state(
+    telescope_id: &str,
+    info: Result<TelescopeInfo, TelescopeError>,
+    lang: Language,
+) -> String {
+    match info {
+        Ok(info) if !info.mount_status.connected => TelescopeOfflineTemplate {
+            lang,
+            id: telescope_id.to_string(),
+            reconnect_in_secs: info.controller_reconnect_in_secs,
         }
+        .render()
+        .expect("Template rendering should always succeed"),
         Ok(info) => TelescopeStateTemplate {
             lang,
             info: info.clone(),
@@ -184,7 +268,7 @@ pub async fn telescope_state(
             // while the telescope is actually going to / on a target; an
             // idle telescope's commanded position is meaningless.
             low_elevation_deg: match &info.status {
-                TelescopeStatus::Idle => None,
+                TelescopeStatus::Idle | TelescopeStatus::Stowed | TelescopeStatus::Parked => None,
                 TelescopeStatus::Slewing | TelescopeStatus::Tracking => info
                     .commanded_horizontal
                     .map(|dir| dir.elevation.to_degrees())
@@ -194,6 +278,8 @@ pub async fn telescope_state(
                 TelescopeStatus::Idle => "Idle".to_string(),
                 TelescopeStatus::Slewing => "Slewing".to_string(),
                 TelescopeStatus::Tracking => "Tracking".to_string(),
+                TelescopeStatus::Stowed => "Stowed".to_string(),
+                TelescopeStatus::Parked => "Parked".to_string(),
             },
             error: match &info.most_recent_error {
                 Some(err) => match err {
@@ -205,6 +291,11 @@ pub async fn telescope_state(
                             max = format!("{max_deg:.0}")
                         )
                     }
+                    TelescopeError::TargetInKeyhole { max_deg } => fl!(
+                        lang.loader(),
+                        "state-error-keyhole",
+                        max = format!("{max_deg:.0}")
+                    ),
                     TelescopeError::TelescopeIOError(_) => fl!(lang.loader(), "state-error-io"),
                     TelescopeError::TelescopeNotConnected => {
                         fl!(lang.loader(), "state-error-not-connected")
@@ -220,6 +311,7 @@ pub async fn telescope_state(
             },
             error_kind: match &info.most_recent_error {
                 Some(TelescopeError::TargetOutOfElevationRange { .. }) => "elevation",
+                Some(TelescopeError::TargetInKeyhole { .. }) => "keyhole",
                 Some(TelescopeError::TelescopeIOError(_)) => "io",
                 Some(TelescopeError::TelescopeNotConnected) => "not-connected",
                 Some(TelescopeError::ReceiverFailed(_)) => "receiver",
@@ -232,8 +324,49 @@ pub async fn telescope_state(
         Err(_) => TelescopeOfflineTemplate {
             lang,
             id: telescope_id.to_string(),
+            reconnect_in_secs: None,
         }
         .render()
         .expect("Template rendering should always succeed"),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::fake_telescope;
+    use crate::models::telescope_types::SubsystemStatus;
+
+    #[tokio::test]
+    async fn state_shows_which_subsystem_is_down() {
+        let telescope = fake_telescope::test_telescope(None, None, 1.0);
+        let healthy = telescope.get_info().await.unwrap();
+        telescope.shutdown().await;
+        assert_eq!(healthy.mount_status, SubsystemStatus::healthy());
+        assert_eq!(healthy.receiver_status, SubsystemStatus::healthy());
+        let lang = Language::English;
+        let receiver_offline = lang.t("state-receiver-offline");
+        let mount_offline = lang.t("state-offline-error");
+
+        let html = render_telescope_state("fake", Ok(healthy.clone()), lang);
+        assert!(!html.contains(&receiver_offline));
+        assert!(!html.contains(&mount_offline));
+
+        let mut receiver_down = healthy.clone();
+        receiver_down.receiver_status = SubsystemStatus {
+            connected: false,
+            error: None,
+        };
+        let html = render_telescope_state("fake", Ok(receiver_down), lang);
+        assert!(html.contains(&receiver_offline));
+        assert!(!html.contains(&mount_offline));
+
+        let mut mount_down = healthy;
+        mount_down.mount_status = SubsystemStatus {
+            connected: false,
+            error: Some(TelescopeError::TelescopeNotConnected),
+        };
+        let html = render_telescope_state("fake", Ok(mount_down), lang);
+        assert!(html.contains(&mount_offline));
+    }
+}
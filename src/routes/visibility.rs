@@ -22,7 +22,7 @@ use serde::Deserialize;
 
 use crate::coords::{
     Direction, ONSALA_LOCATION, PRACTICAL_ELEVATION_LIMIT_DEG, horizontal_from_equatorial,
-    horizontal_from_galactic, horizontal_from_sun,
+    horizontal_from_galactic, horizontal_from_moon, horizontal_from_sun,
 };
 use crate::i18n::Language;
 use crate::models::user::User;
@@ -98,7 +98,7 @@ async fn get_visibility(
         coord.as_str(),
     ) {
         (Err(_), _) => error = Some(fl!(lang.loader(), "vis-error-date")),
-        (Ok(date), c @ ("galactic" | "equatorial" | "sun")) => {
+        (Ok(date), c @ ("galactic" | "equatorial" | "sun" | "moon")) => {
             result = Some(compute_visibility(c, x_val, y_val, date, tz, lang));
         }
         (Ok(_), _) => error = Some(fl!(lang.loader(), "vis-error-coord")),
@@ -200,6 +200,7 @@ fn compute_visibility(
             "galactic" => horizontal_from_galactic(location, when, x_rad, y_rad),
             "equatorial" => horizontal_from_equatorial(location, when, x_rad, y_rad),
             "sun" => horizontal_from_sun(location, when),
+            "moon" => horizontal_from_moon(location, when),
             _ => Direction {
                 azimuth: 0.0,
                 elevation: 0.0,
@@ -236,6 +237,7 @@ fn compute_visibility(
             fl!(lang.loader(), "vis-target-equatorial", x = x, y = y)
         }
         "sun" => fl!(lang.loader(), "observe-coord-sun"),
+        "moon" => fl!(lang.loader(), "observe-coord-moon"),
         _ => coord.to_string(),
     };
     let title_line1 = fl!(
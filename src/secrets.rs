@@ -1,6 +1,8 @@
 use crate::error::InternalError;
+use oauth2::url::Url;
 use serde::Deserialize;
-use std::{collections::HashMap, fs::read_to_string};
+use std::{collections::HashMap, fs::read_to_string, path::Path, time::Duration};
+use tracing::{error, info};
 
 #[derive(Deserialize, Clone)]
 pub struct WebcamCredentials {
@@ -11,10 +13,26 @@ pub struct WebcamCredentials {
 
 #[derive(Deserialize, Clone)]
 pub struct AuthProvider {
+    /// OpenID Connect issuer. When set, endpoints left out below are
+    /// filled in from its discovery document at startup.
+    #[serde(default)]
+    pub issuer: Option<String>,
+    #[serde(default)]
     pub auth_uri: String,
+    #[serde(default)]
     pub token_uri: String,
     pub redirect_uri: String,
+    /// Other hosts (`name` or `name:port`) SALSA is reached by. A login
+    /// started on one of them is sent back there instead of to the host in
+    /// `redirect_uri`. Empty by default: the Host and X-Forwarded-* headers
+    /// are then never used.
+    #[serde(default)]
+    pub redirect_hosts: Vec<String>,
+    #[serde(default)]
     pub user_uri: String,
+    /// Where in the user object from `user_uri` the account id and the
+    /// display name are. Dotted paths reach into nested objects, e.g.
+    /// `data.user.id`.
     pub id_field: String,
     pub display_name_field: String,
     pub scopes: Vec<String>,
@@ -24,6 +42,111 @@ pub struct AuthProvider {
     pub description: Option<String>,
 }
 
+/// The parts of an OpenID Connect discovery document we use.
+#[derive(Deserialize)]
+struct OidcConfiguration {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: Option<String>,
+}
+
+impl AuthProvider {
+    /// What is wrong with the provider's settings, as found in the file,
+    /// before any discovery. Checked at startup, since the login handlers
+    /// take these URLs as valid.
+    fn problems(&self, name: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        let urls = [
+            ("auth_uri", &self.auth_uri),
+            ("token_uri", &self.token_uri),
+            ("user_uri", &self.user_uri),
+            ("redirect_uri", &self.redirect_uri),
+        ];
+        for (field, value) in urls {
+            if value.is_empty() {
+                if field == "redirect_uri" || self.issuer.is_none() {
+                    problems.push(format!("auth_provider.{name}: {field} is missing"));
+                }
+            } else if Url::parse(value).is_err() {
+                problems.push(format!(
+                    "auth_provider.{name}: {field} \"{value}\" is not a URL"
+                ));
+            }
+        }
+        if let Some(issuer) = &self.issuer
+            && Url::parse(issuer).is_err()
+        {
+            problems.push(format!(
+                "auth_provider.{name}: issuer \"{issuer}\" is not a URL"
+            ));
+        }
+        if self.client_id.is_empty() {
+            problems.push(format!("auth_provider.{name}: client_id is missing"));
+        }
+        problems
+    }
+
+    fn missing_endpoints(&self) -> bool {
+        self.auth_uri.is_empty() || self.token_uri.is_empty() || self.user_uri.is_empty()
+    }
+
+    /// Take the discovered endpoints the config left out.
+    fn apply_discovery(&mut self, discovered: OidcConfiguration) {
+        if self.auth_uri.is_empty() {
+            self.auth_uri = discovered.authorization_endpoint;
+        }
+        if self.token_uri.is_empty() {
+            self.token_uri = discovered.token_endpoint;
+        }
+        if self.user_uri.is_empty() {
+            self.user_uri = discovered.userinfo_endpoint.unwrap_or_default();
+        }
+    }
+}
+
+async fn fetch_oidc_configuration(
+    client: &reqwest::Client,
+    issuer: &str,
+) -> Result<OidcConfiguration, reqwest::Error> {
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        issuer.trim_end_matches('/')
+    );
+    client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+/// Why `.secrets.toml` could not be loaded.
+#[derive(Debug)]
+pub enum SecretsError {
+    Read(std::io::Error),
+    Parse(toml::de::Error),
+    /// Every problem found in the auth providers, one per entry.
+    Invalid(Vec<String>),
+}
+
+impl std::fmt::Display for SecretsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretsError::Read(err) => write!(
+                f,
+                "Failed to read secrets: {err} (copy .secrets.toml.example to start from)"
+            ),
+            SecretsError::Parse(err) => write!(f, "Invalid secrets: {err}"),
+            SecretsError::Invalid(problems) => {
+                write!(f, "Invalid secrets: {}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for SecretsError {}
+
 #[derive(Deserialize)]
 pub struct Secrets {
     #[serde(default)]
@@ -32,16 +155,61 @@ pub struct Secrets {
 }
 
 impl Secrets {
-    pub fn read(filename: &str) -> Result<Secrets, InternalError> {
-        let contents = read_to_string(filename)
-            .map_err(|err| InternalError::new(format!("Failed to read from '{filename}': {err}")))
-            .unwrap_or_default();
-        let secrets: Secrets = toml::from_str(&contents).map_err(|err| {
-            InternalError::new(format!("Failed to parse toml from {filename}: {err}"))
-        })?;
+    /// Read and check the secrets in `path`. The file must exist, but
+    /// every section in it is optional.
+    pub fn read(path: &Path) -> Result<Secrets, SecretsError> {
+        let contents = read_to_string(path).map_err(SecretsError::Read)?;
+        Self::parse(&contents)
+    }
+
+    fn parse(contents: &str) -> Result<Secrets, SecretsError> {
+        let secrets: Secrets = toml::from_str(contents).map_err(SecretsError::Parse)?;
+        let mut names: Vec<_> = secrets.auth_provider.keys().collect();
+        names.sort();
+        let problems: Vec<String> = names
+            .into_iter()
+            .flat_map(|name| secrets.auth_provider[name].problems(name))
+            .collect();
+        if !problems.is_empty() {
+            return Err(SecretsError::Invalid(problems));
+        }
         Ok(secrets)
     }
 
+    /// Fill in the endpoints of providers configured with an `issuer` from
+    /// their OpenID Connect discovery documents. Endpoints given in the
+    /// file win. A provider still missing one afterwards, e.g. because its
+    /// issuer couldn't be reached, is dropped so the login page doesn't
+    /// offer something that can't work.
+    pub async fn discover_oidc_endpoints(&mut self) {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Building reqwest client should not fail");
+        for (name, provider) in self.auth_provider.iter_mut() {
+            let Some(issuer) = provider.issuer.clone() else {
+                continue;
+            };
+            if !provider.missing_endpoints() {
+                continue;
+            }
+            match fetch_oidc_configuration(&client, &issuer).await {
+                Ok(discovered) => {
+                    info!("Discovered OpenID Connect endpoints of {name} from {issuer}");
+                    provider.apply_discovery(discovered);
+                }
+                Err(err) => error!("OpenID Connect discovery for {name} failed: {err}"),
+            }
+        }
+        self.auth_provider.retain(|name, provider| {
+            let usable = !provider.missing_endpoints();
+            if !usable {
+                error!("Auth provider {name} lacks endpoints; not offering it");
+            }
+            usable
+        });
+    }
+
     pub fn get_auth_provider_names(&self) -> Vec<String> {
         let mut names: Vec<_> = self.auth_provider.keys().cloned().collect();
         names.sort();
@@ -68,3 +236,71 @@ impl Secrets {
         Ok(auth_provider.clone())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn misconfigured_providers_are_rejected() {
+        let secrets = Secrets::parse(
+            r#"
+            [auth_provider.github]
+            auth_uri = "https://github.com/login/oauth/authorize"
+            token_uri = "github.com/token"
+            redirect_uri = "https://salsa.example.org/auth/authorized"
+            id_field = "id"
+            display_name_field = "login"
+            scopes = []
+            client_id = "id"
+            client_secret = "secret"
+            "#,
+        );
+        let Err(SecretsError::Invalid(problems)) = secrets else {
+            panic!("Expected the provider to be rejected");
+        };
+        assert_eq!(
+            problems,
+            [
+                "auth_provider.github: token_uri \"github.com/token\" is not a URL",
+                "auth_provider.github: user_uri is missing",
+            ]
+        );
+        assert!(Secrets::parse("").is_ok());
+    }
+
+    #[test]
+    fn configured_endpoints_override_discovered_ones() {
+        let mut secrets: Secrets = toml::from_str(
+            r#"
+            [auth_provider.oidc]
+            issuer = "https://id.example.org"
+            token_uri = "https://id.example.org/custom-token"
+            redirect_uri = "https://salsa.example.org/auth/authorized"
+            id_field = "sub"
+            display_name_field = "name"
+            scopes = ["openid", "profile"]
+            client_id = "id"
+            client_secret = "secret"
+            "#,
+        )
+        .unwrap();
+        let provider = secrets.auth_provider.get_mut("oidc").unwrap();
+        assert!(provider.missing_endpoints());
+        provider.apply_discovery(
+            serde_json::from_str(
+                r#"{
+                    "issuer": "https://id.example.org",
+                    "authorization_endpoint": "https://id.example.org/authorize",
+                    "token_endpoint": "https://id.example.org/token",
+                    "userinfo_endpoint": "https://id.example.org/userinfo"
+                }"#,
+            )
+            .unwrap(),
+        );
+        assert!(!provider.missing_endpoints());
+        assert_eq!("https://id.example.org/authorize", provider.auth_uri);
+        assert_eq!("https://id.example.org/custom-token", provider.token_uri);
+        assert_eq!("https://id.example.org/userinfo", provider.user_uri);
+    }
+}
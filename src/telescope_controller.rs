@@ -3,12 +3,27 @@ use crate::models::telescope_types::TelescopeError;
 use std::io::{Read, Write};
 use std::net::{SocketAddr, TcpStream};
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tracing::{debug, warn};
 
-/// Timeout for connecting to and talking with the ROT2PROG rotor
-/// controller. It sits on the local network, so one second is generous;
-/// promote to config if a deployment ever needs a different value.
-const CONTROLLER_IO_TIMEOUT: Duration = Duration::from_secs(1);
+/// Default timeout for connecting to and talking with the ROT2PROG rotor
+/// controller. It sits on the local network, so one second is generous.
+pub const DEFAULT_CONTROLLER_IO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Tries for a command that is safe to repeat, so that one lost packet
+/// doesn't cost the tracker a cycle.
+const IDEMPOTENT_ATTEMPTS: u32 = 3;
+
+/// How a telescope reaches its rotor controller.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ControllerSettings {
+    pub address: String,
+    /// For connecting and for each read and write.
+    pub io_timeout: Duration,
+    /// Answer commands with a `SimulatedRotor` instead of connecting.
+    pub dry_run: bool,
+}
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum TelescopeCommand {
@@ -28,33 +43,229 @@ pub enum TelescopeResponse {
 }
 
 pub struct TelescopeController {
-    stream: TcpStream,
+    link: Link,
+    io_timeout: Duration,
+}
+
+enum Link {
+    Tcp(TcpStream),
+    Simulated(SimulatedRotor),
 }
 
 impl TelescopeController {
-    pub fn connect(address: &str) -> Result<TelescopeController, TelescopeError> {
-        let stream = create_connection(address)?;
-        Ok(TelescopeController { stream })
+    pub fn connect(
+        address: &str,
+        io_timeout: Duration,
+    ) -> Result<TelescopeController, TelescopeError> {
+        let stream = create_connection(address, io_timeout)?;
+        Ok(TelescopeController {
+            link: Link::Tcp(stream),
+            io_timeout,
+        })
     }
 
+    /// A controller whose commands are answered by `rotor` instead of
+    /// hardware. Answers slower than `io_timeout` time out as they would
+    /// on the network.
+    pub fn simulated(rotor: SimulatedRotor, io_timeout: Duration) -> TelescopeController {
+        TelescopeController {
+            link: Link::Simulated(rotor),
+            io_timeout,
+        }
+    }
+
+    /// Send `command` and parse the answer. Commands that are safe to
+    /// repeat are retried when the answer is lost or garbled; the others
+    /// are sent once, as repeating them could act twice.
     pub fn execute(
         &mut self,
         command: TelescopeCommand,
     ) -> Result<TelescopeResponse, TelescopeError> {
-        self.stream
-            .write_all(&command.to_bytes())
-            .map_err(|err| TelescopeError::TelescopeIOError(err.to_string()))?;
-        let mut result = vec![0; 128];
-        let response_length = self
-            .stream
-            .read(&mut result)
-            .map_err(|err| TelescopeError::TelescopeIOError(err.to_string()))?;
-        result.truncate(response_length);
-        command.parse_response(&result)
+        let frame = command.to_bytes();
+        let attempts = if command.is_idempotent() {
+            IDEMPOTENT_ATTEMPTS
+        } else {
+            1
+        };
+        let mut attempt = 1;
+        loop {
+            let result = self
+                .exchange(&frame)
+                .and_then(|response| command.parse_response(&response));
+            match result {
+                Err(err) if attempt < attempts => {
+                    warn!(
+                        "{command:?} failed (attempt {attempt} of {attempts}), retrying: {err:?}"
+                    );
+                    self.discard_pending();
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn exchange(&mut self, frame: &[u8]) -> Result<Vec<u8>, TelescopeError> {
+        match &mut self.link {
+            Link::Tcp(stream) => {
+                stream
+                    .write_all(frame)
+                    .map_err(|err| TelescopeError::TelescopeIOError(err.to_string()))?;
+                let mut result = vec![0; 128];
+                let response_length = stream
+                    .read(&mut result)
+                    .map_err(|err| TelescopeError::TelescopeIOError(err.to_string()))?;
+                result.truncate(response_length);
+                Ok(result)
+            }
+            Link::Simulated(rotor) => {
+                debug!("Dry run, not sent to the controller: {frame:02X?}");
+                rotor.exchange(frame, self.io_timeout)
+            }
+        }
+    }
+
+    /// Drop whatever a late answer left unread, so it isn't taken for the
+    /// answer to the next command.
+    fn discard_pending(&mut self) {
+        let Link::Tcp(stream) = &mut self.link else {
+            return;
+        };
+        if stream.set_nonblocking(true).is_err() {
+            return;
+        }
+        let mut buf = [0; 128];
+        while matches!(stream.read(&mut buf), Ok(n) if n > 0) {}
+        let _ = stream.set_nonblocking(false);
+    }
+}
+
+/// Stands in for the rotor controller of a telescope with `dry_run` set.
+/// It decodes the real command frames and answers with real response
+/// frames, so the protocol code is exercised, but nothing moves: a new
+/// direction is reached at once. Clones share the position, which
+/// survives the reconnects the tracker makes after Stop and Restart.
+#[derive(Clone)]
+pub struct SimulatedRotor {
+    position: Arc<Mutex<Direction>>,
+    link: Arc<Mutex<SimulatedLink>>,
+}
+
+/// Faults to put on the simulated line, for testing.
+#[derive(Default)]
+struct SimulatedLink {
+    answer_delay: Duration,
+    answers_to_drop: u32,
+}
+
+impl Default for SimulatedRotor {
+    fn default() -> Self {
+        SimulatedRotor {
+            position: Arc::new(Mutex::new(Direction {
+                azimuth: 0.0,
+                elevation: 0.0,
+            })),
+            link: Arc::default(),
+        }
+    }
+}
+
+impl SimulatedRotor {
+    /// Answer every command after `delay`.
+    pub fn set_answer_delay(&self, delay: Duration) {
+        self.link.lock().unwrap().answer_delay = delay;
+    }
+
+    /// Lose the answers to the next `count` commands.
+    pub fn drop_answers(&self, count: u32) {
+        self.link.lock().unwrap().answers_to_drop = count;
+    }
+
+    /// What the controller would do on the network: the answer to
+    /// `frame`, or a timeout if it is lost or later than `io_timeout`.
+    /// Lost answers still act on the rotor.
+    fn exchange(&self, frame: &[u8], io_timeout: Duration) -> Result<Vec<u8>, TelescopeError> {
+        let response = self.respond(frame);
+        let (delay, dropped) = {
+            let mut link = self.link.lock().unwrap();
+            let dropped = link.answers_to_drop > 0;
+            link.answers_to_drop = link.answers_to_drop.saturating_sub(1);
+            (link.answer_delay, dropped)
+        };
+        std::thread::sleep(delay.min(io_timeout));
+        if dropped || delay > io_timeout {
+            return Err(TelescopeError::TelescopeIOError(
+                "Simulated controller timed out".to_string(),
+            ));
+        }
+        Ok(response)
+    }
+
+    /// The controller's answer to `frame`. Frames it doesn't understand
+    /// get no answer, which the caller reports as an unexpected response.
+    fn respond(&self, frame: &[u8]) -> Vec<u8> {
+        if frame.len() != 13 || frame[0] != 0x57 || frame[12] != 0x20 {
+            return Vec::new();
+        }
+        let digits = |range: std::ops::RangeInclusive<usize>| -> Vec<u8> {
+            frame[range].iter().map(|b| b.wrapping_sub(0x30)).collect()
+        };
+        let mut position = self.position.lock().unwrap();
+        match frame[11] {
+            // Stop, when idle, and get direction answer with the position.
+            0x0F | 0x6F => direction_frame(*position),
+            0xEE => {
+                let mut ack = vec![0; 12];
+                ack[0] = 0x57;
+                ack[11] = 0x20;
+                ack
+            }
+            0x5F => {
+                *position = Direction {
+                    azimuth: rot2prog_bytes_to_angle(&digits(1..=5)),
+                    elevation: rot2prog_bytes_to_angle(&digits(6..=10)),
+                };
+                direction_frame(*position)
+            }
+            0xF9 => {
+                *position = Direction {
+                    azimuth: rot2prog_legacy_bytes_to_angle(&digits(1..=4), frame[5]),
+                    elevation: rot2prog_legacy_bytes_to_angle(&digits(6..=9), frame[10]),
+                };
+                let mut response = vec![0x57];
+                for angle in [position.azimuth, position.elevation] {
+                    let field = rot2prog_calibration_angle_to_bytes(angle);
+                    response.extend(field[..4].iter().map(|b| b - 0x30));
+                    response.push(field[4]);
+                }
+                response.push(0x20);
+                response
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A position response frame: the angles as raw digit bytes, the way the
+/// controller sends them.
+fn direction_frame(direction: Direction) -> Vec<u8> {
+    let mut frame = vec![0x58];
+    for angle in [direction.azimuth, direction.elevation] {
+        frame.extend(rot2prog_angle_to_bytes(angle).iter().map(|b| b - 0x30));
     }
+    frame.push(0x20);
+    frame
 }
 
 impl TelescopeCommand {
+    /// Whether sending the command twice does no more than sending it
+    /// once. Only reading the position qualifies: a repeated SetDirection
+    /// could land after a newer one, and the controller closes the
+    /// connection after Stop.
+    fn is_idempotent(&self) -> bool {
+        matches!(self, TelescopeCommand::GetDirection)
+    }
+
     fn to_bytes(self) -> Vec<u8> {
         match self {
             TelescopeCommand::Stop => [
@@ -164,8 +375,7 @@ fn parse_legacy_direction_response(
     }
 }
 
-fn create_connection(address: &str) -> Result<TcpStream, TelescopeError> {
-    let timeout = CONTROLLER_IO_TIMEOUT;
+fn create_connection(address: &str, timeout: Duration) -> Result<TcpStream, TelescopeError> {
     let address = SocketAddr::from_str(address).map_err(|err| {
         TelescopeError::TelescopeIOError(format!(
             "invalid controller address '{address}' in config: {err}"
@@ -359,4 +569,103 @@ mod test {
                 .is_err()
         );
     }
+
+    #[test]
+    fn simulated_rotor_answers_in_the_real_protocol() {
+        let mut controller = TelescopeController::simulated(
+            SimulatedRotor::default(),
+            DEFAULT_CONTROLLER_IO_TIMEOUT,
+        );
+        let target = Direction {
+            azimuth: 123.45_f64.to_radians(),
+            elevation: 42.1_f64.to_radians(),
+        };
+        controller
+            .execute(TelescopeCommand::SetDirection(target))
+            .unwrap();
+        let TelescopeResponse::CurrentDirection(reached) =
+            controller.execute(TelescopeCommand::GetDirection).unwrap()
+        else {
+            panic!("Expected direction response");
+        };
+        assert!((reached.azimuth - target.azimuth).abs() < 1e-6);
+        assert!((reached.elevation - target.elevation).abs() < 1e-6);
+        assert_eq!(
+            controller.execute(TelescopeCommand::Restart).unwrap(),
+            TelescopeResponse::Ack
+        );
+
+        let TelescopeResponse::CurrentDirection(calibrated) = controller
+            .execute(TelescopeCommand::Calibrate(Direction {
+                azimuth: 22.3_f64.to_radians(),
+                elevation: 0.5_f64.to_radians(),
+            }))
+            .unwrap()
+        else {
+            panic!("Expected direction response");
+        };
+        assert!((calibrated.azimuth.to_degrees() - 22.3).abs() < 0.01);
+        assert!((calibrated.elevation.to_degrees() - 0.5).abs() < 0.01);
+    }
+
+    fn position_of(response: TelescopeResponse) -> Direction {
+        let TelescopeResponse::CurrentDirection(direction) = response else {
+            panic!("Expected direction response");
+        };
+        direction
+    }
+
+    #[test]
+    fn reading_the_position_survives_lost_answers() {
+        let rotor = SimulatedRotor::default();
+        let mut controller =
+            TelescopeController::simulated(rotor.clone(), Duration::from_millis(20));
+
+        rotor.drop_answers(IDEMPOTENT_ATTEMPTS - 1);
+        assert!(controller.execute(TelescopeCommand::GetDirection).is_ok());
+
+        rotor.drop_answers(IDEMPOTENT_ATTEMPTS);
+        assert!(matches!(
+            controller.execute(TelescopeCommand::GetDirection),
+            Err(TelescopeError::TelescopeIOError(_))
+        ));
+    }
+
+    #[test]
+    fn moving_the_rotor_is_not_retried() {
+        let rotor = SimulatedRotor::default();
+        let mut controller =
+            TelescopeController::simulated(rotor.clone(), Duration::from_millis(20));
+        let target = Direction {
+            azimuth: 1.0,
+            elevation: 0.5,
+        };
+
+        rotor.drop_answers(1);
+        assert!(
+            controller
+                .execute(TelescopeCommand::SetDirection(target))
+                .is_err()
+        );
+        // One attempt used up the one lost answer; the command did reach
+        // the rotor.
+        let reached = position_of(controller.execute(TelescopeCommand::GetDirection).unwrap());
+        assert!((reached.azimuth - target.azimuth).abs() < 1e-3);
+    }
+
+    #[test]
+    fn a_hung_controller_times_out() {
+        let rotor = SimulatedRotor::default();
+        let mut controller =
+            TelescopeController::simulated(rotor.clone(), Duration::from_millis(20));
+
+        rotor.set_answer_delay(Duration::from_millis(10));
+        assert!(controller.execute(TelescopeCommand::GetDirection).is_ok());
+
+        rotor.set_answer_delay(Duration::from_secs(60));
+        let started = std::time::Instant::now();
+        assert!(controller.execute(TelescopeCommand::GetDirection).is_err());
+        // Every attempt gave up after the timeout rather than the delay.
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
 }
@@ -1,15 +1,43 @@
-use crate::coords::{Direction, Location};
-use crate::coords::{horizontal_from_equatorial, horizontal_from_galactic, horizontal_from_sun};
+use crate::coords::{Direction, J2000, Location, angular_separation, seconds_to_direction};
+use crate::coords::{
+    horizontal_from_equatorial, horizontal_from_galactic, horizontal_from_moon,
+    horizontal_from_sun, precess, refraction_correction,
+};
+use crate::metrics::METRICS;
 use crate::models::telescope_types::{
-    CalibrationResult, TelescopeError, TelescopeStatus, TelescopeTarget,
+    CalibrationResult, POSITION_UPDATE_BUFFER, PositionUpdate, StowPhase, TelescopeError,
+    TelescopeStatus, TelescopeTarget,
+};
+use crate::telescope_controller::{
+    ControllerSettings, SimulatedRotor, TelescopeCommand, TelescopeController, TelescopeResponse,
 };
-use crate::telescope_controller::{TelescopeCommand, TelescopeController, TelescopeResponse};
 use crate::tle_cache::TleCacheHandle;
 use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::broadcast;
 use tokio::time::{Instant, sleep_until};
-use tracing::{debug, error, info, warn};
+use tracing::{Instrument, debug, error, info, info_span, warn};
+
+/// How many controller errors `TelescopeTracker::error_history` keeps.
+const ERROR_HISTORY_LEN: usize = 50;
+/// Reconnect backoff after a failed connect: doubles from the initial
+/// value up to the cap, and resets once a connection succeeds.
+const INITIAL_RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Where the rotor is on its cable wrap, for telescopes with a limited
+/// azimuth range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CableWrap {
+    /// Following the target the short way round.
+    Tracking,
+    /// Turning the long way round, since the short way would leave the
+    /// azimuth range.
+    Unwinding,
+}
 
 pub struct TelescopeTrackerInfo {
     pub target: Option<TelescopeTarget>,
@@ -19,6 +47,17 @@ pub struct TelescopeTrackerInfo {
     pub most_recent_error: Option<TelescopeError>,
     pub az_offset_rad: f64,
     pub el_offset_rad: f64,
+    pub estimated_seconds_to_target: Option<f64>,
+    /// While the controller is unreachable, seconds until the next
+    /// connection attempt.
+    pub reconnect_in_secs: Option<f64>,
+    /// When the controller last reported the rotor position.
+    pub last_successful_update: Option<DateTime<Utc>>,
+    /// `None` without an azimuth range or a target.
+    pub cable_wrap: Option<CableWrap>,
+    /// Angle between the commanded and current direction, while there is
+    /// a commanded one.
+    pub pointing_error_degrees: Option<f64>,
 }
 
 pub struct TelescopeTracker {
@@ -27,11 +66,15 @@ pub struct TelescopeTracker {
 }
 
 impl TelescopeTracker {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        controller_address: String,
+        controller: ControllerSettings,
         location: Location,
         min_elevation_rad: f64,
         max_elevation_rad: f64,
+        keyhole_elevation_rad: Option<f64>,
+        azimuth_range_rad: Option<(f64, f64)>,
+        apply_refraction: bool,
         tle_cache: TleCacheHandle,
     ) -> TelescopeTracker {
         let state = Arc::new(Mutex::new(TelescopeTrackerState {
@@ -40,7 +83,12 @@ impl TelescopeTracker {
             el_offset_rad: 0.0,
             commanded_horizontal: None,
             current_direction: None,
+            current_direction_at: None,
+            previous_direction: None,
             most_recent_error: None,
+            error_history: VecDeque::with_capacity(ERROR_HISTORY_LEN),
+            next_connect_attempt: None,
+            position_updates: Some(broadcast::channel(POSITION_UPDATE_BUFFER).0),
             should_restart: false,
             pending_calibration: None,
             quit: false,
@@ -48,8 +96,15 @@ impl TelescopeTracker {
             location,
             min_elevation_rad,
             max_elevation_rad,
+            keyhole_elevation_rad,
+            azimuth_range_rad,
+            cable_wrap: None,
+            apply_refraction,
+            stow: None,
         }));
-        let task = tokio::spawn(tracker_task_function(state.clone(), controller_address));
+        // A child of the telescope's span when created inside it.
+        let span = info_span!("telescope_tracker", controller = %controller.address);
+        let task = tokio::spawn(tracker_task_function(state.clone(), controller).instrument(span));
         TelescopeTracker {
             state,
             task: Arc::new(tokio::sync::Mutex::new(Some(task))),
@@ -60,6 +115,7 @@ impl TelescopeTracker {
         {
             let mut state = self.state.lock().unwrap();
             state.quit = true;
+            state.position_updates = None;
         }
         self.task
             .lock()
@@ -70,6 +126,8 @@ impl TelescopeTracker {
             .expect("Joining task should work");
     }
 
+    /// Track `target` until `stop` is called or another target is set.
+    /// There is no timeout; an unattended telescope keeps following it.
     pub fn set_target(
         &mut self,
         target: TelescopeTarget,
@@ -83,9 +141,13 @@ impl TelescopeTracker {
         // Validate elevation at set time when we can compute the target's
         // current horizontal. Satellites without a TLE in cache are skipped —
         // the periodic update loop will surface the error once the TLE arrives.
-        if let Some(raw) =
-            calculate_target_horizontal(target, state.location, Utc::now(), &state.tle_cache)
-        {
+        if let Some(raw) = calculate_target_horizontal(
+            target,
+            state.location,
+            Utc::now(),
+            &state.tle_cache,
+            state.apply_refraction,
+        ) {
             let horizontal = apply_offset(raw, az_offset_rad, el_offset_rad);
             if horizontal.elevation < state.min_elevation_rad
                 || horizontal.elevation > state.max_elevation_rad
@@ -95,10 +157,14 @@ impl TelescopeTracker {
                     max_deg: state.max_elevation_rad.to_degrees(),
                 });
             }
+            if let Some(err) = keyhole_error(target, horizontal, state.keyhole_elevation_rad) {
+                return Err(err);
+            }
         }
         state.target = Some(target);
         state.az_offset_rad = az_offset_rad;
         state.el_offset_rad = el_offset_rad;
+        state.stow = None;
         Ok(target)
     }
 
@@ -108,6 +174,24 @@ impl TelescopeTracker {
             return Err(TelescopeError::TelescopeNotConnected);
         }
         state.target = None;
+        if state.stow == Some(StowPhase::Driving) {
+            state.stow = None;
+        }
+        Ok(())
+    }
+
+    /// Drive to `position` and stop the rotor once it is there. The status
+    /// is `Stowed` from then until another target is set.
+    pub fn stow(&mut self, position: Direction) -> Result<(), TelescopeError> {
+        self.set_target(
+            TelescopeTarget::Horizontal {
+                azimuth: position.azimuth,
+                elevation: position.elevation,
+            },
+            0.0,
+            0.0,
+        )?;
+        self.state.lock().unwrap().stow = Some(StowPhase::Driving);
         Ok(())
     }
 
@@ -150,14 +234,87 @@ impl TelescopeTracker {
         state.should_restart = true;
     }
 
+    /// Recent controller errors with the time they occurred, oldest first.
+    pub fn error_history(&self) -> Vec<(DateTime<Utc>, TelescopeError)> {
+        self.state
+            .lock()
+            .unwrap()
+            .error_history
+            .iter()
+            .cloned()
+            .collect()
+    }
+
     pub fn info(&self) -> Result<TelescopeTrackerInfo, TelescopeError> {
-        let state = self.state.lock().unwrap();
-        if state.quit {
+        self.state.lock().unwrap().info()
+    }
+
+    /// Receive a `PositionUpdate` after every position poll of the
+    /// controller. The stream ends when the tracker shuts down. `None` if
+    /// it already has.
+    pub fn subscribe_position(&self) -> Option<broadcast::Receiver<PositionUpdate>> {
+        self.state
+            .lock()
+            .unwrap()
+            .position_updates
+            .as_ref()
+            .map(broadcast::Sender::subscribe)
+    }
+}
+
+struct TelescopeTrackerState {
+    target: Option<TelescopeTarget>,
+    az_offset_rad: f64,
+    el_offset_rad: f64,
+    commanded_horizontal: Option<Direction>,
+    current_direction: Option<Direction>,
+    current_direction_at: Option<DateTime<Utc>>,
+    /// The sample before `current_direction`, for estimating the slew rate.
+    previous_direction: Option<(DateTime<Utc>, Direction)>,
+    most_recent_error: Option<TelescopeError>,
+    /// Oldest first. A repeat of `most_recent_error` is not added again, so
+    /// a controller that stays unreachable takes one entry, not one per
+    /// second.
+    error_history: VecDeque<(DateTime<Utc>, TelescopeError)>,
+    /// Set while backing off after a failed connect.
+    next_connect_attempt: Option<DateTime<Utc>>,
+    /// Dropped on shutdown, which ends every subscriber's stream.
+    position_updates: Option<broadcast::Sender<PositionUpdate>>,
+    should_restart: bool,
+    pending_calibration: Option<PendingCalibration>,
+    quit: bool,
+    tle_cache: TleCacheHandle,
+    location: Location,
+    min_elevation_rad: f64,
+    max_elevation_rad: f64,
+    keyhole_elevation_rad: Option<f64>,
+    /// Rotor azimuth limits; commanded azimuths are then unwrapped to lie
+    /// within them.
+    azimuth_range_rad: Option<(f64, f64)>,
+    cable_wrap: Option<CableWrap>,
+    apply_refraction: bool,
+    stow: Option<StowPhase>,
+}
+
+impl TelescopeTrackerState {
+    fn record_direction(&mut self, direction: Direction, when: DateTime<Utc>) {
+        if let (Some(current), Some(current_at)) =
+            (self.current_direction, self.current_direction_at)
+        {
+            self.previous_direction = Some((current_at, current));
+        }
+        self.current_direction = Some(direction);
+        self.current_direction_at = Some(when);
+    }
+
+    fn info(&self) -> Result<TelescopeTrackerInfo, TelescopeError> {
+        if self.quit {
             return Err(TelescopeError::TelescopeNotConnected);
         }
-        let current_horizontal = state.current_direction;
-        let commanded_horizontal = state.commanded_horizontal;
+        let current_horizontal = self.current_direction;
+        let commanded_horizontal = self.commanded_horizontal;
         let status = match commanded_horizontal {
+            _ if self.stow == Some(StowPhase::Stowed) => TelescopeStatus::Stowed,
             Some(commanded_horizontal) => {
                 let Some(current_horizontal) = current_horizontal else {
                     return Err(TelescopeError::TelescopeNotConnected);
@@ -172,40 +329,92 @@ impl TelescopeTracker {
             }
             None => TelescopeStatus::Idle,
         };
-        let (target, most_recent_error, az_offset_rad, el_offset_rad) = {
+        let estimated_seconds_to_target = match (
+            status,
+            self.previous_direction,
+            self.current_direction_at,
+            current_horizontal,
+            commanded_horizontal,
+        ) {
             (
-                state.target,
-                state.most_recent_error.clone(),
-                state.az_offset_rad,
-                state.el_offset_rad,
-            )
+                TelescopeStatus::Slewing,
+                Some((previous_at, previous)),
+                Some(current_at),
+                Some(current),
+                Some(commanded),
+            ) => {
+                let dt = (current_at - previous_at).num_milliseconds() as f64 / 1000.0;
+                if dt > 0.0 {
+                    seconds_to_direction(
+                        current,
+                        commanded,
+                        (current.azimuth - previous.azimuth) / dt,
+                        (current.elevation - previous.elevation) / dt,
+                    )
+                } else {
+                    None
+                }
+            }
+            _ => None,
         };
+        let reconnect_in_secs = self
+            .next_connect_attempt
+            .map(|at| ((at - Utc::now()).num_milliseconds() as f64 / 1000.0).max(0.0));
         Ok(TelescopeTrackerInfo {
-            target,
+            target: self.target,
             current_horizontal,
             commanded_horizontal,
             status,
-            most_recent_error,
-            az_offset_rad,
-            el_offset_rad,
+            most_recent_error: self.most_recent_error.clone(),
+            az_offset_rad: self.az_offset_rad,
+            el_offset_rad: self.el_offset_rad,
+            estimated_seconds_to_target,
+            reconnect_in_secs,
+            last_successful_update: self.current_direction_at,
+            cable_wrap: self.cable_wrap,
+            pointing_error_degrees: commanded_horizontal
+                .zip(current_horizontal)
+                .map(|(commanded, current)| angular_separation(commanded, current).to_degrees()),
         })
     }
-}
 
-struct TelescopeTrackerState {
-    target: Option<TelescopeTarget>,
-    az_offset_rad: f64,
-    el_offset_rad: f64,
-    commanded_horizontal: Option<Direction>,
-    current_direction: Option<Direction>,
-    most_recent_error: Option<TelescopeError>,
-    should_restart: bool,
-    pending_calibration: Option<PendingCalibration>,
-    quit: bool,
-    tle_cache: TleCacheHandle,
-    location: Location,
-    min_elevation_rad: f64,
-    max_elevation_rad: f64,
+    /// Once a stow has reached its position, drop the target so that the
+    /// task stops the rotor there.
+    fn finish_stow(&mut self) {
+        if self.stow != Some(StowPhase::Driving) {
+            return;
+        }
+        let arrived = self
+            .commanded_horizontal
+            .zip(self.current_direction)
+            .is_some_and(|(commanded, current)| directions_are_close(commanded, current, 2.0));
+        if arrived {
+            info!("Reached the stow position, stopping");
+            self.target = None;
+            self.stow = Some(StowPhase::Stowed);
+        }
+    }
+
+    fn publish_position(&self) {
+        if let (Some(sender), Ok(info)) = (&self.position_updates, self.info()) {
+            // Sending fails only when nobody is subscribed.
+            let _ = sender.send(PositionUpdate {
+                status: info.status,
+                current_horizontal: info.current_horizontal,
+                commanded_horizontal: info.commanded_horizontal,
+            });
+        }
+    }
+
+    fn record_error(&mut self, err: TelescopeError) {
+        if self.most_recent_error.as_ref() != Some(&err) {
+            if self.error_history.len() == ERROR_HISTORY_LEN {
+                self.error_history.pop_front();
+            }
+            self.error_history.push_back((Utc::now(), err.clone()));
+        }
+        self.most_recent_error = Some(err);
+    }
 }
 
 struct PendingCalibration {
@@ -214,16 +423,21 @@ struct PendingCalibration {
     responder: tokio::sync::oneshot::Sender<Result<CalibrationResult, TelescopeError>>,
 }
 
+/// With `dry_run` set, commands go to a `SimulatedRotor` instead of the
+/// controller.
 async fn tracker_task_function(
     state: Arc<Mutex<TelescopeTrackerState>>,
-    controller_address: String,
+    settings: ControllerSettings,
 ) {
+    let controller_address = settings.address;
+    let simulated_rotor = settings.dry_run.then(SimulatedRotor::default);
     let mut controller: Option<TelescopeController> = None;
     let mut prev_target: Option<TelescopeTarget> = None;
     // The MD01 controller closes the TCP connection after responding to Stop.
     // So we send Stop once on first startup on its own connection, then reconnect
     // for all subsequent communication. On later reconnects we skip Stop.
     let mut initial_stop_done = false;
+    let mut reconnect_backoff = INITIAL_RECONNECT_BACKOFF;
 
     while !state.lock().unwrap().quit {
         // 1 Hz update freq
@@ -237,14 +451,39 @@ async fn tracker_task_function(
 
         // Establish connection if not already connected
         if controller.is_none() {
-            controller = match TelescopeController::connect(&controller_address) {
-                Ok(c) => Some(c),
+            let next_attempt = state.lock().unwrap().next_connect_attempt;
+            if next_attempt.is_some_and(|at| Utc::now() < at) {
+                continue;
+            }
+            let connected = match &simulated_rotor {
+                Some(rotor) => Ok(TelescopeController::simulated(
+                    rotor.clone(),
+                    settings.io_timeout,
+                )),
+                None => TelescopeController::connect(&controller_address, settings.io_timeout),
+            };
+            controller = match connected {
+                Ok(c) => {
+                    reconnect_backoff = INITIAL_RECONNECT_BACKOFF;
+                    state.lock().unwrap().next_connect_attempt = None;
+                    Some(c)
+                }
                 Err(err) => {
                     error!(
-                        "Failed to connect to controller at {}: {}",
-                        &controller_address, err
+                        "Failed to connect to controller at {}: {}; retrying in {}s",
+                        &controller_address,
+                        err,
+                        reconnect_backoff.as_secs()
                     );
-                    state.lock().unwrap().most_recent_error = Some(err);
+                    METRICS.controller_connection_failed();
+                    let mut state_guard = state.lock().unwrap();
+                    state_guard.record_error(err);
+                    state_guard.next_connect_attempt = Some(
+                        Utc::now()
+                            + chrono::Duration::from_std(reconnect_backoff)
+                                .expect("Backoff should fit in a chrono duration"),
+                    );
+                    reconnect_backoff = (reconnect_backoff * 2).min(MAX_RECONNECT_BACKOFF);
                     continue;
                 }
             };
@@ -261,7 +500,7 @@ async fn tracker_task_function(
                     }
                     Err(err) => {
                         warn!("Initial stop command failed: {}", err);
-                        state.lock().unwrap().most_recent_error = Some(err);
+                        state.lock().unwrap().record_error(err);
                     }
                 }
                 initial_stop_done = true;
@@ -277,7 +516,7 @@ async fn tracker_task_function(
         if need_stop {
             debug!("Target set to None, sending Stop to controller");
             if let Err(err) = ctrl.execute(TelescopeCommand::Stop) {
-                state.lock().unwrap().most_recent_error = Some(err);
+                state.lock().unwrap().record_error(err);
             } else {
                 state.lock().unwrap().commanded_horizontal = None;
             }
@@ -290,7 +529,7 @@ async fn tracker_task_function(
         if state.lock().unwrap().should_restart {
             info!("Restarting controller");
             if let Err(err) = ctrl.execute(TelescopeCommand::Restart) {
-                state.lock().unwrap().most_recent_error = Some(err);
+                state.lock().unwrap().record_error(err);
             }
             controller = None;
             sleep_until(Instant::now() + Duration::from_secs(10)).await;
@@ -309,7 +548,14 @@ async fn tracker_task_function(
                     calibration.adjusted.azimuth.to_degrees(),
                     calibration.adjusted.elevation.to_degrees(),
                 );
-                state.lock().unwrap().current_direction = Some(calibration.adjusted);
+                let mut state_guard = state.lock().unwrap();
+                state_guard.current_direction = Some(calibration.adjusted);
+                // The position was just read, so it is as fresh as after
+                // an update.
+                state_guard.current_direction_at = Some(Utc::now());
+                // The reported position jumped without the rotor moving;
+                // don't mistake it for slewing.
+                state_guard.previous_direction = None;
             }
             // The requester may have timed out and dropped the receiver;
             // nothing to do about it, the calibration already happened.
@@ -323,18 +569,21 @@ async fn tracker_task_function(
         let res = update_direction(&state, Utc::now(), ctrl);
         match res {
             Ok(()) => {
-                state.lock().unwrap().most_recent_error = None;
+                let mut state_guard = state.lock().unwrap();
+                state_guard.most_recent_error = None;
+                state_guard.finish_stow();
             }
             Err(
                 err @ (TelescopeError::TelescopeIOError(_) | TelescopeError::TelescopeNotConnected),
             ) => {
-                state.lock().unwrap().most_recent_error = Some(err);
+                state.lock().unwrap().record_error(err);
                 controller = None;
             }
             Err(err) => {
-                state.lock().unwrap().most_recent_error = Some(err);
+                state.lock().unwrap().record_error(err);
             }
         }
+        state.lock().unwrap().publish_position();
     }
 }
 
@@ -377,7 +626,10 @@ fn update_direction(
         location,
         min_elevation_rad,
         max_elevation_rad,
+        keyhole_elevation_rad,
+        azimuth_range_rad,
         tle_cache,
+        apply_refraction,
     ) = {
         let state_guard = state.lock().unwrap();
         (
@@ -387,7 +639,10 @@ fn update_direction(
             state_guard.location,
             state_guard.min_elevation_rad,
             state_guard.max_elevation_rad,
+            state_guard.keyhole_elevation_rad,
+            state_guard.azimuth_range_rad,
             state_guard.tle_cache.clone(),
+            state_guard.apply_refraction,
         )
     };
 
@@ -399,14 +654,20 @@ fn update_direction(
     }?;
 
     let Some(target) = target else {
-        state.lock().unwrap().current_direction = Some(current_horizontal);
+        let mut state_guard = state.lock().unwrap();
+        state_guard.record_direction(current_horizontal, when);
+        state_guard.cable_wrap = None;
         return Ok(());
     };
 
-    let Some(raw_horizontal) = calculate_target_horizontal(target, location, when, &tle_cache)
+    let Some(raw_horizontal) =
+        calculate_target_horizontal(target, location, when, &tle_cache, apply_refraction)
     else {
         // Satellite not yet in TLE cache — skip this update cycle
-        state.lock().unwrap().current_direction = Some(current_horizontal);
+        state
+            .lock()
+            .unwrap()
+            .record_direction(current_horizontal, when);
         return Ok(());
     };
     let target_horizontal = apply_offset(raw_horizontal, az_offset_rad, el_offset_rad);
@@ -419,35 +680,74 @@ fn update_direction(
             max_deg: max_elevation_rad.to_degrees(),
         };
         let mut state_guard = state.lock().unwrap();
-        state_guard.current_direction = Some(current_horizontal);
-        state_guard.most_recent_error = Some(err.clone());
+        state_guard.record_direction(current_horizontal, when);
+        state_guard.record_error(err.clone());
+        state_guard.commanded_horizontal = None;
+        return Err(err);
+    }
+
+    // Hold still while the target passes through the keyhole. The target is
+    // kept, so tracking resumes once it has descended below the limit.
+    if let Some(err) = keyhole_error(target, target_horizontal, keyhole_elevation_rad) {
+        let mut state_guard = state.lock().unwrap();
+        state_guard.record_direction(current_horizontal, when);
+        state_guard.record_error(err.clone());
         state_guard.commanded_horizontal = None;
         return Err(err);
     }
 
+    let (commanded_horizontal, cable_wrap) = match azimuth_range_rad.and_then(|range| {
+        wrap_azimuth(target_horizontal.azimuth, current_horizontal.azimuth, range)
+    }) {
+        Some((azimuth, wrap)) => (
+            Direction {
+                azimuth,
+                elevation: target_horizontal.elevation,
+            },
+            Some(wrap),
+        ),
+        None => (target_horizontal, None),
+    };
+
     // Check if more than 1 tolerance off, if so we need to send track command
-    if !directions_are_close(target_horizontal, current_horizontal, 1.0) {
-        controller.execute(TelescopeCommand::SetDirection(target_horizontal))?;
+    if !directions_are_close(commanded_horizontal, current_horizontal, 1.0) {
+        controller.execute(TelescopeCommand::SetDirection(commanded_horizontal))?;
     }
 
     let mut state_guard = state.lock().unwrap();
-    state_guard.current_direction = Some(current_horizontal);
-    state_guard.commanded_horizontal = Some(target_horizontal);
+    if cable_wrap == Some(CableWrap::Unwinding) && state_guard.cable_wrap != cable_wrap {
+        info!(
+            "Unwinding cable wrap: turning from az={:.1}° to az={:.1}°",
+            current_horizontal.azimuth.to_degrees(),
+            commanded_horizontal.azimuth.to_degrees()
+        );
+    }
+    state_guard.record_direction(current_horizontal, when);
+    state_guard.commanded_horizontal = Some(commanded_horizontal);
+    state_guard.cable_wrap = cable_wrap;
 
     Ok(())
 }
 
+/// Direction to command for `target`. With `apply_refraction` the elevation
+/// of sky targets is lifted to where the atmosphere makes them appear;
+/// horizontal targets are already given as the direction to point at.
 fn calculate_target_horizontal(
     target: TelescopeTarget,
     location: Location,
     when: DateTime<Utc>,
     tle_cache: &TleCacheHandle,
+    apply_refraction: bool,
 ) -> Option<Direction> {
-    match target {
+    let direction = match target {
         TelescopeTarget::Equatorial {
             right_ascension: ra,
             declination: dec,
-        } => Some(horizontal_from_equatorial(location, when, ra, dec)),
+            epoch,
+        } => {
+            let (ra, dec) = precess(ra, dec, epoch, J2000);
+            Some(horizontal_from_equatorial(location, when, ra, dec))
+        }
         TelescopeTarget::Galactic {
             longitude: l,
             latitude: b,
@@ -460,10 +760,62 @@ fn calculate_target_horizontal(
             elevation: el,
         }),
         TelescopeTarget::Sun => Some(horizontal_from_sun(location, when)),
+        TelescopeTarget::Moon => Some(horizontal_from_moon(location, when)),
         TelescopeTarget::Satellite { norad_id } => {
             tle_cache.satellite_direction(norad_id, location, when)
         }
+    }?;
+    if !apply_refraction || matches!(target, TelescopeTarget::Horizontal { .. }) {
+        return Some(direction);
     }
+    Some(Direction {
+        azimuth: direction.azimuth,
+        elevation: direction.elevation + refraction_correction(direction.elevation),
+    })
+}
+
+/// `TargetInKeyhole` if `target` is a moving target currently above the
+/// keyhole elevation. Fixed horizontal positions (stow, for one) need no
+/// azimuth motion and are fine at any elevation.
+fn keyhole_error(
+    target: TelescopeTarget,
+    target_horizontal: Direction,
+    keyhole_elevation_rad: Option<f64>,
+) -> Option<TelescopeError> {
+    let keyhole_rad = keyhole_elevation_rad?;
+    (!matches!(target, TelescopeTarget::Horizontal { .. })
+        && target_horizontal.elevation > keyhole_rad)
+        .then(|| TelescopeError::TargetInKeyhole {
+            max_deg: keyhole_rad.to_degrees(),
+        })
+}
+
+/// Rotor azimuth to command for `target_azimuth` (in [0, 2π)) on a cable
+/// wrap spanning `range`: of the equivalent angles within the range, the
+/// one nearest the rotor's `current_azimuth`. That is the short way round
+/// unless it would cross a limit, in which case the rotor unwinds. `None`
+/// if no equivalent angle is within the range.
+fn wrap_azimuth(
+    target_azimuth: f64,
+    current_azimuth: f64,
+    range: (f64, f64),
+) -> Option<(f64, CableWrap)> {
+    let (min, max) = range;
+    let full_circle = 2.0 * PI;
+    let first = target_azimuth + ((min - target_azimuth) / full_circle).ceil() * full_circle;
+    let azimuth = std::iter::successors(Some(first), |az| Some(az + full_circle))
+        .take_while(|az| *az <= max)
+        .min_by(|a, b| {
+            (a - current_azimuth)
+                .abs()
+                .total_cmp(&(b - current_azimuth).abs())
+        })?;
+    let wrap = if (azimuth - current_azimuth).abs() > PI {
+        CableWrap::Unwinding
+    } else {
+        CableWrap::Tracking
+    };
+    Some((azimuth, wrap))
 }
 
 fn apply_offset(dir: Direction, az_offset_rad: f64, el_offset_rad: f64) -> Direction {
@@ -480,33 +832,47 @@ fn directions_are_close(a: Direction, b: Direction, tol: f64) -> bool {
     // but to report tracking status we allow more, so that we do not flip
     // status between tracking/slewing (e.g. due to control unit rounding errors)
     // Therefore we have the "tol" multiplier here, which scales the allowed error.
+    // The error is the angle on the sky: near zenith a large azimuth
+    // difference is a small miss.
     let epsilon = tol * 0.1_f64.to_radians();
-    (a.azimuth - b.azimuth).abs() < epsilon && (a.elevation - b.elevation).abs() < epsilon
+    angular_separation(a, b) < epsilon
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    // Past behaviour: route handlers racing teardown would call into the
-    // tracker after shutdown set state.quit=true and the four entry points
-    // would panic the tokio worker (seen in /var/log/syslog.3.gz on 2026-05-08).
-    // Now each method must return TelescopeNotConnected instead.
-    #[tokio::test]
-    async fn methods_after_shutdown_return_not_connected_instead_of_panicking() {
-        let mut tracker = TelescopeTracker::new(
-            // Reserved port 0 / unroutable address — the background task's
-            // connect attempts will fail, but that's fine for this test:
-            // we only exercise the trait-method paths.
-            "127.0.0.1:1".to_string(),
+    use crate::telescope_controller::DEFAULT_CONTROLLER_IO_TIMEOUT;
+
+    /// A tracker for a controller at a reserved port on localhost. The
+    /// background task's connect attempts fail unless `dry_run` is set, which
+    /// is fine for tests that only exercise the trait-method paths.
+    fn test_tracker(dry_run: bool) -> TelescopeTracker {
+        TelescopeTracker::new(
+            ControllerSettings {
+                address: "127.0.0.1:1".to_string(),
+                io_timeout: DEFAULT_CONTROLLER_IO_TIMEOUT,
+                dry_run,
+            },
             Location {
                 longitude: 0.0,
                 latitude: 0.0,
             },
             0.0,
             std::f64::consts::PI,
+            None,
+            None,
+            true,
             TleCacheHandle::new(),
-        );
+        )
+    }
+
+    // Past behaviour: route handlers racing teardown would call into the
+    // tracker after shutdown set state.quit=true and the four entry points
+    // would panic the tokio worker (seen in /var/log/syslog.3.gz on 2026-05-08).
+    // Now each method must return TelescopeNotConnected instead.
+    #[tokio::test]
+    async fn methods_after_shutdown_return_not_connected_instead_of_panicking() {
+        let mut tracker = test_tracker(false);
         tracker.shutdown().await;
 
         assert!(matches!(
@@ -524,4 +890,130 @@ mod tests {
         // restart() returns nothing; just ensure it doesn't panic.
         tracker.restart();
     }
+
+    #[tokio::test]
+    async fn error_history_skips_repeats_and_is_bounded() {
+        let tracker = test_tracker(false);
+        tracker.shutdown().await;
+        {
+            let mut state = tracker.state.lock().unwrap();
+            state.error_history.clear();
+            state.most_recent_error = None;
+            state.record_error(TelescopeError::TelescopeNotConnected);
+            state.record_error(TelescopeError::TelescopeNotConnected);
+        }
+        assert_eq!(tracker.error_history().len(), 1);
+
+        {
+            let mut state = tracker.state.lock().unwrap();
+            for i in 0..ERROR_HISTORY_LEN {
+                state.record_error(TelescopeError::TelescopeIOError(i.to_string()));
+            }
+        }
+        let history = tracker.error_history();
+        assert_eq!(history.len(), ERROR_HISTORY_LEN);
+        assert_eq!(
+            history.last().unwrap().1,
+            TelescopeError::TelescopeIOError((ERROR_HISTORY_LEN - 1).to_string())
+        );
+        assert_eq!(
+            history[0].1,
+            TelescopeError::TelescopeIOError("0".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn stow_reports_stowed_once_the_position_is_reached() {
+        let mut tracker = test_tracker(false);
+        let stow = Direction {
+            azimuth: 1.0,
+            elevation: 0.5,
+        };
+        tracker.stow(stow).unwrap();
+        let reach = |current: Direction| {
+            let mut state = tracker.state.lock().unwrap();
+            state.commanded_horizontal = Some(stow);
+            state.current_direction = Some(current);
+            state.finish_stow();
+        };
+        reach(Direction {
+            azimuth: 0.5,
+            elevation: 0.5,
+        });
+        assert_eq!(tracker.info().unwrap().status, TelescopeStatus::Slewing);
+        reach(stow);
+        let info = tracker.info().unwrap();
+        assert_eq!(info.status, TelescopeStatus::Stowed);
+        assert_eq!(info.target, None);
+
+        tracker
+            .set_target(
+                TelescopeTarget::Horizontal {
+                    azimuth: 2.0,
+                    elevation: 1.0,
+                },
+                0.0,
+                0.0,
+            )
+            .unwrap();
+        assert_ne!(tracker.info().unwrap().status, TelescopeStatus::Stowed);
+        tracker.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn calibration_counts_as_a_position_update() {
+        let tracker = test_tracker(true);
+        let before = Utc::now();
+        let calibration = tracker
+            .request_calibration(0.01, 0.01)
+            .unwrap()
+            .await
+            .unwrap();
+        assert!(calibration.is_ok(), "{calibration:?}");
+        let info = tracker.info().unwrap();
+        assert!(info.last_successful_update.is_some_and(|at| at >= before));
+        tracker.shutdown().await;
+    }
+
+    #[test]
+    fn closeness_is_judged_by_the_angle_on_the_sky() {
+        let at = |az: f64, el: f64| Direction {
+            azimuth: az.to_radians(),
+            elevation: el.to_radians(),
+        };
+        // Low down, azimuth and elevation errors count about equally.
+        assert!(directions_are_close(at(100.0, 5.0), at(100.05, 5.05), 1.0));
+        assert!(!directions_are_close(at(100.0, 5.0), at(100.15, 5.0), 1.0));
+        assert!(!directions_are_close(at(100.0, 5.0), at(100.0, 5.15), 1.0));
+        // Near zenith, 3° of azimuth is only about 0.05° on the sky, so
+        // there is nothing to re-command and the dish is still tracking.
+        assert!(directions_are_close(at(100.0, 89.0), at(103.0, 89.0), 1.0));
+        assert!(!directions_are_close(
+            at(100.0, 89.0),
+            at(100.0, 89.15),
+            1.0
+        ));
+    }
+
+    #[test]
+    fn source_crossing_the_wrap_limit_unwinds_the_cable() {
+        let deg = f64::to_radians;
+        let range = (deg(-90.0), deg(450.0));
+        let wrapped = |target: f64, current: f64| {
+            let (azimuth, wrap) = wrap_azimuth(deg(target), deg(current), range).unwrap();
+            (azimuth.to_degrees().round(), wrap)
+        };
+
+        // Crossing north from 355° continues to 370° instead of turning back.
+        assert_eq!(wrapped(10.0, 355.0), (370.0, CableWrap::Tracking));
+        assert_eq!(wrapped(80.0, 435.0), (440.0, CableWrap::Tracking));
+        // 455° is past the limit: go back the long way round to 95°...
+        assert_eq!(wrapped(95.0, 440.0), (95.0, CableWrap::Unwinding));
+        assert_eq!(wrapped(96.0, 300.0), (96.0, CableWrap::Unwinding));
+        // ...and follow the source from there once the rotor has caught up.
+        assert_eq!(wrapped(97.0, 96.0), (97.0, CableWrap::Tracking));
+        // Near the lower limit, the other side of the wrap is used.
+        assert_eq!(wrapped(265.0, -85.0), (265.0, CableWrap::Unwinding));
+        assert_eq!(wrapped(275.0, -85.0), (-85.0, CableWrap::Tracking));
+    }
 }
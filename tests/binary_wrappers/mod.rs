@@ -1,15 +1,24 @@
 use reqwest::StatusCode;
-use reqwest::blocking::get;
+use reqwest::blocking::{Client, ClientBuilder, get};
+use reqwest::cookie::Jar;
+use reqwest::header::{HeaderMap, HeaderValue};
 use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
 use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 use tempfile::TempDir;
 
+/// The CSRF token test clients present, in both the cookie and the
+/// header, as a browser running the site's script would.
+pub const CSRF_TOKEN: &str = "test-csrf-token";
+
 pub struct SalsaTestServer {
     process: Child,
     port: u16,
     database_dir: TempDir,
+    exited: bool,
 }
 
 pub struct LocalSalsaUser {
@@ -19,8 +28,16 @@ pub struct LocalSalsaUser {
 
 impl SalsaTestServer {
     pub fn spawn() -> Self {
+        Self::spawn_with_config_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/test_config"))
+    }
+
+    /// Like `spawn`, reading config.toml and .secrets.toml from
+    /// `config_dir` instead of tests/test_config.
+    pub fn spawn_with_config_dir(config_dir: &str) -> Self {
         let database_dir = TempDir::new().expect("Need to be able to create tempdir in test");
         let backend_executable = env!("CARGO_BIN_EXE_salsa");
+        // Run from the database directory, so that nothing the backend
+        // needs can come from the working directory by accident.
         let mut process = Command::new(backend_executable)
             .args([
                 "-p",
@@ -31,25 +48,27 @@ impl SalsaTestServer {
                     .to_str()
                     .expect("TempDir path should convert to str"),
                 "--config-dir",
-                "tests/test_config",
+                config_dir,
+                "--assets-dir",
+                concat!(env!("CARGO_MANIFEST_DIR"), "/assets"),
             ]) // Let the OS decide the port
+            .current_dir(database_dir.path())
             .stdout(Stdio::piped())
             .spawn()
             .expect("Could not start backend");
         let mut stdout_reader =
             BufReader::new(process.stdout.take().expect("Should be able to get stdout"));
+        // Console logging shares stdout, so skip any log lines printed
+        // before the port.
         let mut buf = String::new();
-        let res = stdout_reader.read_line(&mut buf);
-        match res {
-            Ok(_) => {}
-            Err(err) => {
-                panic!("{err}");
-            }
+        while !buf.starts_with("port:") {
+            buf.clear();
+            let read = stdout_reader
+                .read_line(&mut buf)
+                .unwrap_or_else(|err| panic!("{err}"));
+            assert!(read > 0, "Backend should print its port before exiting");
         }
-        let port = buf
-            .split_once(":")
-            .expect("Backend should print something with \":\"")
-            .1
+        let port = buf["port:".len()..]
             .trim()
             .parse::<u16>()
             .expect("Backend should print a number");
@@ -72,6 +91,7 @@ impl SalsaTestServer {
             process,
             port,
             database_dir,
+            exited: false,
         }
     }
 
@@ -79,6 +99,46 @@ impl SalsaTestServer {
         format!("http://127.0.0.1:{}", self.port)
     }
 
+    /// A client that behaves like a browser on the site: it keeps cookies
+    /// and echoes the CSRF token on every request.
+    pub fn client_builder(&self) -> ClientBuilder {
+        let jar = Jar::default();
+        jar.add_cookie_str(
+            &format!("csrf={CSRF_TOKEN}"),
+            &self.addr().parse().expect("Server address should be a URL"),
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert("X-CSRF-Token", HeaderValue::from_static(CSRF_TOKEN));
+        Client::builder()
+            .cookie_provider(Arc::new(jar))
+            .default_headers(headers)
+    }
+
+    pub fn client(&self) -> Client {
+        self.client_builder()
+            .build()
+            .expect("Should be possible to create reqwest client")
+    }
+
+    /// The backend's database, for changes no test user is allowed to make.
+    pub fn database_path(&self) -> PathBuf {
+        self.database_dir.path().join("database.sqlite3")
+    }
+
+    /// Send SIGTERM, as a service manager stopping the backend would, and
+    /// wait for it to shut down.
+    pub fn terminate(&mut self) {
+        let status = Command::new("kill")
+            .args(["-TERM", &self.process.id().to_string()])
+            .status()
+            .expect("Should be able to run kill");
+        assert!(status.success(), "kill should succeed");
+        self.process
+            .wait()
+            .expect("Backend should stop on terminate signal");
+        self.exited = true;
+    }
+
     pub fn add_local_user(&self, username: &str, password: &str) -> LocalSalsaUser {
         let manage_user_executable = env!("CARGO_BIN_EXE_manage_users");
         let output = Command::new(manage_user_executable)
@@ -121,6 +181,9 @@ impl SalsaTestServer {
 
 impl Drop for SalsaTestServer {
     fn drop(&mut self) {
+        if self.exited {
+            return;
+        }
         self.process.kill().expect("Should be able to kill backend");
         self.process
             .wait()
@@ -146,12 +209,18 @@ impl Drop for SimSalsaBin {
 
 impl SimSalsaBin {
     pub fn spawn() -> Self {
+        Self::spawn_with_args(&[])
+    }
+
+    /// Spawn with extra command line options, e.g. `--start-azimuth`.
+    pub fn spawn_with_args(extra_args: &[&str]) -> Self {
         let backend_executable = env!("CARGO_BIN_EXE_simsalsabin");
         let mut process = Command::new(backend_executable)
             .args([
                 "-p", // Let the OS decide the port
                 "0",
             ])
+            .args(extra_args)
             .stdout(Stdio::piped())
             .spawn()
             .expect("Could not start backend");
@@ -1,9 +1,40 @@
 mod binary_wrappers;
 pub use binary_wrappers::*; // Silences any dead code warnings
 
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
 #[test]
 fn can_start_and_connect_to_simulated_telescope() {
     let simulated_telescope = SimSalsaBin::spawn();
     println!("Port: {}", simulated_telescope.port);
     let _test_server = SalsaTestServer::spawn();
 }
+
+#[test]
+fn simulated_telescope_starts_at_configured_position() {
+    let simulated_telescope =
+        SimSalsaBin::spawn_with_args(&["--start-azimuth", "123.45", "--start-elevation", "-5"]);
+    let mut stream = TcpStream::connect(("127.0.0.1", simulated_telescope.port))
+        .expect("Should be able to connect to simulated telescope");
+    // ROT2PROG get direction command.
+    stream
+        .write_all(&[
+            0x57, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x6F, 0x20,
+        ])
+        .unwrap();
+    let mut response = [0; 12];
+    stream.read_exact(&mut response).unwrap();
+
+    // Raw digits of (degrees + 360) * 100.
+    let angle = |digits: &[u8]| {
+        digits
+            .iter()
+            .fold(0.0, |value, &digit| value * 10.0 + f64::from(digit))
+            / 100.0
+            - 360.0
+    };
+    assert_eq!(response[0], 0x58);
+    assert!((angle(&response[1..=5]) - 123.45).abs() < 1e-9);
+    assert!((angle(&response[6..=10]) + 5.0).abs() < 1e-9);
+}
@@ -1,7 +1,9 @@
 use chrono::{DurationRound, TimeDelta, Utc};
 use reqwest::StatusCode;
 use reqwest::blocking::Client;
-use reqwest::header::{COOKIE, SET_COOKIE};
+use reqwest::header::{COOKIE, LOCATION, SET_COOKIE};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::time::Duration;
 
 mod binary_wrappers;
 pub use binary_wrappers::*;
@@ -11,10 +13,135 @@ fn can_start_and_stop_backend() {
     SalsaTestServer::spawn();
 }
 
+#[test]
+fn invalid_log_level_is_rejected_at_startup() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_salsa"))
+        .args(["-p", "0", "--log-level", "loud"])
+        .output()
+        .expect("Could not start backend");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--log-level"));
+}
+
+#[test]
+fn missing_secrets_file_is_reported_at_startup() {
+    let config_dir = tempfile::TempDir::new().unwrap();
+    std::fs::copy(
+        "tests/test_config/config.toml",
+        config_dir.path().join("config.toml"),
+    )
+    .unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_salsa"))
+        .args(["-p", "0", "--config-dir"])
+        .arg(config_dir.path())
+        .arg("--database-dir")
+        .arg(config_dir.path())
+        .output()
+        .expect("Could not start backend");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(".secrets.toml"), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
+
+#[test]
+fn content_pages_come_from_the_assets_dir() {
+    // The test server runs outside the repository, so this only passes if
+    // the page is read from --assets-dir rather than the working directory.
+    let server = SalsaTestServer::spawn();
+    let page = server
+        .client()
+        .get(server.addr() + "/about")
+        .send()
+        .expect("Should be able to send request")
+        .text()
+        .unwrap();
+    assert!(page.contains("About the telescopes"));
+}
+
+#[test]
+fn responses_carry_a_request_id() {
+    let server = SalsaTestServer::spawn();
+    let client = server.client();
+
+    let res = client
+        .get(server.addr() + "/")
+        .send()
+        .expect("Should be able to send request");
+    assert!(res.headers().contains_key("x-request-id"));
+
+    let res = client
+        .get(server.addr() + "/")
+        .header("x-request-id", "from-the-proxy")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(res.headers()["x-request-id"], "from-the-proxy");
+}
+
+#[test]
+fn oversized_request_bodies_are_refused() {
+    let server = SalsaTestServer::spawn();
+    let res = server
+        .client()
+        .post(server.addr() + "/auth/local")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(format!("username={}", "x".repeat(1024 * 1024)))
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, res.status());
+}
+
+#[test]
+fn unchanged_assets_are_not_sent_again() {
+    let server = SalsaTestServer::spawn();
+    let client = server.client();
+
+    let res = client
+        .get(server.addr() + "/observe_chart.js")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    assert_eq!(res.headers()["cache-control"], "no-cache");
+    let etag = res.headers()["etag"].clone();
+    let last_modified = res.headers()["last-modified"].clone();
+
+    let res = client
+        .get(server.addr() + "/observe_chart.js")
+        .header("if-none-match", etag.clone())
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::NOT_MODIFIED, res.status());
+    assert_eq!(res.headers()["etag"], etag);
+    let res = client
+        .get(server.addr() + "/observe_chart.js")
+        .header("if-modified-since", last_modified)
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::NOT_MODIFIED, res.status());
+    let res = client
+        .get(server.addr() + "/observe_chart.js")
+        .header("if-none-match", "W/\"stale\"")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+
+    let res = client
+        .get(server.addr() + "/vendor/htmx-2.0.10.min.js")
+        .send()
+        .expect("Should be able to send request");
+    assert!(
+        res.headers()["cache-control"]
+            .to_str()
+            .unwrap()
+            .contains("immutable")
+    );
+}
+
 #[test]
 fn login_with_unknown_local_user_fails() {
     let server = SalsaTestServer::spawn();
-    let client = Client::builder()
+    let client = server
+        .client_builder()
         .redirect(reqwest::redirect::Policy::none())
         .build()
         .expect("Should be possible to create reqwest client");
@@ -37,7 +164,8 @@ fn login_with_unknown_local_user_fails() {
 fn login_with_local_user_possible() {
     let server = SalsaTestServer::spawn();
     let user = server.add_local_user("test", "password");
-    let client = Client::builder()
+    let client = server
+        .client_builder()
         .redirect(reqwest::redirect::Policy::none())
         .build()
         .expect("Should be possible to create reqwest client");
@@ -56,7 +184,8 @@ fn login_with_local_user_possible() {
 fn login_with_wrong_password_fails() {
     let server = SalsaTestServer::spawn();
     let user = server.add_local_user("test", "password");
-    let client = Client::builder()
+    let client = server
+        .client_builder()
         .redirect(reqwest::redirect::Policy::none())
         .build()
         .expect("Should be possible to create reqwest client");
@@ -82,7 +211,7 @@ fn login_with_wrong_password_fails() {
 fn create_booking_not_logged_in_isnt_allowed() {
     let server = SalsaTestServer::spawn();
 
-    let client = Client::builder().cookie_store(true).build().unwrap();
+    let client = server.client();
     let res = client
         .post(server.addr() + "/bookings")
         .form(&[("start_timestamp", "1751331600"), ("telescope", "fake1")])
@@ -97,7 +226,7 @@ fn create_booking() {
     let server = SalsaTestServer::spawn();
     let user = server.add_local_user("user", "password");
 
-    let client = Client::builder().cookie_store(true).build().unwrap();
+    let client = server.client();
     server.login(&client, &user);
     let next_hour = Utc::now()
         .duration_round_up(TimeDelta::hours(1))
@@ -115,12 +244,51 @@ fn create_booking() {
     assert_eq!(StatusCode::OK, res.status());
 }
 
+#[test]
+fn only_owner_can_cancel_booking() {
+    let server = SalsaTestServer::spawn();
+    let owner = server.add_local_user("owner", "password");
+    let other = server.add_local_user("other", "password");
+
+    let owner_client = server.client();
+    server.login(&owner_client, &owner);
+    let next_hour = Utc::now()
+        .duration_round_up(TimeDelta::hours(1))
+        .expect("Should be possible to round up to closest hour")
+        .timestamp();
+    let res = owner_client
+        .post(server.addr() + "/bookings")
+        .form(&[
+            ("start_timestamp", format!("{}", next_hour).as_str()),
+            ("telescope", "fake1"),
+        ])
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+
+    let other_client = server.client();
+    server.login(&other_client, &other);
+    for id in [1, 2] {
+        let res = other_client
+            .delete(server.addr() + &format!("/bookings/{id}"))
+            .send()
+            .expect("Should be able to send request");
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+    }
+
+    let res = owner_client
+        .delete(server.addr() + "/bookings/1")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+}
+
 #[test]
 fn cant_book_a_past_slot() {
     let server = SalsaTestServer::spawn();
     let user = server.add_local_user("user", "password");
 
-    let client = Client::builder().cookie_store(true).build().unwrap();
+    let client = server.client();
     server.login(&client, &user);
     // Slot that ended a day ago.
     let past = (Utc::now() - chrono::Duration::days(1)).timestamp();
@@ -141,6 +309,34 @@ fn cant_book_a_past_slot() {
     );
 }
 
+#[test]
+fn cant_book_too_far_ahead() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+
+    let client = server.client();
+    server.login(&client, &user);
+    let far_ahead = (Utc::now() + chrono::Duration::days(400))
+        .duration_round_up(TimeDelta::hours(1))
+        .expect("Should be possible to round up to closest hour")
+        .timestamp();
+    let res = client
+        .post(server.addr() + "/bookings")
+        .form(&[
+            ("start_timestamp", format!("{}", far_ahead).as_str()),
+            ("telescope", "fake1"),
+        ])
+        .send()
+        .expect("Should be able to send request");
+
+    assert_eq!(StatusCode::OK, res.status());
+    let body = res.text().expect("Should be able to read body");
+    assert!(
+        body.contains("days ahead"),
+        "Expected advance-notice error in body, got: {body}"
+    );
+}
+
 #[test]
 fn cant_double_book_same_slot() {
     let server = SalsaTestServer::spawn();
@@ -156,7 +352,7 @@ fn cant_double_book_same_slot() {
         ("telescope", "fake1".to_string()),
     ];
 
-    let client_a = Client::builder().cookie_store(true).build().unwrap();
+    let client_a = server.client();
     server.login(&client_a, &user_a);
     let res_a = client_a
         .post(server.addr() + "/bookings")
@@ -165,7 +361,7 @@ fn cant_double_book_same_slot() {
         .expect("Should be able to send request");
     assert_eq!(StatusCode::OK, res_a.status());
 
-    let client_b = Client::builder().cookie_store(true).build().unwrap();
+    let client_b = server.client();
     server.login(&client_b, &user_b);
     let res_b = client_b
         .post(server.addr() + "/bookings")
@@ -181,10 +377,67 @@ fn cant_double_book_same_slot() {
 }
 
 #[test]
-fn malformed_cookie_is_ignored() {
+fn posts_without_a_matching_csrf_token_are_rejected() {
     let server = SalsaTestServer::spawn();
+    server.add_local_user("user", "password");
+    let login = |client: &Client, extra: &[(&str, &str)]| {
+        let mut form = vec![("username", "user"), ("password", "password")];
+        form.extend_from_slice(extra);
+        client
+            .post(server.addr() + "/auth/local")
+            .form(&form)
+            .send()
+            .expect("Should be able to send request")
+            .status()
+    };
+
+    // A first visit hands out the token cookie.
+    let res = Client::new()
+        .get(server.addr())
+        .send()
+        .expect("Should be able to send request");
+    assert!(
+        res.headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .any(|cookie| cookie.to_str().unwrap().starts_with("csrf="))
+    );
 
+    // No cookie and no token, as from a page on another site.
     let client = Client::new();
+    assert_eq!(StatusCode::FORBIDDEN, login(&client, &[]));
+
+    // The cookie alone isn't enough: the token must be echoed back.
+    let jar = std::sync::Arc::new(reqwest::cookie::Jar::default());
+    jar.add_cookie_str(
+        &format!("csrf={CSRF_TOKEN}"),
+        &server.addr().parse().unwrap(),
+    );
+    let client = Client::builder().cookie_provider(jar).build().unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, login(&client, &[]));
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        login(&client, &[("csrf_token", "a-guess")])
+    );
+
+    // Plain forms carry it as a field, htmx as a header.
+    assert_eq!(
+        StatusCode::OK,
+        login(&client, &[("csrf_token", CSRF_TOKEN)])
+    );
+    let res = client
+        .post(server.addr() + "/auth/logout")
+        .header("X-CSRF-Token", CSRF_TOKEN)
+        .send()
+        .expect("Should be able to send request");
+    assert_ne!(StatusCode::FORBIDDEN, res.status());
+}
+
+#[test]
+fn malformed_cookie_is_ignored() {
+    let server = SalsaTestServer::spawn();
+
+    let client = server.client();
     let res = client
         .get(server.addr())
         .header(COOKIE, "a_cookie:thisisnthowitsdone")
@@ -198,7 +451,7 @@ fn malformed_cookie_is_ignored() {
 fn invalid_session_is_200_ok_and_resets_cookie() {
     let server = SalsaTestServer::spawn();
 
-    let client = Client::new();
+    let client = server.client();
     let res = client
         .get(server.addr())
         .header(COOKIE, "__Host-session=notavaildsession")
@@ -216,7 +469,7 @@ fn invalid_session_is_200_ok_and_resets_cookie() {
 fn cant_open_websocket_for_spectrum_if_not_logged_in() {
     let server = SalsaTestServer::spawn();
 
-    let client = Client::new();
+    let client = server.client();
     let res = client
         .get(server.addr() + "/telescope/fake1/spectrum")
         .header("Connection", "upgrade")
@@ -230,114 +483,1300 @@ fn cant_open_websocket_for_spectrum_if_not_logged_in() {
 }
 
 #[test]
-fn cant_observe_if_not_logged_in() {
+fn position_websocket_is_public() {
     let server = SalsaTestServer::spawn();
 
-    let client = Client::new();
+    let client = server.client();
+    let upgrade = |telescope: &str| {
+        client
+            .get(server.addr() + "/telescope/" + telescope + "/ws")
+            .header("Connection", "upgrade")
+            .header("Upgrade", "websocket")
+            .header("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ==")
+            .header("Sec-WebSocket-Version", "13")
+            .send()
+            .expect("Request should complete")
+    };
+
+    assert_eq!(StatusCode::SWITCHING_PROTOCOLS, upgrade("fake1").status());
+    assert_eq!(StatusCode::NOT_FOUND, upgrade("nonexistent").status());
+}
+
+#[test]
+fn cant_stream_spectrum_if_not_logged_in() {
+    let server = SalsaTestServer::spawn();
+
+    let client = server.client();
     let res = client
-        .get(server.addr() + "/observe/fake1")
+        .get(server.addr() + "/observe/fake1/spectrum/stream")
         .send()
-        .expect("Should be able to send request");
+        .expect("Request should complete");
 
     assert_eq!(StatusCode::UNAUTHORIZED, res.status());
 }
 
 #[test]
-fn cant_set_target_if_not_logged_in() {
+fn health_reports_fake_telescopes_as_up() {
     let server = SalsaTestServer::spawn();
 
-    let client = Client::new();
-    let res = client
-        .post(server.addr() + "/observe/fake1/set-target")
-        .form(&[("x", "42"), ("y", "90"), ("coordinate_system", "galactic")])
+    let res = server
+        .client()
+        .get(server.addr() + "/health")
         .send()
-        .expect("Should be able to send request");
+        .expect("Request should complete");
 
-    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+    assert_eq!(StatusCode::OK, res.status());
+    let body: serde_json::Value = res.json().expect("Health report should be JSON");
+    assert_eq!(body["status"], "ok");
+    assert!(!body["telescopes"].as_array().unwrap().is_empty());
 }
 
 #[test]
-fn cant_start_observation_if_not_logged_in() {
+fn metrics_are_exposed_in_prometheus_format() {
     let server = SalsaTestServer::spawn();
 
-    let client = Client::new();
-    let res = client
-        .post(server.addr() + "/observe/fake1/observe")
-        .header("Content-Type", "application/x-www-form-urlencoded")
-        .body("mode=FreqSwitched")
+    let res = server
+        .client()
+        .get(server.addr() + "/metrics")
         .send()
-        .expect("Should be able to send request");
+        .expect("Request should complete");
 
-    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+    assert_eq!(StatusCode::OK, res.status());
+    let body = res.text().expect("Metrics should be text");
+    assert!(body.contains("salsa_active_bookings 0\n"));
+    assert!(body.contains("salsa_telescope_status{telescope=\"fake1\""));
+    assert!(body.contains("# TYPE salsa_http_request_duration_seconds histogram"));
 }
 
-// TODO: Test for websocket upgrade without active booking. Requires better db
-// support in these tests.
+#[test]
+fn logout_all_ends_sessions_on_other_clients() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let first = server.client();
+    let second = server.client();
+    server.login(&first, &user);
+    server.login(&second, &user);
+
+    let account_status = |client: &Client| {
+        client
+            .get(server.addr() + "/account")
+            .send()
+            .expect("Should be able to send request")
+            .status()
+    };
+    assert_eq!(StatusCode::OK, account_status(&second));
+
+    let res = first
+        .post(server.addr() + "/auth/logout-all")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+
+    assert_eq!(StatusCode::UNAUTHORIZED, account_status(&first));
+    assert_eq!(StatusCode::UNAUTHORIZED, account_status(&second));
+}
 
 #[test]
-fn interferometry_list_redirects_to_login_if_not_logged_in() {
+fn oauth_callback_is_rate_limited() {
     let server = SalsaTestServer::spawn();
-    let client = Client::builder()
+
+    let client = server.client();
+    let statuses: Vec<StatusCode> = (0..20)
+        .map(|_| {
+            client
+                .get(server.addr() + "/auth/authorized")
+                .send()
+                .expect("Should be able to send request")
+                .status()
+        })
+        .collect();
+
+    assert_ne!(StatusCode::TOO_MANY_REQUESTS, statuses[0]);
+    assert_eq!(Some(&StatusCode::TOO_MANY_REQUESTS), statuses.last());
+}
+
+/// Serve an OAuth2 provider's token and user endpoints on a local port,
+/// always answering with the identity `external_id`. Returns the port.
+fn spawn_fake_oauth_provider(external_id: &str) -> u16 {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let user = format!(r#"{{"id":"{external_id}","login":"{external_id}"}}"#);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                return;
+            };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+            let body = if request_line.contains("/token") {
+                r#"{"access_token":"token","token_type":"bearer"}"#.to_string()
+            } else {
+                user.clone()
+            };
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+    });
+    port
+}
+
+#[test]
+fn oauth_callback_started_by_someone_else_links_nothing() {
+    let port = spawn_fake_oauth_provider("attacker");
+    let config_dir = tempfile::TempDir::new().unwrap();
+    std::fs::copy(
+        "tests/test_config/config.toml",
+        config_dir.path().join("config.toml"),
+    )
+    .unwrap();
+    std::fs::write(
+        config_dir.path().join(".secrets.toml"),
+        format!(
+            r#"[auth_provider.mock]
+auth_uri = "http://127.0.0.1:{port}/authorize"
+token_uri = "http://127.0.0.1:{port}/token"
+redirect_uri = "http://127.0.0.1/auth/authorized"
+user_uri = "http://127.0.0.1:{port}/user"
+id_field = "id"
+display_name_field = "login"
+scopes = []
+client_id = "salsa"
+client_secret = "secret"
+"#
+        ),
+    )
+    .unwrap();
+    let server = SalsaTestServer::spawn_with_config_dir(config_dir.path().to_str().unwrap());
+
+    // The attacker starts a login but keeps the callback for the victim.
+    let attacker = server
+        .client_builder()
         .redirect(reqwest::redirect::Policy::none())
         .build()
         .unwrap();
+    let res = attacker
+        .get(server.addr() + "/auth/redirect/mock")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::SEE_OTHER, res.status());
+    let location = reqwest::Url::parse(res.headers()[LOCATION].to_str().unwrap()).unwrap();
+    let (_, oauth_state) = location
+        .query_pairs()
+        .find(|(name, _)| name == "state")
+        .expect("Provider URL should carry the state");
+
+    let victim = server.add_local_user("victim", "password");
+    let client = server.client();
+    server.login(&client, &victim);
+    client
+        .get(server.addr() + "/auth/authorized")
+        .query(&[("code", "code"), ("state", oauth_state.as_ref())])
+        .send()
+        .expect("Should be able to send request");
+
+    let owner: Vec<String> = rusqlite::Connection::open(server.database_path())
+        .unwrap()
+        .prepare(
+            "SELECT u.username FROM user_identity i INNER JOIN user u ON i.user_id = u.id \
+             WHERE i.provider = 'mock' AND i.external_id = 'attacker'",
+        )
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(owner, vec!["attacker".to_string()]);
+}
+
+#[test]
+fn coordinate_conversion_api() {
+    let server = SalsaTestServer::spawn();
+
+    let client = server.client();
     let res = client
-        .get(server.addr() + "/interferometry")
+        .get(server.addr() + "/api/convert?from=galactic&to=equatorial&x=0&y=0")
         .send()
         .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    let converted: serde_json::Value = res.json().expect("Should be JSON");
+    assert_eq!(converted["system"], "equatorial");
+    let ra = converted["x"].as_f64().unwrap();
+    let dec = converted["y"].as_f64().unwrap();
+    assert!((ra - 266.40).abs() < 0.01, "ra = {ra}");
+    assert!((dec + 28.94).abs() < 0.01, "dec = {dec}");
 
-    assert_eq!(StatusCode::SEE_OTHER, res.status());
-    let location = res.headers().get("location").unwrap().to_str().unwrap();
-    assert!(
-        location.contains("/auth/login"),
-        "Expected login redirect, got: {location}"
+    let convert = |query: &str| {
+        client
+            .get(server.addr() + "/api/convert?" + query)
+            .send()
+            .expect("Should be able to send request")
+            .status()
+    };
+    assert_eq!(
+        StatusCode::BAD_REQUEST,
+        convert("from=ecliptic&to=galactic&x=0&y=0")
+    );
+    assert_eq!(
+        StatusCode::BAD_REQUEST,
+        convert("from=galactic&to=equatorial&x=NaN&y=0")
+    );
+    assert_eq!(
+        StatusCode::BAD_REQUEST,
+        convert("from=galactic&to=equatorial&x=0&y=inf")
+    );
+    // Horizontal coordinates depend on where they are seen from.
+    assert_eq!(
+        StatusCode::BAD_REQUEST,
+        convert("from=galactic&to=horizontal&x=0&y=0")
+    );
+    assert_eq!(
+        StatusCode::NOT_FOUND,
+        convert("from=galactic&to=horizontal&x=0&y=0&telescope=nowhere")
+    );
+    assert_eq!(
+        StatusCode::OK,
+        convert("from=galactic&to=horizontal&x=0&y=0&telescope=fake1")
     );
 }
 
 #[test]
-fn cant_start_interferometry_if_not_logged_in() {
+fn cant_observe_if_not_logged_in() {
     let server = SalsaTestServer::spawn();
-    let client = Client::builder()
-        .redirect(reqwest::redirect::Policy::none())
-        .build()
-        .unwrap();
+
+    let client = server.client();
     let res = client
-        .post(server.addr() + "/interferometry/start")
-        .form(&[
-            ("telescope_a", "fake1"),
-            ("telescope_b", "fake2"),
-            ("coordinate_system", "sun"),
-            ("target_x", "0"),
-            ("target_y", "0"),
-            ("center_freq_mhz", "1420.4"),
-            ("bandwidth_mhz", "2.5"),
-            ("spectral_channels", "128"),
-        ])
+        .get(server.addr() + "/observe/fake1")
         .send()
         .expect("Should be able to send request");
 
-    assert_eq!(StatusCode::SEE_OTHER, res.status());
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
 }
 
 #[test]
-fn cant_stop_interferometry_if_not_logged_in() {
+fn cant_fetch_error_log_if_not_logged_in() {
     let server = SalsaTestServer::spawn();
-    let client = Client::builder()
-        .redirect(reqwest::redirect::Policy::none())
-        .build()
-        .unwrap();
+
+    let client = server.client();
     let res = client
-        .post(server.addr() + "/interferometry/stop")
+        .get(server.addr() + "/telescope/fake1/errors")
         .send()
-        .expect("Should be able to send request");
+        .expect("Request should complete");
 
-    assert_eq!(StatusCode::SEE_OTHER, res.status());
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
 }
 
 #[test]
-fn cant_fetch_interferometry_session_data_if_not_logged_in() {
+fn malformed_config_fails_with_a_message_instead_of_a_panic() {
+    let config_dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        config_dir.path().join("config.toml"),
+        "[[telescopes]]\nname = \"fake1\"\nlocation = [11.9, 57.4\n",
+    )
+    .unwrap();
+    let database_dir = tempfile::TempDir::new().unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_salsa"))
+        .args(["-p", "0", "--database-dir"])
+        .arg(database_dir.path())
+        .arg("--config-dir")
+        .arg(config_dir.path())
+        .output()
+        .expect("Should be able to run the server");
+
+    assert_eq!(Some(1), output.status.code());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("panicked"), "{stderr}");
+    assert!(stderr.contains("config.toml"), "{stderr}");
+    assert!(stderr.contains("line 3"), "{stderr}");
+}
+
+#[test]
+fn telescope_api_is_public() {
     let server = SalsaTestServer::spawn();
-    let client = Client::new();
+
+    let client = server.client();
+    let names: Vec<String> = client
+        .get(server.addr() + "/api/telescopes")
+        .send()
+        .expect("Should be able to send request")
+        .json()
+        .expect("Should be a JSON list of names");
+    assert!(names.contains(&"fake1".to_string()));
+
+    let res = client
+        .get(server.addr() + "/api/telescopes/fake1/info")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    let info: serde_json::Value = res.json().expect("Should be JSON");
+    assert_eq!(info["id"], "fake1");
+
+    let res = client
+        .get(server.addr() + "/api/telescopes/nonexistent/info")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::NOT_FOUND, res.status());
+}
+
+#[test]
+fn telescope_history_returns_the_samples_in_range() {
+    let server = SalsaTestServer::spawn();
+    let now = Utc::now();
+    let minutes_ago = |minutes| now - chrono::Duration::minutes(minutes);
+    let db = rusqlite::Connection::open(server.database_path()).unwrap();
+    for minutes in [30, 20, 10] {
+        db.execute(
+            "INSERT INTO telescope_position (telescope_id, timestamp, azimuth_deg, elevation_deg, status) \
+             VALUES ('fake1', ?1, 90.0, 45.0, 'tracking')",
+            [minutes_ago(minutes).timestamp()],
+        )
+        .unwrap();
+    }
+
+    let client = server.client();
+    let res = client
+        .get(server.addr() + "/api/telescopes/fake1/history")
+        .query(&[
+            ("from", minutes_ago(25).to_rfc3339()),
+            ("to", minutes_ago(5).to_rfc3339()),
+        ])
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    let samples: Vec<serde_json::Value> = res.json().expect("Should be a JSON list");
+    assert_eq!(2, samples.len());
+    assert_eq!(samples[0]["elevation_deg"], 45.0);
+    assert_eq!(samples[0]["status"], "Tracking");
+
+    let res = client
+        .get(server.addr() + "/api/telescopes/fake1/history")
+        .query(&[
+            ("from", minutes_ago(5).to_rfc3339()),
+            ("to", minutes_ago(25).to_rfc3339()),
+        ])
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::BAD_REQUEST, res.status());
+
+    let res = client
+        .get(server.addr() + "/api/telescopes/nonexistent/history")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::NOT_FOUND, res.status());
+}
+
+#[test]
+fn api_control_needs_a_booking() {
+    let server = SalsaTestServer::spawn();
+    let target = serde_json::json!({ "system": "equatorial", "x": 10.0, "y": 200.0 });
+
+    let res = server
+        .client()
+        .post(server.addr() + "/api/telescopes/fake1/target")
+        .json(&target)
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+    let body: serde_json::Value = res.json().expect("Should be JSON");
+    assert!(body["error"].is_string());
+
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    let res = client
+        .post(server.addr() + "/api/telescopes/fake1/integration")
+        .json(&serde_json::json!({ "action": "stop" }))
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+}
+
+#[test]
+fn api_token_stands_in_for_a_session_until_revoked() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    let body = client
+        .post(server.addr() + "/account/api-token")
+        .send()
+        .expect("Should be able to send request")
+        .text()
+        .unwrap();
+    let token = body
+        .split_once("<code")
+        .and_then(|(_, rest)| rest.split_once('>'))
+        .and_then(|(_, rest)| rest.split_once("</code>"))
+        .map(|(token, _)| token.trim().to_string())
+        .expect("Token should be shown once");
+
+    let scripted = server.client();
+    let res = scripted
+        .get(server.addr() + "/account")
+        .bearer_auth(&token)
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    let res = scripted
+        .get(server.addr() + "/account")
+        .bearer_auth("1.not-the-token")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+
+    client
+        .post(server.addr() + "/account/api-token/revoke")
+        .send()
+        .expect("Should be able to send request");
+    let res = scripted
+        .get(server.addr() + "/account")
+        .bearer_auth(&token)
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+}
+
+#[test]
+fn cant_set_target_if_not_logged_in() {
+    let server = SalsaTestServer::spawn();
+
+    let client = server.client();
+    let res = client
+        .post(server.addr() + "/observe/fake1/set-target")
+        .form(&[("x", "42"), ("y", "90"), ("coordinate_system", "galactic")])
+        .send()
+        .expect("Should be able to send request");
+
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+}
+
+#[test]
+fn cant_repoint_if_not_logged_in() {
+    let server = SalsaTestServer::spawn();
+
+    let client = server.client();
+    let res = client
+        .post(server.addr() + "/observe/fake1/repoint")
+        .form(&[
+            ("x", "42"),
+            ("y", "90"),
+            ("coordinate_system", "galactic"),
+            ("az_offset_deg", "5"),
+        ])
+        .send()
+        .expect("Should be able to send request");
+
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+}
+
+#[test]
+fn cant_park_if_not_logged_in() {
+    let server = SalsaTestServer::spawn();
+
+    let client = server.client();
+    let res = client
+        .post(server.addr() + "/observe/fake1/park")
+        .send()
+        .expect("Should be able to send request");
+
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+}
+
+#[test]
+fn jog_needs_a_booking_and_steps_from_the_current_position() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    let jog = |az: &str, el: &str| {
+        client
+            .post(server.addr() + "/observe/fake1/jog")
+            .form(&[("az_deg", az), ("el_deg", el)])
+            .send()
+            .expect("Should be able to send request")
+    };
+    assert_eq!(StatusCode::UNAUTHORIZED, jog("1", "0").status());
+
+    book_fake1(&server, &client);
+    point_and_wait_for_tracking(&server, &client);
+    let info = || -> serde_json::Value {
+        client
+            .get(server.addr() + "/api/telescopes/fake1/info")
+            .send()
+            .expect("Should be able to send request")
+            .json()
+            .expect("Should be JSON")
+    };
+
+    assert_eq!(StatusCode::BAD_REQUEST, jog("30", "0").status());
+    assert_eq!(StatusCode::OK, jog("2", "-1").status());
+    let commanded = &info()["commanded_horizontal"];
+    let azimuth = commanded["azimuth"].as_f64().unwrap().to_degrees();
+    let elevation = commanded["elevation"].as_f64().unwrap().to_degrees();
+    assert!((azimuth - 92.0).abs() < 0.5, "azimuth {azimuth}");
+    assert!((elevation - 59.0).abs() < 0.5, "elevation {elevation}");
+}
+
+#[test]
+fn overlapping_bookings_cant_both_control_the_telescope() {
+    let server = SalsaTestServer::spawn();
+    let alice = server.add_local_user("alice", "password");
+    let bob = server.add_local_user("bob", "password");
+    let alice_client = server.client();
+    server.login(&alice_client, &alice);
+    let bob_client = server.client();
+    server.login(&bob_client, &bob);
+    let res = alice_client
+        .post(server.addr() + "/bookings")
+        .form(&[
+            (
+                "start_timestamp",
+                (Utc::now().timestamp() - 60).to_string().as_str(),
+            ),
+            ("telescope", "fake1"),
+        ])
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    // The booking form refuses overlaps, so give bob the same slot directly.
+    rusqlite::Connection::open(server.database_path())
+        .unwrap()
+        .execute(
+            "INSERT INTO booking (user_id, telescope_id, start_timestamp, end_timestamp) \
+             SELECT u.id, b.telescope_id, b.start_timestamp, b.end_timestamp \
+             FROM booking b, user u WHERE u.username = 'bob'",
+            [],
+        )
+        .unwrap();
+    let set_target = |client: &Client| {
+        client
+            .post(server.addr() + "/observe/fake1/set-target")
+            .form(&[
+                ("x", "90"),
+                ("y", "60"),
+                ("coordinate_system", "horizontal"),
+            ])
+            .send()
+            .expect("Should be able to send request")
+            .status()
+    };
+
+    assert_eq!(StatusCode::OK, set_target(&alice_client));
+    assert_eq!(StatusCode::CONFLICT, set_target(&bob_client));
+    assert_eq!(StatusCode::OK, set_target(&alice_client));
+}
+
+#[test]
+fn only_admins_can_reload_telescopes() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+
+    let res = server
+        .client()
+        .post(server.addr() + "/admin/reload-telescopes")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+
+    let client = server.client();
+    server.login(&client, &user);
+    let res = client
+        .post(server.addr() + "/admin/reload-telescopes")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::FORBIDDEN, res.status());
+}
+
+#[test]
+fn admin_user_list_is_translated() {
+    let server = SalsaTestServer::spawn();
+    let admin = server.add_local_user("admin", "password");
+    server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &admin);
+    // No test user is an admin, so grant it directly.
+    rusqlite::Connection::open(server.database_path())
+        .unwrap()
+        .execute("UPDATE user SET is_admin = 1 WHERE username = 'admin'", [])
+        .unwrap();
+
+    let page = client
+        .get(server.addr() + "/admin/users")
+        .header("accept-language", "sv")
+        .send()
+        .expect("Should be able to send request")
+        .text()
+        .unwrap();
+    assert!(page.contains("Gör till admin"), "{page}");
+    assert!(page.contains("Inaktivera user? Användaren loggas ut."));
+}
+
+#[test]
+fn disabled_users_session_stops_authenticating() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+
+    let res = client
+        .get(server.addr() + "/admin/users")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::FORBIDDEN, res.status());
+    let res = client
+        .get(server.addr() + "/account")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+
+    // No test user is an admin, so disable the account directly.
+    rusqlite::Connection::open(server.database_path())
+        .unwrap()
+        .execute("UPDATE user SET disabled = 1 WHERE username = 'user'", [])
+        .unwrap();
+    let res = client
+        .get(server.addr() + "/account")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+}
+
+#[test]
+fn observation_data_is_compressed_when_the_client_accepts_it() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    let channels: Vec<String> = (0..512).map(|i| format!("{}.5", i)).collect();
+    let json = format!("[{}]", channels.join(","));
+    rusqlite::Connection::open(server.database_path())
+        .unwrap()
+        .execute(
+            "INSERT INTO observation (user_id, telescope_id, start_time, coordinate_system, \
+             target_x, target_y, integration_time_secs, frequencies_json, amplitudes_json) \
+             SELECT id, 'fake1', 0, 'galactic', 0.0, 0.0, 10.0, ?1, ?1 FROM user \
+             WHERE username = 'user'",
+            [&json],
+        )
+        .unwrap();
+
+    let res = client
+        .get(server.addr() + "/observations/1")
+        .header("accept-encoding", "gzip")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    assert_eq!(res.headers()["content-encoding"], "gzip");
+
+    let res = client
+        .get(server.addr() + "/observations/1")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    assert!(res.headers().get("content-encoding").is_none());
+}
+
+#[test]
+fn bookings_are_recorded_in_the_audit_log() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    let next_hour = Utc::now()
+        .duration_round_up(TimeDelta::hours(1))
+        .expect("Should be possible to round up to closest hour")
+        .timestamp();
+    let res = client
+        .post(server.addr() + "/bookings")
+        .form(&[
+            ("start_timestamp", format!("{}", next_hour).as_str()),
+            ("telescope", "fake1"),
+        ])
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    let res = client
+        .delete(server.addr() + "/bookings/1")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+
+    let res = client
+        .get(server.addr() + "/admin/audit")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::FORBIDDEN, res.status());
+
+    // No test user is an admin, so grant it directly.
+    rusqlite::Connection::open(server.database_path())
+        .unwrap()
+        .execute("UPDATE user SET is_admin = 1 WHERE username = 'user'", [])
+        .unwrap();
+    let audit = |query: &str| {
+        client
+            .get(server.addr() + "/admin/audit" + query)
+            .send()
+            .expect("Should be able to send request")
+            .text()
+            .unwrap()
+    };
+    let page = audit("?user=user");
+    assert!(page.contains("create_booking"));
+    assert!(page.contains("cancel_booking"));
+    assert!(!audit("?user=someone-else").contains("create_booking"));
+
+    let page = client
+        .get(server.addr() + "/admin/audit?user=someone-else")
+        .header("accept-language", "sv")
+        .send()
+        .expect("Should be able to send request")
+        .text()
+        .unwrap();
+    assert!(page.contains("Granskningslogg"), "{page}");
+    assert!(page.contains("Inget registrerat i detta intervall."));
+}
+
+#[test]
+fn display_name_overrides_the_provider_name() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    let set_display_name = |body: String| {
+        client
+            .patch(server.addr() + "/account/profile")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .expect("Should be able to send request")
+    };
+    let index = || {
+        client
+            .get(server.addr() + "/")
+            .send()
+            .expect("Should be able to send request")
+            .text()
+            .unwrap()
+    };
+
+    // Control characters are dropped and the ends trimmed.
+    let res = set_display_name("display_name=+Ada%07+Lovelace+".to_string());
+    assert_eq!(StatusCode::OK, res.status());
+    assert!(index().contains("Ada Lovelace"));
+
+    let res = set_display_name(format!("display_name={}", "x".repeat(65)));
+    assert_eq!(StatusCode::BAD_REQUEST, res.status());
+    assert!(index().contains("Ada Lovelace"));
+
+    let res = set_display_name("display_name=".to_string());
+    assert_eq!(StatusCode::OK, res.status());
+    assert!(!index().contains("Ada Lovelace"));
+}
+
+#[test]
+fn cant_start_observation_if_not_logged_in() {
+    let server = SalsaTestServer::spawn();
+
+    let client = server.client();
+    let res = client
+        .post(server.addr() + "/observe/fake1/observe")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("mode=FreqSwitched")
+        .send()
+        .expect("Should be able to send request");
+
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+}
+
+#[test]
+fn stopping_an_integration_saves_it_once() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    book_and_start_integration(&server, &client);
+    std::thread::sleep(Duration::from_secs(3));
+    for _ in 0..2 {
+        let res = client
+            .post(server.addr() + "/observe/fake1/stop")
+            .send()
+            .expect("Should be able to send request");
+        assert_eq!(StatusCode::OK, res.status());
+    }
+
+    let res = client
+        .get(server.addr() + "/observations/1")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    let res = client
+        .get(server.addr() + "/observations/2")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::NOT_FOUND, res.status());
+}
+
+#[test]
+fn drift_scans_save_a_spectrum_per_cadence() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    book_fake1(&server, &client);
+    point_and_wait_for_tracking(&server, &client);
+
+    let res = client
+        .post(server.addr() + "/observe/fake1/observe")
+        .form(&[
+            ("integration_mode", "interactive"),
+            ("drift_scan", "true"),
+            ("drift_cadence_secs", "1"),
+        ])
+        .send()
+        .expect("Should be able to send request");
+    let body = res.text().expect("Should have a body");
+    assert!(
+        body.contains("drift scan needs"),
+        "Too short a cadence should be refused: {body}"
+    );
+    let res = client
+        .post(server.addr() + "/observe/fake1/observe")
+        .form(&[
+            ("integration_mode", "interactive"),
+            ("drift_scan", "true"),
+            ("drift_cadence_secs", "2"),
+        ])
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    std::thread::sleep(Duration::from_secs(5));
+    let res = client
+        .post(server.addr() + "/observe/fake1/stop")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+
+    let observation: serde_json::Value = client
+        .get(server.addr() + "/observations/1")
+        .send()
+        .expect("Should be able to send request")
+        .json()
+        .expect("Should be JSON");
+    assert_eq!(observation["drift_cadence_secs"], 2.0);
+    let waterfall: serde_json::Value = client
+        .get(server.addr() + "/observations/1/waterfall")
+        .send()
+        .expect("Should be able to send request")
+        .json()
+        .expect("Should be JSON");
+    let rows = waterfall["rows"].as_array().expect("Rows should be a list");
+    assert!(rows.len() >= 2, "Expected a row per cadence, got {rows:?}");
+    assert_eq!(rows[0]["offset_secs"], 0.0);
+    assert_eq!(rows[1]["offset_secs"], 2.0);
+    assert_eq!(
+        rows[0]["amplitudes"].as_array().map(Vec::len),
+        waterfall["frequencies"].as_array().map(Vec::len)
+    );
+}
+
+#[test]
+fn running_integration_is_saved_on_shutdown() {
+    let mut server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    {
+        let client = server.client();
+        server.login(&client, &user);
+        book_and_start_integration(&server, &client);
+        std::thread::sleep(Duration::from_secs(3));
+    }
+
+    server.terminate();
+    assert_eq!(saved_observations(&server, "fake1"), 1);
+}
+
+#[test]
+fn shutdown_ends_open_spectrum_streams() {
+    let mut server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    book_and_start_integration(&server, &client);
+    // Left open, as an observe page would keep it.
+    let stream = client
+        .get(server.addr() + "/observe/fake1/spectrum/stream")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, stream.status());
+    std::thread::sleep(Duration::from_secs(2));
+
+    let (stopped_tx, stopped_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        server.terminate();
+        stopped_tx.send(server).unwrap();
+    });
+    let server = stopped_rx
+        .recv_timeout(Duration::from_secs(30))
+        .expect("Backend should shut down with a stream open");
+    drop(stream);
+    assert_eq!(saved_observations(&server, "fake1"), 1);
+}
+
+#[test]
+fn reloading_a_changed_telescope_saves_its_integration() {
+    let config_dir = tempfile::TempDir::new().unwrap();
+    for file in ["config.toml", ".secrets.toml"] {
+        std::fs::copy(
+            format!("tests/test_config/{file}"),
+            config_dir.path().join(file),
+        )
+        .unwrap();
+    }
+    let server = SalsaTestServer::spawn_with_config_dir(config_dir.path().to_str().unwrap());
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    book_and_start_integration(&server, &client);
+    std::thread::sleep(Duration::from_secs(2));
+
+    let config_path = config_dir.path().join("config.toml");
+    let config = std::fs::read_to_string(&config_path).unwrap();
+    std::fs::write(
+        &config_path,
+        config.replacen("min_elevation = 5.0", "min_elevation = 6.0", 1),
+    )
+    .unwrap();
+    rusqlite::Connection::open(server.database_path())
+        .unwrap()
+        .execute("UPDATE user SET is_admin = 1 WHERE username = 'user'", [])
+        .unwrap();
+    let res = client
+        .post(server.addr() + "/admin/reload-telescopes")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    assert_eq!(saved_observations(&server, "fake1"), 1);
+}
+
+#[test]
+fn a_new_target_saves_the_running_integration() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    book_and_start_integration(&server, &client);
+    std::thread::sleep(Duration::from_secs(2));
+
+    let res = client
+        .post(server.addr() + "/observe/fake1/set-target")
+        .form(&[
+            ("x", "180"),
+            ("y", "45"),
+            ("coordinate_system", "horizontal"),
+        ])
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    assert_eq!(saved_observations(&server, "fake1"), 1);
+}
+
+#[test]
+fn admin_stow_saves_the_running_integration() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    book_and_start_integration(&server, &client);
+    std::thread::sleep(Duration::from_secs(2));
+
+    let admin = server.add_local_user("admin", "password");
+    rusqlite::Connection::open(server.database_path())
+        .unwrap()
+        .execute("UPDATE user SET is_admin = 1 WHERE username = 'admin'", [])
+        .unwrap();
+    let admin_client = server.client();
+    server.login(&admin_client, &admin);
+    let res = admin_client
+        .post(server.addr() + "/admin/telescope/fake1/stow")
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    assert_eq!(saved_observations(&server, "fake1"), 1);
+}
+
+/// Book fake1 for the current hour, point it and start an interactive
+/// integration once it is tracking.
+fn book_and_start_integration(server: &SalsaTestServer, client: &Client) {
+    book_fake1(server, client);
+    point_and_wait_for_tracking(server, client);
+    let res = client
+        .post(server.addr() + "/observe/fake1/observe")
+        .form(&[("integration_mode", "interactive")])
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+}
+
+/// Book fake1 for the current hour.
+fn book_fake1(server: &SalsaTestServer, client: &Client) {
+    let res = client
+        .post(server.addr() + "/bookings")
+        .form(&[
+            (
+                "start_timestamp",
+                (Utc::now().timestamp() - 60).to_string().as_str(),
+            ),
+            ("telescope", "fake1"),
+        ])
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+}
+
+/// Point fake1 at a fixed horizontal position and wait until it tracks it.
+fn point_and_wait_for_tracking(server: &SalsaTestServer, client: &Client) {
+    let res = client
+        .post(server.addr() + "/observe/fake1/set-target")
+        .form(&[
+            ("x", "90"),
+            ("y", "60"),
+            ("coordinate_system", "horizontal"),
+        ])
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    let tracking = (0..100).any(|_| {
+        std::thread::sleep(Duration::from_millis(200));
+        let info: serde_json::Value = client
+            .get(server.addr() + "/api/telescopes/fake1/info")
+            .send()
+            .expect("Should be able to send request")
+            .json()
+            .expect("Should be JSON");
+        info["status"] == "Tracking"
+    });
+    assert!(tracking, "Telescope should reach its target");
+}
+
+fn saved_observations(server: &SalsaTestServer, telescope_id: &str) -> i64 {
+    rusqlite::Connection::open(server.database_path())
+        .unwrap()
+        .query_row(
+            "SELECT COUNT(*) FROM observation WHERE telescope_id = ?1",
+            [telescope_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+}
+
+#[test]
+fn queued_observations_run_during_a_booking() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    let queue = server.addr() + "/api/telescopes/fake1/queue";
+    for azimuth in [90.0, 100.0, 110.0] {
+        let res = client
+            .post(&queue)
+            .json(&serde_json::json!({
+                "system": "horizontal",
+                "x": azimuth,
+                "y": 60.0,
+                "integration_time_secs": 2.0,
+            }))
+            .send()
+            .expect("Should be able to send request");
+        assert_eq!(StatusCode::CREATED, res.status());
+    }
+    let items: serde_json::Value = client
+        .get(&queue)
+        .send()
+        .expect("Should be able to send request")
+        .json()
+        .expect("Should be JSON");
+    let last = items[2]["id"].as_i64().unwrap();
+    let res = client
+        .delete(format!("{queue}/{last}"))
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::NO_CONTENT, res.status());
+
+    book_fake1(&server, &client);
+    let statuses = || -> Vec<String> {
+        let items: Vec<serde_json::Value> = client
+            .get(&queue)
+            .send()
+            .expect("Should be able to send request")
+            .json()
+            .expect("Should be JSON");
+        items
+            .iter()
+            .map(|item| item["status"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let finished = (0..300).any(|_| {
+        std::thread::sleep(Duration::from_millis(200));
+        statuses()[..2].iter().all(|status| status == "done")
+    });
+    assert!(finished, "Queue should be worked through: {:?}", statuses());
+    assert_eq!(statuses(), ["done", "done", "cancelled"]);
+
+    let first = items[0]["id"].as_i64().unwrap();
+    let res = client
+        .delete(format!("{queue}/{first}"))
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::CONFLICT, res.status());
+    assert_eq!(saved_observations(&server, "fake1"), 2);
+}
+
+// TODO: Test for websocket upgrade without active booking. Requires better db
+// support in these tests.
+
+#[test]
+fn cant_download_observation_csv_if_not_logged_in() {
+    let server = SalsaTestServer::spawn();
+
+    let client = server.client();
+    let res = client
+        .get(server.addr() + "/observations/1/csv")
+        .send()
+        .expect("Should be able to send request");
+
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+}
+
+#[test]
+fn cant_stack_observations_if_not_logged_in() {
+    let server = SalsaTestServer::spawn();
+
+    let client = server.client();
+    let res = client
+        .post(server.addr() + "/observations/stack")
+        .json(&serde_json::json!({ "observation_ids": [1, 2] }))
+        .send()
+        .expect("Should be able to send request");
+
+    assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+}
+
+#[test]
+fn observations_given_twice_are_stacked_once() {
+    let server = SalsaTestServer::spawn();
+    let user = server.add_local_user("user", "password");
+    let client = server.client();
+    server.login(&client, &user);
+    let db = rusqlite::Connection::open(server.database_path()).unwrap();
+    for amplitudes in ["[1.0,1.0]", "[4.0,4.0]"] {
+        db.execute(
+            "INSERT INTO observation (user_id, telescope_id, start_time, coordinate_system, \
+             target_x, target_y, integration_time_secs, frequencies_json, amplitudes_json) \
+             SELECT id, 'fake1', 0, 'galactic', 0.0, 0.0, 10.0, '[1.0,2.0]', ?1 FROM user \
+             WHERE username = 'user'",
+            [amplitudes],
+        )
+        .unwrap();
+    }
+
+    let res = client
+        .post(server.addr() + "/observations/stack")
+        .json(&serde_json::json!({ "observation_ids": [1, 2, 1] }))
+        .send()
+        .expect("Should be able to send request");
+    assert_eq!(StatusCode::OK, res.status());
+    let stacked: serde_json::Value = res.json().expect("Should be JSON");
+    assert_eq!(stacked["observation_ids"], serde_json::json!([1, 2]));
+    assert_eq!(stacked["integration_time_secs"], 20.0);
+    assert_eq!(stacked["amplitudes"], serde_json::json!([2.5, 2.5]));
+}
+
+#[test]
+fn interferometry_list_redirects_to_login_if_not_logged_in() {
+    let server = SalsaTestServer::spawn();
+    let client = server
+        .client_builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let res = client
+        .get(server.addr() + "/interferometry")
+        .send()
+        .expect("Should be able to send request");
+
+    assert_eq!(StatusCode::SEE_OTHER, res.status());
+    let location = res.headers().get("location").unwrap().to_str().unwrap();
+    assert!(
+        location.contains("/auth/login"),
+        "Expected login redirect, got: {location}"
+    );
+}
+
+#[test]
+fn cant_start_interferometry_if_not_logged_in() {
+    let server = SalsaTestServer::spawn();
+    let client = server
+        .client_builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let res = client
+        .post(server.addr() + "/interferometry/start")
+        .form(&[
+            ("telescope_a", "fake1"),
+            ("telescope_b", "fake2"),
+            ("coordinate_system", "sun"),
+            ("target_x", "0"),
+            ("target_y", "0"),
+            ("center_freq_mhz", "1420.4"),
+            ("bandwidth_mhz", "2.5"),
+            ("spectral_channels", "128"),
+        ])
+        .send()
+        .expect("Should be able to send request");
+
+    assert_eq!(StatusCode::SEE_OTHER, res.status());
+}
+
+#[test]
+fn cant_stop_interferometry_if_not_logged_in() {
+    let server = SalsaTestServer::spawn();
+    let client = server
+        .client_builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let res = client
+        .post(server.addr() + "/interferometry/stop")
+        .send()
+        .expect("Should be able to send request");
+
+    assert_eq!(StatusCode::SEE_OTHER, res.status());
+}
+
+#[test]
+fn cant_fetch_interferometry_session_data_if_not_logged_in() {
+    let server = SalsaTestServer::spawn();
+    let client = server.client();
     let res = client
         .get(server.addr() + "/interferometry/1/data")
         .send()
@@ -350,7 +1789,7 @@ fn cant_fetch_interferometry_session_data_if_not_logged_in() {
 fn cant_start_interferometry_without_active_booking() {
     let server = SalsaTestServer::spawn();
     let user = server.add_local_user("interf_user", "password");
-    let client = Client::builder().cookie_store(true).build().unwrap();
+    let client = server.client();
     server.login(&client, &user);
 
     let res = client
@@ -375,7 +1814,7 @@ fn cant_start_interferometry_without_active_booking() {
 fn cant_start_interferometry_with_same_telescope_twice() {
     let server = SalsaTestServer::spawn();
     let user = server.add_local_user("interf_user", "password");
-    let client = Client::builder().cookie_store(true).build().unwrap();
+    let client = server.client();
     server.login(&client, &user);
 
     let res = client